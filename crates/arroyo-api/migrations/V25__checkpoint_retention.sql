@@ -0,0 +1,4 @@
+ALTER TABLE checkpoints
+ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE job_configs
+ADD COLUMN checkpoint_retention JSONB;
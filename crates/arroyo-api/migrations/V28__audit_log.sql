@@ -0,0 +1,13 @@
+CREATE TABLE audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    pub_id VARCHAR NOT NULL UNIQUE,
+    organization_id VARCHAR NOT NULL,
+    actor VARCHAR NOT NULL,
+    actor_role VARCHAR NOT NULL,
+    action VARCHAR NOT NULL,
+    target_id VARCHAR NOT NULL,
+    request JSONB,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX audit_log_organization_id_idx ON audit_log (organization_id, id);
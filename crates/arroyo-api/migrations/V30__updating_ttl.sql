@@ -0,0 +1,2 @@
+ALTER TABLE pipelines
+ADD COLUMN updating_ttl_micros BIGINT;
@@ -1,9 +1,38 @@
+----------- transactions -------------------
+--! begin_transaction
+BEGIN;
+
+--! commit_transaction
+COMMIT;
+
+--! rollback_transaction
+ROLLBACK;
+
 ----------- api keys -------------------
 --! get_api_key
-SELECT user_id, organization_id
+SELECT user_id, organization_id, role
 FROM api_keys
 WHERE api_key = :api_key;
 
+--! create_api_key
+INSERT INTO api_keys (pub_id, user_id, organization_id, created_by, name, api_key, key_prefix, role)
+VALUES (:pub_id, :user_id, :organization_id, :created_by, :name, :api_key, :key_prefix, :role);
+
+--! get_api_keys : DbApiKey()
+SELECT pub_id, name, user_id, role, key_prefix, created_at
+FROM api_keys
+WHERE organization_id = :organization_id
+ORDER BY created_at DESC;
+
+--! get_api_key_by_pub_id: DbApiKey()
+SELECT pub_id, name, user_id, role, key_prefix, created_at
+FROM api_keys
+WHERE organization_id = :organization_id AND pub_id = :pub_id;
+
+--! delete_api_key
+DELETE FROM api_keys
+WHERE organization_id = :organization_id AND pub_id = :pub_id;
+
 ----------- connection profiles ----------------
 --! create_connection_profile
 INSERT INTO connection_profiles (pub_id, organization_id, created_by, name, type, config)
@@ -31,6 +60,11 @@ FROM connection_profiles
 WHERE connection_profiles.organization_id = :organization_id AND connection_profiles.pub_id = :pub_id
 ORDER BY COALESCE(connection_profiles.updated_at, connection_profiles.created_at) DESC;
 
+--! update_connection_profile
+UPDATE connection_profiles
+SET config = :config, updated_at = :updated_at, updated_by = :updated_by
+WHERE organization_id = :organization_id AND pub_id = :pub_id;
+
 --! delete_connection_profile
 DELETE FROM connection_profiles
 WHERE organization_id = :organization_id AND pub_id = :pub_id;
@@ -114,21 +148,42 @@ FROM connection_tables
 LEFT JOIN connection_profiles ON connection_profiles.id = connection_tables.connection_id
 WHERE connection_tables.organization_id = :organization_id AND connection_tables.pub_id = :pub_id;
 
+--! update_connection_table(schema?)
+UPDATE connection_tables
+SET table_type = :table_type, config = :config, schema = :schema, updated_at = :updated_at, updated_by = :updated_by
+WHERE organization_id = :organization_id AND pub_id = :pub_id;
+
 --! delete_connection_table
 DELETE FROM connection_tables
 WHERE organization_id = :organization_id AND pub_id = :pub_id;
 
+--: DbConnectionTableConsumer (state?)
+
+--! get_connection_table_consumers : DbConnectionTableConsumer
+SELECT pipelines.id as pipeline_id,
+    pipelines.pub_id as pipeline_pub_id,
+    pipelines.name as name,
+    pipelines.program as program,
+    job_configs.id as job_id,
+    job_statuses.state as state
+FROM connection_table_pipelines
+    INNER JOIN connection_tables ON connection_tables.id = connection_table_pipelines.connection_table_id
+    INNER JOIN pipelines ON pipelines.id = connection_table_pipelines.pipeline_id
+    INNER JOIN job_configs ON job_configs.pipeline_id = pipelines.id
+    LEFT JOIN job_statuses ON job_statuses.id = job_configs.id
+WHERE connection_tables.organization_id = :organization_id AND connection_tables.pub_id = :pub_id;
+
 
 ----------- pipelines -------------------
 
---: DbPipeline (state?, ttl_micros?)
+--: DbPipeline (state?, ttl_micros?, autoscaler?, checkpoint_retention?, checkpoint_alignment?, notifications?, updating_ttl_micros?)
 
---! create_pipeline(textual_repr?)
-INSERT INTO pipelines (pub_id, organization_id, created_by, name, type, textual_repr, udfs, program, proto_version)
-VALUES (:pub_id, :organization_id, :created_by, :name, :type, :textual_repr, :udfs, :program, :proto_version);
+--! create_pipeline(textual_repr?, updating_ttl_micros?)
+INSERT INTO pipelines (pub_id, organization_id, created_by, name, type, textual_repr, udfs, program, proto_version, updating_ttl_micros)
+VALUES (:pub_id, :organization_id, :created_by, :name, :type, :textual_repr, :udfs, :program, :proto_version, :updating_ttl_micros);
 
 --! get_pipelines : DbPipeline
-SELECT pipelines.id, pipelines.pub_id, name, type, textual_repr, udfs, program, checkpoint_interval_micros, stop, pipelines.created_at, state, parallelism_overrides, ttl_micros
+SELECT pipelines.id, pipelines.pub_id, name, type, textual_repr, udfs, program, checkpoint_interval_micros, stop, pipelines.created_at, state, parallelism_overrides, ttl_micros, autoscaler, checkpoint_retention, checkpoint_alignment, notifications, updating_ttl_micros
 FROM pipelines
     INNER JOIN job_configs on pipelines.id = job_configs.pipeline_id
     LEFT JOIN job_statuses ON job_configs.id = job_statuses.id
@@ -143,7 +198,7 @@ ORDER BY pipelines.created_at DESC
 LIMIT cast(:limit as integer);
 
 --! get_pipeline: DbPipeline
-SELECT pipelines.id, pipelines.pub_id, name, type, textual_repr, udfs, program, checkpoint_interval_micros, stop, pipelines.created_at, state, parallelism_overrides, ttl_micros
+SELECT pipelines.id, pipelines.pub_id, name, type, textual_repr, udfs, program, checkpoint_interval_micros, stop, pipelines.created_at, state, parallelism_overrides, ttl_micros, autoscaler, checkpoint_retention, checkpoint_alignment, notifications, updating_ttl_micros
 FROM pipelines
     INNER JOIN job_configs on pipelines.id = job_configs.pipeline_id
     LEFT JOIN job_statuses ON job_configs.id = job_statuses.id
@@ -158,6 +213,11 @@ WHERE pub_id = :pub_id AND organization_id = :organization_id;
 INSERT INTO connection_table_pipelines(pub_id, pipeline_id, connection_table_id)
 VALUES (:pub_id, :pipeline_id, :connection_table_id);
 
+--! update_pipeline_program
+UPDATE pipelines
+SET program = :program
+WHERE id = :id;
+
 --! delete_pipeline
 DELETE FROM pipelines
 WHERE pub_id = :pub_id AND organization_id = :organization_id;
@@ -165,7 +225,7 @@ WHERE pub_id = :pub_id AND organization_id = :organization_id;
 
 ----------- jobs -----------------------
 
---! update_job(checkpoint_interval_micros?, stop?, parallelism_overrides?)
+--! update_job(checkpoint_interval_micros?, stop?, parallelism_overrides?, autoscaler?, checkpoint_retention?, checkpoint_alignment?, notifications?)
 UPDATE job_configs
 SET
    updated_at = :updated_at,
@@ -173,7 +233,11 @@ SET
 
    stop = COALESCE(:stop, stop),
    checkpoint_interval_micros = COALESCE(:checkpoint_interval_micros, checkpoint_interval_micros),
-   parallelism_overrides = COALESCE(:parallelism_overrides, parallelism_overrides)
+   parallelism_overrides = COALESCE(:parallelism_overrides, parallelism_overrides),
+   autoscaler = COALESCE(:autoscaler, autoscaler),
+   checkpoint_retention = COALESCE(:checkpoint_retention, checkpoint_retention),
+   checkpoint_alignment = COALESCE(:checkpoint_alignment, checkpoint_alignment),
+   notifications = COALESCE(:notifications, notifications)
 WHERE id = :job_id AND organization_id = :organization_id;
 
 --! restart_job(mode)
@@ -185,10 +249,10 @@ SET
    restart_mode = :mode
 WHERE id = :job_id AND organization_id = :organization_id;
 
---! create_job(ttl_micros?)
+--! create_job(ttl_micros?, autoscaler?, checkpoint_retention?, checkpoint_alignment?, notifications?)
 INSERT INTO job_configs
-(id, organization_id, pipeline_name, created_by, pipeline_id, checkpoint_interval_micros, ttl_micros)
-VALUES (:id, :organization_id, :pipeline_name, :created_by, :pipeline_id, :checkpoint_interval_micros, :ttl_micros);
+(id, organization_id, pipeline_name, created_by, pipeline_id, checkpoint_interval_micros, ttl_micros, autoscaler, checkpoint_retention, checkpoint_alignment, notifications)
+VALUES (:id, :organization_id, :pipeline_name, :created_by, :pipeline_id, :checkpoint_interval_micros, :ttl_micros, :autoscaler, :checkpoint_retention, :checkpoint_alignment, :notifications);
 
 --! create_job_status
 INSERT INTO job_statuses (pub_id, id, organization_id) VALUES (:pub_id, :id, :organization_id);
@@ -236,7 +300,7 @@ WHERE job_configs.organization_id = :organization_id AND job_configs.id = :job_i
 --: DbCheckpoint (finish_time?, operators?)
 
 --! get_job_checkpoints: DbCheckpoint
-SELECT epoch, state_backend, start_time, finish_time, operators FROM checkpoints
+SELECT pub_id, epoch, state_backend, start_time, finish_time, operators, pinned FROM checkpoints
 JOIN job_configs ON checkpoints.job_id = job_configs.id
 WHERE job_configs.id = :job_id
     AND checkpoints.organization_id = :organization_id
@@ -245,7 +309,7 @@ WHERE job_configs.id = :job_id
 ORDER BY epoch;
 
 --! get_job_checkpoint: DbCheckpoint
-SELECT epoch, state_backend, start_time, finish_time, operators FROM checkpoints
+SELECT pub_id, epoch, state_backend, start_time, finish_time, operators, pinned FROM checkpoints
 JOIN job_configs ON checkpoints.job_id = job_configs.id
 WHERE job_configs.id = :job_id
     AND checkpoints.organization_id = :organization_id
@@ -253,6 +317,44 @@ WHERE job_configs.id = :job_id
     AND state != 'failed'
     AND checkpoints.pub_id = :checkpoint_pub_id;
 
+--! get_latest_completed_checkpoint: DbCheckpoint
+SELECT pub_id, epoch, state_backend, start_time, finish_time, operators, pinned FROM checkpoints
+JOIN job_configs ON checkpoints.job_id = job_configs.id
+WHERE job_configs.id = :job_id
+    AND checkpoints.organization_id = :organization_id
+    AND state != 'compacted'
+    AND state != 'failed'
+    AND finish_time IS NOT NULL
+ORDER BY epoch DESC
+LIMIT 1;
+
+--! get_completed_checkpoints_after: DbCheckpoint
+SELECT pub_id, epoch, state_backend, start_time, finish_time, operators, pinned FROM checkpoints
+JOIN job_configs ON checkpoints.job_id = job_configs.id
+WHERE job_configs.id = :job_id
+    AND checkpoints.organization_id = :organization_id
+    AND state != 'compacted'
+    AND state != 'failed'
+    AND finish_time IS NOT NULL
+    AND epoch > :epoch
+ORDER BY epoch
+LIMIT cast(:limit as integer);
+
+--! set_checkpoint_pinned
+UPDATE checkpoints
+SET pinned = :pinned
+WHERE job_id = :job_id
+    AND organization_id = :organization_id
+    AND pub_id = :checkpoint_pub_id
+    AND state = 'ready';
+
+--! discard_checkpoints_after
+UPDATE checkpoints
+SET state = 'failed'
+WHERE job_id = :job_id
+    AND organization_id = :organization_id
+    AND epoch > :epoch;
+
 --! get_checkpoint_details: (finish_time?, operators?)
 SELECT epoch, state_backend, start_time, finish_time, operators FROM checkpoints
 WHERE job_id = :job_id
@@ -281,6 +383,31 @@ WHERE job_configs.organization_id = :organization_id AND job_configs.id = :job_i
 ORDER BY jlm.created_at DESC
 LIMIT cast(:limit as integer);
 
+--! get_operator_errors_after : DbLogMessage
+SELECT jlm.pub_id, jlm.job_id, jlm.operator_id, jlm.task_index, jlm.created_at, jlm.log_level, jlm.message, jlm.details
+FROM job_log_messages jlm
+JOIN job_configs ON job_configs.id = jlm.job_id
+WHERE job_configs.organization_id = :organization_id AND job_configs.id = :job_id
+  AND jlm.log_level = 'error'
+  AND (jlm.id > (
+    SELECT id FROM job_log_messages
+    WHERE pub_id = :after
+) OR :after = '')
+ORDER BY jlm.id
+LIMIT cast(:limit as integer);
+
+--! get_job_log_messages : DbLogMessage
+SELECT jlm.pub_id, jlm.job_id, jlm.operator_id, jlm.task_index, jlm.created_at, jlm.log_level, jlm.message, jlm.details
+FROM job_log_messages jlm
+JOIN job_configs ON job_configs.id = jlm.job_id
+WHERE job_configs.organization_id = :organization_id AND job_configs.id = :job_id
+  AND (jlm.created_at < (
+    SELECT created_at FROM job_log_messages
+    WHERE pub_id = :starting_after
+) OR :starting_after = '')
+ORDER BY jlm.created_at DESC
+LIMIT cast(:limit as integer);
+
 ----------- udfs -----------------------
 
 --: DbUdf (description?)
@@ -307,3 +434,20 @@ WHERE organization_id = :organization_id;
 --! delete_udf
 DELETE FROM udfs
 WHERE organization_id = :organization_id AND pub_id = :pub_id;
+
+----------- audit log -----------------------
+
+--! create_audit_log_entry(request?)
+INSERT INTO audit_log (pub_id, organization_id, actor, actor_role, action, target_id, request)
+VALUES (:pub_id, :organization_id, :actor, :actor_role, :action, :target_id, :request);
+
+--! get_audit_log : DbAuditLogEntry(request?)
+SELECT pub_id, actor, actor_role, action, target_id, request, created_at
+FROM audit_log
+WHERE organization_id = :organization_id
+  AND (id < (
+    SELECT id FROM audit_log
+    WHERE pub_id = :starting_after
+) OR :starting_after = '')
+ORDER BY id DESC
+LIMIT cast(:limit as integer);
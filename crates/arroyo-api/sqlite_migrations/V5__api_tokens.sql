@@ -0,0 +1,10 @@
+-- api keys are now stored as sha256 hashes; any pre-existing plaintext keys are no longer valid
+DELETE FROM api_keys;
+
+ALTER TABLE api_keys
+ADD COLUMN role TEXT NOT NULL DEFAULT 'admin';
+
+ALTER TABLE api_keys
+ADD COLUMN key_prefix TEXT NOT NULL DEFAULT '';
+
+CREATE UNIQUE INDEX api_keys_api_key_idx ON api_keys (api_key);
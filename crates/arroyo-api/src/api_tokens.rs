@@ -0,0 +1,166 @@
+use crate::auth::generate_api_token;
+use crate::queries::api_queries;
+use crate::queries::api_queries::DbApiKey;
+use crate::rest::AppState;
+use crate::rest_utils::{
+    authenticate, bad_request, internal_server_error, map_insert_err, not_found, ApiError,
+    BearerAuth, ErrorResp,
+};
+use crate::{audit, to_micros};
+use arroyo_rpc::api_types::audit::AuditAction;
+use arroyo_rpc::api_types::auth::{ApiToken, ApiTokenPost, CreatedApiToken, Role};
+use arroyo_rpc::api_types::ApiTokenCollection;
+use arroyo_rpc::public_ids::{generate_id, IdTypes};
+use axum::extract::{Path, State};
+use axum::Json;
+use axum_extra::extract::WithRejection;
+use std::str::FromStr;
+
+impl From<DbApiKey> for ApiToken {
+    fn from(val: DbApiKey) -> Self {
+        ApiToken {
+            id: val.pub_id,
+            name: val.name,
+            user_id: val.user_id,
+            role: Role::from_str(&val.role).unwrap_or(Role::Viewer),
+            prefix: val.key_prefix,
+            created_at: to_micros(val.created_at),
+        }
+    }
+}
+
+/// Create an API token
+#[utoipa::path(
+    post,
+    path = "/v1/api_tokens",
+    tag = "api_tokens",
+    request_body = ApiTokenPost,
+    responses(
+        (status = 200, description = "Created API token", body = CreatedApiToken),
+    ),
+)]
+pub async fn create_api_token(
+    State(state): State<AppState>,
+    bearer_auth: BearerAuth,
+    WithRejection(Json(req), _): WithRejection<Json<ApiTokenPost>, ApiError>,
+) -> Result<Json<CreatedApiToken>, ErrorResp> {
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Admin)?;
+
+    if req.name.trim().is_empty() {
+        return Err(bad_request("API token name must not be empty"));
+    }
+
+    let role = req.role.unwrap_or(auth_data.role);
+
+    let client = state.database.client().await?;
+
+    let pub_id = generate_id(IdTypes::ApiKey);
+    let (secret, hash, prefix) = generate_api_token();
+
+    api_queries::execute_create_api_key(
+        &client,
+        &pub_id,
+        &auth_data.user_id,
+        &auth_data.organization_id,
+        &auth_data.user_id,
+        &req.name,
+        &hash,
+        &prefix,
+        &role.to_string(),
+    )
+    .await
+    .map_err(|e| map_insert_err("API token", e))?;
+
+    audit::record(
+        &state.database,
+        &auth_data,
+        AuditAction::CreateApiToken,
+        &pub_id,
+        &ApiTokenPost {
+            name: req.name,
+            role: Some(role),
+        },
+    )
+    .await;
+
+    let token =
+        api_queries::fetch_get_api_key_by_pub_id(&client, &auth_data.organization_id, &pub_id)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| internal_server_error("Failed to fetch created API token"))?
+            .into();
+
+    Ok(Json(CreatedApiToken { token, secret }))
+}
+
+/// List API tokens
+#[utoipa::path(
+    get,
+    path = "/v1/api_tokens",
+    tag = "api_tokens",
+    responses(
+        (status = 200, description = "List of API tokens", body = ApiTokenCollection),
+    ),
+)]
+pub async fn get_api_tokens(
+    State(state): State<AppState>,
+    bearer_auth: BearerAuth,
+) -> Result<Json<ApiTokenCollection>, ErrorResp> {
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Admin)?;
+
+    let tokens = api_queries::fetch_get_api_keys(
+        &state.database.client().await?,
+        &auth_data.organization_id,
+    )
+    .await?;
+
+    Ok(Json(ApiTokenCollection {
+        data: tokens.into_iter().map(|t| t.into()).collect(),
+    }))
+}
+
+/// Revoke an API token
+#[utoipa::path(
+    delete,
+    path = "/v1/api_tokens/{id}",
+    tag = "api_tokens",
+    params(
+        ("id" = String, Path, description = "API token id")
+    ),
+    responses(
+        (status = 200, description = "Revoked API token"),
+    ),
+)]
+pub async fn delete_api_token(
+    State(state): State<AppState>,
+    bearer_auth: BearerAuth,
+    Path(token_pub_id): Path<String>,
+) -> Result<(), ErrorResp> {
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Admin)?;
+
+    let count = api_queries::execute_delete_api_key(
+        &state.database.client().await?,
+        &auth_data.organization_id,
+        &token_pub_id,
+    )
+    .await?;
+
+    if count != 1 {
+        return Err(not_found("API token"));
+    }
+
+    audit::record(
+        &state.database,
+        &auth_data,
+        AuditAction::RevokeApiToken,
+        &token_pub_id,
+        &(),
+    )
+    .await;
+
+    Ok(())
+}
@@ -0,0 +1,208 @@
+use crate::queries::api_queries;
+use crate::queries::api_queries::DbAuditLogEntry;
+use crate::rest::AppState;
+use crate::rest_utils::{
+    authenticate, log_and_map, paginate_results, validate_pagination_params, BearerAuth, ErrorResp,
+};
+use crate::{to_micros, AuthData};
+use arroyo_rpc::api_types::audit::{AuditAction, AuditLogEntry};
+use arroyo_rpc::api_types::auth::Role;
+use arroyo_rpc::api_types::{AuditLogCollection, PaginationQueryParams};
+use arroyo_rpc::public_ids::{generate_id, IdTypes};
+use arroyo_rpc::secrets::REDACTED;
+use axum::extract::{Query, State};
+use axum::Json;
+use cornucopia_async::DatabaseSource;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::str::FromStr;
+use tracing::error;
+
+/// Request fields that may carry credentials, like connection configs and notification
+/// headers; their values are never written to the audit log
+const REDACTED_FIELDS: &[&str] = &["config", "headers", "webhookUrl"];
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                if REDACTED_FIELDS.contains(&name.as_str()) && !field.is_null() {
+                    *field = Value::String(REDACTED.to_string());
+                } else {
+                    redact(field);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// The top-level fields that differ between `before` and `after`, as
+/// `{"field": {"before": ..., "after": ...}}`
+fn diff(before: Value, after: Value) -> Value {
+    let (Value::Object(mut before), Value::Object(after)) = (before, after) else {
+        return json!({ "before": before, "after": after });
+    };
+
+    let mut changes = Map::new();
+    for (name, after) in after {
+        let before = before.remove(&name).unwrap_or(Value::Null);
+        if before != after {
+            changes.insert(name, json!({ "before": before, "after": after }));
+        }
+    }
+    for (name, before) in before {
+        if !before.is_null() {
+            changes.insert(name, json!({ "before": before, "after": Value::Null }));
+        }
+    }
+    Value::Object(changes)
+}
+
+fn to_value(action: AuditAction, value: &impl Serialize) -> Option<Value> {
+    match serde_json::to_value(value) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            error!(
+                "Failed to serialize audit log request for {}: {:?}",
+                action, e
+            );
+            None
+        }
+    }
+}
+
+/// Records a mutating API call in the audit log; actions without a request body should pass
+/// `&()`. Sensitive fields of the request are redacted. Failures are logged rather than
+/// returned, as by the time this is called the action has already been performed.
+pub(crate) async fn record(
+    db: &DatabaseSource,
+    auth_data: &AuthData,
+    action: AuditAction,
+    target_id: &str,
+    request: &impl Serialize,
+) {
+    let request = to_value(action, request)
+        .filter(|request| !request.is_null())
+        .map(|mut request| {
+            redact(&mut request);
+            request
+        });
+
+    write(db, auth_data, action, target_id, request).await;
+}
+
+/// Records an update in the audit log as the fields of the target that changed between
+/// `before` and `after`, with sensitive fields redacted
+pub(crate) async fn record_update(
+    db: &DatabaseSource,
+    auth_data: &AuthData,
+    action: AuditAction,
+    target_id: &str,
+    before: &impl Serialize,
+    after: &impl Serialize,
+) {
+    let changes = to_value(action, before)
+        .zip(to_value(action, after))
+        .map(|(before, after)| {
+            let mut changes = diff(before, after);
+            redact(&mut changes);
+            changes
+        });
+
+    write(db, auth_data, action, target_id, changes).await;
+}
+
+async fn write(
+    db: &DatabaseSource,
+    auth_data: &AuthData,
+    action: AuditAction,
+    target_id: &str,
+    request: Option<Value>,
+) {
+    let result = match db.client().await {
+        Ok(client) => api_queries::execute_create_audit_log_entry(
+            &client,
+            &generate_id(IdTypes::AuditLogEntry),
+            &auth_data.organization_id,
+            &auth_data.user_id,
+            &auth_data.role.to_string(),
+            &action.to_string(),
+            &target_id.to_string(),
+            &request,
+        )
+        .await
+        .map(|_| ()),
+        Err(e) => Err(e),
+    };
+
+    if let Err(e) = result {
+        error!(
+            "Failed to write audit log entry for {} on {}: {:?}",
+            action, target_id, e
+        );
+    }
+}
+
+impl TryFrom<DbAuditLogEntry> for AuditLogEntry {
+    type Error = String;
+
+    fn try_from(val: DbAuditLogEntry) -> Result<Self, Self::Error> {
+        Ok(AuditLogEntry {
+            id: val.pub_id,
+            actor: val.actor,
+            actor_role: Role::from_str(&val.actor_role)
+                .map_err(|_| format!("invalid role '{}'", val.actor_role))?,
+            action: AuditAction::from_str(&val.action)
+                .map_err(|_| format!("invalid action '{}'", val.action))?,
+            target_id: val.target_id,
+            request: val.request,
+            created_at: to_micros(val.created_at),
+        })
+    }
+}
+
+/// List the audit log of control-plane actions
+#[utoipa::path(
+    get,
+    path = "/v1/audit",
+    tag = "audit",
+    params(
+        PaginationQueryParams
+    ),
+    responses(
+        (status = 200, description = "Got audit log", body = AuditLogCollection),
+    ),
+)]
+pub async fn get_audit_log(
+    State(state): State<AppState>,
+    bearer_auth: BearerAuth,
+    query_params: Query<PaginationQueryParams>,
+) -> Result<Json<AuditLogCollection>, ErrorResp> {
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Admin)?;
+
+    let (starting_after, limit) =
+        validate_pagination_params(query_params.starting_after.clone(), query_params.limit)?;
+
+    let entries = api_queries::fetch_get_audit_log(
+        &state.database.client().await?,
+        &auth_data.organization_id,
+        &starting_after.unwrap_or_default(),
+        &(limit as i32),
+    )
+    .await
+    .map_err(log_and_map)?
+    .into_iter()
+    .map(|e| e.try_into())
+    .collect::<Result<Vec<AuditLogEntry>, _>>()
+    .map_err(log_and_map)?;
+
+    let (entries, has_more) = paginate_results(entries, limit);
+
+    Ok(Json(AuditLogCollection {
+        data: entries,
+        has_more,
+    }))
+}
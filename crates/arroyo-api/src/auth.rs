@@ -0,0 +1,389 @@
+use crate::queries::api_queries;
+use crate::rest_utils::{forbidden, log_and_map, unauthorized, ErrorResp};
+use crate::{AuthData, OrgMetadata};
+use anyhow::{anyhow, bail, Context};
+use arroyo_rpc::api_types::auth::Role;
+use arroyo_types::{
+    string_config, API_ADMIN_TOKEN_ENV, API_AUTH_MODE_ENV, API_JWKS_FILE_ENV, API_JWT_AUDIENCE_ENV,
+    API_JWT_DEFAULT_ORG_ENV, API_JWT_ISSUER_ENV,
+};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use cornucopia_async::Database;
+use jwt_simple::prelude::*;
+use once_cell::sync::OnceCell;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::str::FromStr;
+use tracing::{info, warn};
+
+/// Prefix for API tokens issued by Arroyo, used to distinguish them from JWTs
+const API_TOKEN_PREFIX: &str = "arroyo_";
+const API_TOKEN_LENGTH: usize = 40;
+const API_TOKEN_DISPLAY_LENGTH: usize = 12;
+
+/// Organization used for requests that don't carry one (unauthenticated mode and the admin
+/// token)
+const DEFAULT_ORGANIZATION: &str = "org";
+const LOCAL_USER: &str = "user";
+const ADMIN_USER: &str = "admin";
+const DEFAULT_JWT_ROLE: Role = Role::Viewer;
+
+static AUTH_CONFIG: OnceCell<AuthConfig> = OnceCell::new();
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum AuthMode {
+    /// No authentication; requests without a token are treated as the local admin user
+    None,
+    /// Requests must present an API token (or the configured admin token)
+    Token,
+    /// Requests must present an API token or a JWT signed by a key in the configured JWKS
+    Jwt,
+}
+
+struct AuthConfig {
+    mode: AuthMode,
+    admin_token_hash: Option<String>,
+    jwt: Option<JwtVerifier>,
+}
+
+impl AuthConfig {
+    fn from_env() -> anyhow::Result<Self> {
+        let mode = match string_config(API_AUTH_MODE_ENV, "none")
+            .to_lowercase()
+            .as_str()
+        {
+            "none" => AuthMode::None,
+            "token" => AuthMode::Token,
+            "jwt" => AuthMode::Jwt,
+            other => bail!(
+                "invalid value for {}: '{}'; expected one of 'none', 'token', or 'jwt'",
+                API_AUTH_MODE_ENV,
+                other
+            ),
+        };
+
+        let admin_token_hash = std::env::var(API_ADMIN_TOKEN_ENV)
+            .ok()
+            .filter(|t| !t.is_empty())
+            .map(|t| hash_token(&t));
+
+        let jwt = if mode == AuthMode::Jwt {
+            let path = std::env::var(API_JWKS_FILE_ENV).map_err(|_| {
+                anyhow!(
+                    "{} must be set when using jwt authentication",
+                    API_JWKS_FILE_ENV
+                )
+            })?;
+            Some(JwtVerifier::load(&path)?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            mode,
+            admin_token_hash,
+            jwt,
+        })
+    }
+}
+
+/// Loads and validates the authentication configuration from the environment. This is called
+/// on API startup so that misconfiguration is reported immediately rather than on first request.
+pub(crate) fn init_auth() -> anyhow::Result<()> {
+    let config = AUTH_CONFIG.get_or_try_init(AuthConfig::from_env)?;
+    info!("API authentication mode: {:?}", config.mode);
+    Ok(())
+}
+
+fn auth_config() -> &'static AuthConfig {
+    AUTH_CONFIG
+        .get_or_init(|| AuthConfig::from_env().expect("invalid API authentication configuration"))
+}
+
+fn local_org_metadata() -> OrgMetadata {
+    OrgMetadata {
+        can_create_programs: true,
+        max_nexmark_qps: f64::MAX,
+        max_impulse_qps: f64::MAX,
+        max_parallelism: u32::MAX,
+        max_operators: u32::MAX,
+        max_running_jobs: u32::MAX,
+        kafka_qps: u32::MAX,
+    }
+}
+
+impl AuthData {
+    /// Returns a 403 error unless the authenticated user has at least the given role
+    pub(crate) fn require_role(&self, role: Role) -> Result<(), ErrorResp> {
+        if self.role < role {
+            return Err(forbidden(format!(
+                "This action requires the {} role, but you have the {} role",
+                role, self.role
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn auth_data(user_id: String, organization_id: String, role: Role) -> AuthData {
+    AuthData {
+        user_id,
+        organization_id,
+        role,
+        org_metadata: local_org_metadata(),
+    }
+}
+
+pub(crate) fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Generates a new API token, returning the secret, its hash, and a displayable prefix
+pub(crate) fn generate_api_token() -> (String, String, String) {
+    let secret: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(API_TOKEN_LENGTH)
+        .map(char::from)
+        .collect();
+    let token = format!("{}{}", API_TOKEN_PREFIX, secret);
+    let hash = hash_token(&token);
+    let prefix = token[..API_TOKEN_DISPLAY_LENGTH].to_string();
+
+    (token, hash, prefix)
+}
+
+pub(crate) async fn authenticate(
+    client: &Database<'_>,
+    token: Option<&str>,
+) -> Result<AuthData, ErrorResp> {
+    let config = auth_config();
+
+    let Some(token) = token else {
+        return if config.mode == AuthMode::None {
+            Ok(auth_data(
+                LOCAL_USER.to_string(),
+                DEFAULT_ORGANIZATION.to_string(),
+                Role::Admin,
+            ))
+        } else {
+            Err(unauthorized("Missing bearer token"))
+        };
+    };
+
+    let hash = hash_token(token);
+
+    if config.admin_token_hash.as_ref() == Some(&hash) {
+        return Ok(auth_data(
+            ADMIN_USER.to_string(),
+            DEFAULT_ORGANIZATION.to_string(),
+            Role::Admin,
+        ));
+    }
+
+    if token.starts_with(API_TOKEN_PREFIX) {
+        let key = api_queries::fetch_get_api_key(client, &hash)
+            .await
+            .map_err(log_and_map)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| unauthorized("Invalid API token"))?;
+
+        let role = Role::from_str(&key.role)
+            .map_err(|_| log_and_map(format!("API token has invalid role '{}'", key.role)))?;
+
+        return Ok(auth_data(key.user_id, key.organization_id, role));
+    }
+
+    match (&config.jwt, config.mode) {
+        (Some(verifier), _) => verifier.verify(token),
+        // bearer tokens that aren't ours are ignored when authentication is disabled
+        (None, AuthMode::None) => Ok(auth_data(
+            LOCAL_USER.to_string(),
+            DEFAULT_ORGANIZATION.to_string(),
+            Role::Admin,
+        )),
+        (None, _) => Err(unauthorized("Invalid API token")),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArroyoClaims {
+    #[serde(default, alias = "organization_id")]
+    org_id: Option<String>,
+    #[serde(default)]
+    role: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Jwks {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    alg: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+    crv: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+enum VerificationKey {
+    Rs256(RS256PublicKey),
+    Rs384(RS384PublicKey),
+    Rs512(RS512PublicKey),
+    Es256(ES256PublicKey),
+}
+
+impl VerificationKey {
+    fn algorithm(&self) -> &'static str {
+        match self {
+            VerificationKey::Rs256(_) => "RS256",
+            VerificationKey::Rs384(_) => "RS384",
+            VerificationKey::Rs512(_) => "RS512",
+            VerificationKey::Es256(_) => "ES256",
+        }
+    }
+
+    fn verify(
+        &self,
+        token: &str,
+        options: VerificationOptions,
+    ) -> Result<JWTClaims<ArroyoClaims>, jwt_simple::Error> {
+        match self {
+            VerificationKey::Rs256(k) => k.verify_token(token, Some(options)),
+            VerificationKey::Rs384(k) => k.verify_token(token, Some(options)),
+            VerificationKey::Rs512(k) => k.verify_token(token, Some(options)),
+            VerificationKey::Es256(k) => k.verify_token(token, Some(options)),
+        }
+    }
+}
+
+fn decode_component(jwk: &Jwk, name: &str, value: &Option<String>) -> anyhow::Result<Vec<u8>> {
+    let value = value.as_ref().ok_or_else(|| {
+        anyhow!(
+            "JWK {} is missing required field '{}'",
+            jwk.kid.as_deref().unwrap_or("<no kid>"),
+            name
+        )
+    })?;
+
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .with_context(|| format!("invalid base64 in JWK field '{}'", name))
+}
+
+impl TryFrom<&Jwk> for VerificationKey {
+    type Error = anyhow::Error;
+
+    fn try_from(jwk: &Jwk) -> anyhow::Result<Self> {
+        match jwk.kty.as_str() {
+            "RSA" => {
+                let n = decode_component(jwk, "n", &jwk.n)?;
+                let e = decode_component(jwk, "e", &jwk.e)?;
+                Ok(match jwk.alg.as_deref().unwrap_or("RS256") {
+                    "RS256" => VerificationKey::Rs256(RS256PublicKey::from_components(&n, &e)?),
+                    "RS384" => VerificationKey::Rs384(RS384PublicKey::from_components(&n, &e)?),
+                    "RS512" => VerificationKey::Rs512(RS512PublicKey::from_components(&n, &e)?),
+                    alg => bail!("unsupported RSA JWK algorithm '{}'", alg),
+                })
+            }
+            "EC" => {
+                if jwk.crv.as_deref() != Some("P-256") {
+                    bail!(
+                        "unsupported EC JWK curve '{}'; only P-256 is supported",
+                        jwk.crv.as_deref().unwrap_or_default()
+                    );
+                }
+                let mut point = vec![0x04];
+                point.extend(decode_component(jwk, "x", &jwk.x)?);
+                point.extend(decode_component(jwk, "y", &jwk.y)?);
+                Ok(VerificationKey::Es256(ES256PublicKey::from_bytes(&point)?))
+            }
+            kty => bail!("unsupported JWK key type '{}'", kty),
+        }
+    }
+}
+
+struct JwtVerifier {
+    keys: Vec<(Option<String>, VerificationKey)>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    default_org: Option<String>,
+}
+
+impl JwtVerifier {
+    fn load(path: &str) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read JWKS file {}", path))?;
+        let jwks: Jwks = serde_json::from_str(&contents)
+            .with_context(|| format!("failed to parse JWKS file {}", path))?;
+
+        let mut keys = vec![];
+        for jwk in &jwks.keys {
+            match VerificationKey::try_from(jwk) {
+                Ok(key) => keys.push((jwk.kid.clone(), key)),
+                Err(e) => warn!("Skipping JWK in {}: {:?}", path, e),
+            }
+        }
+
+        if keys.is_empty() {
+            bail!("JWKS file {} does not contain any supported keys", path);
+        }
+
+        Ok(Self {
+            keys,
+            issuer: std::env::var(API_JWT_ISSUER_ENV).ok(),
+            audience: std::env::var(API_JWT_AUDIENCE_ENV).ok(),
+            default_org: std::env::var(API_JWT_DEFAULT_ORG_ENV)
+                .ok()
+                .filter(|org| !org.is_empty()),
+        })
+    }
+
+    fn verify(&self, token: &str) -> Result<AuthData, ErrorResp> {
+        let metadata =
+            Token::decode_metadata(token).map_err(|_| unauthorized("Invalid bearer token"))?;
+
+        let options = VerificationOptions {
+            allowed_issuers: self.issuer.as_ref().map(|i| HashSet::from([i.clone()])),
+            allowed_audiences: self.audience.as_ref().map(|a| HashSet::from([a.clone()])),
+            ..Default::default()
+        };
+
+        let claims = self
+            .keys
+            .iter()
+            .filter(|(kid, key)| {
+                key.algorithm() == metadata.algorithm()
+                    && (metadata.key_id().is_none() || kid.as_deref() == metadata.key_id())
+            })
+            .find_map(|(_, key)| key.verify(token, options.clone()).ok())
+            .ok_or_else(|| unauthorized("Invalid bearer token"))?;
+
+        let user_id = claims
+            .subject
+            .ok_or_else(|| unauthorized("JWT is missing the 'sub' claim"))?;
+
+        let role = match &claims.custom.role {
+            Some(role) => Role::from_str(role)
+                .map_err(|_| unauthorized(format!("JWT contains unknown role '{}'", role)))?,
+            None => DEFAULT_JWT_ROLE,
+        };
+
+        let org_id = claims
+            .custom
+            .org_id
+            .or_else(|| self.default_org.clone())
+            .ok_or_else(|| unauthorized("JWT is missing the 'org_id' claim"))?;
+
+        Ok(auth_data(user_id, org_id, role))
+    }
+}
@@ -1,23 +1,31 @@
 use axum::extract::{Path, State};
 use axum::Json;
 use axum_extra::extract::WithRejection;
+use serde_json::json;
 use std::collections::BTreeMap;
 
 use arroyo_connectors::connector_for_type;
+use arroyo_rpc::api_types::audit::AuditAction;
+use arroyo_rpc::api_types::auth::Role;
 use arroyo_rpc::api_types::connections::{
-    ConnectionAutocompleteResp, ConnectionProfile, ConnectionProfilePost, TestSourceMessage,
+    ConnectionAutocompleteResp, ConnectionProfile, ConnectionProfilePatch, ConnectionProfilePost,
+    ConnectionProfileUpdate, ConnectionTable, TestSourceMessage,
 };
 use arroyo_rpc::api_types::ConnectionProfileCollection;
+use arroyo_rpc::secrets::{encrypt_sensitive, redact_sensitive, restore_redacted};
+use time::OffsetDateTime;
 use tracing::warn;
 
 use arroyo_rpc::public_ids::{generate_id, IdTypes};
 
+use crate::audit;
+use crate::connection_tables::{get_all_connection_tables, TableConsumers};
 use crate::queries::api_queries;
 use crate::queries::api_queries::DbConnectionProfile;
 use crate::rest::AppState;
 use crate::rest_utils::{
-    authenticate, bad_request, log_and_map, map_delete_err, not_found, ApiError, BearerAuth,
-    ErrorResp,
+    authenticate, bad_request, in_transaction, log_and_map, map_delete_err, not_found, ApiError,
+    BearerAuth, ErrorResp,
 };
 use crate::AuthData;
 use cornucopia_async::Database;
@@ -47,6 +55,20 @@ impl TryFrom<DbConnectionProfile> for ConnectionProfile {
     }
 }
 
+fn connection_schema(connector: &str) -> Option<serde_json::Value> {
+    connector_for_type(connector)?
+        .metadata()
+        .connection_config
+        .and_then(|s| serde_json::from_str(&s).ok())
+}
+
+/// Redacts credentials from a connection profile before it is returned from the API
+pub(crate) fn redact_profile(profile: &mut ConnectionProfile) {
+    if let Some(schema) = connection_schema(&profile.connector) {
+        redact_sensitive(&schema, &mut profile.config);
+    }
+}
+
 /// Test connection profile
 #[utoipa::path(
     post,
@@ -62,7 +84,8 @@ pub async fn test_connection_profile(
     bearer_auth: BearerAuth,
     WithRejection(Json(req), _): WithRejection<Json<ConnectionProfilePost>, ApiError>,
 ) -> Result<Json<TestSourceMessage>, ErrorResp> {
-    let _auth_data = authenticate(&state.database, bearer_auth).await.unwrap();
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Admin)?;
 
     let connector = connector_for_type(&req.connector)
         .ok_or_else(|| bad_request("Unknown connector type".to_string()))?;
@@ -97,12 +120,19 @@ pub async fn create_connection_profile(
     WithRejection(Json(req), _): WithRejection<Json<ConnectionProfilePost>, ApiError>,
 ) -> Result<Json<ConnectionProfile>, ErrorResp> {
     let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Admin)?;
 
     connector_for_type(&req.connector)
         .ok_or_else(|| bad_request("Unknown connector type".to_string()))?
         .validate_config(&req.config)
         .map_err(|e| bad_request(format!("Invalid config: {:?}", e)))?;
 
+    // credentials are stored encrypted if a master key is configured
+    let mut config = req.config.clone();
+    if let Some(schema) = connection_schema(&req.connector) {
+        encrypt_sensitive(&schema, &mut config).map_err(log_and_map)?;
+    }
+
     let pub_id = generate_id(IdTypes::ConnectionProfile);
     api_queries::execute_create_connection_profile(
         &state.database.client().await?,
@@ -111,11 +141,21 @@ pub async fn create_connection_profile(
         &auth_data.user_id,
         &req.name,
         &req.connector,
-        &req.config,
+        &config,
     )
     .await?;
 
-    let connection_profile = api_queries::fetch_get_connection_profile_by_pub_id(
+    // the config may contain credentials, so it is not recorded
+    audit::record(
+        &state.database,
+        &auth_data,
+        AuditAction::CreateConnectionProfile,
+        &pub_id,
+        &json!({ "name": req.name, "connector": req.connector }),
+    )
+    .await;
+
+    let mut connection_profile = api_queries::fetch_get_connection_profile_by_pub_id(
         &state.database.client().await?,
         &auth_data.organization_id,
         &pub_id,
@@ -127,6 +167,8 @@ pub async fn create_connection_profile(
     .try_into()
     .map_err(log_and_map)?;
 
+    redact_profile(&mut connection_profile);
+
     Ok(Json(connection_profile))
 }
 
@@ -144,12 +186,136 @@ pub async fn get_connection_profiles(
     bearer_auth: BearerAuth,
 ) -> Result<Json<ConnectionProfileCollection>, ErrorResp> {
     let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Editor)?;
 
-    let data = get_all_connection_profiles(&auth_data, &state.database.client().await?).await?;
+    let mut data = get_all_connection_profiles(&auth_data, &state.database.client().await?).await?;
+    data.iter_mut().for_each(redact_profile);
 
     Ok(Json(ConnectionProfileCollection { data }))
 }
 
+/// Update a connection profile's config. Pipelines that use tables with this profile are updated
+/// to use the new config, and running pipelines are restarted from a checkpoint if requested.
+#[utoipa::path(
+    patch,
+    path = "/v1/connection_profiles/{id}",
+    tag = "connection_profiles",
+    params(
+       ("id" = String, Path, description = "Connection Profile id")
+    ),
+    request_body = ConnectionProfilePatch,
+    responses(
+       (status = 200, description = "Updated connection profile", body = ConnectionProfileUpdate),
+    ),
+)]
+pub(crate) async fn patch_connection_profile(
+    State(state): State<AppState>,
+    bearer_auth: BearerAuth,
+    Path(pub_id): Path<String>,
+    WithRejection(Json(req), _): WithRejection<Json<ConnectionProfilePatch>, ApiError>,
+) -> Result<Json<ConnectionProfileUpdate>, ErrorResp> {
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Admin)?;
+
+    let client = state.database.client().await?;
+
+    let existing = api_queries::fetch_get_connection_profile_by_pub_id(
+        &client,
+        &auth_data.organization_id,
+        &pub_id,
+    )
+    .await?
+    .into_iter()
+    .next()
+    .ok_or_else(|| not_found("Connection profile"))?;
+
+    // credentials that were redacted when the profile was read keep their stored values
+    let mut config = req.config.clone();
+    restore_redacted(&mut config, &existing.config);
+
+    connector_for_type(&existing.r#type)
+        .ok_or_else(|| bad_request("Unknown connector type".to_string()))?
+        .validate_config(&config)
+        .map_err(|e| bad_request(format!("Invalid config: {:?}", e)))?;
+
+    if let Some(schema) = connection_schema(&existing.r#type) {
+        encrypt_sensitive(&schema, &mut config).map_err(log_and_map)?;
+    }
+
+    let uses_profile = |t: &ConnectionTable| {
+        t.connection_profile
+            .as_ref()
+            .is_some_and(|p| p.id == pub_id)
+    };
+
+    // the profile and the programs of the pipelines that use it are updated together
+    let consumers = in_transaction(&client, async {
+        let old_tables: Vec<_> = get_all_connection_tables(&auth_data, &client)
+            .await?
+            .into_iter()
+            .filter(uses_profile)
+            .collect();
+
+        api_queries::execute_update_connection_profile(
+            &client,
+            &config,
+            &OffsetDateTime::now_utc(),
+            &auth_data.user_id,
+            &auth_data.organization_id,
+            &pub_id,
+        )
+        .await?;
+
+        let new_tables: Vec<_> = get_all_connection_tables(&auth_data, &client)
+            .await?
+            .into_iter()
+            .filter(uses_profile)
+            .collect();
+
+        let mut consumers = TableConsumers::default();
+        for old in &old_tables {
+            if let Some(new) = new_tables.iter().find(|t| t.pub_id == old.pub_id) {
+                consumers.update(&client, &auth_data, old, new).await?;
+            }
+        }
+        if req.restart_pipelines == Some(true) {
+            consumers.restart(&client, &auth_data).await?;
+        }
+
+        Ok(consumers)
+    })
+    .await?;
+
+    // the config may contain credentials, so it is not recorded
+    audit::record(
+        &state.database,
+        &auth_data,
+        AuditAction::UpdateConnectionProfile,
+        &pub_id,
+        &json!({ "restartPipelines": req.restart_pipelines }),
+    )
+    .await;
+
+    let mut profile: ConnectionProfile = api_queries::fetch_get_connection_profile_by_pub_id(
+        &client,
+        &auth_data.organization_id,
+        &pub_id,
+    )
+    .await?
+    .into_iter()
+    .next()
+    .ok_or_else(|| not_found("Connection profile"))?
+    .try_into()
+    .map_err(log_and_map)?;
+
+    redact_profile(&mut profile);
+
+    Ok(Json(ConnectionProfileUpdate {
+        profile,
+        pipelines: consumers.into_affected(),
+    }))
+}
+
 /// Delete a Connection Profile
 #[utoipa::path(
     delete,
@@ -168,6 +334,7 @@ pub(crate) async fn delete_connection_profile(
     Path(pub_id): Path<String>,
 ) -> Result<(), ErrorResp> {
     let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Admin)?;
 
     let deleted = api_queries::execute_delete_connection_profile(
         &state.database.client().await?,
@@ -181,6 +348,15 @@ pub(crate) async fn delete_connection_profile(
         return Err(not_found("Connection profile"));
     }
 
+    audit::record(
+        &state.database,
+        &auth_data,
+        AuditAction::DeleteConnectionProfile,
+        &pub_id,
+        &(),
+    )
+    .await;
+
     Ok(())
 }
 
@@ -202,6 +378,7 @@ pub(crate) async fn get_connection_profile_autocomplete(
     Path(pub_id): Path<String>,
 ) -> Result<Json<ConnectionAutocompleteResp>, ErrorResp> {
     let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Editor)?;
 
     let connection_profile = api_queries::fetch_get_connection_profile_by_pub_id(
         &state.database.client().await?,
@@ -5,8 +5,11 @@ use axum::response::Sse;
 use axum::Json;
 use axum_extra::extract::WithRejection;
 use futures_util::stream::Stream;
+use prost::Message;
 use serde_json::{json, Value};
+use std::collections::BTreeMap;
 use std::convert::Infallible;
+use time::OffsetDateTime;
 use tokio::sync::mpsc::channel;
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_stream::StreamExt;
@@ -15,14 +18,18 @@ use tracing::debug;
 use arroyo_connectors::confluent::ConfluentProfile;
 use arroyo_connectors::connector_for_type;
 use arroyo_connectors::kafka::{KafkaConfig, KafkaTable, SchemaRegistry};
+use arroyo_datastream::logical::OperatorName;
 use arroyo_formats::{avro, json};
-use arroyo_operator::connector::ErasedConnector;
+use arroyo_operator::connector::{Connection, ErasedConnector};
+use arroyo_rpc::api_types::audit::AuditAction;
+use arroyo_rpc::api_types::auth::Role;
 use arroyo_rpc::api_types::connections::{
-    ConnectionProfile, ConnectionSchema, ConnectionTable, ConnectionTablePost, ConnectionType,
-    SchemaDefinition,
+    AffectedPipeline, ConnectionProfile, ConnectionSchema, ConnectionTable, ConnectionTablePatch,
+    ConnectionTablePost, ConnectionTableUpdate, ConnectionType, SchemaDefinition,
 };
 use arroyo_rpc::api_types::{ConnectionTableCollection, PaginationQueryParams};
 use arroyo_rpc::formats::{AvroFormat, Format, JsonFormat};
+use arroyo_rpc::grpc::api::{ArrowProgram, ConnectorOp};
 use arroyo_rpc::public_ids::{generate_id, IdTypes};
 use arroyo_rpc::schema_resolver::{
     ConfluentSchemaRegistry, ConfluentSchemaSubjectResponse, ConfluentSchemaType,
@@ -31,13 +38,17 @@ use arroyo_types::raw_schema;
 
 use crate::rest::AppState;
 use crate::rest_utils::{
-    authenticate, bad_request, internal_server_error, log_and_map, map_delete_err, map_insert_err,
-    not_found, paginate_results, required_field, validate_pagination_params, ApiError, BearerAuth,
-    ErrorResp,
+    authenticate, bad_request, in_transaction, internal_server_error, log_and_map, map_delete_err,
+    map_insert_err, not_found, paginate_results, required_field, validate_pagination_params,
+    ApiError, BearerAuth, ErrorResp,
 };
 use crate::{
+    audit,
+    connection_profiles::redact_profile,
     queries::api_queries::{self, DbConnectionTable},
-    to_micros, AuthData,
+    to_micros,
+    types::public::RestartMode,
+    AuthData,
 };
 use cornucopia_async::{Database, DatabaseSource};
 
@@ -141,6 +152,7 @@ pub(crate) async fn delete_connection_table(
     Path(pub_id): Path<String>,
 ) -> Result<(), ErrorResp> {
     let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Editor)?;
 
     let deleted = api_queries::execute_delete_connection_table(
         &state.database.client().await?,
@@ -154,6 +166,15 @@ pub(crate) async fn delete_connection_table(
         return Err(not_found("Connection table"));
     }
 
+    audit::record(
+        &state.database,
+        &auth_data,
+        AuditAction::DeleteConnectionTable,
+        &pub_id,
+        &(),
+    )
+    .await;
+
     Ok(())
 }
 
@@ -174,6 +195,7 @@ pub(crate) async fn test_connection_table(
     WithRejection(Json(req), _): WithRejection<Json<ConnectionTablePost>, ApiError>,
 ) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ErrorResp> {
     let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Editor)?;
 
     let (connector, _, profile, schema) =
         get_and_validate_connector(&req, &auth_data, &state.database).await?;
@@ -191,6 +213,13 @@ pub(crate) async fn test_connection_table(
     ))
 }
 
+fn redact_table(mut table: ConnectionTable) -> ConnectionTable {
+    if let Some(profile) = &mut table.connection_profile {
+        redact_profile(profile);
+    }
+    table
+}
+
 fn get_connection_profile(
     c: &DbConnectionTable,
     connector: &dyn ErasedConnector,
@@ -246,6 +275,152 @@ pub(crate) async fn get_all_connection_tables(
     Ok(vec)
 }
 
+/// Pipelines that use connection tables whose config has changed, keyed by pipeline id
+#[derive(Default)]
+pub(crate) struct TableConsumers {
+    pipelines: BTreeMap<String, (AffectedPipeline, String)>,
+}
+
+impl TableConsumers {
+    /// Updates the programs of the pipelines that use a table, replacing the connector configs
+    /// built from `old` with those built from `new`. Jobs keep running with the config they were
+    /// started with until they are next restarted.
+    pub(crate) async fn update(
+        &mut self,
+        db: &Database<'_>,
+        auth: &AuthData,
+        old: &ConnectionTable,
+        new: &ConnectionTable,
+    ) -> Result<(), ErrorResp> {
+        let old_connection = table_connection(old).map_err(log_and_map)?;
+        let new_connection = table_connection(new).map_err(log_and_map)?;
+
+        let consumers = api_queries::fetch_get_connection_table_consumers(
+            db,
+            &auth.organization_id,
+            &old.pub_id,
+        )
+        .await?;
+
+        for consumer in consumers {
+            let program =
+                replace_connector_config(&consumer.program, &old_connection, &new_connection)
+                    .map_err(log_and_map)?;
+
+            if let Some(program) = &program {
+                api_queries::execute_update_pipeline_program(db, program, &consumer.pipeline_id)
+                    .await?;
+            }
+
+            let (affected, _) = self
+                .pipelines
+                .entry(consumer.pipeline_pub_id.clone())
+                .or_insert_with(|| {
+                    (
+                        AffectedPipeline {
+                            id: consumer.pipeline_pub_id,
+                            name: consumer.name,
+                            state: consumer.state,
+                            updated: false,
+                            restarted: false,
+                        },
+                        consumer.job_id,
+                    )
+                });
+            affected.updated |= program.is_some();
+        }
+
+        Ok(())
+    }
+
+    /// Restarts running pipelines whose programs were updated from a checkpoint, so that they
+    /// pick up the new config
+    pub(crate) async fn restart(
+        &mut self,
+        db: &Database<'_>,
+        auth: &AuthData,
+    ) -> Result<(), ErrorResp> {
+        for (affected, job_id) in self.pipelines.values_mut() {
+            if !affected.updated || affected.state.as_deref() != Some("Running") {
+                continue;
+            }
+
+            api_queries::execute_restart_job(
+                db,
+                &OffsetDateTime::now_utc(),
+                &auth.user_id,
+                &RestartMode::safe,
+                job_id,
+                &auth.organization_id,
+            )
+            .await?;
+
+            affected.restarted = true;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn into_affected(self) -> Vec<AffectedPipeline> {
+        self.pipelines.into_values().map(|(p, _)| p).collect()
+    }
+}
+
+/// Builds the connection that pipelines are compiled against for a saved table
+fn table_connection(table: &ConnectionTable) -> anyhow::Result<Connection> {
+    let connector = connector_for_type(&table.connector)
+        .ok_or_else(|| anyhow!("Unknown connector '{}'", table.connector))?;
+
+    connector.from_config(
+        Some(table.id),
+        &table.name,
+        &table
+            .connection_profile
+            .as_ref()
+            .map(|c| c.config.clone())
+            .unwrap_or(json!({})),
+        &table.config,
+        Some(&table.schema),
+    )
+}
+
+/// Replaces the config of connector operators that were built from `old` with the config from
+/// `new`, returning the updated program if any operators were changed. Operators are matched by
+/// the id of the table they were created from; programs compiled before operators recorded it
+/// are matched by their config.
+fn replace_connector_config(
+    program: &[u8],
+    old: &Connection,
+    new: &Connection,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut program = ArrowProgram::decode(program)?;
+    let mut replaced = false;
+
+    for node in &mut program.nodes {
+        if !matches!(
+            OperatorName::try_from(node.operator_name.as_str()),
+            Ok(OperatorName::ConnectorSource | OperatorName::ConnectorSink)
+        ) {
+            continue;
+        }
+
+        let mut op = ConnectorOp::decode(&node.operator_config[..])?;
+        let built_from_old = match op.connection_id {
+            Some(id) => Some(id) == old.id,
+            None => op.connector == old.connector && op.config == old.config,
+        };
+
+        if built_from_old {
+            op.config = new.config.clone();
+            op.description = new.description.clone();
+            node.operator_config = op.encode_to_vec();
+            replaced = true;
+        }
+    }
+
+    Ok(replaced.then(|| program.encode_to_vec()))
+}
+
 /// Create a new connection table
 #[utoipa::path(
     post,
@@ -262,6 +437,7 @@ pub async fn create_connection_table(
     WithRejection(Json(req), _): WithRejection<Json<ConnectionTablePost>, ApiError>,
 ) -> Result<Json<ConnectionTable>, ErrorResp> {
     let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Editor)?;
 
     // let transaction = client.transaction().await.map_err(log_and_map)?;
     // transaction
@@ -303,6 +479,15 @@ pub async fn create_connection_table(
 
     // transaction.commit().await.map_err(log_and_map)?;
 
+    audit::record(
+        &state.database,
+        &auth_data,
+        AuditAction::CreateConnectionTable,
+        &pub_id,
+        &req,
+    )
+    .await;
+
     let table =
         api_queries::fetch_get_connection_table(&client, &auth_data.organization_id, &pub_id)
             .await?
@@ -312,7 +497,131 @@ pub async fn create_connection_table(
             .try_into()
             .map_err(log_and_map)?;
 
-    Ok(Json(table))
+    Ok(Json(redact_table(table)))
+}
+
+/// Update a connection table's config or schema. Pipelines that use the table are updated to use
+/// the new config, and running pipelines are restarted from a checkpoint if requested. The
+/// schema can only be changed while no pipelines use the table, as their plans depend on it.
+#[utoipa::path(
+    patch,
+    path = "/v1/connection_tables/{id}",
+    tag = "connection_tables",
+    params(
+        ("id" = String, Path, description = "Connection Table id")
+    ),
+    request_body = ConnectionTablePatch,
+    responses(
+        (status = 200, description = "Updated connection table", body = ConnectionTableUpdate),
+    ),
+)]
+pub(crate) async fn patch_connection_table(
+    State(state): State<AppState>,
+    bearer_auth: BearerAuth,
+    Path(pub_id): Path<String>,
+    WithRejection(Json(req), _): WithRejection<Json<ConnectionTablePatch>, ApiError>,
+) -> Result<Json<ConnectionTableUpdate>, ErrorResp> {
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Editor)?;
+
+    let client = state.database.client().await?;
+
+    let existing =
+        api_queries::fetch_get_connection_table(&client, &auth_data.organization_id, &pub_id)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| not_found("Connection table"))?;
+    let existing_schema = existing.schema.clone();
+    let old: ConnectionTable = existing.try_into().map_err(log_and_map)?;
+
+    // validate the updated table in the same way as a new one
+    let post = ConnectionTablePost {
+        name: old.name.clone(),
+        connector: old.connector.clone(),
+        connection_profile_id: old.connection_profile.as_ref().map(|p| p.id.clone()),
+        config: req.config.clone().unwrap_or_else(|| old.config.clone()),
+        schema: req.schema.clone(),
+    };
+
+    let (connector, _, profile, schema) =
+        get_and_validate_connector(&post, &auth_data, &state.database).await?;
+
+    let table_type = connector
+        .table_type(&profile, &post.config)
+        .map_err(|e| bad_request(format!("Failed to parse config: {:?}", e)))?;
+
+    if table_type != old.table_type && old.consumers > 0 {
+        return Err(bad_request(format!(
+            "Connection table '{}' is used by pipelines, so it cannot be changed from a {} to a {}",
+            old.name, old.table_type, table_type
+        )));
+    }
+
+    let schema = match schema {
+        Some(schema) => {
+            if schema.definition.is_none() && schema.inferred != Some(true) {
+                return Err(required_field("schema.definition"));
+            }
+            let schema = serde_json::to_value(schema).unwrap();
+            if Some(&schema) != existing_schema.as_ref() && old.consumers > 0 {
+                return Err(bad_request(format!(
+                    "Connection table '{}' is used by pipelines, so its schema cannot be changed",
+                    old.name
+                )));
+            }
+            Some(schema)
+        }
+        None => existing_schema,
+    };
+
+    // the table and the programs of the pipelines that use it are updated together
+    let (table, consumers) = in_transaction(&client, async {
+        api_queries::execute_update_connection_table(
+            &client,
+            &table_type.to_string(),
+            &post.config,
+            &schema,
+            &OffsetDateTime::now_utc(),
+            &auth_data.user_id,
+            &auth_data.organization_id,
+            &pub_id,
+        )
+        .await?;
+
+        let table: ConnectionTable =
+            api_queries::fetch_get_connection_table(&client, &auth_data.organization_id, &pub_id)
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| not_found("Connection table"))?
+                .try_into()
+                .map_err(log_and_map)?;
+
+        let mut consumers = TableConsumers::default();
+        consumers.update(&client, &auth_data, &old, &table).await?;
+        if req.restart_pipelines == Some(true) {
+            consumers.restart(&client, &auth_data).await?;
+        }
+
+        Ok((table, consumers))
+    })
+    .await?;
+
+    audit::record_update(
+        &state.database,
+        &auth_data,
+        AuditAction::UpdateConnectionTable,
+        &pub_id,
+        &old,
+        &table,
+    )
+    .await;
+
+    Ok(Json(ConnectionTableUpdate {
+        table: redact_table(table),
+        pipelines: consumers.into_affected(),
+    }))
 }
 
 impl TryInto<ConnectionTable> for DbConnectionTable {
@@ -419,6 +728,7 @@ pub(crate) async fn get_connection_tables(
             result
         })
         .filter_map(Result::ok)
+        .map(redact_table)
         .collect();
 
     Ok(Json(ConnectionTableCollection {
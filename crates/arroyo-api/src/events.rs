@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::time::{Duration, Instant, SystemTime};
+
+use arroyo_rpc::api_types::pipelines::{Job, JobLogMessage, JobMetricsSnapshot, PipelineEvent};
+use arroyo_rpc::api_types::PipelineEventsQueryParams;
+use axum::extract::{Path, Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use cornucopia_async::{Database, DatabaseSource};
+use futures_util::stream::Stream;
+use tokio::sync::mpsc::Sender;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{info, warn};
+
+use crate::jobs::to_api_checkpoints;
+use crate::metrics::fetch_job_metrics;
+use crate::pipelines::query_pipeline_by_pub_id;
+use crate::queries::api_queries;
+use crate::rest::AppState;
+use crate::rest_utils::{authenticate, log_and_map, BearerAuth, ErrorResp};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_METRICS_INTERVAL_SECS: u64 = 5;
+const PAGE_SIZE: i32 = 50;
+
+/// Tracks what has already been sent on a pipeline's event stream, so that each poll only sends
+/// what has changed since the last one
+#[derive(Default)]
+struct EventTracker {
+    jobs: HashMap<String, Job>,
+    /// the epoch of the last completed checkpoint sent for each job
+    last_checkpoint: HashMap<String, u32>,
+    /// the id of the last error sent for each job
+    last_error: HashMap<String, String>,
+}
+
+impl EventTracker {
+    /// Returns the events since the last poll, or None if the pipeline no longer exists.
+    /// Checkpoints and errors that already existed when the stream was opened are not sent.
+    async fn poll(
+        &mut self,
+        db: &DatabaseSource,
+        organization_id: &str,
+        pipeline_pub_id: &str,
+    ) -> Result<Option<Vec<PipelineEvent>>, ErrorResp> {
+        let client = db.client().await?;
+
+        let jobs: Vec<Job> =
+            api_queries::fetch_get_pipeline_jobs(&client, &organization_id, &pipeline_pub_id)
+                .await?
+                .into_iter()
+                .map(|j| j.into())
+                .collect();
+
+        if jobs.is_empty() {
+            return Ok(None);
+        }
+
+        let mut events = vec![];
+
+        for job in jobs {
+            if !self.last_checkpoint.contains_key(&job.id) {
+                // start from the latest checkpoint and error, rather than replaying the history
+                let latest = api_queries::fetch_get_latest_completed_checkpoint(
+                    &client,
+                    &job.id,
+                    &organization_id,
+                )
+                .await
+                .map_err(log_and_map)?;
+                self.last_checkpoint.insert(
+                    job.id.clone(),
+                    latest.first().map(|c| c.epoch as u32).unwrap_or(0),
+                );
+
+                let latest = api_queries::fetch_get_operator_errors(
+                    &client,
+                    &organization_id,
+                    &job.id,
+                    &"",
+                    &1,
+                )
+                .await
+                .map_err(log_and_map)?;
+                self.last_error.insert(
+                    job.id.clone(),
+                    latest
+                        .into_iter()
+                        .next()
+                        .map(|e| e.pub_id)
+                        .unwrap_or_default(),
+                );
+            }
+
+            self.poll_checkpoints(&client, organization_id, &job.id, &mut events)
+                .await?;
+            self.poll_errors(&client, organization_id, &job.id, &mut events)
+                .await?;
+
+            if self.jobs.get(&job.id) != Some(&job) {
+                self.jobs.insert(job.id.clone(), job.clone());
+                events.push(PipelineEvent::JobState(job));
+            }
+        }
+
+        Ok(Some(events))
+    }
+
+    /// Pages through the checkpoints that have completed since the last one that was sent
+    async fn poll_checkpoints(
+        &mut self,
+        client: &Database<'_>,
+        organization_id: &str,
+        job_id: &str,
+        events: &mut Vec<PipelineEvent>,
+    ) -> Result<(), ErrorResp> {
+        let last = self.last_checkpoint.get_mut(job_id).unwrap();
+
+        loop {
+            let completed = api_queries::fetch_get_completed_checkpoints_after(
+                client,
+                &job_id,
+                &organization_id,
+                &(*last as i32),
+                &PAGE_SIZE,
+            )
+            .await
+            .map_err(log_and_map)?;
+
+            let page_len = completed.len();
+            if let Some(c) = completed.last() {
+                *last = c.epoch as u32;
+            }
+
+            events.extend(
+                to_api_checkpoints(job_id, completed)
+                    .await?
+                    .into_iter()
+                    .map(PipelineEvent::CheckpointCompleted),
+            );
+
+            if page_len < PAGE_SIZE as usize {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Pages through the errors that have been logged since the last one that was sent
+    async fn poll_errors(
+        &mut self,
+        client: &Database<'_>,
+        organization_id: &str,
+        job_id: &str,
+        events: &mut Vec<PipelineEvent>,
+    ) -> Result<(), ErrorResp> {
+        let last = self.last_error.get_mut(job_id).unwrap();
+
+        loop {
+            let errors: Vec<JobLogMessage> = api_queries::fetch_get_operator_errors_after(
+                client,
+                &organization_id,
+                &job_id,
+                &last.as_str(),
+                &PAGE_SIZE,
+            )
+            .await
+            .map_err(log_and_map)?
+            .into_iter()
+            .map(|m| m.into())
+            .collect();
+
+            let page_len = errors.len();
+            if let Some(e) = errors.last() {
+                *last = e.id.clone();
+            }
+
+            events.extend(errors.into_iter().map(PipelineEvent::JobError));
+
+            if page_len < PAGE_SIZE as usize {
+                return Ok(());
+            }
+        }
+    }
+
+    fn running_jobs(&self) -> impl Iterator<Item = &str> {
+        self.jobs
+            .values()
+            .filter(|j| j.state == "Running")
+            .map(|j| j.id.as_str())
+    }
+}
+
+fn to_sse(event: &PipelineEvent) -> Event {
+    let e = Event::default().event(event.event_type());
+    match event {
+        PipelineEvent::JobState(job) => e.json_data(job),
+        PipelineEvent::CheckpointCompleted(checkpoint) => e.json_data(checkpoint),
+        PipelineEvent::JobError(error) => e.json_data(error),
+        PipelineEvent::Metrics(metrics) => e.json_data(metrics),
+    }
+    .unwrap()
+}
+
+async fn send_all(
+    tx: &Sender<Result<Event, Infallible>>,
+    events: impl IntoIterator<Item = PipelineEvent>,
+) -> bool {
+    for event in events {
+        if tx.send(Ok(to_sse(&event))).await.is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Subscribe to a pipeline's events
+///
+/// Streams changes to the pipeline's jobs, completed checkpoints, new errors and periodic
+/// metric snapshots as 'text/event-stream'. The SSE event type is one of `job_state`,
+/// `checkpoint_completed`, `job_error` or `metrics`, and the data is the corresponding
+/// `PipelineEvent` payload. The current state of each job is sent when the stream is opened.
+#[utoipa::path(
+    get,
+    path = "/v1/pipelines/{id}/events",
+    tag = "pipelines",
+    params(
+        ("id" = String, Path, description = "Pipeline id"),
+        PipelineEventsQueryParams,
+    ),
+    responses(
+        (status = 200, description = "Pipeline events as 'text/event-stream'"),
+    ),
+)]
+pub async fn get_pipeline_events(
+    State(state): State<AppState>,
+    bearer_auth: BearerAuth,
+    Path(pipeline_pub_id): Path<String>,
+    Query(params): Query<PipelineEventsQueryParams>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ErrorResp> {
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+
+    query_pipeline_by_pub_id(
+        &pipeline_pub_id,
+        &state.database.client().await?,
+        &auth_data,
+    )
+    .await?;
+
+    let metrics_interval = Duration::from_secs(
+        params
+            .metrics_interval_secs
+            .unwrap_or(DEFAULT_METRICS_INTERVAL_SECS),
+    );
+
+    let (tx, rx) = tokio::sync::mpsc::channel(32);
+
+    tokio::spawn(async move {
+        let mut tracker = EventTracker::default();
+        let mut last_metrics = Instant::now();
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = tx.closed() => break,
+            }
+
+            match tracker
+                .poll(
+                    &state.database,
+                    &auth_data.organization_id,
+                    &pipeline_pub_id,
+                )
+                .await
+            {
+                Ok(Some(events)) => {
+                    if !send_all(&tx, events).await {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    warn!(
+                        message = "failed to poll pipeline events",
+                        pipeline_id = pipeline_pub_id,
+                        error = format!("{:?}", e)
+                    );
+                    continue;
+                }
+            }
+
+            if metrics_interval.is_zero() || last_metrics.elapsed() < metrics_interval {
+                continue;
+            }
+            last_metrics = Instant::now();
+
+            let mut snapshots = vec![];
+            for job_id in tracker.running_jobs() {
+                match fetch_job_metrics(state.controller_addr.clone(), job_id.to_string()).await {
+                    Ok(data) => snapshots.push(PipelineEvent::Metrics(JobMetricsSnapshot {
+                        job_id: job_id.to_string(),
+                        timestamp: arroyo_types::to_micros(SystemTime::now()),
+                        data,
+                    })),
+                    Err(e) => {
+                        warn!(
+                            message = "failed to fetch metrics for pipeline events",
+                            job_id,
+                            error = format!("{:?}", e)
+                        );
+                    }
+                }
+            }
+
+            if !send_all(&tx, snapshots).await {
+                break;
+            }
+        }
+
+        info!("Closing event stream for {}", pipeline_pub_id);
+    });
+
+    Ok(Sse::new(ReceiverStream::new(rx)).keep_alive(KeepAlive::default()))
+}
@@ -1,9 +1,14 @@
 use crate::queries::api_queries::{DbCheckpoint, DbLogMessage, DbPipelineJob};
+use arroyo_rpc::api_types::audit::AuditAction;
+use arroyo_rpc::api_types::auth::Role;
 use arroyo_rpc::api_types::checkpoints::{
-    Checkpoint, CheckpointEventSpan, CheckpointSpanType, OperatorCheckpointGroup,
-    SubtaskCheckpointGroup,
+    Checkpoint, CheckpointAlignmentConfig, CheckpointEventSpan, CheckpointPatch,
+    CheckpointRetention, CheckpointSpanType, OperatorCheckpointGroup, SubtaskCheckpointGroup,
+};
+use arroyo_rpc::api_types::notifications::NotificationConfig;
+use arroyo_rpc::api_types::pipelines::{
+    AutoscalerConfig, JobLogLevel, JobLogMessage, OutputData, StopType,
 };
-use arroyo_rpc::api_types::pipelines::{JobLogLevel, JobLogMessage, OutputData, StopType};
 use arroyo_rpc::api_types::{
     CheckpointCollection, JobCollection, JobLogMessageCollection,
     OperatorCheckpointGroupCollection, PaginationQueryParams,
@@ -14,9 +19,11 @@ use arroyo_rpc::grpc::api::{
 };
 use arroyo_rpc::grpc::controller_grpc_client::ControllerGrpcClient;
 use arroyo_rpc::public_ids::{generate_id, IdTypes};
+use arroyo_state::{BackingStore, StateBackend};
 use axum::extract::{Path, Query, State};
 use axum::response::sse::{Event, Sse};
 use axum::Json;
+use axum_extra::extract::WithRejection;
 use futures_util::stream::Stream;
 use std::convert::Infallible;
 use std::{collections::HashMap, time::Duration};
@@ -31,10 +38,10 @@ use crate::pipelines::{query_job_by_pub_id, query_pipeline_by_pub_id};
 use crate::rest::AppState;
 use crate::rest_utils::{
     authenticate, bad_request, log_and_map, not_found, paginate_results,
-    validate_pagination_params, BearerAuth, ErrorResp,
+    validate_pagination_params, ApiError, BearerAuth, ErrorResp,
 };
 use crate::types::public::LogLevel;
-use crate::{queries::api_queries, to_micros, types::public, AuthData};
+use crate::{audit, queries::api_queries, to_micros, types::public, AuthData};
 use cornucopia_async::DatabaseSource;
 
 pub(crate) async fn create_job<'a>(
@@ -42,6 +49,10 @@ pub(crate) async fn create_job<'a>(
     pipeline_id: i64,
     checkpoint_interval: Duration,
     preview: bool,
+    autoscaler: Option<&AutoscalerConfig>,
+    checkpoint_retention: Option<&CheckpointRetention>,
+    checkpoint_alignment: Option<&CheckpointAlignmentConfig>,
+    notifications: Option<&NotificationConfig>,
     auth: &AuthData,
     db: &DatabaseSource,
 ) -> Result<String, ErrorResp> {
@@ -96,6 +107,22 @@ pub(crate) async fn create_job<'a>(
         } else {
             None
         }),
+        &autoscaler
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(log_and_map)?,
+        &checkpoint_retention
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(log_and_map)?,
+        &checkpoint_alignment
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(log_and_map)?,
+        &notifications
+            .map(serde_json::to_value)
+            .transpose()
+            .map_err(log_and_map)?,
     )
     .await?;
 
@@ -220,6 +247,56 @@ pub async fn get_job_errors(
     }))
 }
 
+/// List all of a job's log messages, including errors and autoscaler decisions
+#[utoipa::path(
+    get,
+    path = "/v1/pipelines/{pipeline_id}/jobs/{job_id}/logs",
+    tag = "jobs",
+    params(
+        ("pipeline_id" = String, Path, description = "Pipeline id"),
+        ("job_id" = String, Path, description = "Job id"),
+        ("starting_after" = Option<String>, Query, description = "Starting after"),
+        ("limit" = Option<u32>, Query, description = "Limit"),
+    ),
+    responses(
+        (status = 200, description = "Got job's log messages", body = JobLogMessageCollection),
+    ),
+)]
+pub async fn get_job_logs(
+    State(state): State<AppState>,
+    bearer_auth: BearerAuth,
+    Path((pipeline_pub_id, job_pub_id)): Path<(String, String)>,
+    query_params: Query<PaginationQueryParams>,
+) -> Result<Json<JobLogMessageCollection>, ErrorResp> {
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+    let db = state.database.client().await?;
+
+    let (starting_after, limit) =
+        validate_pagination_params(query_params.starting_after.clone(), query_params.limit)?;
+
+    query_job_by_pub_id(&pipeline_pub_id, &job_pub_id, &db, &auth_data).await?;
+
+    let messages = api_queries::fetch_get_job_log_messages(
+        &db,
+        &auth_data.organization_id,
+        &job_pub_id,
+        &starting_after.unwrap_or_default(),
+        &(limit as i32),
+    )
+    .await
+    .map_err(log_and_map)?
+    .into_iter()
+    .map(|m| m.into())
+    .collect();
+
+    let (messages, has_more) = paginate_results(messages, limit);
+
+    Ok(Json(JobLogMessageCollection {
+        data: messages,
+        has_more,
+    }))
+}
+
 impl From<DbLogMessage> for JobLogMessage {
     fn from(val: DbLogMessage) -> Self {
         let level: JobLogLevel = match val.log_level {
@@ -266,12 +343,233 @@ pub async fn get_job_checkpoints(
     let checkpoints =
         api_queries::fetch_get_job_checkpoints(&db, &job_pub_id, &auth_data.organization_id)
             .await
-            .map_err(log_and_map)?
-            .into_iter()
-            .map(|m| m.into())
-            .collect();
+            .map_err(log_and_map)?;
 
-    Ok(Json(CheckpointCollection { data: checkpoints }))
+    Ok(Json(CheckpointCollection {
+        data: to_api_checkpoints(&job_pub_id, checkpoints).await?,
+    }))
+}
+
+/// Update a checkpoint
+///
+/// Pinned checkpoints are retained regardless of the pipeline's checkpoint retention policy
+#[utoipa::path(
+    patch,
+    path = "/v1/pipelines/{pipeline_id}/jobs/{job_id}/checkpoints/{checkpoint_id}",
+    tag = "jobs",
+    params(
+        ("pipeline_id" = String, Path, description = "Pipeline id"),
+        ("job_id" = String, Path, description = "Job id"),
+        ("checkpoint_id" = String, Path, description = "Checkpoint id")
+    ),
+    request_body = CheckpointPatch,
+    responses(
+        (status = 200, description = "Updated checkpoint", body = Checkpoint),
+    ),
+)]
+pub async fn patch_checkpoint(
+    State(state): State<AppState>,
+    bearer_auth: BearerAuth,
+    Path((pipeline_pub_id, job_pub_id, checkpoint_pub_id)): Path<(String, String, String)>,
+    WithRejection(Json(checkpoint_patch), _): WithRejection<Json<CheckpointPatch>, ApiError>,
+) -> Result<Json<Checkpoint>, ErrorResp> {
+    let db = state.database.client().await?;
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Editor)?;
+
+    query_job_by_pub_id(&pipeline_pub_id, &job_pub_id, &db, &auth_data).await?;
+
+    let not_found_err = || {
+        not_found(&format!(
+            "Checkpoint '{}' for job '{}'",
+            checkpoint_pub_id, job_pub_id
+        ))
+    };
+
+    let fetch_checkpoint = || {
+        api_queries::fetch_get_job_checkpoint(
+            &db,
+            &job_pub_id,
+            &auth_data.organization_id,
+            &checkpoint_pub_id,
+        )
+    };
+
+    let existing = fetch_checkpoint()
+        .await
+        .map_err(log_and_map)?
+        .into_iter()
+        .next()
+        .ok_or_else(not_found_err)?;
+
+    let updated = api_queries::execute_set_checkpoint_pinned(
+        &db,
+        &checkpoint_patch.pinned,
+        &job_pub_id,
+        &auth_data.organization_id,
+        &checkpoint_pub_id,
+    )
+    .await
+    .map_err(log_and_map)?;
+
+    // only completed checkpoints can be pinned
+    if updated == 0 {
+        return Err(not_found_err());
+    }
+
+    audit::record_update(
+        &state.database,
+        &auth_data,
+        AuditAction::UpdateCheckpoint,
+        &checkpoint_pub_id,
+        &CheckpointPatch {
+            pinned: existing.pinned,
+        },
+        &checkpoint_patch,
+    )
+    .await;
+
+    let checkpoint = fetch_checkpoint().await.map_err(log_and_map)?;
+
+    to_api_checkpoints(&job_pub_id, checkpoint)
+        .await?
+        .pop()
+        .map(Json)
+        .ok_or_else(not_found_err)
+}
+
+/// Restore a job to a checkpoint
+///
+/// Discards the job's checkpoints after the given one, so that the next time the pipeline is
+/// started it restores from this checkpoint. The pipeline must be stopped, and none of the
+/// discarded checkpoints may be pinned.
+#[utoipa::path(
+    post,
+    path = "/v1/pipelines/{pipeline_id}/jobs/{job_id}/checkpoints/{checkpoint_id}/restore",
+    tag = "jobs",
+    params(
+        ("pipeline_id" = String, Path, description = "Pipeline id"),
+        ("job_id" = String, Path, description = "Job id"),
+        ("checkpoint_id" = String, Path, description = "Checkpoint id")
+    ),
+    responses(
+        (status = 200, description = "Restored checkpoint", body = Checkpoint),
+    ),
+)]
+pub async fn restore_checkpoint(
+    State(state): State<AppState>,
+    bearer_auth: BearerAuth,
+    Path((pipeline_pub_id, job_pub_id, checkpoint_pub_id)): Path<(String, String, String)>,
+) -> Result<Json<Checkpoint>, ErrorResp> {
+    let db = state.database.client().await?;
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Editor)?;
+
+    let job = query_job_by_pub_id(&pipeline_pub_id, &job_pub_id, &db, &auth_data).await?;
+    if job.state != "Stopped" && job.state != "Failed" {
+        return Err(bad_request(
+            "Pipeline must be stopped before it can be restored to a checkpoint".to_string(),
+        ));
+    }
+
+    let checkpoints =
+        api_queries::fetch_get_job_checkpoints(&db, &job_pub_id, &auth_data.organization_id)
+            .await
+            .map_err(log_and_map)?;
+
+    let checkpoint = checkpoints
+        .iter()
+        .find(|c| c.pub_id == checkpoint_pub_id)
+        .ok_or_else(|| {
+            not_found(&format!(
+                "Checkpoint '{}' for job '{}'",
+                checkpoint_pub_id, job_pub_id
+            ))
+        })?;
+
+    if checkpoint.finish_time.is_none() {
+        return Err(bad_request(format!(
+            "Checkpoint '{}' has not completed",
+            checkpoint_pub_id
+        )));
+    }
+
+    if let Some(pinned) = checkpoints
+        .iter()
+        .find(|c| c.epoch > checkpoint.epoch && c.pinned)
+    {
+        return Err(bad_request(format!(
+            "Restoring to checkpoint '{}' would discard pinned checkpoint '{}'; unpin it first",
+            checkpoint_pub_id, pinned.pub_id
+        )));
+    }
+
+    api_queries::execute_discard_checkpoints_after(
+        &db,
+        &job_pub_id,
+        &auth_data.organization_id,
+        &checkpoint.epoch,
+    )
+    .await
+    .map_err(log_and_map)?;
+
+    audit::record(
+        &state.database,
+        &auth_data,
+        AuditAction::RestoreCheckpoint,
+        &checkpoint_pub_id,
+        &(),
+    )
+    .await;
+
+    let checkpoint = checkpoints
+        .into_iter()
+        .filter(|c| c.pub_id == checkpoint_pub_id)
+        .collect();
+
+    to_api_checkpoints(&job_pub_id, checkpoint)
+        .await?
+        .pop()
+        .map(Json)
+        .ok_or_else(|| not_found("Checkpoint"))
+}
+
+pub(crate) async fn to_api_checkpoints(
+    job_id: &str,
+    checkpoints: Vec<DbCheckpoint>,
+) -> Result<Vec<Checkpoint>, ErrorResp> {
+    let epochs: Vec<u32> = checkpoints.iter().map(|c| c.epoch as u32).collect();
+    let urls = StateBackend::checkpoint_urls(job_id, &epochs)
+        .await
+        .map_err(log_and_map)?;
+
+    checkpoints
+        .into_iter()
+        .zip(urls)
+        .map(|(c, storage_url)| {
+            let bytes = c
+                .operators
+                .map(serde_json::from_value::<HashMap<String, OperatorCheckpointDetail>>)
+                .transpose()
+                .map_err(log_and_map)?
+                .unwrap_or_default()
+                .values()
+                .flat_map(|op| op.tasks.values())
+                .map(|task| task.bytes.unwrap_or(0))
+                .sum();
+
+            Ok(Checkpoint {
+                id: c.pub_id,
+                epoch: c.epoch as u32,
+                backend: c.state_backend,
+                start_time: to_micros(c.start_time),
+                finish_time: c.finish_time.map(to_micros),
+                pinned: c.pinned,
+                bytes,
+                storage_url,
+            })
+        })
+        .collect()
 }
 
 fn get_event_spans(subtask_details: &TaskCheckpointDetail) -> Vec<CheckpointEventSpan> {
@@ -525,14 +823,3 @@ pub async fn get_jobs(
         data: jobs.into_iter().map(|p| p.into()).collect(),
     }))
 }
-
-impl From<DbCheckpoint> for Checkpoint {
-    fn from(val: DbCheckpoint) -> Self {
-        Checkpoint {
-            epoch: val.epoch as u32,
-            backend: val.state_backend,
-            start_time: to_micros(val.start_time),
-            finish_time: val.finish_time.map(to_micros),
-        }
-    }
-}
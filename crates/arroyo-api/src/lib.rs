@@ -10,19 +10,23 @@ use tonic::transport::Channel;
 use tracing::{error, info};
 use utoipa::OpenApi;
 
+use crate::api_tokens::{__path_create_api_token, __path_delete_api_token, __path_get_api_tokens};
+use crate::audit::__path_get_audit_log;
 use crate::connection_profiles::{
     __path_create_connection_profile, __path_delete_connection_profile,
     __path_get_connection_profile_autocomplete, __path_get_connection_profiles,
-    __path_test_connection_profile,
+    __path_patch_connection_profile, __path_test_connection_profile,
 };
 use crate::connection_tables::{
     __path_create_connection_table, __path_delete_connection_table, __path_get_connection_tables,
-    __path_test_connection_table, __path_test_schema,
+    __path_patch_connection_table, __path_test_connection_table, __path_test_schema,
 };
 use crate::connectors::__path_get_connectors;
+use crate::events::__path_get_pipeline_events;
 use crate::jobs::{
     __path_get_checkpoint_details, __path_get_job_checkpoints, __path_get_job_errors,
-    __path_get_job_output, __path_get_jobs,
+    __path_get_job_logs, __path_get_job_output, __path_get_jobs, __path_patch_checkpoint,
+    __path_restore_checkpoint,
 };
 use crate::metrics::__path_get_operator_metric_groups;
 use crate::pipelines::__path_get_pipelines;
@@ -33,7 +37,10 @@ use crate::pipelines::{
 use crate::rest::__path_ping;
 use crate::rest_utils::{service_unavailable, ErrorResp};
 use crate::udfs::{__path_create_udf, __path_delete_udf, __path_get_udfs, __path_validate_udf};
-use arroyo_rpc::api_types::{checkpoints::*, connections::*, metrics::*, pipelines::*, udfs::*, *};
+use arroyo_rpc::api_types::{
+    audit::*, auth::*, checkpoints::*, connections::*, metrics::*, notifications::*, pipelines::*,
+    udfs::*, *,
+};
 use arroyo_rpc::formats::*;
 use arroyo_rpc::grpc::compiler_grpc_client::CompilerGrpcClient;
 use arroyo_types::{
@@ -41,10 +48,14 @@ use arroyo_types::{
     CONTROLLER_ADDR_ENV, HTTP_PORT_ENV,
 };
 
+mod api_tokens;
+mod audit;
+mod auth;
 mod cloud;
 mod connection_profiles;
 mod connection_tables;
 mod connectors;
+mod events;
 mod jobs;
 mod metrics;
 mod pipelines;
@@ -102,7 +113,7 @@ pub struct OrgMetadata {
 pub struct AuthData {
     pub user_id: String,
     pub organization_id: String,
-    pub role: String,
+    pub role: Role,
     pub org_metadata: OrgMetadata,
 }
 
@@ -134,6 +145,9 @@ pub async fn start_server(database: DatabaseSource) -> anyhow::Result<()> {
     let http_port = service_port("api", ports::API_HTTP, HTTP_PORT_ENV);
     let addr = format!("0.0.0.0:{}", http_port).parse().unwrap();
 
+    auth::init_auth()?;
+    arroyo_rpc::secrets::validate_master_key()?;
+
     let app = rest::create_rest_app(database, &controller_addr);
 
     info!("Starting API server on {:?}", addr);
@@ -229,8 +243,12 @@ impl IntoResponse for HttpError {
         get_pipelines,
         get_jobs,
         get_pipeline_jobs,
+        get_pipeline_events,
         get_job_errors,
+        get_job_logs,
         get_job_checkpoints,
+        patch_checkpoint,
+        restore_checkpoint,
         get_job_output,
         get_operator_metric_groups,
         get_connectors,
@@ -241,19 +259,30 @@ impl IntoResponse for HttpError {
         get_connection_tables,
         create_connection_table,
         create_connection_profile,
+        patch_connection_profile,
+        patch_connection_table,
         delete_connection_table,
         test_connection_table,
         test_schema,
         get_checkpoint_details,
         create_udf,
         get_udfs,
-        delete_udf
+        delete_udf,
+        create_api_token,
+        get_api_tokens,
+        delete_api_token,
+        get_audit_log
     ),
     components(schemas(
         ErrorResp,
         PipelinePost,
         PipelinePatch,
         PipelineRestart,
+        AutoscalerConfig,
+        NotificationConfig,
+        NotificationChannel,
+        JobNotificationEvent,
+        JobNotification,
         Pipeline,
         PipelineGraph,
         PipelineNode,
@@ -266,8 +295,14 @@ impl IntoResponse for HttpError {
         JobLogMessageCollection,
         JobLogLevel,
         Checkpoint,
+        CheckpointPatch,
+        CheckpointRetention,
+        CheckpointAlignmentConfig,
+        CheckpointAlignmentMode,
         CheckpointCollection,
         OutputData,
+        PipelineEvent,
+        JobMetricsSnapshot,
         MetricName,
         Metric,
         SubtaskMetrics,
@@ -277,10 +312,15 @@ impl IntoResponse for HttpError {
         Connector,
         ConnectionProfile,
         ConnectionProfilePost,
+        ConnectionProfilePatch,
+        ConnectionProfileUpdate,
+        AffectedPipeline,
         ConnectionAutocompleteResp,
         ConnectionProfileCollection,
         ConnectionTable,
         ConnectionTablePost,
+        ConnectionTablePatch,
+        ConnectionTableUpdate,
         ConnectionTableCollection,
         ConnectionSchema,
         ConnectionType,
@@ -302,6 +342,7 @@ impl IntoResponse for HttpError {
         FramingMethod,
         NewlineDelimitedFraming,
         PaginationQueryParams,
+        PipelineEventsQueryParams,
         CheckpointEventSpan,
         CheckpointSpanType,
         OperatorCheckpointGroupCollection,
@@ -309,6 +350,11 @@ impl IntoResponse for HttpError {
         OperatorCheckpointGroup,
         ValidateQueryPost,
         QueryValidationResult,
+        QueryExplanation,
+        ExplainedExtension,
+        ExplainedOperator,
+        ExplainedStateTable,
+        ExplainedEdge,
         ValidateUdfPost,
         UdfValidationResult,
         Udf,
@@ -316,6 +362,14 @@ impl IntoResponse for HttpError {
         GlobalUdf,
         GlobalUdfCollection,
         BadData,
+        ApiToken,
+        ApiTokenPost,
+        CreatedApiToken,
+        ApiTokenCollection,
+        Role,
+        AuditAction,
+        AuditLogEntry,
+        AuditLogCollection,
     )),
     tags(
         (name = "ping", description = "Ping endpoint"),
@@ -324,6 +378,8 @@ impl IntoResponse for HttpError {
         (name = "pipelines", description = "Pipeline management endpoints"),
         (name = "jobs", description = "Job management endpoints"),
         (name = "connectors", description = "Connector management endpoints"),
+        (name = "api_tokens", description = "API token management endpoints"),
+        (name = "audit", description = "Audit log endpoints"),
     )
 )]
 pub struct ApiDoc;
@@ -38,28 +38,29 @@ pub async fn get_operator_metric_groups(
     )
     .await?;
 
-    let mut controller = ControllerGrpcClient::connect(state.controller_addr)
-        .await
-        .map_err(log_and_map)?;
+    let data = fetch_job_metrics(state.controller_addr, job.id).await?;
+
+    Ok(Json(OperatorMetricGroupCollection { data }))
+}
 
-    let data = match controller
-        .job_metrics(JobMetricsReq { job_id: job.id })
+/// Fetches the current metrics for a job from the controller, returning no metrics if the
+/// job isn't running
+pub(crate) async fn fetch_job_metrics(
+    controller_addr: String,
+    job_id: String,
+) -> Result<Vec<OperatorMetricGroup>, ErrorResp> {
+    let mut controller = ControllerGrpcClient::connect(controller_addr)
         .await
-    {
-        Ok(resp) => {
-            let metrics: Vec<OperatorMetricGroup> =
-                serde_json::from_str(&resp.into_inner().metrics).map_err(log_and_map)?;
+        .map_err(log_and_map)?;
 
-            metrics
-        }
+    match controller.job_metrics(JobMetricsReq { job_id }).await {
+        Ok(resp) => serde_json::from_str(&resp.into_inner().metrics).map_err(log_and_map),
         Err(e) => {
             if e.code() == Code::NotFound {
-                vec![]
+                Ok(vec![])
             } else {
-                return Err(log_and_map(e));
+                Err(log_and_map(e))
             }
         }
-    };
-
-    Ok(Json(OperatorMetricGroupCollection { data }))
+    }
 }
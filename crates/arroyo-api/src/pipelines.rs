@@ -13,11 +13,17 @@ use std::env;
 use petgraph::visit::NodeRef;
 use std::time::Duration;
 
-use crate::{compiler_service, connection_profiles, jobs, types};
+use crate::{audit, compiler_service, connection_profiles, jobs, types};
 use arroyo_datastream::preview_sink;
+use arroyo_rpc::api_types::audit::AuditAction;
+use arroyo_rpc::api_types::auth::Role;
+use arroyo_rpc::api_types::checkpoints::{
+    CheckpointAlignmentConfig, CheckpointAlignmentMode, CheckpointRetention,
+};
+use arroyo_rpc::api_types::notifications::NotificationConfig;
 use arroyo_rpc::api_types::pipelines::{
-    Job, Pipeline, PipelinePatch, PipelinePost, PipelineRestart, QueryValidationResult, StopType,
-    ValidateQueryPost,
+    AutoscalerConfig, Job, Pipeline, PipelinePatch, PipelinePost, PipelineRestart,
+    QueryValidationResult, StopType, ValidateQueryPost,
 };
 use arroyo_rpc::api_types::udfs::{GlobalUdf, Udf};
 use arroyo_rpc::api_types::{JobCollection, PaginationQueryParams, PipelineCollection};
@@ -33,6 +39,7 @@ use arroyo_rpc::public_ids::{generate_id, IdTypes};
 use arroyo_rpc::schema_resolver::{ConfluentSchemaRegistry, ConfluentSchemaType};
 use arroyo_rpc::{error_chain, OperatorConfig};
 use arroyo_server_common::log_event;
+use arroyo_types::KEY_GROUPS;
 use arroyo_udf_host::ParsedUdfFile;
 use prost::Message;
 use serde_json::json;
@@ -59,6 +66,7 @@ async fn compile_sql<'a>(
     query: String,
     local_udfs: &Vec<Udf>,
     parallelism: usize,
+    updating_ttl: Option<Duration>,
     auth_data: &AuthData,
     validate_only: bool,
     db: &DatabaseSource,
@@ -155,6 +163,7 @@ async fn compile_sql<'a>(
         schema_provider,
         SqlConfig {
             default_parallelism: parallelism,
+            updating_ttl,
         },
     )
     .await
@@ -164,12 +173,148 @@ async fn compile_sql<'a>(
     })
 }
 
+fn validate_autoscaler(config: &AutoscalerConfig) -> Result<(), ErrorResp> {
+    if config.min_parallelism < 1 || config.min_parallelism > config.max_parallelism {
+        return Err(bad_request(
+            "autoscaler min_parallelism must be at least 1 and no greater than max_parallelism"
+                .to_string(),
+        ));
+    }
+
+    if config.max_parallelism > KEY_GROUPS as u64 {
+        return Err(bad_request(format!(
+            "autoscaler max_parallelism must be at most {}",
+            KEY_GROUPS
+        )));
+    }
+
+    if !(0.0..=1.0).contains(&config.scale_up_backpressure)
+        || !(0.0..=1.0).contains(&config.scale_down_backpressure)
+        || config.scale_down_backpressure >= config.scale_up_backpressure
+    {
+        return Err(bad_request(
+            "autoscaler backpressure thresholds must be between 0 and 1, with \
+            scale_down_backpressure less than scale_up_backpressure"
+                .to_string(),
+        ));
+    }
+
+    if config.window_secs == 0 {
+        return Err(bad_request(
+            "autoscaler window_secs must be greater than 0".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_checkpoint_retention(retention: &CheckpointRetention) -> Result<(), ErrorResp> {
+    if retention.keep_last < 1 {
+        return Err(bad_request(
+            "checkpoint retention keep_last must be at least 1".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_checkpoint_alignment(alignment: &CheckpointAlignmentConfig) -> Result<(), ErrorResp> {
+    if alignment.mode == CheckpointAlignmentMode::Auto && alignment.alignment_timeout_micros == 0 {
+        return Err(bad_request(
+            "checkpoint alignment_timeout_micros must be greater than 0".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_notifications(config: &NotificationConfig) -> Result<(), ErrorResp> {
+    for channel in &config.channels {
+        let url = channel.url();
+        if !(url.starts_with("http://") || url.starts_with("https://")) {
+            return Err(bad_request(format!(
+                "notification url '{}' must be an http or https url",
+                url
+            )));
+        }
+    }
+
+    if !config.channels.is_empty() && config.events.is_empty() {
+        return Err(bad_request(
+            "notification events must not be empty when channels are configured".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
 fn set_parallelism(program: &mut LogicalProgram, parallelism: usize) {
     for node in program.graph.node_weights_mut() {
         node.parallelism = parallelism;
     }
 }
 
+fn parse_parallelism_overrides(value: &serde_json::Value) -> HashMap<String, usize> {
+    value
+        .as_object()
+        .unwrap()
+        .into_iter()
+        .map(|(k, v)| (k.clone(), v.as_u64().unwrap() as usize))
+        .collect()
+}
+
+fn apply_parallelism_overrides(
+    program: &mut LogicalProgram,
+    overrides: &HashMap<String, u64>,
+) -> Result<(), ErrorResp> {
+    if let Some(node_id) = overrides
+        .keys()
+        .find(|node_id| program.operator_index(node_id).is_none())
+    {
+        return Err(bad_request(format!(
+            "parallelism_overrides contains unknown node '{}'",
+            node_id
+        )));
+    }
+
+    program
+        .apply_parallelism_overrides(
+            &overrides
+                .iter()
+                .map(|(k, v)| (k.clone(), *v as usize))
+                .collect(),
+        )
+        .map_err(|e| bad_request(e.to_string()))
+}
+
+fn validate_parallelism(program: &LogicalProgram, auth: &AuthData) -> Result<(), ErrorResp> {
+    for node in program.graph.node_weights() {
+        if node.parallelism == 0 {
+            return Err(bad_request(format!(
+                "parallelism for node '{}' must be at least 1",
+                node.operator_id
+            )));
+        }
+
+        if node.parallelism > auth.org_metadata.max_parallelism as usize {
+            return Err(bad_request(format!(
+                "Your plan allows you to run pipelines up to parallelism {};
+            contact support@arroyo.systems for an increase",
+                auth.org_metadata.max_parallelism
+            )));
+        }
+
+        if node.parallelism > KEY_GROUPS as usize {
+            return Err(bad_request(format!(
+                "parallelism must be at most {}",
+                KEY_GROUPS
+            )));
+        }
+    }
+
+    Ok(())
+}
+
 #[allow(unused)]
 async fn try_register_confluent_schema(
     sink: &mut ConnectorOp,
@@ -283,23 +428,61 @@ pub(crate) async fn create_pipeline_int<'a>(
         )));
     }
 
+    if req.parallelism > KEY_GROUPS as u64 {
+        return Err(bad_request(format!(
+            "parallelism must be at most {}",
+            KEY_GROUPS
+        )));
+    }
+
+    if let Some(autoscaler) = &req.autoscaler {
+        validate_autoscaler(autoscaler)?;
+    }
+
+    if let Some(retention) = &req.checkpoint_retention {
+        validate_checkpoint_retention(retention)?;
+    }
+
+    if let Some(alignment) = &req.checkpoint_alignment {
+        validate_checkpoint_alignment(alignment)?;
+    }
+
+    if let Some(notifications) = &req.notifications {
+        validate_notifications(notifications)?;
+    }
+
+    if req.updating_ttl_micros == Some(0) {
+        return Err(bad_request("updating_ttl_micros must be positive"));
+    }
+
     let mut compiled = compile_sql(
         req.query.clone(),
         req.udfs.as_ref().unwrap_or(&vec![]),
         req.parallelism as usize,
+        req.updating_ttl_micros.map(Duration::from_micros),
         &auth,
         false,
         db,
     )
     .await?;
 
+    if compiled.explanation.is_some() {
+        return Err(bad_request(
+            "EXPLAIN queries can only be validated, not run as pipelines",
+        ));
+    }
+
     if compiled.program.graph.node_count() > auth.org_metadata.max_operators as usize {
         return Err(bad_request(
             format!("This pipeline is too large to create under your plan, which only allows pipelines up to {} nodes;
                 contact support@arroyo.systems for an increase", auth.org_metadata.max_operators)));
     }
 
-    set_parallelism(&mut compiled.program, 1);
+    if let Some(overrides) = &req.parallelism_overrides {
+        apply_parallelism_overrides(&mut compiled.program, overrides)?;
+    }
+
+    validate_parallelism(&compiled.program, &auth)?;
 
     if is_preview && !env::var("PREVIEW_SINKS").is_ok_and(|s| s == "true") {
         for node in compiled.program.graph.node_weights_mut() {
@@ -342,6 +525,7 @@ pub(crate) async fn create_pipeline_int<'a>(
         &udfs,
         &program_bytes,
         &2,
+        &req.updating_ttl_micros.map(|t| t as i64),
     )
     .await?;
 
@@ -381,15 +565,7 @@ impl TryInto<Pipeline> for DbPipeline {
             .try_into()
             .map_err(log_and_map)?;
 
-        program.update_parallelism(
-            &self
-                .parallelism_overrides
-                .as_object()
-                .unwrap()
-                .into_iter()
-                .map(|(k, v)| (k.clone(), v.as_u64().unwrap() as usize))
-                .collect(),
-        );
+        program.update_parallelism(&parse_parallelism_overrides(&self.parallelism_overrides));
 
         let stop = match self.stop {
             StopMode::none => StopType::None,
@@ -412,6 +588,34 @@ impl TryInto<Pipeline> for DbPipeline {
             action_text,
             action_in_progress,
             preview: self.ttl_micros.is_some(),
+            autoscaler: self
+                .autoscaler
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(log_and_map)?,
+            checkpoint_retention: self
+                .checkpoint_retention
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(log_and_map)?
+                .unwrap_or_default(),
+            checkpoint_alignment: self
+                .checkpoint_alignment
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(log_and_map)?
+                .unwrap_or_default(),
+            notifications: {
+                let mut notifications: NotificationConfig = self
+                    .notifications
+                    .map(serde_json::from_value)
+                    .transpose()
+                    .map_err(log_and_map)?
+                    .unwrap_or_default();
+                notifications.redact();
+                notifications
+            },
+            updating_ttl_micros: self.updating_ttl_micros.map(|t| t as u64),
         })
     }
 }
@@ -455,19 +659,28 @@ pub async fn validate_query(
         validate_query_post.query,
         &udfs,
         1,
+        validate_query_post
+            .updating_ttl_micros
+            .map(Duration::from_micros),
         &auth_data,
         true,
         &state.database,
     )
     .await
     {
-        Ok(CompiledSql { program, .. }) => QueryValidationResult {
+        Ok(CompiledSql {
+            program,
+            explanation,
+            ..
+        }) => QueryValidationResult {
             graph: Some(program.try_into().map_err(log_and_map)?),
             errors: vec![],
+            explanation,
         },
         Err(e) => QueryValidationResult {
             graph: None,
             errors: vec![e.message],
+            explanation: None,
         },
     };
 
@@ -493,6 +706,7 @@ pub async fn create_pipeline(
     WithRejection(Json(pipeline_post), _): WithRejection<Json<PipelinePost>, ApiError>,
 ) -> Result<Json<Pipeline>, ErrorResp> {
     let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Editor)?;
 
     let pipeline_pub_id = generate_id(IdTypes::Pipeline);
 
@@ -518,6 +732,10 @@ pub async fn create_pipeline(
         pipeline_id,
         checkpoint_interval,
         preview,
+        pipeline_post.autoscaler.as_ref(),
+        pipeline_post.checkpoint_retention.as_ref(),
+        pipeline_post.checkpoint_alignment.as_ref(),
+        pipeline_post.notifications.as_ref(),
         &auth_data,
         &state.database,
     )
@@ -525,6 +743,15 @@ pub async fn create_pipeline(
 
     // transaction.commit().await?;
 
+    audit::record(
+        &state.database,
+        &auth_data,
+        AuditAction::CreatePipeline,
+        &pipeline_pub_id,
+        &pipeline_post,
+    )
+    .await;
+
     log_event(
         "job_created",
         json!({
@@ -569,6 +796,7 @@ pub async fn patch_pipeline(
     WithRejection(Json(pipeline_patch), _): WithRejection<Json<PipelinePatch>, ApiError>,
 ) -> Result<Json<Pipeline>, ErrorResp> {
     let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Editor)?;
     let db = state.database.client().await?;
 
     // this assumes there is just one job for the pipeline
@@ -580,6 +808,20 @@ pub async fn patch_pipeline(
             .ok_or_else(|| bad_request("There are no jobs for the pipeline"))?
             .id;
 
+    let stored = api_queries::fetch_get_pipeline(&db, &pipeline_pub_id, &auth_data.organization_id)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| not_found("Pipeline"))?;
+    let stored_notifications: NotificationConfig = stored
+        .notifications
+        .clone()
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(log_and_map)?
+        .unwrap_or_default();
+    let before: Pipeline = stored.try_into()?;
+
     let interval = pipeline_patch
         .checkpoint_interval_micros
         .map(Duration::from_micros);
@@ -600,21 +842,63 @@ pub async fn patch_pipeline(
         }
     }
 
-    let parallelism_overrides = if let Some(parallelism) = pipeline_patch.parallelism {
-        let res = api_queries::fetch_get_job_details(&db, &auth_data.organization_id, &job_id)
-            .await?
-            .into_iter()
-            .next()
-            .ok_or_else(|| not_found("Job"))?;
+    let parallelism_overrides =
+        if pipeline_patch.parallelism.is_some() || pipeline_patch.parallelism_overrides.is_some() {
+            let res = api_queries::fetch_get_job_details(&db, &auth_data.organization_id, &job_id)
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| not_found("Job"))?;
 
-        let program = ArrowProgram::decode(&res.program[..]).map_err(log_and_map)?;
-        let map: HashMap<String, u32> = program
-            .nodes
-            .into_iter()
-            .map(|node| (node.node_id, parallelism as u32))
-            .collect();
+            let mut program: LogicalProgram = ArrowProgram::decode(&res.program[..])
+                .map_err(log_and_map)?
+                .try_into()
+                .map_err(log_and_map)?;
 
-        Some(serde_json::to_value(map).map_err(log_and_map)?)
+            program.update_parallelism(&parse_parallelism_overrides(&res.parallelism_overrides));
+
+            if let Some(parallelism) = pipeline_patch.parallelism {
+                set_parallelism(&mut program, parallelism as usize);
+            }
+
+            if let Some(overrides) = &pipeline_patch.parallelism_overrides {
+                apply_parallelism_overrides(&mut program, overrides)?;
+            }
+
+            validate_parallelism(&program, &auth_data)?;
+
+            Some(serde_json::to_value(program.tasks_per_operator()).map_err(log_and_map)?)
+        } else {
+            None
+        };
+
+    let autoscaler = if let Some(autoscaler) = &pipeline_patch.autoscaler {
+        validate_autoscaler(autoscaler)?;
+        Some(serde_json::to_value(autoscaler).map_err(log_and_map)?)
+    } else {
+        None
+    };
+
+    let checkpoint_retention = if let Some(retention) = &pipeline_patch.checkpoint_retention {
+        validate_checkpoint_retention(retention)?;
+        Some(serde_json::to_value(retention).map_err(log_and_map)?)
+    } else {
+        None
+    };
+
+    let checkpoint_alignment = if let Some(alignment) = &pipeline_patch.checkpoint_alignment {
+        validate_checkpoint_alignment(alignment)?;
+        Some(serde_json::to_value(alignment).map_err(log_and_map)?)
+    } else {
+        None
+    };
+
+    let notifications = if let Some(notifications) = &pipeline_patch.notifications {
+        // credentials that were redacted when the pipeline was read keep their stored values
+        let mut notifications = notifications.clone();
+        notifications.restore_redacted(&stored_notifications);
+        validate_notifications(&notifications)?;
+        Some(serde_json::to_value(notifications).map_err(log_and_map)?)
     } else {
         None
     };
@@ -626,6 +910,10 @@ pub async fn patch_pipeline(
         stop,
         &interval.map(|i| i.as_micros() as i64),
         &parallelism_overrides,
+        &autoscaler,
+        &checkpoint_retention,
+        &checkpoint_alignment,
+        &notifications,
         &job_id,
         &auth_data.organization_id,
     )
@@ -636,6 +924,17 @@ pub async fn patch_pipeline(
     }
 
     let pipeline = query_pipeline_by_pub_id(&pipeline_pub_id, &db, &auth_data).await?;
+
+    audit::record_update(
+        &state.database,
+        &auth_data,
+        AuditAction::UpdatePipeline,
+        &pipeline_pub_id,
+        &before,
+        &pipeline,
+    )
+    .await;
+
     Ok(Json(pipeline))
 }
 
@@ -658,6 +957,7 @@ pub async fn restart_pipeline(
     WithRejection(Json(req), _): WithRejection<Json<PipelineRestart>, ApiError>,
 ) -> Result<Json<Pipeline>, ErrorResp> {
     let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Editor)?;
     let db = state.database.client().await?;
 
     let job_id = api_queries::fetch_get_pipeline_jobs(&db, &auth_data.organization_id, &id)
@@ -687,6 +987,15 @@ pub async fn restart_pipeline(
         return Err(not_found("Pipeline"));
     }
 
+    audit::record(
+        &state.database,
+        &auth_data,
+        AuditAction::RestartPipeline,
+        &id,
+        &req,
+    )
+    .await;
+
     let pipeline = query_pipeline_by_pub_id(&id, &db, &auth_data).await?;
     Ok(Json(pipeline))
 }
@@ -787,6 +1096,7 @@ pub async fn delete_pipeline(
     Path(pipeline_pub_id): Path<String>,
 ) -> Result<(), ErrorResp> {
     let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Editor)?;
 
     let jobs: Vec<Job> = api_queries::fetch_get_pipeline_jobs(
         &state.database.client().await?,
@@ -818,6 +1128,15 @@ pub async fn delete_pipeline(
         return Err(not_found("Pipeline"));
     }
 
+    audit::record(
+        &state.database,
+        &auth_data,
+        AuditAction::DeletePipeline,
+        &pipeline_pub_id,
+        &(),
+    )
+    .await;
+
     Ok(())
 }
 
@@ -12,17 +12,21 @@ use tower_http::cors::CorsLayer;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
+use crate::api_tokens::{create_api_token, delete_api_token, get_api_tokens};
+use crate::audit::get_audit_log;
 use crate::connection_profiles::{
     create_connection_profile, delete_connection_profile, get_connection_profile_autocomplete,
-    get_connection_profiles, test_connection_profile,
+    get_connection_profiles, patch_connection_profile, test_connection_profile,
 };
 use crate::connection_tables::{
-    create_connection_table, delete_connection_table, get_connection_tables, test_connection_table,
-    test_schema,
+    create_connection_table, delete_connection_table, get_connection_tables,
+    patch_connection_table, test_connection_table, test_schema,
 };
 use crate::connectors::get_connectors;
+use crate::events::get_pipeline_events;
 use crate::jobs::{
-    get_checkpoint_details, get_job_checkpoints, get_job_errors, get_job_output, get_jobs,
+    get_checkpoint_details, get_job_checkpoints, get_job_errors, get_job_logs, get_job_output,
+    get_jobs, patch_checkpoint, restore_checkpoint,
 };
 use crate::metrics::get_operator_metric_groups;
 use crate::pipelines::{
@@ -119,7 +123,16 @@ pub fn create_rest_app(database: DatabaseSource, controller_addr: &str) -> Route
     let jobs_routes = Router::new()
         .route("/", get(get_pipeline_jobs))
         .route("/:job_id/errors", get(get_job_errors))
+        .route("/:job_id/logs", get(get_job_logs))
         .route("/:job_id/checkpoints", get(get_job_checkpoints))
+        .route(
+            "/:job_id/checkpoints/:checkpoint_id",
+            patch(patch_checkpoint),
+        )
+        .route(
+            "/:job_id/checkpoints/:checkpoint_id/restore",
+            post(restore_checkpoint),
+        )
         .route(
             "/:job_id/checkpoints/:checkpoint_id/operator_checkpoint_groups",
             get(get_checkpoint_details),
@@ -136,6 +149,7 @@ pub fn create_rest_app(database: DatabaseSource, controller_addr: &str) -> Route
         .route("/connection_profiles/test", post(test_connection_profile))
         .route("/connection_profiles", post(create_connection_profile))
         .route("/connection_profiles", get(get_connection_profiles))
+        .route("/connection_profiles/:id", patch(patch_connection_profile))
         .route(
             "/connection_profiles/:id",
             delete(delete_connection_profile),
@@ -148,11 +162,16 @@ pub fn create_rest_app(database: DatabaseSource, controller_addr: &str) -> Route
         .route("/connection_tables", post(create_connection_table))
         .route("/connection_tables/test", post(test_connection_table))
         .route("/connection_tables/schemas/test", post(test_schema))
+        .route("/connection_tables/:id", patch(patch_connection_table))
         .route("/connection_tables/:id", delete(delete_connection_table))
         .route("/udfs", post(create_udf))
         .route("/udfs", get(get_udfs))
         .route("/udfs/validate", post(validate_udf))
         .route("/udfs/:id", delete(delete_udf))
+        .route("/api_tokens", post(create_api_token))
+        .route("/api_tokens", get(get_api_tokens))
+        .route("/api_tokens/:id", delete(delete_api_token))
+        .route("/audit", get(get_audit_log))
         .route("/pipelines", post(create_pipeline))
         .route("/pipelines", get(get_pipelines))
         .route("/jobs", get(get_jobs))
@@ -160,6 +179,7 @@ pub fn create_rest_app(database: DatabaseSource, controller_addr: &str) -> Route
         .route("/pipelines/:id", patch(patch_pipeline))
         .route("/pipelines/:id", get(get_pipeline))
         .route("/pipelines/:id/restart", post(restart_pipeline))
+        .route("/pipelines/:id/events", get(get_pipeline_events))
         .route("/pipelines/:id", delete(delete_pipeline))
         .nest("/pipelines/:id/jobs", jobs_routes)
         .fallback(api_fallback);
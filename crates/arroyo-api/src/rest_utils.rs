@@ -1,3 +1,4 @@
+use crate::queries::api_queries;
 use crate::{cloud, AuthData};
 use arroyo_server_common::log_event;
 use axum::extract::rejection::JsonRejection;
@@ -5,10 +6,11 @@ use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use axum::{Json, TypedHeader};
 use serde_json::json;
+use std::future::Future;
 use tracing::{error, warn};
 
 use axum::headers::authorization::{Authorization, Bearer};
-use cornucopia_async::{DatabaseSource, DbError};
+use cornucopia_async::{Database, DatabaseSource, DbError};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
@@ -103,6 +105,28 @@ impl IntoResponse for ErrorResp {
     }
 }
 
+/// Runs `f` in a transaction on `db`, committing it if `f` succeeds and rolling it back if it
+/// fails. All of the queries in `f` must be made through `db`.
+pub(crate) async fn in_transaction<T>(
+    db: &Database<'_>,
+    f: impl Future<Output = Result<T, ErrorResp>>,
+) -> Result<T, ErrorResp> {
+    api_queries::execute_begin_transaction(db).await?;
+
+    match f.await {
+        Ok(result) => {
+            api_queries::execute_commit_transaction(db).await?;
+            Ok(result)
+        }
+        Err(e) => {
+            if let Err(rollback) = api_queries::execute_rollback_transaction(db).await {
+                error!("Failed to roll back transaction: {:?}", rollback);
+            }
+            Err(e)
+        }
+    }
+}
+
 pub(crate) async fn authenticate(
     db: &DatabaseSource,
     bearer_auth: BearerAuth,
@@ -117,6 +141,20 @@ pub(crate) fn bad_request(message: impl Into<String>) -> ErrorResp {
     }
 }
 
+pub(crate) fn unauthorized(message: impl Into<String>) -> ErrorResp {
+    ErrorResp {
+        status_code: StatusCode::UNAUTHORIZED,
+        message: message.into(),
+    }
+}
+
+pub(crate) fn forbidden(message: impl Into<String>) -> ErrorResp {
+    ErrorResp {
+        status_code: StatusCode::FORBIDDEN,
+        message: message.into(),
+    }
+}
+
 pub(crate) fn service_unavailable(object: &str) -> ErrorResp {
     ErrorResp {
         status_code: StatusCode::SERVICE_UNAVAILABLE,
@@ -5,7 +5,9 @@ use crate::rest_utils::{
     authenticate, bad_request, internal_server_error, map_insert_err, not_found, ApiError,
     BearerAuth, ErrorResp,
 };
-use crate::{compiler_service, to_micros};
+use crate::{audit, compiler_service, to_micros};
+use arroyo_rpc::api_types::audit::AuditAction;
+use arroyo_rpc::api_types::auth::Role;
 use arroyo_rpc::api_types::udfs::{GlobalUdf, UdfPost, UdfValidationResult, ValidateUdfPost};
 use arroyo_rpc::api_types::GlobalUdfCollection;
 use arroyo_rpc::grpc::compiler_grpc_client::CompilerGrpcClient;
@@ -56,7 +58,8 @@ pub async fn create_udf(
     bearer_auth: BearerAuth,
     WithRejection(Json(req), _): WithRejection<Json<UdfPost>, ApiError>,
 ) -> Result<Json<GlobalUdf>, ErrorResp> {
-    let auth_data = authenticate(&state.database, bearer_auth).await.unwrap();
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Editor)?;
 
     // let transaction = client.transaction().await.map_err(log_and_map)?;
     // transaction
@@ -86,12 +89,21 @@ pub async fn create_udf(
         &req.prefix,
         &udf_name,
         &req.definition,
-        &req.description.unwrap_or_default(),
+        &req.description.clone().unwrap_or_default(),
         &udf_url,
     )
     .await
     .map_err(|e| map_insert_err("udf", e))?;
 
+    audit::record(
+        &state.database,
+        &auth_data,
+        AuditAction::CreateUdf,
+        &pub_id,
+        &req,
+    )
+    .await;
+
     let created_udf = api_queries::fetch_get_udf(&client, &auth_data.organization_id, &pub_id)
         .await?
         .into_iter()
@@ -117,7 +129,7 @@ pub async fn get_udfs(
     State(state): State<AppState>,
     bearer_auth: BearerAuth,
 ) -> Result<Json<GlobalUdfCollection>, ErrorResp> {
-    let auth_data = authenticate(&state.database, bearer_auth).await.unwrap();
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
 
     let udfs =
         api_queries::fetch_get_udfs(&state.database.client().await?, &auth_data.organization_id)
@@ -145,7 +157,8 @@ pub async fn delete_udf(
     bearer_auth: BearerAuth,
     Path(udf_pub_id): Path<String>,
 ) -> Result<(), ErrorResp> {
-    let auth_data = authenticate(&state.database, bearer_auth).await.unwrap();
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Editor)?;
 
     let count = api_queries::execute_delete_udf(
         &state.database.client().await?,
@@ -158,6 +171,15 @@ pub async fn delete_udf(
         return Err(not_found("UDF"));
     }
 
+    audit::record(
+        &state.database,
+        &auth_data,
+        AuditAction::DeleteUdf,
+        &udf_pub_id,
+        &(),
+    )
+    .await;
+
     Ok(())
 }
 
@@ -243,8 +265,13 @@ pub async fn build_udf(
     ),
 )]
 pub async fn validate_udf(
+    State(state): State<AppState>,
+    bearer_auth: BearerAuth,
     WithRejection(Json(req), _): WithRejection<Json<ValidateUdfPost>, ApiError>,
 ) -> Result<Json<UdfValidationResult>, ErrorResp> {
+    let auth_data = authenticate(&state.database, bearer_auth).await?;
+    auth_data.require_role(Role::Editor)?;
+
     let check_udfs_resp = build_udf(&mut compiler_service().await?, &req.definition, false).await?;
 
     Ok(Json(UdfValidationResult {
@@ -160,6 +160,7 @@ async fn test_kafka_checkpoint_flushes() {
         min_epoch: 0,
         timestamp: SystemTime::now(),
         then_stop: false,
+        alignment: CheckpointAlignment::Aligned,
     };
     sink_with_writes
         .sink
@@ -21,7 +21,8 @@ use arroyo_rpc::grpc::{CheckpointMetadata, OperatorCheckpointMetadata, OperatorM
 use arroyo_rpc::schema_resolver::FailingSchemaResolver;
 use arroyo_rpc::{CheckpointCompleted, ControlMessage, ControlResp};
 use arroyo_types::{
-    single_item_hash_map, to_micros, ArrowMessage, CheckpointBarrier, SignalMessage, TaskInfo,
+    single_item_hash_map, to_micros, ArrowMessage, CheckpointAlignment, CheckpointBarrier,
+    SignalMessage, TaskInfo, KEY_GROUPS,
 };
 use rdkafka::admin::{AdminClient, AdminOptions, NewTopic};
 use rdkafka::producer::{BaseProducer, BaseRecord};
@@ -266,6 +267,7 @@ async fn test_kafka() {
         min_epoch: 0,
         timestamp: (SystemTime::now()),
         then_stop: false,
+        alignment: CheckpointAlignment::Aligned,
     });
     reader.to_control_tx.send(barrier).await.unwrap();
     let checkpoint_completed = reader.assert_control_checkpoint(1).await;
@@ -295,7 +297,9 @@ async fn test_kafka() {
             min_watermark: Some(0),
             max_watermark: Some(0),
             parallelism: 1,
+            key_groups: KEY_GROUPS,
         }),
+        in_flight_data: vec![],
     })
     .await
     .unwrap();
@@ -1,4 +1,4 @@
---! all_jobs : Job(ttl_micros?, state?, start_time?, finish_time?, tasks?, failure_message?, run_id?, pipeline_path?, wasm_path?)
+--! all_jobs : Job(ttl_micros?, state?, start_time?, finish_time?, tasks?, failure_message?, run_id?, pipeline_path?, wasm_path?, autoscaler?, checkpoint_retention?, checkpoint_alignment?, notifications?, pipeline_pub_id?)
 SELECT
     job_configs.id as id,
     job_configs.organization_id as org_id,
@@ -19,9 +19,15 @@ SELECT
     wasm_path,
     job_configs.restart_nonce as config_restart_nonce,
     job_statuses.restart_nonce as status_restart_nonce,
-    restart_mode
+    restart_mode,
+    autoscaler,
+    checkpoint_retention,
+    checkpoint_alignment,
+    notifications,
+    pipelines.pub_id as pipeline_pub_id
 FROM job_configs
-LEFT JOIN job_statuses ON job_configs.id = job_statuses.id;
+LEFT JOIN job_statuses ON job_configs.id = job_statuses.id
+LEFT JOIN pipelines ON job_configs.pipeline_id = pipelines.id;
 
 --! update_job_status (start_time?, finish_time?, tasks?, failure_message?, pipeline_path?, wasm_path?)
 UPDATE job_statuses
@@ -37,17 +43,35 @@ SET state = :state,
     restart_nonce = :restart_nonce
 WHERE id = :job_id;
 
+--! update_parallelism_overrides
+UPDATE job_configs
+SET parallelism_overrides = :parallelism_overrides,
+    updated_at = :updated_at
+WHERE id = :job_id;
+
 --! get_program
 SELECT program, proto_version FROM pipelines WHERE id = :id;
 
 --! mark_checkpoints_compacted
 UPDATE checkpoints
     set state = 'compacted'
-WHERE job_id = :job_id AND epoch < :epoch;
+WHERE job_id = :job_id AND epoch < :epoch AND state = 'compacting';
 
 --! drop_old_checkpoint_rows
 DELETE FROM checkpoints
-WHERE job_id = :job_id AND epoch < :epoch;
+WHERE job_id = :job_id AND epoch < :epoch AND state != 'ready';
+
+--: RetentionCandidate (finish_time?)
+
+--! get_ready_checkpoints : RetentionCandidate
+SELECT epoch, finish_time, pinned
+FROM checkpoints
+WHERE job_id = :job_id AND epoch < :epoch AND state = 'ready';
+
+--! get_compacting_checkpoints
+SELECT DISTINCT epoch
+FROM checkpoints
+WHERE job_id = :job_id AND state = 'compacting';
 
 --! create_checkpoint
 INSERT INTO checkpoints
@@ -73,7 +97,13 @@ WHERE pub_id = :pub_id;
 UPDATE checkpoints
 SET
     state = 'compacting'
-WHERE job_id = :job_id AND epoch >= :min_epoch AND epoch < :epoch;
+WHERE job_id = :job_id AND epoch >= :min_epoch AND epoch < :epoch AND state != 'ready';
+
+--! mark_checkpoint_compacting
+UPDATE checkpoints
+SET
+    state = 'compacting'
+WHERE job_id = :job_id AND epoch = :epoch AND state = 'ready' AND NOT pinned;
 
 --! mark_failed
 UPDATE checkpoints
@@ -88,6 +118,15 @@ WHERE job_id = :job_id AND (state = 'ready' or state = 'committing')
 ORDER BY epoch DESC
 LIMIT 1;
 
---! create_job_log_message
+--! create_job_log_message (operator_id?, task_index?)
 INSERT INTO job_log_messages (pub_id, job_id, operator_id, task_index, log_level, message, details)
 VALUES (:pub_id, :job_id, :operator_id, :task_index, :log_level, :message, :details);
+
+--: RecentLogMessage (operator_id?, task_index?)
+
+--! get_recent_job_log_messages : RecentLogMessage
+SELECT pub_id, created_at, operator_id, task_index, log_level, message, details
+FROM job_log_messages
+WHERE job_id = :job_id
+ORDER BY created_at DESC
+LIMIT cast(:limit as integer);
@@ -0,0 +1,313 @@
+use arroyo_rpc::api_types::pipelines::AutoscalerConfig;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+/// The metrics for a single operator (across all of its subtasks) over the autoscaler's window
+#[derive(Debug, Clone, Default)]
+pub struct OperatorMetricsWindow {
+    pub backpressure: Vec<(SystemTime, f64)>,
+    pub messages_recv: Vec<(SystemTime, f64)>,
+}
+
+impl OperatorMetricsWindow {
+    fn average_backpressure(&self) -> Option<f64> {
+        average(&self.backpressure)
+    }
+
+    fn average_messages_recv(&self) -> Option<f64> {
+        average(&self.messages_recv)
+    }
+}
+
+fn average(values: &[(SystemTime, f64)]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().map(|(_, v)| v).sum::<f64>() / values.len() as f64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScalingDirection {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScalingDecision {
+    pub direction: ScalingDirection,
+    pub from: usize,
+    pub to: usize,
+    pub reason: String,
+    /// the operator whose metrics triggered the decision, if there was a single one
+    pub operator_id: Option<String>,
+    pub backpressure: Option<f64>,
+    pub messages_recv_rate: Option<f64>,
+}
+
+impl ScalingDecision {
+    pub fn message(&self) -> String {
+        format!(
+            "Autoscaler rescaling job from parallelism {} to {}: {}",
+            self.from, self.to, self.reason
+        )
+    }
+}
+
+/// Decides whether the job should be rescaled given the metrics observed over the configured
+/// window. This is a pure function of its inputs so that the policy can be tested against
+/// recorded metric series.
+pub fn decide(
+    config: &AutoscalerConfig,
+    parallelism: usize,
+    metrics: &HashMap<String, OperatorMetricsWindow>,
+) -> Option<ScalingDecision> {
+    if !config.enabled {
+        return None;
+    }
+
+    let min = config.min_parallelism as usize;
+    let max = config.max_parallelism as usize;
+
+    if parallelism < min || parallelism > max {
+        let to = parallelism.clamp(min, max);
+        return Some(ScalingDecision {
+            direction: if to > parallelism {
+                ScalingDirection::Up
+            } else {
+                ScalingDirection::Down
+            },
+            from: parallelism,
+            to,
+            reason: format!("parallelism is outside of the bounds [{}, {}]", min, max),
+            operator_id: None,
+            backpressure: None,
+            messages_recv_rate: None,
+        });
+    }
+
+    let averages: Vec<_> = metrics
+        .iter()
+        .filter_map(|(op, window)| Some((op, window.average_backpressure()?, window)))
+        .collect();
+
+    let (max_op, max_backpressure, max_window) = averages
+        .iter()
+        .max_by(|(_, a, _), (_, b, _)| a.total_cmp(b))?;
+
+    if *max_backpressure >= config.scale_up_backpressure {
+        let to = (parallelism * 2).min(max);
+        if to > parallelism {
+            return Some(ScalingDecision {
+                direction: ScalingDirection::Up,
+                from: parallelism,
+                to,
+                reason: format!(
+                    "operator {} had average backpressure of {:.2} over the last {}s, above the threshold of {:.2}",
+                    max_op, max_backpressure, config.window_secs, config.scale_up_backpressure
+                ),
+                operator_id: Some(max_op.to_string()),
+                backpressure: Some(*max_backpressure),
+                messages_recv_rate: max_window.average_messages_recv(),
+            });
+        }
+    } else if *max_backpressure < config.scale_down_backpressure {
+        let to = parallelism.div_ceil(2).max(min);
+        if to < parallelism {
+            return Some(ScalingDecision {
+                direction: ScalingDirection::Down,
+                from: parallelism,
+                to,
+                reason: format!(
+                    "all operators had average backpressure below {:.2} over the last {}s (max {:.2})",
+                    config.scale_down_backpressure, config.window_secs, max_backpressure
+                ),
+                operator_id: None,
+                backpressure: Some(*max_backpressure),
+                messages_recv_rate: None,
+            });
+        }
+    }
+
+    None
+}
+
+/// Tracks the autoscaling state for a single run of a job. A new autoscaler is created each
+/// time the job is scheduled, so the start of the run (including after a rescale) begins the
+/// cooldown period.
+pub struct Autoscaler {
+    config: AutoscalerConfig,
+    started: SystemTime,
+    decided: bool,
+}
+
+impl Autoscaler {
+    pub fn new(config: AutoscalerConfig, now: SystemTime) -> Self {
+        Self {
+            config,
+            started: now,
+            decided: false,
+        }
+    }
+
+    pub fn update_config(&mut self, config: AutoscalerConfig) {
+        self.config = config;
+    }
+
+    pub fn window(&self) -> Duration {
+        Duration::from_secs(self.config.window_secs)
+    }
+
+    /// Returns a scaling decision if one should be made now. At most one decision is made per
+    /// run, as acting on it will cause the job to be rescheduled.
+    pub fn evaluate(
+        &mut self,
+        now: SystemTime,
+        parallelism: usize,
+        metrics: &HashMap<String, OperatorMetricsWindow>,
+    ) -> Option<ScalingDecision> {
+        if self.decided {
+            return None;
+        }
+
+        // wait until we have a full window of metrics and the cooldown has elapsed
+        let wait = self
+            .window()
+            .max(Duration::from_secs(self.config.cooldown_secs));
+        if now.duration_since(self.started).unwrap_or_default() < wait {
+            return None;
+        }
+
+        let decision = decide(&self.config, parallelism, metrics)?;
+        self.decided = true;
+        Some(decision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job_controller::job_metrics::COLLECTION_RATE;
+
+    fn config() -> AutoscalerConfig {
+        AutoscalerConfig {
+            enabled: true,
+            min_parallelism: 1,
+            max_parallelism: 8,
+            window_secs: 60,
+            cooldown_secs: 120,
+            scale_up_backpressure: 0.5,
+            scale_down_backpressure: 0.05,
+        }
+    }
+
+    fn series(start: SystemTime, values: &[f64]) -> Vec<(SystemTime, f64)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (start + COLLECTION_RATE * i as u32, *v))
+            .collect()
+    }
+
+    fn metrics(
+        start: SystemTime,
+        ops: &[(&str, &[f64])],
+    ) -> HashMap<String, OperatorMetricsWindow> {
+        ops.iter()
+            .map(|(op, bp)| {
+                (
+                    op.to_string(),
+                    OperatorMetricsWindow {
+                        backpressure: series(start, bp),
+                        messages_recv: series(start, &vec![100.0; bp.len()]),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_scale_up_on_backpressure() {
+        let now = SystemTime::now();
+        let m = metrics(
+            now,
+            &[
+                ("source", &[0.9, 0.8, 0.95, 0.85]),
+                ("sink", &[0.0, 0.01, 0.0, 0.0]),
+            ],
+        );
+
+        let decision = decide(&config(), 2, &m).unwrap();
+        assert_eq!(decision.direction, ScalingDirection::Up);
+        assert_eq!(decision.to, 4);
+        assert_eq!(decision.operator_id.as_deref(), Some("source"));
+        assert_eq!(decision.messages_recv_rate, Some(100.0));
+
+        // bounded by the max parallelism
+        assert_eq!(decide(&config(), 6, &m).unwrap().to, 8);
+        assert_eq!(decide(&config(), 8, &m), None);
+    }
+
+    #[test]
+    fn test_scale_down_when_idle() {
+        let now = SystemTime::now();
+        let m = metrics(now, &[("source", &[0.01, 0.0, 0.02]), ("sink", &[0.0; 3])]);
+
+        let decision = decide(&config(), 5, &m).unwrap();
+        assert_eq!(decision.direction, ScalingDirection::Down);
+        assert_eq!(decision.to, 3);
+
+        assert_eq!(decide(&config(), 1, &m), None);
+    }
+
+    #[test]
+    fn test_no_decision_in_band() {
+        let now = SystemTime::now();
+        let m = metrics(now, &[("source", &[0.2, 0.3, 0.1])]);
+        assert_eq!(decide(&config(), 4, &m), None);
+
+        // a brief spike doesn't move the average over the threshold
+        let m = metrics(now, &[("source", &[0.1, 0.1, 1.0, 0.1, 0.1])]);
+        assert_eq!(decide(&config(), 4, &m), None);
+
+        assert_eq!(decide(&config(), 4, &HashMap::new()), None);
+
+        let mut disabled = config();
+        disabled.enabled = false;
+        let m = metrics(now, &[("source", &[1.0; 5])]);
+        assert_eq!(decide(&disabled, 4, &m), None);
+    }
+
+    #[test]
+    fn test_clamps_to_bounds() {
+        let m = HashMap::new();
+        let decision = decide(&config(), 12, &m).unwrap();
+        assert_eq!(decision.direction, ScalingDirection::Down);
+        assert_eq!(decision.to, 8);
+    }
+
+    #[test]
+    fn test_cooldown() {
+        let start = SystemTime::now();
+        let mut autoscaler = Autoscaler::new(config(), start);
+        let m = metrics(start, &[("source", &[1.0; 30])]);
+
+        assert_eq!(
+            autoscaler.evaluate(start + Duration::from_secs(60), 2, &m),
+            None
+        );
+
+        let decision = autoscaler
+            .evaluate(start + Duration::from_secs(121), 2, &m)
+            .unwrap();
+        assert_eq!(decision.to, 4);
+
+        // only one decision is made per run
+        assert_eq!(
+            autoscaler.evaluate(start + Duration::from_secs(200), 2, &m),
+            None
+        );
+    }
+}
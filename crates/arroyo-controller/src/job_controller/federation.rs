@@ -0,0 +1,461 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use arroyo_datastream::logical::LogicalProgram;
+use arroyo_rpc::grpc::{self, MetricFamily};
+use arroyo_types::{to_millis, INPUT_WATERMARK, MAX_EVENT_TIME};
+use prometheus::core::{Collector, Desc};
+use prometheus::proto;
+use tokio::sync::RwLock;
+
+use crate::job_controller::job_metrics::JobMetrics;
+
+/// Jobs whose workers haven't been scraped for this long are no longer reported
+const STALE_AFTER: Duration = Duration::from_secs(30);
+
+const WORKER_PREFIX: &str = "arroyo_worker_";
+const JOB_PREFIX: &str = "arroyo_job_";
+
+/// Identifies the job that a set of federated metrics belongs to
+#[derive(Clone, Debug, Default)]
+pub struct JobLabels {
+    pub job_id: String,
+    pub pipeline_id: String,
+    pub pipeline_name: String,
+}
+
+impl JobLabels {
+    fn pairs(&self) -> [(&'static str, &str); 3] {
+        [
+            ("job_id", &self.job_id),
+            ("pipeline_id", &self.pipeline_id),
+            ("pipeline_name", &self.pipeline_name),
+        ]
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CheckpointSummary {
+    pub epoch: u32,
+    pub duration: Duration,
+    /// bytes written by each (operator_id, subtask_idx)
+    pub bytes: HashMap<(String, u32), u64>,
+}
+
+#[derive(Default)]
+struct FederatedState {
+    families: Vec<MetricFamily>,
+    scraped_at: Option<Instant>,
+    checkpoint: Option<CheckpointSummary>,
+}
+
+/// The latest metrics scraped from a job's workers and its last completed checkpoint, which
+/// are re-exported from the controller with labels identifying the job and pipeline
+#[derive(Clone)]
+pub struct FederatedMetrics {
+    labels: Arc<JobLabels>,
+    program: Arc<LogicalProgram>,
+    state: Arc<std::sync::RwLock<FederatedState>>,
+}
+
+impl FederatedMetrics {
+    pub fn new(program: Arc<LogicalProgram>, labels: JobLabels) -> Self {
+        Self {
+            labels: Arc::new(labels),
+            program,
+            state: Default::default(),
+        }
+    }
+
+    /// Replaces the worker metrics with a new scrape, keeping only the per-task metrics for
+    /// operators in this job
+    pub fn record_scrape(&self, families: Vec<MetricFamily>) {
+        let families = families
+            .into_iter()
+            .filter(|f| {
+                f.name
+                    .as_ref()
+                    .map(|n| n.starts_with(WORKER_PREFIX))
+                    .unwrap_or(false)
+            })
+            .map(|mut f| {
+                f.metric.retain(|m| {
+                    find_label(&m.label, "operator_id")
+                        .and_then(|id| self.program.operator_index(id))
+                        .is_some()
+                });
+                f
+            })
+            .filter(|f| !f.metric.is_empty())
+            .collect();
+
+        let mut state = self.state.write().unwrap();
+        state.families = families;
+        state.scraped_at = Some(Instant::now());
+    }
+
+    pub fn record_checkpoint(&self, checkpoint: CheckpointSummary) {
+        self.state.write().unwrap().checkpoint = Some(checkpoint);
+    }
+
+    fn collect_into(&self, now: SystemTime, out: &mut Families) {
+        let state = self.state.read().unwrap();
+        if !state
+            .scraped_at
+            .map(|t| t.elapsed() < STALE_AFTER)
+            .unwrap_or(false)
+        {
+            return;
+        }
+
+        let now = to_millis(now) as f64 / 1000.0;
+        let mut operator_names = HashMap::new();
+        let mut event_times: BTreeMap<(String, String), Vec<f64>> = BTreeMap::new();
+
+        for family in &state.families {
+            let name = family.name();
+            let federated = format!("{}{}", JOB_PREFIX, &name[WORKER_PREFIX.len()..]);
+
+            for metric in &family.metric {
+                let labels: Vec<_> = metric
+                    .label
+                    .iter()
+                    .filter_map(|l| Some((l.name.as_deref()?, l.value.as_deref()?)))
+                    .collect();
+
+                out.push(
+                    &federated,
+                    family.help(),
+                    convert_type(family.r#type()),
+                    self.to_metric(&labels, metric),
+                );
+
+                let (Some(operator_id), Some(operator_name)) = (
+                    find_label(&metric.label, "operator_id"),
+                    find_label(&metric.label, "operator_name"),
+                ) else {
+                    continue;
+                };
+
+                operator_names.insert(operator_id, operator_name);
+
+                let Some(value) = metric.gauge.as_ref().and_then(|g| g.value) else {
+                    continue;
+                };
+
+                // these are reported as 0 until the task has seen its first watermark or event
+                if value <= 0.0 {
+                    continue;
+                }
+
+                if name == INPUT_WATERMARK {
+                    out.push(
+                        "arroyo_job_watermark_lag_seconds",
+                        "Difference between the current time and the input watermark of the subtask",
+                        proto::MetricType::GAUGE,
+                        self.gauge(&labels, (now - value / 1000.0).max(0.0)),
+                    );
+                } else if name == MAX_EVENT_TIME {
+                    event_times
+                        .entry((operator_id.to_string(), operator_name.to_string()))
+                        .or_default()
+                        .push(value / 1000.0);
+                }
+            }
+        }
+
+        for ((operator_id, operator_name), times) in event_times {
+            let max = times.iter().copied().fold(f64::MIN, f64::max);
+            let min = times.iter().copied().fold(f64::MAX, f64::min);
+            out.push(
+                "arroyo_job_event_time_skew_seconds",
+                "Difference between the maximum event time seen by the most and least advanced subtasks of the source",
+                proto::MetricType::GAUGE,
+                self.gauge(
+                    &[
+                        ("operator_id", operator_id.as_str()),
+                        ("operator_name", operator_name.as_str()),
+                    ],
+                    max - min,
+                ),
+            );
+        }
+
+        let Some(checkpoint) = &state.checkpoint else {
+            return;
+        };
+
+        out.push(
+            "arroyo_job_checkpoint_duration_seconds",
+            "Time taken by the last completed checkpoint",
+            proto::MetricType::GAUGE,
+            self.gauge(&[], checkpoint.duration.as_secs_f64()),
+        );
+        out.push(
+            "arroyo_job_checkpoint_bytes",
+            "Total bytes written by the last completed checkpoint",
+            proto::MetricType::GAUGE,
+            self.gauge(&[], checkpoint.bytes.values().sum::<u64>() as f64),
+        );
+        out.push(
+            "arroyo_job_checkpoint_epoch",
+            "Epoch of the last completed checkpoint",
+            proto::MetricType::GAUGE,
+            self.gauge(&[], checkpoint.epoch as f64),
+        );
+
+        for ((operator_id, subtask_idx), bytes) in &checkpoint.bytes {
+            let operator_name = operator_names
+                .get(operator_id.as_str())
+                .copied()
+                .unwrap_or_default();
+            let subtask_idx = subtask_idx.to_string();
+            out.push(
+                "arroyo_job_state_size_bytes",
+                "Size of the state written by the subtask in the last completed checkpoint",
+                proto::MetricType::GAUGE,
+                self.gauge(
+                    &[
+                        ("operator_id", operator_id.as_str()),
+                        ("operator_name", operator_name),
+                        ("subtask_idx", subtask_idx.as_str()),
+                    ],
+                    *bytes as f64,
+                ),
+            );
+        }
+    }
+
+    fn labeled(&self, labels: &[(&str, &str)]) -> proto::Metric {
+        let mut metric = proto::Metric::default();
+        for (name, value) in self.labels.pairs().iter().chain(labels) {
+            let mut pair = proto::LabelPair::default();
+            pair.set_name(name.to_string());
+            pair.set_value(value.to_string());
+            metric.mut_label().push(pair);
+        }
+        metric
+    }
+
+    fn gauge(&self, labels: &[(&str, &str)], value: f64) -> proto::Metric {
+        let mut metric = self.labeled(labels);
+        let mut gauge = proto::Gauge::default();
+        gauge.set_value(value);
+        metric.set_gauge(gauge);
+        metric
+    }
+
+    fn to_metric(&self, labels: &[(&str, &str)], m: &grpc::Metric) -> proto::Metric {
+        let mut metric = self.labeled(labels);
+
+        if let Some(value) = m.gauge.as_ref().and_then(|g| g.value) {
+            let mut gauge = proto::Gauge::default();
+            gauge.set_value(value);
+            metric.set_gauge(gauge);
+        }
+
+        if let Some(value) = m.counter.as_ref().and_then(|c| c.value) {
+            let mut counter = proto::Counter::default();
+            counter.set_value(value);
+            metric.set_counter(counter);
+        }
+
+        if let Some(value) = m.untyped.as_ref().and_then(|u| u.value) {
+            let mut untyped = proto::Untyped::default();
+            untyped.set_value(value);
+            metric.set_untyped(untyped);
+        }
+
+        if let Some(h) = &m.histogram {
+            let mut histogram = proto::Histogram::default();
+            histogram.set_sample_count(h.sample_count());
+            histogram.set_sample_sum(h.sample_sum());
+            for b in &h.bucket {
+                let mut bucket = proto::Bucket::default();
+                bucket.set_cumulative_count(b.cumulative_count());
+                bucket.set_upper_bound(b.upper_bound());
+                histogram.mut_bucket().push(bucket);
+            }
+            metric.set_histogram(histogram);
+        }
+
+        if let Some(s) = &m.summary {
+            let mut summary = proto::Summary::default();
+            summary.set_sample_count(s.sample_count());
+            summary.set_sample_sum(s.sample_sum());
+            for q in &s.quantile {
+                let mut quantile = proto::Quantile::default();
+                quantile.set_quantile(q.quantile());
+                quantile.set_value(q.value());
+                summary.mut_quantile().push(quantile);
+            }
+            metric.set_summary(summary);
+        }
+
+        metric
+    }
+}
+
+fn find_label<'a>(labels: &'a [grpc::LabelPair], name: &str) -> Option<&'a str> {
+    labels
+        .iter()
+        .find(|l| l.name.as_deref() == Some(name))?
+        .value
+        .as_deref()
+}
+
+fn convert_type(t: grpc::MetricType) -> proto::MetricType {
+    match t {
+        grpc::MetricType::Counter => proto::MetricType::COUNTER,
+        grpc::MetricType::Gauge => proto::MetricType::GAUGE,
+        grpc::MetricType::Summary => proto::MetricType::SUMMARY,
+        grpc::MetricType::Untyped => proto::MetricType::UNTYPED,
+        grpc::MetricType::Histogram => proto::MetricType::HISTOGRAM,
+    }
+}
+
+#[derive(Default)]
+struct Families(BTreeMap<String, proto::MetricFamily>);
+
+impl Families {
+    fn push(&mut self, name: &str, help: &str, t: proto::MetricType, metric: proto::Metric) {
+        self.0
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                let mut family = proto::MetricFamily::default();
+                family.set_name(name.to_string());
+                family.set_help(help.to_string());
+                family.set_field_type(t);
+                family
+            })
+            .mut_metric()
+            .push(metric);
+    }
+}
+
+/// Exports the metrics of all running jobs, labeled by job and pipeline, through the
+/// controller's prometheus registry
+pub struct FederatedCollector {
+    metrics: Arc<RwLock<HashMap<Arc<String>, JobMetrics>>>,
+}
+
+impl FederatedCollector {
+    pub fn new(metrics: Arc<RwLock<HashMap<Arc<String>, JobMetrics>>>) -> Self {
+        Self { metrics }
+    }
+}
+
+impl Collector for FederatedCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        // the set of metrics depends on the running jobs, so this is registered unchecked
+        vec![]
+    }
+
+    fn collect(&self) -> Vec<proto::MetricFamily> {
+        // collect is called synchronously from the metrics endpoint, so we skip this scrape
+        // rather than block if the job metrics are being modified
+        let Ok(jobs) = self.metrics.try_read() else {
+            return vec![];
+        };
+
+        let now = SystemTime::now();
+        let mut families = Families::default();
+        for job in jobs.values() {
+            job.federated().collect_into(now, &mut families);
+        }
+
+        families.0.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arroyo_datastream::logical::{LogicalNode, OperatorName};
+
+    fn program() -> Arc<LogicalProgram> {
+        let mut graph = petgraph::graph::DiGraph::new();
+        graph.add_node(LogicalNode {
+            operator_id: "watermark_1".to_string(),
+            description: "watermark".to_string(),
+            operator_name: OperatorName::ExpressionWatermark,
+            operator_config: vec![],
+            parallelism: 2,
+        });
+        Arc::new(LogicalProgram::new(graph, Default::default()))
+    }
+
+    fn gauge_family(name: &str, operator_id: &str, values: &[f64]) -> MetricFamily {
+        MetricFamily {
+            name: Some(name.to_string()),
+            help: Some("help".to_string()),
+            r#type: Some(grpc::MetricType::Gauge as i32),
+            metric: values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| grpc::Metric {
+                    label: [
+                        ("operator_id", operator_id.to_string()),
+                        ("operator_name", "watermark".to_string()),
+                        ("subtask_idx", i.to_string()),
+                    ]
+                    .into_iter()
+                    .map(|(k, v)| grpc::LabelPair {
+                        name: Some(k.to_string()),
+                        value: Some(v),
+                    })
+                    .collect(),
+                    gauge: Some(grpc::Gauge { value: Some(*v) }),
+                    ..Default::default()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_federation() {
+        let metrics = FederatedMetrics::new(
+            program(),
+            JobLabels {
+                job_id: "job_1".to_string(),
+                pipeline_id: "pl_1".to_string(),
+                pipeline_name: "my_pipeline".to_string(),
+            },
+        );
+
+        metrics.record_scrape(vec![
+            gauge_family(MAX_EVENT_TIME, "watermark_1", &[10_000.0, 4_000.0]),
+            gauge_family(INPUT_WATERMARK, "watermark_1", &[0.0, 5_000.0]),
+            // operators that aren't part of the job are dropped
+            gauge_family(MAX_EVENT_TIME, "other_1", &[10_000.0]),
+        ]);
+
+        let mut families = Families::default();
+        metrics.collect_into(arroyo_types::from_millis(65_000), &mut families);
+        let families = families.0;
+
+        let max_event_time = &families["arroyo_job_max_event_time"];
+        assert_eq!(max_event_time.get_metric().len(), 2);
+        let labels: HashMap<_, _> = max_event_time.get_metric()[0]
+            .get_label()
+            .iter()
+            .map(|l| (l.get_name(), l.get_value()))
+            .collect();
+        assert_eq!(labels["pipeline_id"], "pl_1");
+        assert_eq!(labels["pipeline_name"], "my_pipeline");
+        assert_eq!(labels["job_id"], "job_1");
+        assert_eq!(labels["operator_id"], "watermark_1");
+
+        let skew = &families["arroyo_job_event_time_skew_seconds"];
+        assert_eq!(skew.get_metric().len(), 1);
+        assert_eq!(skew.get_metric()[0].get_gauge().get_value(), 6.0);
+
+        // the subtask that hasn't seen a watermark doesn't report lag
+        let lag = &families["arroyo_job_watermark_lag_seconds"];
+        assert_eq!(lag.get_metric().len(), 1);
+        assert_eq!(lag.get_metric()[0].get_gauge().get_value(), 60.0);
+
+        assert!(!families.contains_key("arroyo_job_checkpoint_duration_seconds"));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::job_controller::autoscaler::OperatorMetricsWindow;
+use crate::job_controller::federation::{FederatedMetrics, JobLabels};
 use arroyo_datastream::logical::LogicalProgram;
 use arroyo_rpc::api_types::metrics::{
     Metric, MetricGroup, MetricName, OperatorMetricGroup, SubtaskMetrics,
@@ -23,6 +25,15 @@ pub const RATE_METRICS: [MetricName; 4] = [
     MetricName::MessagesSent,
 ];
 
+/// Metrics whose latest reported values are recorded as-is; only tasks that report them (for
+/// example, sources for max event time) will have them
+pub const GAUGE_METRICS: [MetricName; 4] = [
+    MetricName::InputWatermark,
+    MetricName::OutputWatermark,
+    MetricName::MaxEventTime,
+    MetricName::SourceIdle,
+];
+
 pub fn get_metric_name(name: &str) -> Option<MetricName> {
     MetricName::from_str(&name["arroyo_worker_".len()..]).ok()
 }
@@ -37,10 +48,11 @@ pub struct TaskKey {
 pub struct JobMetrics {
     program: Arc<LogicalProgram>,
     tasks: Arc<RwLock<HashMap<TaskKey, TaskMetrics>>>,
+    federated: FederatedMetrics,
 }
 
 impl JobMetrics {
-    pub fn new(program: Arc<LogicalProgram>) -> Self {
+    pub fn new(program: Arc<LogicalProgram>, labels: JobLabels) -> Self {
         let mut tasks = HashMap::new();
         for op in program.graph.node_indices() {
             for i in 0..program.graph[op].parallelism {
@@ -55,11 +67,16 @@ impl JobMetrics {
         }
 
         Self {
+            federated: FederatedMetrics::new(program.clone(), labels),
             program,
             tasks: Arc::new(RwLock::new(tasks)),
         }
     }
 
+    pub fn federated(&self) -> &FederatedMetrics {
+        &self.federated
+    }
+
     pub async fn update(
         &self,
         operator_id: u32,
@@ -87,6 +104,25 @@ impl JobMetrics {
             }
         }
 
+        for metric in GAUGE_METRICS {
+            let Some(value) = values.get(&metric).copied() else {
+                continue;
+            };
+
+            let value = match metric {
+                MetricName::SourceIdle => value as f64,
+                // times are reported as 0 until the task has seen its first watermark or event
+                _ if value == 0 => continue,
+                // workers report times in millis
+                _ => (value * 1000) as f64,
+            };
+
+            task.gauges
+                .entry(metric)
+                .or_insert_with(|| CircularBuffer::new((UNIX_EPOCH, 0.0)))
+                .push((now, value));
+        }
+
         let queue_size = values
             .get(&MetricName::TxQueueSize)
             .copied()
@@ -101,6 +137,37 @@ impl JobMetrics {
         task.update_backpressure(now, backpressure);
     }
 
+    /// Returns the backpressure and message rate samples collected since `since` for each
+    /// operator, combined across all of its subtasks
+    pub async fn operator_windows(
+        &self,
+        since: SystemTime,
+    ) -> HashMap<String, OperatorMetricsWindow> {
+        let mut windows: HashMap<String, OperatorMetricsWindow> = HashMap::new();
+
+        for (k, v) in self.tasks.read().await.iter() {
+            let Some(node) = self
+                .program
+                .graph
+                .node_weight(NodeIndex::new(k.operator_id as usize))
+            else {
+                continue;
+            };
+
+            let window = windows.entry(node.operator_id.clone()).or_default();
+            window
+                .backpressure
+                .extend(v.backpressure.iter().filter(|(t, _)| *t >= since));
+            if let Some(rate) = v.rates.get(&MetricName::MessagesRecv) {
+                window
+                    .messages_recv
+                    .extend(rate.iter().filter(|(t, _)| *t >= since));
+            }
+        }
+
+        windows
+    }
+
     pub async fn get_groups(&self) -> Vec<OperatorMetricGroup> {
         let mut metric_groups: HashMap<u32, HashMap<MetricName, Vec<SubtaskMetrics>>> =
             HashMap::new();
@@ -121,6 +188,19 @@ impl JobMetrics {
                 });
             }
 
+            for (metric, values) in &v.gauges {
+                op.entry(*metric).or_default().push(SubtaskMetrics {
+                    index: k.subtask_idx,
+                    metrics: values
+                        .iter()
+                        .map(|(t, v)| Metric {
+                            time: to_micros(t),
+                            value: v,
+                        })
+                        .collect(),
+                });
+            }
+
             op.entry(MetricName::Backpressure)
                 .or_default()
                 .push(SubtaskMetrics {
@@ -161,6 +241,7 @@ impl JobMetrics {
 pub struct TaskMetrics {
     rates: HashMap<MetricName, RateMetric>,
     backpressure: CircularBuffer<(SystemTime, f64), NUM_BUCKETS>,
+    gauges: HashMap<MetricName, CircularBuffer<(SystemTime, f64), NUM_BUCKETS>>,
 }
 
 impl TaskMetrics {
@@ -171,6 +252,7 @@ impl TaskMetrics {
                 .map(|&m| (m, RateMetric::new()))
                 .collect(),
             backpressure: CircularBuffer::new((UNIX_EPOCH, 0.0)),
+            gauges: HashMap::new(),
         }
     }
 
@@ -279,7 +361,14 @@ impl<T: Copy, const N: usize> CircularBuffer<T, N> {
 
 #[cfg(test)]
 mod tests {
-    use crate::job_controller::job_metrics::{RateMetric, COLLECTION_RATE, NUM_BUCKETS};
+    use crate::job_controller::federation::JobLabels;
+    use crate::job_controller::job_metrics::{
+        JobMetrics, RateMetric, COLLECTION_RATE, NUM_BUCKETS,
+    };
+    use arroyo_datastream::logical::{LogicalNode, LogicalProgram, OperatorName};
+    use arroyo_rpc::api_types::metrics::MetricName;
+    use std::collections::HashMap;
+    use std::sync::Arc;
     use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
     #[test]
@@ -320,4 +409,53 @@ mod tests {
             last_time = time;
         }
     }
+
+    #[tokio::test]
+    async fn test_gauge_metrics() {
+        let mut graph = petgraph::graph::DiGraph::new();
+        graph.add_node(LogicalNode {
+            operator_id: "watermark_1".to_string(),
+            description: "watermark".to_string(),
+            operator_name: OperatorName::ExpressionWatermark,
+            operator_config: vec![],
+            parallelism: 1,
+        });
+        let metrics = JobMetrics::new(
+            Arc::new(LogicalProgram::new(graph, Default::default())),
+            JobLabels::default(),
+        );
+
+        metrics
+            .update(
+                0,
+                0,
+                &HashMap::from([
+                    (MetricName::InputWatermark, 0),
+                    (MetricName::OutputWatermark, 1_000),
+                    (MetricName::SourceIdle, 1),
+                ]),
+            )
+            .await;
+
+        let groups = metrics.get_groups().await;
+        let group = |name: MetricName| {
+            groups[0]
+                .metric_groups
+                .iter()
+                .find(|g| g.name == name)
+                .map(|g| {
+                    g.subtasks[0]
+                        .metrics
+                        .iter()
+                        .map(|m| m.value)
+                        .collect::<Vec<_>>()
+                })
+        };
+
+        // times are converted to micros, and ignored until they're set
+        assert_eq!(group(MetricName::InputWatermark), None);
+        assert_eq!(group(MetricName::OutputWatermark), Some(vec![1_000_000.0]));
+        assert_eq!(group(MetricName::SourceIdle), Some(vec![1.0]));
+        assert_eq!(group(MetricName::MaxEventTime), None);
+    }
 }
@@ -1,7 +1,7 @@
 use std::str::FromStr;
 use std::sync::Arc;
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap},
     env,
     time::{Duration, Instant, SystemTime},
 };
@@ -19,6 +19,7 @@ use cornucopia_async::DatabaseSource;
 use time::OffsetDateTime;
 
 use arroyo_datastream::logical::LogicalProgram;
+use arroyo_rpc::api_types::checkpoints::{CheckpointAlignmentConfig, CheckpointAlignmentMode};
 use arroyo_rpc::api_types::metrics::MetricName;
 use arroyo_rpc::public_ids::{generate_id, IdTypes};
 use arroyo_state::checkpoint_state::CheckpointState;
@@ -29,15 +30,21 @@ use tracing::{error, info, warn};
 
 use crate::job_controller::job_metrics::{get_metric_name, JobMetrics};
 use crate::types::public::CheckpointState as DbCheckpointState;
+use crate::types::public::LogLevel;
 use crate::{queries::controller_queries, JobConfig, JobMessage, RunningMessage};
 use arroyo_state::committing_state::CommittingState;
 
+use self::autoscaler::Autoscaler;
 use self::checkpointer::CheckpointingOrCommittingState;
+use self::federation::CheckpointSummary;
+use self::retention::RetentionCandidate;
 
+pub mod autoscaler;
 mod checkpointer;
+pub mod federation;
 pub mod job_metrics;
+pub mod retention;
 
-const CHECKPOINTS_TO_KEEP: u32 = 4;
 const CHECKPOINT_ROWS_TO_KEEP: u32 = 100;
 const COMPACT_EVERY: u32 = 2;
 const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(30);
@@ -315,6 +322,7 @@ impl RunningJobModel {
         &mut self,
         organization_id: &str,
         db: &DatabaseSource,
+        alignment: &CheckpointAlignmentConfig,
         then_stop: bool,
     ) -> anyhow::Result<()> {
         self.epoch += 1;
@@ -336,6 +344,9 @@ impl RunningJobModel {
                     min_epoch: self.min_epoch,
                     then_stop,
                     is_commit: false,
+                    unaligned: alignment.mode == CheckpointAlignmentMode::Unaligned,
+                    alignment_timeout_micros: (alignment.mode == CheckpointAlignmentMode::Auto)
+                        .then_some(alignment.alignment_timeout_micros),
                 }))
                 .await?;
         }
@@ -417,11 +428,30 @@ impl RunningJobModel {
                     checkpointing.save_state().await?;
 
                     let committing_state = checkpointing.committing_state();
-                    let duration = checkpointing
+                    let elapsed = checkpointing
                         .start_time()
                         .elapsed()
-                        .unwrap_or(Duration::ZERO)
-                        .as_secs_f32();
+                        .unwrap_or(Duration::ZERO);
+                    let duration = elapsed.as_secs_f32();
+
+                    self.metrics
+                        .federated()
+                        .record_checkpoint(CheckpointSummary {
+                            epoch: self.epoch,
+                            duration: elapsed,
+                            bytes: checkpointing
+                                .operator_details
+                                .iter()
+                                .flat_map(|(operator_id, detail)| {
+                                    detail.tasks.iter().map(move |(subtask_idx, task)| {
+                                        (
+                                            (operator_id.clone(), *subtask_idx),
+                                            task.bytes.unwrap_or_default(),
+                                        )
+                                    })
+                                })
+                                .collect(),
+                        });
                     // shortcut if committing is unnecessary
                     if committing_state.done() {
                         info!("no committing");
@@ -478,9 +508,10 @@ impl RunningJobModel {
         Ok(())
     }
 
-    pub fn cleanup_needed(&self) -> Option<u32> {
-        if self.epoch - self.min_epoch > CHECKPOINTS_TO_KEEP && self.epoch % COMPACT_EVERY == 0 {
-            Some(self.epoch - CHECKPOINTS_TO_KEEP)
+    pub fn cleanup_needed(&self, keep_last: u32) -> Option<u32> {
+        let keep_last = keep_last.max(1);
+        if self.epoch - self.min_epoch > keep_last && self.epoch % COMPACT_EVERY == 0 {
+            Some(self.epoch - keep_last)
         } else {
             None
         }
@@ -534,6 +565,7 @@ pub struct JobController {
     config: JobConfig,
     model: RunningJobModel,
     cleanup_task: Option<JoinHandle<anyhow::Result<u32>>>,
+    autoscaler: Option<Autoscaler>,
 }
 
 impl std::fmt::Debug for JobController {
@@ -562,6 +594,11 @@ impl JobController {
         commit_state: Option<CommittingState>,
         metrics: JobMetrics,
     ) -> Self {
+        let autoscaler = config
+            .autoscaler
+            .clone()
+            .map(|c| Autoscaler::new(c, SystemTime::now()));
+
         Self {
             db,
             model: RunningJobModel {
@@ -607,10 +644,18 @@ impl JobController {
             },
             config,
             cleanup_task: None,
+            autoscaler,
         }
     }
 
     pub fn update_config(&mut self, config: JobConfig) {
+        match (&mut self.autoscaler, &config.autoscaler) {
+            (Some(autoscaler), Some(c)) => autoscaler.update_config(c.clone()),
+            (None, Some(c)) => {
+                self.autoscaler = Some(Autoscaler::new(c.clone(), SystemTime::now()))
+            }
+            (_, None) => self.autoscaler = None,
+        }
         self.config = config;
     }
 
@@ -642,12 +687,15 @@ impl JobController {
 
         self.model.metric_update_task = Some(tokio::spawn(async move {
             let mut metrics: HashMap<(u32, u32), HashMap<MetricName, u64>> = HashMap::new();
+            let mut families = vec![];
 
             for (id, mut connect) in workers {
                 let Ok(e) = connect.get_metrics(MetricsReq {}).await else {
                     warn!("Failed to collect metrics from worker {:?}", id);
                     return;
                 };
+                let e = e.into_inner();
+                families.extend(e.metrics.iter().cloned());
 
                 fn find_label<'a>(labels: &'a [LabelPair], name: &'static str) -> Option<&'a str> {
                     Some(
@@ -660,8 +708,7 @@ impl JobController {
                     )
                 }
 
-                e.into_inner()
-                    .metrics
+                e.metrics
                     .into_iter()
                     .filter_map(|f| Some((get_metric_name(&f.name?)?, f.metric)))
                     .flat_map(|(metric, values)| {
@@ -690,6 +737,8 @@ impl JobController {
             for ((operator_idx, subtask_idx), values) in metrics {
                 job_metrics.update(operator_idx, subtask_idx, &values).await;
             }
+
+            job_metrics.federated().record_scrape(families);
         }));
     }
 
@@ -740,7 +789,10 @@ impl JobController {
             }
         }
 
-        if let Some(new_epoch) = self.model.cleanup_needed() {
+        if let Some(new_epoch) = self
+            .model
+            .cleanup_needed(self.config.checkpoint_retention.keep_last)
+        {
             if self.cleanup_task.is_none() && self.model.checkpoint_state.is_none() {
                 self.cleanup_task = Some(self.start_cleanup(new_epoch));
             }
@@ -760,11 +812,89 @@ impl JobController {
         if self.model.last_updated_metrics.elapsed() > job_metrics::COLLECTION_RATE {
             self.update_metrics().await;
             self.model.last_updated_metrics = Instant::now();
+
+            if self.model.checkpoint_state.is_none() {
+                self.autoscale().await?;
+            }
         }
 
         Ok(ControllerProgress::Continue)
     }
 
+    /// Evaluates the autoscaling policy against the recent metrics; if it decides to rescale, the
+    /// new parallelism is written to the job's config, which will cause the job to be rescaled
+    /// on the next config update
+    async fn autoscale(&mut self) -> anyhow::Result<()> {
+        let Some(autoscaler) = &mut self.autoscaler else {
+            return Ok(());
+        };
+
+        let now = SystemTime::now();
+        let windows = self
+            .model
+            .metrics
+            .operator_windows(now - autoscaler.window())
+            .await;
+
+        let parallelism = self
+            .model
+            .operator_parallelism
+            .values()
+            .copied()
+            .max()
+            .unwrap_or(1);
+
+        let Some(decision) = autoscaler.evaluate(now, parallelism, &windows) else {
+            return Ok(());
+        };
+
+        info!(
+            message = "autoscaler rescaling job",
+            job_id = *self.config.id,
+            from = decision.from,
+            to = decision.to,
+            reason = decision.reason
+        );
+
+        // scale each operator proportionally, so that per-operator overrides are preserved
+        let overrides: HashMap<_, _> = self
+            .model
+            .operator_parallelism
+            .iter()
+            .map(|(op, p)| {
+                (
+                    op.clone(),
+                    (p * decision.to)
+                        .div_ceil(decision.from)
+                        .clamp(1, decision.to),
+                )
+            })
+            .collect();
+
+        let c = self.db.client().await?;
+        controller_queries::execute_update_parallelism_overrides(
+            &c,
+            &serde_json::to_value(&overrides)?,
+            &OffsetDateTime::now_utc(),
+            &*self.config.id,
+        )
+        .await?;
+
+        controller_queries::execute_create_job_log_message(
+            &c,
+            &generate_id(IdTypes::JobLogMessage),
+            &*self.config.id,
+            &None::<&str>,
+            &None::<i64>,
+            &LogLevel::info,
+            &decision.message(),
+            &serde_json::to_string(&decision)?,
+        )
+        .await?;
+
+        Ok(())
+    }
+
     pub async fn stop_job(&mut self, stop_mode: StopMode) -> anyhow::Result<()> {
         for c in self.model.workers.values_mut() {
             c.connect
@@ -780,7 +910,12 @@ impl JobController {
     pub async fn checkpoint(&mut self, then_stop: bool) -> anyhow::Result<bool> {
         if self.model.checkpoint_state.is_none() {
             self.model
-                .start_checkpoint(&self.config.organization_id, &self.db, then_stop)
+                .start_checkpoint(
+                    &self.config.organization_id,
+                    &self.db,
+                    &self.config.checkpoint_alignment,
+                    then_stop,
+                )
                 .await?;
             Ok(true)
         } else {
@@ -855,6 +990,7 @@ impl JobController {
         let min_epoch = self.model.min_epoch.max(1);
         let job_id = self.config.id.clone();
         let db = self.db.clone();
+        let retention = self.config.checkpoint_retention.clone();
 
         info!(
             message = "Starting cleaning",
@@ -868,15 +1004,71 @@ impl JobController {
         tokio::spawn(async move {
             let checkpoint = StateBackend::load_checkpoint_metadata(&job_id, cur_epoch).await?;
 
+            let c = db.client().await?;
+
+            // determine which of the completed checkpoints before the new min epoch should be
+            // retained; this includes checkpoints retained by previous cleanups, which may have
+            // since expired or been unpinned
+            let candidates: Vec<_> =
+                controller_queries::fetch_get_ready_checkpoints(&c, &*job_id, &(new_min as i32))
+                    .await?
+                    .into_iter()
+                    .map(|r| RetentionCandidate {
+                        epoch: r.epoch as u32,
+                        finish_time: r.finish_time.map(|t| t.into()),
+                        pinned: r.pinned,
+                    })
+                    .collect();
+
+            let retained = retention::epochs_to_retain(&retention, &candidates, SystemTime::now());
+
             controller_queries::execute_mark_compacting(
-                &db.client().await?,
+                &c,
                 &*job_id,
                 &(min_epoch as i32),
                 &(new_min as i32),
             )
             .await?;
 
-            StateBackend::cleanup_checkpoint(checkpoint, min_epoch, new_min).await?;
+            for candidate in &candidates {
+                if !retained.contains(&candidate.epoch) {
+                    controller_queries::execute_mark_checkpoint_compacting(
+                        &c,
+                        &*job_id,
+                        &(candidate.epoch as i32),
+                    )
+                    .await?;
+                }
+            }
+
+            // re-read the state from the database, as checkpoints may have been pinned
+            // concurrently, which prevents them from being marked as compacting
+            let mut epochs_to_keep: Vec<u32> =
+                controller_queries::fetch_get_ready_checkpoints(&c, &*job_id, &(new_min as i32))
+                    .await?
+                    .into_iter()
+                    .map(|r| r.epoch as u32)
+                    .collect();
+            epochs_to_keep.push(new_min);
+
+            let epochs_to_delete: Vec<u32> =
+                controller_queries::fetch_get_compacting_checkpoints(&c, &*job_id)
+                    .await?
+                    .into_iter()
+                    .map(|e| e as u32)
+                    .chain(min_epoch..new_min)
+                    .filter(|e| !epochs_to_keep.contains(e))
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect();
+
+            StateBackend::cleanup_checkpoint(
+                checkpoint,
+                &epochs_to_delete,
+                &epochs_to_keep,
+                new_min,
+            )
+            .await?;
 
             controller_queries::execute_mark_checkpoints_compacted(
                 &db.client().await?,
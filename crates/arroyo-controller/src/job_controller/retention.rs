@@ -0,0 +1,139 @@
+use arroyo_rpc::api_types::checkpoints::CheckpointRetention;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A completed checkpoint older than the most recent `keep_last` checkpoints, which may be
+/// retained or deleted according to the retention policy
+#[derive(Debug, Clone)]
+pub struct RetentionCandidate {
+    pub epoch: u32,
+    pub finish_time: Option<SystemTime>,
+    pub pinned: bool,
+}
+
+/// Returns the epochs of the candidates that should be retained: all pinned checkpoints, and if
+/// configured, the first checkpoint of each hour that finished in the last `keep_hourly_days`.
+pub fn epochs_to_retain(
+    policy: &CheckpointRetention,
+    candidates: &[RetentionCandidate],
+    now: SystemTime,
+) -> HashSet<u32> {
+    let mut retained: HashSet<u32> = candidates
+        .iter()
+        .filter(|c| c.pinned)
+        .map(|c| c.epoch)
+        .collect();
+
+    if let Some(days) = policy.keep_hourly_days {
+        let cutoff = now - Duration::from_secs(days as u64 * 24 * 60 * 60);
+
+        let mut first_per_hour: HashMap<u64, u32> = HashMap::new();
+        for c in candidates {
+            let Some(finish_time) = c.finish_time else {
+                continue;
+            };
+
+            if finish_time < cutoff {
+                continue;
+            }
+
+            let hour = finish_time
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+                / 3600;
+
+            first_per_hour
+                .entry(hour)
+                .and_modify(|e| *e = (*e).min(c.epoch))
+                .or_insert(c.epoch);
+        }
+
+        retained.extend(first_per_hour.into_values());
+    }
+
+    retained
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates(start: SystemTime, interval: Duration, count: u32) -> Vec<RetentionCandidate> {
+        (1..=count)
+            .map(|epoch| RetentionCandidate {
+                epoch,
+                finish_time: Some(start + interval * epoch),
+                pinned: false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_default_retains_nothing() {
+        let now = SystemTime::now();
+        let c = candidates(
+            now - Duration::from_secs(3600),
+            Duration::from_secs(10),
+            100,
+        );
+        assert!(epochs_to_retain(&CheckpointRetention::default(), &c, now).is_empty());
+    }
+
+    #[test]
+    fn test_pinned_always_retained() {
+        let now = SystemTime::now();
+        let mut c = candidates(
+            now - Duration::from_secs(365 * 24 * 3600),
+            Duration::from_secs(10),
+            10,
+        );
+        c[3].pinned = true;
+
+        let policy = CheckpointRetention {
+            keep_last: 4,
+            keep_hourly_days: Some(1),
+        };
+
+        assert_eq!(
+            epochs_to_retain(&policy, &c, now),
+            HashSet::from([c[3].epoch])
+        );
+    }
+
+    #[test]
+    fn test_hourly() {
+        let hour = Duration::from_secs(3600);
+        // align to the start of an hour so that each hour contains 6 checkpoints
+        let now = UNIX_EPOCH
+            + hour
+                * (SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs()
+                    / 3600) as u32;
+
+        // a checkpoint every 10 minutes for the last 3 days
+        let start = now - hour * 72;
+        let c = candidates(start, Duration::from_secs(600), 72 * 6 - 1);
+
+        let policy = CheckpointRetention {
+            keep_last: 4,
+            keep_hourly_days: Some(2),
+        };
+
+        let retained = epochs_to_retain(&policy, &c, now);
+
+        // one per hour for the last 2 days
+        assert_eq!(retained.len(), 48);
+        for epoch in &retained {
+            let finish_time = c[*epoch as usize - 1].finish_time.unwrap();
+            assert!(finish_time >= now - hour * 48);
+            // the first checkpoint in each hour finishes on the hour
+            assert_eq!(
+                finish_time.duration_since(UNIX_EPOCH).unwrap().as_secs() % 3600,
+                0
+            );
+        }
+    }
+}
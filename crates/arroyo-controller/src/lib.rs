@@ -3,6 +3,9 @@
 #![allow(clippy::type_complexity)]
 
 use anyhow::Result;
+use arroyo_rpc::api_types::checkpoints::{CheckpointAlignmentConfig, CheckpointRetention};
+use arroyo_rpc::api_types::notifications::NotificationConfig;
+use arroyo_rpc::api_types::pipelines::AutoscalerConfig;
 use arroyo_rpc::grpc::controller_grpc_server::{ControllerGrpc, ControllerGrpcServer};
 use arroyo_rpc::grpc::{
     GrpcOutputSubscription, HeartbeatNodeReq, HeartbeatNodeResp, HeartbeatReq, HeartbeatResp,
@@ -38,11 +41,13 @@ use tracing::{debug, info, warn};
 
 //pub mod compiler;
 pub mod job_controller;
+mod notifications;
 pub mod schedulers;
 mod states;
 
 include!(concat!(env!("OUT_DIR"), "/controller-sql.rs"));
 
+use crate::job_controller::federation::FederatedCollector;
 use crate::job_controller::job_metrics::JobMetrics;
 use crate::schedulers::{NodeScheduler, ProcessScheduler, Scheduler};
 use types::public::LogLevel;
@@ -68,18 +73,23 @@ lazy_static! {
     .unwrap();
 }
 
-#[derive(Eq, PartialEq, Clone, Debug)]
+#[derive(PartialEq, Clone, Debug)]
 pub struct JobConfig {
     id: Arc<String>,
     organization_id: String,
     pipeline_name: String,
     pipeline_id: i64,
+    pipeline_pub_id: String,
     stop_mode: StopMode,
     checkpoint_interval: Duration,
     ttl: Option<Duration>,
     parallelism_overrides: HashMap<String, usize>,
     restart_nonce: i32,
     restart_mode: RestartMode,
+    autoscaler: Option<AutoscalerConfig>,
+    checkpoint_retention: CheckpointRetention,
+    checkpoint_alignment: CheckpointAlignmentConfig,
+    notifications: NotificationConfig,
 }
 
 #[derive(Clone, Debug)]
@@ -414,8 +424,8 @@ impl ControllerGrpc for ControllerServer {
             &client,
             &generate_id(IdTypes::JobLogMessage),
             &req.job_id,
-            &req.operator_id,
-            &(req.task_index as i64),
+            &Some(req.operator_id),
+            &Some(req.task_index as i64),
             &LogLevel::error,
             &req.message,
             &req.details,
@@ -467,12 +477,19 @@ impl ControllerServer {
             }
         };
 
+        let metrics: Arc<RwLock<HashMap<Arc<String>, JobMetrics>>> = Default::default();
+
+        // exposes per-job metrics, labeled by pipeline, on the admin server's /metrics endpoint
+        if let Err(e) = prometheus::register(Box::new(FederatedCollector::new(metrics.clone()))) {
+            warn!("Failed to register federated job metrics: {:?}", e);
+        }
+
         Self {
             scheduler,
             data_txs: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
             job_state: Arc::new(tokio::sync::Mutex::new(HashMap::new())),
             db: database,
-            metrics: Default::default(),
+            metrics,
         }
     }
 
@@ -517,6 +534,7 @@ impl ControllerServer {
                         organization_id: p.org_id,
                         pipeline_name: p.pipeline_name,
                         pipeline_id: p.pipeline_id,
+                        pipeline_pub_id: p.pipeline_pub_id.unwrap_or_default(),
                         stop_mode: p.stop,
                         checkpoint_interval: Duration::from_micros(
                             p.checkpoint_interval_micros as u64,
@@ -531,6 +549,59 @@ impl ControllerServer {
                             .collect(),
                         restart_nonce: p.config_restart_nonce,
                         restart_mode: p.restart_mode,
+                        autoscaler: p.autoscaler.and_then(|a| {
+                            serde_json::from_value(a)
+                                .map_err(|e| {
+                                    warn!(
+                                        message = "invalid autoscaler config",
+                                        job_id = *id,
+                                        error = format!("{:?}", e)
+                                    )
+                                })
+                                .ok()
+                        }),
+                        checkpoint_retention: p
+                            .checkpoint_retention
+                            .and_then(|r| {
+                                serde_json::from_value(r)
+                                    .map_err(|e| {
+                                        warn!(
+                                            message = "invalid checkpoint retention config",
+                                            job_id = *id,
+                                            error = format!("{:?}", e)
+                                        )
+                                    })
+                                    .ok()
+                            })
+                            .unwrap_or_default(),
+                        checkpoint_alignment: p
+                            .checkpoint_alignment
+                            .and_then(|a| {
+                                serde_json::from_value(a)
+                                    .map_err(|e| {
+                                        warn!(
+                                            message = "invalid checkpoint alignment config",
+                                            job_id = *id,
+                                            error = format!("{:?}", e)
+                                        )
+                                    })
+                                    .ok()
+                            })
+                            .unwrap_or_default(),
+                        notifications: p
+                            .notifications
+                            .and_then(|n| {
+                                serde_json::from_value(n)
+                                    .map_err(|e| {
+                                        warn!(
+                                            message = "invalid notification config",
+                                            job_id = *id,
+                                            error = format!("{:?}", e)
+                                        )
+                                    })
+                                    .ok()
+                            })
+                            .unwrap_or_default(),
                     };
 
                     let mut jobs = jobs.lock().await;
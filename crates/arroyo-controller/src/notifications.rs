@@ -0,0 +1,395 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::anyhow;
+use arroyo_rpc::api_types::notifications::{
+    JobNotification, JobNotificationEvent, NotificationChannel,
+};
+use arroyo_rpc::api_types::pipelines::{JobLogLevel, JobLogMessage};
+use arroyo_types::to_micros;
+use cornucopia_async::DatabaseSource;
+use reqwest::StatusCode;
+use serde_json::json;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::queries::controller_queries;
+use crate::queries::controller_queries::RecentLogMessage;
+use crate::types::public::LogLevel;
+use crate::{JobConfig, JobStatus};
+
+const RECENT_LOG_MESSAGES: i32 = 10;
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Error, Debug)]
+pub enum SendError {
+    #[error("{0}")]
+    Retryable(anyhow::Error),
+    #[error("{0}")]
+    Permanent(anyhow::Error),
+}
+
+/// A destination for job notifications. Implementations are constructed from the
+/// `NotificationChannel`s configured on a pipeline by `sender_for`.
+#[async_trait::async_trait]
+pub trait NotificationSender: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn send(&self, notification: &JobNotification) -> Result<(), SendError>;
+}
+
+pub fn sender_for(
+    channel: &NotificationChannel,
+    client: &reqwest::Client,
+) -> Box<dyn NotificationSender> {
+    match channel {
+        NotificationChannel::Webhook { url, headers } => Box::new(WebhookSender {
+            client: client.clone(),
+            url: url.clone(),
+            headers: headers.clone(),
+        }),
+        NotificationChannel::Slack { webhook_url } => Box::new(SlackSender {
+            client: client.clone(),
+            webhook_url: webhook_url.clone(),
+        }),
+    }
+}
+
+async fn post(request: reqwest::RequestBuilder) -> Result<(), SendError> {
+    let response = request
+        .timeout(SEND_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| SendError::Retryable(e.into()))?;
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    let err = anyhow!("received status {}: {}", status, body);
+    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        Err(SendError::Retryable(err))
+    } else {
+        Err(SendError::Permanent(err))
+    }
+}
+
+/// POSTs the notification as JSON
+pub struct WebhookSender {
+    client: reqwest::Client,
+    url: String,
+    headers: BTreeMap<String, String>,
+}
+
+#[async_trait::async_trait]
+impl NotificationSender for WebhookSender {
+    fn name(&self) -> &'static str {
+        "webhook"
+    }
+
+    async fn send(&self, notification: &JobNotification) -> Result<(), SendError> {
+        let mut request = self.client.post(&self.url).json(notification);
+        for (k, v) in &self.headers {
+            request = request.header(k, v);
+        }
+
+        post(request).await
+    }
+}
+
+/// Posts a text summary of the notification in the format accepted by Slack incoming webhooks
+pub struct SlackSender {
+    client: reqwest::Client,
+    webhook_url: String,
+}
+
+fn slack_text(notification: &JobNotification) -> String {
+    let mut text = format!(
+        "Pipeline *{}* ({}) job {} transitioned from {} to *{}*",
+        notification.pipeline_name,
+        notification.pipeline_id,
+        notification.job_id,
+        notification.previous_state,
+        notification.state
+    );
+
+    if let Some(message) = &notification.failure_message {
+        text.push_str(&format!("\n>{}", message));
+    }
+
+    if notification.suppressed > 0 {
+        text.push_str(&format!(
+            "\n_{} similar notifications were suppressed_",
+            notification.suppressed
+        ));
+    }
+
+    for log in notification
+        .recent_logs
+        .iter()
+        .filter(|l| matches!(l.level, JobLogLevel::Error))
+        .take(3)
+    {
+        text.push_str(&format!("\n• `{}`", log.message));
+    }
+
+    text
+}
+
+#[async_trait::async_trait]
+impl NotificationSender for SlackSender {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    async fn send(&self, notification: &JobNotification) -> Result<(), SendError> {
+        post(
+            self.client
+                .post(&self.webhook_url)
+                .json(&json!({ "text": slack_text(notification) })),
+        )
+        .await
+    }
+}
+
+async fn send_with_retries(
+    sender: &dyn NotificationSender,
+    notification: &JobNotification,
+) -> anyhow::Result<()> {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match sender.send(notification).await {
+            Ok(()) => return Ok(()),
+            Err(SendError::Retryable(e)) if attempt < MAX_ATTEMPTS => {
+                warn!(
+                    message = "failed to send notification, retrying",
+                    channel = sender.name(),
+                    job_id = notification.job_id,
+                    attempt,
+                    error = format!("{:?}", e)
+                );
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(SendError::Retryable(e)) | Err(SendError::Permanent(e)) => return Err(e),
+        }
+    }
+}
+
+impl From<RecentLogMessage> for JobLogMessage {
+    fn from(val: RecentLogMessage) -> Self {
+        JobLogMessage {
+            id: val.pub_id,
+            created_at: (val.created_at.unix_timestamp_nanos() / 1_000) as u64,
+            operator_id: val.operator_id,
+            task_index: val.task_index.map(|i| i as u64),
+            level: match val.log_level {
+                LogLevel::info => JobLogLevel::Info,
+                LogLevel::warn => JobLogLevel::Warn,
+                LogLevel::error => JobLogLevel::Error,
+            },
+            message: val.message,
+            details: val.details,
+        }
+    }
+}
+
+async fn load_recent_logs(db: &DatabaseSource, job_id: &str) -> anyhow::Result<Vec<JobLogMessage>> {
+    let client = db.client().await?;
+
+    Ok(
+        controller_queries::fetch_get_recent_job_log_messages(
+            &client,
+            job_id,
+            &RECENT_LOG_MESSAGES,
+        )
+        .await?
+        .into_iter()
+        .map(|l| l.into())
+        .collect(),
+    )
+}
+
+/// Tracks when each type of notification was last sent for a job, so that a job that is
+/// repeatedly failing and recovering doesn't flood its channels
+#[derive(Default)]
+pub struct Notifier {
+    client: reqwest::Client,
+    last_sent: HashMap<JobNotificationEvent, (Instant, u64)>,
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of notifications that were suppressed since the last one if a
+    /// notification should be sent for `event` now, or None if it should be suppressed
+    fn debounce(
+        &mut self,
+        event: JobNotificationEvent,
+        debounce: Duration,
+        now: Instant,
+    ) -> Option<u64> {
+        match self.last_sent.get_mut(&event) {
+            Some((last, suppressed)) if now.duration_since(*last) < debounce => {
+                *suppressed += 1;
+                None
+            }
+            _ => {
+                let (_, suppressed) = self.last_sent.insert(event, (now, 0)).unwrap_or((now, 0));
+                Some(suppressed)
+            }
+        }
+    }
+
+    /// Sends notifications for a transition from `previous_state` into the job's current state,
+    /// if the pipeline has configured channels for it. Sending happens in the background and
+    /// does not block the state machine.
+    pub fn notify(
+        &mut self,
+        config: &JobConfig,
+        status: &JobStatus,
+        db: &DatabaseSource,
+        previous_state: &str,
+    ) {
+        let notifications = &config.notifications;
+        if notifications.channels.is_empty() {
+            return;
+        }
+
+        let Some(event) = JobNotificationEvent::for_state(&status.state) else {
+            return;
+        };
+
+        if !notifications.events.contains(&event) {
+            return;
+        }
+
+        let Some(suppressed) = self.debounce(
+            event,
+            Duration::from_secs(notifications.debounce_secs),
+            Instant::now(),
+        ) else {
+            return;
+        };
+
+        let senders: Vec<_> = notifications
+            .channels
+            .iter()
+            .map(|c| sender_for(c, &self.client))
+            .collect();
+
+        let db = db.clone();
+        let mut notification = JobNotification {
+            event,
+            pipeline_id: config.pipeline_pub_id.clone(),
+            pipeline_name: config.pipeline_name.clone(),
+            job_id: (*config.id).clone(),
+            run_id: status.run_id as u64,
+            previous_state: previous_state.to_string(),
+            state: status.state.clone(),
+            failure_message: status.failure_message.clone(),
+            restarts: status.restarts as u64,
+            suppressed,
+            timestamp: to_micros(SystemTime::now()),
+            recent_logs: vec![],
+        };
+
+        tokio::spawn(async move {
+            match load_recent_logs(&db, &notification.job_id).await {
+                Ok(logs) => {
+                    notification.recent_logs = logs;
+                }
+                Err(e) => {
+                    warn!(
+                        message = "failed to load recent logs for notification",
+                        job_id = notification.job_id,
+                        error = format!("{:?}", e)
+                    );
+                }
+            }
+
+            let notification = &notification;
+            futures::future::join_all(senders.iter().map(|sender| async move {
+                match send_with_retries(sender.as_ref(), notification).await {
+                    Ok(()) => {
+                        info!(
+                            message = "sent notification",
+                            channel = sender.name(),
+                            job_id = notification.job_id,
+                            state = notification.state
+                        );
+                    }
+                    Err(e) => {
+                        warn!(
+                            message = "failed to send notification",
+                            channel = sender.name(),
+                            job_id = notification.job_id,
+                            error = format!("{:?}", e)
+                        );
+                    }
+                }
+            }))
+            .await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debounce() {
+        let mut notifier = Notifier::new();
+        let debounce = Duration::from_secs(60);
+        let start = Instant::now();
+
+        assert_eq!(
+            notifier.debounce(JobNotificationEvent::Recovering, debounce, start),
+            Some(0)
+        );
+        assert_eq!(
+            notifier.debounce(
+                JobNotificationEvent::Recovering,
+                debounce,
+                start + Duration::from_secs(10)
+            ),
+            None
+        );
+        assert_eq!(
+            notifier.debounce(
+                JobNotificationEvent::Recovering,
+                debounce,
+                start + Duration::from_secs(20)
+            ),
+            None
+        );
+
+        // other events are debounced independently
+        assert_eq!(
+            notifier.debounce(
+                JobNotificationEvent::Failed,
+                debounce,
+                start + Duration::from_secs(30)
+            ),
+            Some(0)
+        );
+
+        assert_eq!(
+            notifier.debounce(
+                JobNotificationEvent::Recovering,
+                debounce,
+                start + Duration::from_secs(61)
+            ),
+            Some(2)
+        );
+    }
+}
@@ -17,6 +17,7 @@ use anyhow::{anyhow, Result};
 use cornucopia_async::DatabaseSource;
 
 use crate::job_controller::JobController;
+use crate::notifications::Notifier;
 use crate::queries::controller_queries;
 use crate::types::public::StopMode;
 use crate::{schedulers::Scheduler, JobConfig, JobMessage, JobStatus};
@@ -362,9 +363,21 @@ pub struct JobContext<'a> {
     job_controller: Option<JobController>,
     last_transitioned_at: Instant,
     metrics: Arc<tokio::sync::RwLock<HashMap<Arc<String>, JobMetrics>>>,
+    notifier: Notifier,
 }
 
 impl<'a> JobContext<'a> {
+    /// Reloads the program from the database, picking up connector config changes that were made
+    /// while the job was running
+    pub async fn reload_program(&mut self) -> anyhow::Result<()> {
+        if let Some(program) =
+            StateMachine::get_program(&self.db, &self.config.id, self.config.pipeline_id).await?
+        {
+            *self.program = program;
+        }
+        Ok(())
+    }
+
     pub fn handle(&mut self, msg: JobMessage) -> Result<(), StateError> {
         warn!("unhandled job message {:?}", msg);
         Ok(())
@@ -534,6 +547,11 @@ async fn execute_state<'a>(
             .update_db(&ctx.db)
             .await
             .expect("Failed to update status");
+
+        if s.name() != state_name {
+            ctx.notifier
+                .notify(&ctx.config, ctx.status, &ctx.db, state_name);
+        }
     }
 
     (next, ctx)
@@ -560,6 +578,7 @@ pub async fn run_to_completion(
         job_controller: None,
         last_transitioned_at: Instant::now(),
         metrics,
+        notifier: Notifier::new(),
     };
 
     loop {
@@ -30,6 +30,14 @@ impl State for Restarting {
                     match job_controller.checkpoint_finished().await {
                         Ok(done) => {
                             if done && job_controller.finished() {
+                                if let Err(e) = ctx.reload_program().await {
+                                    return Err(ctx.retryable(
+                                        self,
+                                        "failed to reload program",
+                                        e,
+                                        10,
+                                    ));
+                                }
                                 return Ok(Transition::next(*self, Scheduling {}));
                             }
                         }
@@ -76,6 +84,10 @@ impl State for Restarting {
                     return Err(ctx.retryable(self, "failed to tear down existing cluster", e, 10));
                 }
 
+                if let Err(e) = ctx.reload_program().await {
+                    return Err(ctx.retryable(self, "failed to reload program", e, 10));
+                }
+
                 Ok(Transition::next(*self, Scheduling {}))
             }
         }
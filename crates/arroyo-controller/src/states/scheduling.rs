@@ -19,6 +19,7 @@ use arroyo_state::{
     BackingStore, StateBackend,
 };
 
+use crate::job_controller::federation::JobLabels;
 use crate::job_controller::job_metrics::JobMetrics;
 use crate::{
     job_controller::JobController,
@@ -45,6 +46,8 @@ struct WorkerStatus {
 #[derive(Debug)]
 pub struct Scheduling {}
 
+/// Subtasks with the same index share a slot, so a job needs as many slots as its operator
+/// with the highest parallelism
 fn slots_for_job(job: &LogicalProgram) -> usize {
     job.graph
         .node_weights()
@@ -543,7 +546,14 @@ impl State for Scheduling {
         let needs_commit = committing_state.is_some();
 
         let program = Arc::new(ctx.program.clone());
-        let metrics = JobMetrics::new(program.clone());
+        let metrics = JobMetrics::new(
+            program.clone(),
+            JobLabels {
+                job_id: (*ctx.config.id).clone(),
+                pipeline_id: ctx.config.pipeline_pub_id.clone(),
+                pipeline_name: ctx.config.pipeline_name.clone(),
+            },
+        );
         ctx.metrics
             .write()
             .await
@@ -2,6 +2,7 @@
 #![allow(clippy::comparison_chain)]
 
 pub mod logical;
+pub mod state_tables;
 
 use arroyo_rpc::grpc::api;
 use bincode::{Decode, Encode};
@@ -54,6 +55,7 @@ pub enum WindowType {
     Sliding { width: Duration, slide: Duration },
     Instant,
     Session { gap: Duration },
+    Cumulating { step: Duration, max_size: Duration },
 }
 
 fn format_duration(duration: Duration) -> String {
@@ -94,6 +96,14 @@ impl Debug for WindowType {
             Self::Session { gap } => {
                 write!(f, "SessionWindow({})", format_duration(*gap))
             }
+            Self::Cumulating { step, max_size } => {
+                write!(
+                    f,
+                    "CumulatingWindow(step: {}, max_size: {})",
+                    format_duration(*step),
+                    format_duration(*max_size)
+                )
+            }
         }
     }
 }
@@ -220,5 +230,6 @@ pub fn preview_sink() -> api::ConnectorOp {
         config: "{\"connection\": {}, \"table\": {}, \"connection_schema\": {\"fields\":[]}}"
             .to_string(),
         description: "PreviewSink".to_string(),
+        connection_id: None,
     }
 }
@@ -1,6 +1,6 @@
 use datafusion_proto::protobuf::ArrowType;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, bail};
 use arrow_schema::DataType;
 use arroyo_rpc::api_types::pipelines::{PipelineEdge, PipelineGraph, PipelineNode};
 use arroyo_rpc::df::ArroyoSchema;
@@ -8,8 +8,9 @@ use arroyo_rpc::grpc::api;
 use arroyo_rpc::grpc::api::{
     ArrowDylibUdfConfig, ArrowProgram, ArrowProgramConfig, ConnectorOp, EdgeType,
 };
-use petgraph::graph::DiGraph;
+use petgraph::graph::{DiGraph, NodeIndex};
 use petgraph::prelude::EdgeRef;
+use petgraph::unionfind::UnionFind;
 use petgraph::Direction;
 use prost::Message;
 use rand::distributions::Alphanumeric;
@@ -29,7 +30,10 @@ pub enum OperatorName {
     AsyncUdf,
     Join,
     InstantJoin,
+    TemporalJoin,
     WindowFunction,
+    Deduplicate,
+    MatchRecognize,
     TumblingWindowAggregate,
     SlidingWindowAggregate,
     SessionWindowAggregate,
@@ -44,6 +48,8 @@ pub enum LogicalEdgeType {
     Shuffle,
     LeftJoin,
     RightJoin,
+    /// Carries the rows a windowed operator was too late to aggregate to a side-output sink
+    LateData,
 }
 
 impl Display for LogicalEdgeType {
@@ -53,6 +59,7 @@ impl Display for LogicalEdgeType {
             LogicalEdgeType::Shuffle => write!(f, "⤨"),
             LogicalEdgeType::LeftJoin => write!(f, "-[left]⤨"),
             LogicalEdgeType::RightJoin => write!(f, "-[right]⤨"),
+            LogicalEdgeType::LateData => write!(f, "-[late]⤨"),
         }
     }
 }
@@ -65,6 +72,7 @@ impl From<arroyo_rpc::grpc::api::EdgeType> for LogicalEdgeType {
             EdgeType::Shuffle => LogicalEdgeType::Shuffle,
             EdgeType::LeftJoin => LogicalEdgeType::LeftJoin,
             EdgeType::RightJoin => LogicalEdgeType::RightJoin,
+            EdgeType::LateData => LogicalEdgeType::LateData,
         }
     }
 }
@@ -76,6 +84,7 @@ impl From<LogicalEdgeType> for api::EdgeType {
             LogicalEdgeType::Shuffle => EdgeType::Shuffle,
             LogicalEdgeType::LeftJoin => EdgeType::LeftJoin,
             LogicalEdgeType::RightJoin => EdgeType::RightJoin,
+            LogicalEdgeType::LateData => EdgeType::LateData,
         }
     }
 }
@@ -219,6 +228,107 @@ impl LogicalProgram {
         }
     }
 
+    /// Applies per-operator parallelism overrides. Operators connected by forward edges must run
+    /// with the same parallelism, so each override is applied to every operator in its forward
+    /// group; two overrides that disagree within a group are an error.
+    pub fn apply_parallelism_overrides(
+        &mut self,
+        overrides: &HashMap<String, usize>,
+    ) -> anyhow::Result<()> {
+        for group in self.forward_groups() {
+            let mut parallelism: Option<(&str, usize)> = None;
+            for idx in &group {
+                let node = &self.graph[*idx];
+                let Some(p) = overrides.get(&node.operator_id) else {
+                    continue;
+                };
+
+                if *p == 0 {
+                    bail!(
+                        "parallelism for operator {} must be at least 1",
+                        node.operator_id
+                    );
+                }
+
+                match parallelism {
+                    Some((other, existing)) if existing != *p => {
+                        bail!(
+                            "operators {} and {} are connected by a forward edge and must have the \
+                            same parallelism, but were configured with {} and {}",
+                            other,
+                            node.operator_id,
+                            existing,
+                            p
+                        );
+                    }
+                    _ => parallelism = Some((&node.operator_id, *p)),
+                }
+            }
+
+            if let Some((_, p)) = parallelism {
+                for idx in group {
+                    self.graph[idx].parallelism = p;
+                }
+            }
+        }
+
+        self.validate_join_parallelism()
+    }
+
+    /// Join operators route each input subtask to a side by dividing the input index by half
+    /// the total number of inputs, so both sides must run with the same parallelism
+    pub fn validate_join_parallelism(&self) -> anyhow::Result<()> {
+        for idx in self.graph.node_indices() {
+            let mut left = None;
+            let mut right = None;
+            for edge in self.graph.edges_directed(idx, Direction::Incoming) {
+                let parallelism = self.graph[edge.source()].parallelism;
+                match edge.weight().edge_type {
+                    LogicalEdgeType::LeftJoin => left = Some(parallelism),
+                    LogicalEdgeType::RightJoin => right = Some(parallelism),
+                    _ => {}
+                }
+            }
+
+            if let (Some(left), Some(right)) = (left, right) {
+                if left != right {
+                    bail!(
+                        "the inputs to join operator {} must have the same parallelism, but the \
+                        left side has {} and the right side has {}",
+                        self.graph[idx].operator_id,
+                        left,
+                        right
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the sets of operators that are connected by forward edges, and so must be
+    /// scheduled with the same parallelism
+    pub fn forward_groups(&self) -> Vec<Vec<NodeIndex>> {
+        let mut union_find = UnionFind::new(self.graph.node_count());
+        for edge in self.graph.edge_references() {
+            if edge.weight().edge_type == LogicalEdgeType::Forward {
+                union_find.union(edge.source().index(), edge.target().index());
+            }
+        }
+
+        let mut groups: HashMap<usize, Vec<NodeIndex>> = HashMap::new();
+        for idx in self.graph.node_indices() {
+            groups
+                .entry(union_find.find(idx.index()))
+                .or_default()
+                .push(idx);
+        }
+
+        let mut groups: Vec<_> = groups.into_values().collect();
+        groups.sort_by_key(|g| g[0]);
+        groups
+    }
+
     pub fn task_count(&self) -> usize {
         // TODO: this can be cached
         self.graph.node_weights().map(|nw| nw.parallelism).sum()
@@ -271,7 +381,10 @@ impl LogicalProgram {
                 | OperatorName::ArrowKey => continue,
                 OperatorName::Join => "join-with-expiration".to_string(),
                 OperatorName::InstantJoin => "windowed-join".to_string(),
+                OperatorName::TemporalJoin => "temporal-join".to_string(),
                 OperatorName::WindowFunction => "sql-window-function".to_string(),
+                OperatorName::Deduplicate => "deduplicate".to_string(),
+                OperatorName::MatchRecognize => "match-recognize".to_string(),
                 OperatorName::TumblingWindowAggregate => {
                     "sql-tumbling-window-aggregate".to_string()
                 }
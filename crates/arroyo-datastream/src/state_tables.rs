@@ -0,0 +1,76 @@
+//! The state tables that operators keep. The operators create their tables from these, and
+//! EXPLAIN uses them to describe an operator's state without constructing the operator.
+
+use std::time::Duration;
+
+/// The name and description of one of an operator's state tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateTable {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+const fn table(name: &'static str, description: &'static str) -> StateTable {
+    StateTable { name, description }
+}
+
+pub const EXPRESSION_WATERMARK: StateTable = table("s", "expression watermark state");
+pub const ASYNC_UDF: StateTable = table("a", "AsyncMapOperator state");
+pub const TUMBLING_WINDOW: StateTable = table("t", "tumbling_intermediate");
+pub const SLIDING_WINDOW: StateTable = table("t", "Sliding_intermediate");
+pub const SESSION_WINDOW_EARLIEST: StateTable =
+    table("e", "earliest start time of all active batches.");
+pub const SESSION_WINDOW: StateTable = table("s", "session");
+pub const UPDATING_AGGREGATE_FINAL: StateTable = table("f", "final_table");
+pub const UPDATING_AGGREGATE_PARTIAL: StateTable = table("p", "partial_table");
+pub const JOIN_LEFT: StateTable = table("left", "left join data");
+pub const JOIN_RIGHT: StateTable = table("right", "right join data");
+pub const TEMPORAL_JOIN_LEFT: StateTable = table("left", "temporal join pending left rows");
+pub const TEMPORAL_JOIN_RIGHT: StateTable = table("right", "temporal join pending versions");
+pub const TEMPORAL_JOIN_VERSIONS: StateTable = table("versions", "temporal join current versions");
+pub const WINDOW_FUNCTION_INPUT: StateTable = table("input", "window function input");
+pub const DEDUPLICATE_SEEN_KEYS: StateTable = table("s", "deduplication seen keys");
+pub const DEDUPLICATE_PENDING: StateTable = table("p", "deduplication pending rows");
+pub const MATCH_RECOGNIZE_ROWS: StateTable = table("i", "match_recognize buffered rows");
+pub const MATCH_RECOGNIZE_MATCHES: StateTable = table("m", "match_recognize last match ends");
+
+/// Partial aggregates are kept for the width of the window, or of the largest cumulative
+/// window, plus however long late rows may still update it
+pub fn tumbling_window_retention(
+    width: Duration,
+    cumulate_max_size: Option<Duration>,
+    allowed_lateness: Option<Duration>,
+) -> Duration {
+    cumulate_max_size.unwrap_or(width) + allowed_lateness.unwrap_or_default()
+}
+
+/// Sliding windows are emitted once per slide, so lateness is tracked in whole slides
+pub fn sliding_window_lateness(allowed_lateness: Duration, slide: Duration) -> Duration {
+    let slides = allowed_lateness.as_nanos().div_ceil(slide.as_nanos());
+    slide * slides as u32
+}
+
+/// `allowed_lateness` should already be rounded with [`sliding_window_lateness`]
+pub fn sliding_window_retention(width: Duration, allowed_lateness: Option<Duration>) -> Duration {
+    width + allowed_lateness.unwrap_or_default()
+}
+
+pub fn session_window_retention(gap: Duration, allowed_lateness: Option<Duration>) -> Duration {
+    // TODO: something better
+    gap * 100 + allowed_lateness.unwrap_or_default()
+}
+
+/// The retention of the left and right sides of a join. Interval joins bound how far behind
+/// the watermark each side needs to be kept; other joins keep rows for the TTL.
+pub fn join_retention(
+    ttl: Duration,
+    left_ttl: Option<Duration>,
+    right_ttl: Option<Duration>,
+) -> (Duration, Duration) {
+    (left_ttl.unwrap_or(ttl), right_ttl.unwrap_or(ttl))
+}
+
+/// Partial matches are bounded by `WITHIN` if it's given, and otherwise by the TTL
+pub fn match_recognize_retention(ttl: Duration, within: Option<Duration>) -> Duration {
+    within.unwrap_or(ttl)
+}
@@ -280,6 +280,10 @@ impl<'a> PlanToGraphVisitor<'a> {
         Ok(())
     }
 
+    pub(crate) fn node_count(&self) -> usize {
+        self.graph.node_count()
+    }
+
     pub fn into_graph(self) -> LogicalGraph {
         self.graph
     }
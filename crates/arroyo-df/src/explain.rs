@@ -0,0 +1,347 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use arroyo_datastream::logical::{LogicalNode, LogicalProgram, OperatorName};
+use arroyo_datastream::state_tables::{self, StateTable};
+use arroyo_rpc::api_types::pipelines::{
+    ExplainedEdge, ExplainedExtension, ExplainedOperator, ExplainedStateTable, QueryExplanation,
+};
+use arroyo_rpc::grpc::api;
+use arroyo_types::{DEFAULT_JOIN_TTL, DEFAULT_TEMPORAL_JOIN_TTL, DEFAULT_UPDATING_AGGREGATE_TTL};
+use datafusion::common::tree_node::{TreeNode, TreeNodeRecursion, TreeNodeVisitor};
+use datafusion::common::Result as DFResult;
+use datafusion::logical_expr::LogicalPlan;
+use petgraph::visit::EdgeRef;
+use prost::Message;
+
+/// Collects the Arroyo extension nodes of a rewritten plan, from the sink down
+#[derive(Default)]
+struct ExtensionVisitor {
+    extensions: Vec<ExplainedExtension>,
+}
+
+impl TreeNodeVisitor for ExtensionVisitor {
+    type Node = LogicalPlan;
+
+    fn f_down(&mut self, node: &Self::Node) -> DFResult<TreeNodeRecursion> {
+        if let LogicalPlan::Extension(extension) = node {
+            self.extensions.push(ExplainedExtension {
+                name: extension.node.name().to_string(),
+                description: node.display().to_string(),
+            });
+        }
+        Ok(TreeNodeRecursion::Continue)
+    }
+}
+
+fn expiring_table(
+    table: StateTable,
+    retention: Duration,
+    generational: bool,
+) -> ExplainedStateTable {
+    ExplainedStateTable {
+        name: table.name.to_string(),
+        description: table.description.to_string(),
+        table_type: "expiring_time_key".to_string(),
+        retention_micros: Some(retention.as_micros() as u64),
+        generational,
+    }
+}
+
+fn global_table(table: StateTable) -> ExplainedStateTable {
+    ExplainedStateTable {
+        name: table.name.to_string(),
+        description: table.description.to_string(),
+        table_type: "global".to_string(),
+        retention_micros: None,
+        generational: false,
+    }
+}
+
+/// The TTL and state tables the operator for `node` will create, resolving defaults the way
+/// the operator's constructor does. Connectors manage their own state, so they have no tables
+/// here.
+fn operator_state_tables(
+    node: &LogicalNode,
+) -> Result<(Option<Duration>, Option<Vec<ExplainedStateTable>>)> {
+    let config = node.operator_config.as_slice();
+    let micros = Duration::from_micros;
+    let tables = match node.operator_name {
+        OperatorName::ConnectorSource | OperatorName::ConnectorSink => return Ok((None, None)),
+        OperatorName::ArrowValue | OperatorName::ArrowKey => vec![],
+        OperatorName::ExpressionWatermark => vec![global_table(state_tables::EXPRESSION_WATERMARK)],
+        OperatorName::AsyncUdf => vec![global_table(state_tables::ASYNC_UDF)],
+        OperatorName::TumblingWindowAggregate => {
+            let config = api::TumblingWindowAggregateOperator::decode(config)?;
+            let retention = state_tables::tumbling_window_retention(
+                micros(config.width_micros),
+                config.cumulate_max_size_micros.map(micros),
+                config.allowed_lateness_micros.map(micros),
+            );
+            vec![expiring_table(
+                state_tables::TUMBLING_WINDOW,
+                retention,
+                false,
+            )]
+        }
+        OperatorName::SlidingWindowAggregate => {
+            let config = api::SlidingWindowAggregateOperator::decode(config)?;
+            let lateness = config.allowed_lateness_micros.map(|lateness| {
+                state_tables::sliding_window_lateness(micros(lateness), micros(config.slide_micros))
+            });
+            let retention =
+                state_tables::sliding_window_retention(micros(config.width_micros), lateness);
+            vec![expiring_table(
+                state_tables::SLIDING_WINDOW,
+                retention,
+                false,
+            )]
+        }
+        OperatorName::SessionWindowAggregate => {
+            let config = api::SessionWindowAggregateOperator::decode(config)?;
+            let retention = state_tables::session_window_retention(
+                micros(config.gap_micros),
+                config.allowed_lateness_micros.map(micros),
+            );
+            vec![
+                global_table(state_tables::SESSION_WINDOW_EARLIEST),
+                expiring_table(state_tables::SESSION_WINDOW, retention, false),
+            ]
+        }
+        OperatorName::UpdatingAggregate => {
+            let config = api::UpdatingAggregateOperator::decode(config)?;
+            let ttl = config
+                .ttl_micros
+                .map(micros)
+                .unwrap_or(DEFAULT_UPDATING_AGGREGATE_TTL);
+            return Ok((
+                Some(ttl),
+                Some(vec![
+                    expiring_table(state_tables::UPDATING_AGGREGATE_FINAL, ttl, true),
+                    expiring_table(state_tables::UPDATING_AGGREGATE_PARTIAL, ttl, true),
+                ]),
+            ));
+        }
+        OperatorName::Join => {
+            let config = api::JoinOperator::decode(config)?;
+            let ttl = config.ttl_micros.map(micros).unwrap_or(DEFAULT_JOIN_TTL);
+            let (left, right) = state_tables::join_retention(
+                ttl,
+                config.left_ttl_micros.map(micros),
+                config.right_ttl_micros.map(micros),
+            );
+            let tables = vec![
+                expiring_table(state_tables::JOIN_LEFT, left, false),
+                expiring_table(state_tables::JOIN_RIGHT, right, false),
+            ];
+            // interval joins are bounded by their intervals rather than the TTL
+            let interval = config.left_ttl_micros.is_some() || config.right_ttl_micros.is_some();
+            return Ok(((!interval).then_some(ttl), Some(tables)));
+        }
+        OperatorName::InstantJoin => vec![
+            expiring_table(state_tables::JOIN_LEFT, Duration::ZERO, false),
+            expiring_table(state_tables::JOIN_RIGHT, Duration::ZERO, false),
+        ],
+        OperatorName::TemporalJoin => {
+            let config = api::JoinOperator::decode(config)?;
+            let ttl = config
+                .ttl_micros
+                .map(micros)
+                .unwrap_or(DEFAULT_TEMPORAL_JOIN_TTL);
+            return Ok((
+                Some(ttl),
+                Some(vec![
+                    expiring_table(state_tables::TEMPORAL_JOIN_LEFT, Duration::ZERO, false),
+                    expiring_table(state_tables::TEMPORAL_JOIN_RIGHT, Duration::ZERO, false),
+                    expiring_table(state_tables::TEMPORAL_JOIN_VERSIONS, ttl, true),
+                ]),
+            ));
+        }
+        OperatorName::WindowFunction => vec![expiring_table(
+            state_tables::WINDOW_FUNCTION_INPUT,
+            Duration::ZERO,
+            false,
+        )],
+        OperatorName::Deduplicate => {
+            let config = api::DeduplicateOperator::decode(config)?;
+            let ttl = micros(config.ttl_micros);
+            return Ok((
+                Some(ttl),
+                Some(vec![
+                    expiring_table(state_tables::DEDUPLICATE_PENDING, Duration::ZERO, false),
+                    expiring_table(state_tables::DEDUPLICATE_SEEN_KEYS, ttl, true),
+                ]),
+            ));
+        }
+        OperatorName::MatchRecognize => {
+            let config = api::MatchRecognizeOperator::decode(config)?;
+            let ttl = micros(config.ttl_micros);
+            let limit =
+                state_tables::match_recognize_retention(ttl, config.within_micros.map(micros));
+            return Ok((
+                config.within_micros.is_none().then_some(ttl),
+                Some(vec![
+                    expiring_table(state_tables::MATCH_RECOGNIZE_ROWS, limit, false),
+                    expiring_table(state_tables::MATCH_RECOGNIZE_MATCHES, limit, true),
+                ]),
+            ));
+        }
+    };
+    Ok((None, Some(tables)))
+}
+
+fn operator(node: &LogicalNode) -> Result<String> {
+    Ok(match node.operator_name {
+        OperatorName::ConnectorSource | OperatorName::ConnectorSink => {
+            let op = api::ConnectorOp::decode(node.operator_config.as_slice()).map_err(|_| {
+                anyhow!(
+                    "could not decode connector configuration for {}",
+                    node.operator_id
+                )
+            })?;
+            format!("{}({})", node.operator_name, op.connector)
+        }
+        op => op.to_string(),
+    })
+}
+
+/// Describes the plan of an `EXPLAIN`ed query: its logical plans before and after they were
+/// rewritten, the extension nodes of the rewritten plans, and the operators and edges of the
+/// program they were planned into, along with the state each operator keeps.
+pub(crate) fn explain(
+    verbose: bool,
+    unrewritten_plans: &[LogicalPlan],
+    plans: &[LogicalPlan],
+    program: &LogicalProgram,
+) -> Result<QueryExplanation> {
+    let mut visitor = ExtensionVisitor::default();
+    for plan in plans {
+        plan.visit(&mut visitor)?;
+    }
+
+    let operators = program
+        .graph
+        .node_weights()
+        .map(|node| {
+            let (ttl, state_tables) = operator_state_tables(node)?;
+            Ok(ExplainedOperator {
+                node_id: node.operator_id.clone(),
+                operator: operator(node)?,
+                description: node.description.clone(),
+                parallelism: node.parallelism as u32,
+                ttl_micros: ttl.map(|ttl| ttl.as_micros() as u64),
+                state_tables,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let edges: Vec<_> = program
+        .graph
+        .edge_references()
+        .map(|edge| {
+            let schema = &edge.weight().schema;
+            ExplainedEdge {
+                src_id: program.graph[edge.source()].operator_id.clone(),
+                dest_id: program.graph[edge.target()].operator_id.clone(),
+                edge_type: format!("{:?}", edge.weight().edge_type),
+                key_fields: schema
+                    .key_indices
+                    .iter()
+                    .flatten()
+                    .map(|index| schema.schema.field(*index).name().clone())
+                    .collect(),
+                fields: verbose.then(|| {
+                    schema
+                        .schema
+                        .fields()
+                        .iter()
+                        .map(|field| format!("{}: {}", field.name(), field.data_type()))
+                        .collect()
+                }),
+            }
+        })
+        .collect();
+
+    let logical_plans: Vec<_> = plans
+        .iter()
+        .map(|plan| plan.display_indent().to_string())
+        .collect();
+    let unrewritten_logical_plans = verbose.then(|| {
+        unrewritten_plans
+            .iter()
+            .map(|plan| plan.display_indent().to_string())
+            .collect::<Vec<_>>()
+    });
+
+    let mut text = String::new();
+    if let Some(unrewritten_logical_plans) = &unrewritten_logical_plans {
+        writeln!(text, "Unrewritten logical plan:")?;
+        for plan in unrewritten_logical_plans {
+            writeln!(text, "{}", plan)?;
+        }
+        writeln!(text)?;
+    }
+    writeln!(text, "Logical plan:")?;
+    for plan in &logical_plans {
+        writeln!(text, "{}", plan)?;
+    }
+    writeln!(text)?;
+    writeln!(text, "Extensions:")?;
+    for extension in &visitor.extensions {
+        writeln!(text, "  {}", extension.description)?;
+    }
+    writeln!(text)?;
+    writeln!(text, "Operators:")?;
+    for operator in &operators {
+        writeln!(
+            text,
+            "  {} [{}, parallelism={}]: {}",
+            operator.node_id, operator.operator, operator.parallelism, operator.description
+        )?;
+        if let Some(ttl) = operator.ttl_micros {
+            writeln!(text, "    ttl: {:?}", Duration::from_micros(ttl))?;
+        }
+        match &operator.state_tables {
+            Some(tables) => {
+                for table in tables {
+                    write!(text, "    table {} ({}", table.name, table.table_type)?;
+                    if let Some(retention) = table.retention_micros {
+                        write!(text, ", retention={:?}", Duration::from_micros(retention))?;
+                    }
+                    if table.generational {
+                        write!(text, ", generational")?;
+                    }
+                    writeln!(text, "): {}", table.description)?;
+                }
+            }
+            None => writeln!(text, "    state managed by the connector")?,
+        }
+    }
+    writeln!(text)?;
+    writeln!(text, "Edges:")?;
+    for edge in &edges {
+        write!(
+            text,
+            "  {} -> {} [{}]",
+            edge.src_id, edge.dest_id, edge.edge_type
+        )?;
+        if !edge.key_fields.is_empty() {
+            write!(text, " keyed by ({})", edge.key_fields.join(", "))?;
+        }
+        writeln!(text)?;
+        if let Some(fields) = &edge.fields {
+            writeln!(text, "    fields: {}", fields.join(", "))?;
+        }
+    }
+
+    Ok(QueryExplanation {
+        verbose,
+        logical_plans,
+        unrewritten_logical_plans,
+        extensions: visitor.extensions,
+        operators,
+        edges,
+        text,
+    })
+}
@@ -2,6 +2,7 @@ use std::{collections::HashMap, fmt::Formatter, sync::Arc, time::Duration};
 
 use anyhow::{bail, Result};
 use arrow::datatypes::IntervalMonthDayNanoType;
+use arrow_schema::DataType;
 
 use arroyo_datastream::{
     logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName},
@@ -13,11 +14,14 @@ use arroyo_rpc::{
         SessionWindowAggregateOperator, SlidingWindowAggregateOperator,
         TumblingWindowAggregateOperator,
     },
-    TIMESTAMP_FIELD,
+    IS_RETRACT_FIELD, TIMESTAMP_FIELD,
 };
+use arroyo_types::sliding_window_pane;
 use datafusion::common::{
-    Column, DFField, DFSchema, DFSchemaRef, DataFusionError, Result as DFResult, ScalarValue,
+    plan_err, Column, DFField, DFSchema, DFSchemaRef, DataFusionError, Result as DFResult,
+    ScalarValue,
 };
+use datafusion::functions::datetime::date_bin;
 use datafusion::logical_expr;
 use datafusion::logical_expr::{
     expr::ScalarFunction, Aggregate, BinaryExpr, Expr, Extension, LogicalPlan,
@@ -46,6 +50,8 @@ pub(crate) struct AggregateExtension {
     pub(crate) schema: DFSchemaRef,
     pub(crate) key_fields: Vec<usize>,
     pub(crate) final_calculation: LogicalPlan,
+    // when set, late rows update already-emitted windows, so the output is updating
+    pub(crate) allowed_lateness: Option<Duration>,
 }
 
 impl AggregateExtension {
@@ -53,17 +59,57 @@ impl AggregateExtension {
         window_behavior: WindowBehavior,
         aggregate: LogicalPlan,
         key_fields: Vec<usize>,
-    ) -> Self {
+        allowed_lateness: Option<Duration>,
+    ) -> DFResult<Self> {
         let final_calculation =
             Self::final_projection(&aggregate, window_behavior.clone()).unwrap();
 
-        Self {
+        // only windows computed by their own operator can be updated by late rows
+        if allowed_lateness.is_some()
+            && !matches!(
+                window_behavior,
+                WindowBehavior::FromOperator {
+                    window: WindowType::Tumbling { .. }
+                        | WindowType::Sliding { .. }
+                        | WindowType::Session { .. },
+                    is_nested: false,
+                    ..
+                }
+            )
+        {
+            return plan_err!(
+                "allowed_lateness hint is only supported for tumbling, sliding and session windows that are not nested in another window"
+            );
+        }
+
+        let schema = if allowed_lateness.is_some() {
+            let mut fields = final_calculation.schema().fields().clone();
+            fields.push(DFField::new_unqualified(
+                IS_RETRACT_FIELD,
+                DataType::Boolean,
+                false,
+            ));
+            Arc::new(
+                DFSchema::new_with_metadata(fields, final_calculation.schema().metadata().clone())
+                    .unwrap(),
+            )
+        } else {
+            final_calculation.schema().clone()
+        };
+
+        Ok(Self {
             window_behavior,
             aggregate,
-            schema: final_calculation.schema().clone(),
+            schema,
             key_fields,
             final_calculation,
-        }
+            allowed_lateness,
+        })
+    }
+
+    fn allowed_lateness_micros(&self) -> Option<u64> {
+        self.allowed_lateness
+            .map(|lateness| lateness.as_micros() as u64)
     }
 
     pub fn tumbling_window_config(
@@ -72,6 +118,7 @@ impl AggregateExtension {
         index: usize,
         input_schema: DFSchemaRef,
         width: Duration,
+        cumulate_max_size: Option<Duration>,
     ) -> Result<LogicalNode> {
         let binning_function_proto = planner.binning_function_proto(width, input_schema.clone())?;
         let SplitPlanOutput {
@@ -87,7 +134,10 @@ impl AggregateExtension {
         )?;
 
         let config = TumblingWindowAggregateOperator {
-            name: "TumblingWindow".to_string(),
+            name: match cumulate_max_size {
+                Some(max_size) => format!("CumulatingWindow<{:?}>", max_size),
+                None => "TumblingWindow".to_string(),
+            },
             width_micros: width.as_micros() as u64,
             binning_function: binning_function_proto.encode_to_vec(),
             input_schema: Some(
@@ -101,13 +151,18 @@ impl AggregateExtension {
             partial_aggregation_plan: partial_aggregation_plan.encode_to_vec(),
             final_aggregation_plan: finish_plan.encode_to_vec(),
             final_projection: Some(final_physical_plan_node.encode_to_vec()),
+            allowed_lateness_micros: self.allowed_lateness_micros(),
+            cumulate_max_size_micros: cumulate_max_size.map(|max_size| max_size.as_micros() as u64),
         };
 
         Ok(LogicalNode {
             operator_id: format!("tumbling_{}", index),
             operator_name: OperatorName::TumblingWindowAggregate,
             operator_config: config.encode_to_vec(),
-            description: format!("TumblingWindow<{}>", config.name),
+            description: match cumulate_max_size {
+                Some(_) => config.name.clone(),
+                None => format!("TumblingWindow<{}>", config.name),
+            },
             parallelism: 1,
         })
     }
@@ -120,7 +175,9 @@ impl AggregateExtension {
         width: Duration,
         slide: Duration,
     ) -> Result<LogicalNode> {
-        let binning_function_proto = planner.binning_function_proto(slide, input_schema.clone())?;
+        // rows are partially aggregated into panes that evenly divide both the width and the slide
+        let pane = sliding_window_pane(width, slide);
+        let binning_function_proto = planner.binning_function_proto(pane, input_schema.clone())?;
 
         let SplitPlanOutput {
             partial_aggregation_plan,
@@ -150,6 +207,7 @@ impl AggregateExtension {
             partial_aggregation_plan: partial_aggregation_plan.encode_to_vec(),
             final_aggregation_plan: finish_plan.encode_to_vec(),
             final_projection: final_physical_plan_node.encode_to_vec(),
+            allowed_lateness_micros: self.allowed_lateness_micros(),
             // TODO add final aggregation.
         };
         Ok(LogicalNode {
@@ -212,6 +270,7 @@ impl AggregateExtension {
             unkeyed_aggregate_schema: None,
             partial_aggregation_plan: vec![],
             final_aggregation_plan: physical_plan_node.encode_to_vec(),
+            allowed_lateness_micros: self.allowed_lateness_micros(),
         };
 
         Ok(LogicalNode {
@@ -269,6 +328,8 @@ impl AggregateExtension {
             partial_aggregation_plan: partial_aggregation_plan.encode_to_vec(),
             final_aggregation_plan: finish_plan.encode_to_vec(),
             final_projection,
+            allowed_lateness_micros: None,
+            cumulate_max_size_micros: None,
         };
 
         Ok(LogicalNode {
@@ -300,6 +361,8 @@ impl AggregateExtension {
             .iter()
             .map(|field| Expr::Column(field.qualified_column()))
             .collect();
+        // cumulating windows start at their period rather than at the bin start
+        let mut period = None;
         let (window_field, window_index, width, is_nested) = match window_behavior {
             WindowBehavior::InData => return Ok(timestamp_append),
             WindowBehavior::FromOperator {
@@ -311,6 +374,10 @@ impl AggregateExtension {
                 WindowType::Tumbling { width, .. } | WindowType::Sliding { width, .. } => {
                     (window_field, window_index, width, is_nested)
                 }
+                WindowType::Cumulating { step, max_size } => {
+                    period = Some(max_size);
+                    (window_field, window_index, step, is_nested)
+                }
                 WindowType::Session { .. } => {
                     return Ok(LogicalPlan::Extension(Extension {
                         node: Arc::new(WindowAppendExtension::new(
@@ -337,8 +404,16 @@ impl AggregateExtension {
         let window_expression = Expr::ScalarFunction(ScalarFunction {
             func_def: ScalarFunctionDefinition::UDF(Arc::new(window_scalar_function())),
             args: vec![
-                // copy bin_start as first argument
-                Expr::Column(timestamp_column.clone()),
+                // copy bin_start as first argument, or round it down to the period for cumulating windows
+                match period {
+                    Some(period) => date_bin().call(vec![
+                        Expr::Literal(ScalarValue::IntervalMonthDayNano(Some(
+                            IntervalMonthDayNanoType::make_value(0, 0, period.as_nanos() as i64),
+                        ))),
+                        Expr::Column(timestamp_column.clone()),
+                    ]),
+                    None => Expr::Column(timestamp_column.clone()),
+                },
                 // add width interval to _timestamp for bin end
                 Expr::BinaryExpr(BinaryExpr {
                     left: Box::new(Expr::Column(timestamp_column.clone())),
@@ -474,6 +549,7 @@ impl UserDefinedLogicalNodeCore for AggregateExtension {
             self.window_behavior.clone(),
             inputs[0].clone(),
             self.key_fields.clone(),
+            self.allowed_lateness,
         )
     }
 }
@@ -506,9 +582,20 @@ impl ArroyoExtension for AggregateExtension {
                     self.instant_window_config(planner, index, input_df_schema, true)?
                 } else {
                     match window {
-                        WindowType::Tumbling { width } => {
-                            self.tumbling_window_config(planner, index, input_df_schema, *width)?
-                        }
+                        WindowType::Tumbling { width } => self.tumbling_window_config(
+                            planner,
+                            index,
+                            input_df_schema,
+                            *width,
+                            None,
+                        )?,
+                        WindowType::Cumulating { step, max_size } => self.tumbling_window_config(
+                            planner,
+                            index,
+                            input_df_schema,
+                            *step,
+                            Some(*max_size),
+                        )?,
                         WindowType::Sliding { width, slide } => self.sliding_window_config(
                             planner,
                             index,
@@ -0,0 +1,118 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{bail, Result};
+use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName};
+use arroyo_rpc::{df::ArroyoSchema, grpc::api::DeduplicateOperator};
+use datafusion::common::{DFSchema, DFSchemaRef, Result as DFResult};
+use datafusion::logical_expr::{LogicalPlan, UserDefinedLogicalNodeCore};
+use prost::Message;
+
+use crate::builder::{NamedNode, Planner};
+
+use super::{ArroyoExtension, NodeWithIncomingEdges};
+
+pub(crate) const DEDUPLICATE_EXTENSION_NAME: &str = "DeduplicateExtension";
+
+/// Passes on the earliest row by event time of each key of its keyed input, dropping later rows
+/// with the same key until `ttl` has passed since that row.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct DeduplicateExtension {
+    pub(crate) input: LogicalPlan,
+    pub(crate) key_fields: Vec<usize>,
+    pub(crate) ttl: Duration,
+    // the input's schema without the key fields
+    schema: DFSchemaRef,
+}
+
+impl DeduplicateExtension {
+    pub fn new(input: LogicalPlan, key_fields: Vec<usize>, ttl: Duration) -> DFResult<Self> {
+        let input_schema = input.schema();
+        let fields = input_schema
+            .fields()
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !key_fields.contains(index))
+            .map(|(_, field)| field.clone())
+            .collect();
+        let schema = Arc::new(DFSchema::new_with_metadata(
+            fields,
+            input_schema.metadata().clone(),
+        )?);
+        Ok(Self {
+            input,
+            key_fields,
+            ttl,
+            schema,
+        })
+    }
+}
+
+impl UserDefinedLogicalNodeCore for DeduplicateExtension {
+    fn name(&self) -> &str {
+        DEDUPLICATE_EXTENSION_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.input]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<datafusion::prelude::Expr> {
+        vec![]
+    }
+
+    fn fmt_for_explain(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "DeduplicateExtension<ttl={:?}>", self.ttl)
+    }
+
+    fn from_template(&self, _exprs: &[datafusion::prelude::Expr], inputs: &[LogicalPlan]) -> Self {
+        Self::new(inputs[0].clone(), self.key_fields.clone(), self.ttl)
+            .expect("input should still have the key fields")
+    }
+}
+
+impl ArroyoExtension for DeduplicateExtension {
+    fn node_name(&self) -> Option<NamedNode> {
+        None
+    }
+
+    fn plan_node(
+        &self,
+        _planner: &Planner,
+        index: usize,
+        input_schemas: Vec<arroyo_rpc::df::ArroyoSchemaRef>,
+    ) -> Result<NodeWithIncomingEdges> {
+        if input_schemas.len() != 1 {
+            bail!(
+                "DeduplicateExtension requires exactly one input schema, found {}",
+                input_schemas.len()
+            );
+        }
+        let input_schema = input_schemas[0].clone();
+
+        let config = DeduplicateOperator {
+            name: "Deduplicate".to_string(),
+            input_schema: Some(input_schema.as_ref().clone().try_into()?),
+            ttl_micros: self.ttl.as_micros() as u64,
+        };
+        let node = LogicalNode {
+            operator_id: format!("deduplicate_{}", index),
+            description: format!("deduplicate<ttl={:?}>", self.ttl),
+            operator_name: OperatorName::Deduplicate,
+            operator_config: config.encode_to_vec(),
+            parallelism: 1,
+        };
+        let edge = LogicalEdge::project_all(LogicalEdgeType::Shuffle, (*input_schema).clone());
+        Ok(NodeWithIncomingEdges {
+            node,
+            edges: vec![edge],
+        })
+    }
+
+    fn output_schema(&self) -> ArroyoSchema {
+        ArroyoSchema::from_schema_unkeyed(Arc::new(self.schema().as_ref().into())).unwrap()
+    }
+}
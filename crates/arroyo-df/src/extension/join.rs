@@ -12,13 +12,25 @@ use datafusion_proto::generated::datafusion::PhysicalPlanNode;
 use datafusion_proto::physical_plan::AsExecutionPlan;
 use prost::Message;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub(crate) const JOIN_NODE_NAME: &str = "JoinNode";
 
+/// How long each side of an interval join keeps its rows behind the watermark, derived from
+/// the bounds the join places on the difference between the two event times
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct IntervalJoinTtls {
+    pub(crate) left: Duration,
+    pub(crate) right: Duration,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct JoinExtension {
     pub(crate) rewritten_join: LogicalPlan,
     pub(crate) is_instant: bool,
+    pub(crate) is_temporal: bool,
+    pub(crate) ttl: Option<Duration>,
+    pub(crate) interval: Option<IntervalJoinTtls>,
 }
 
 impl ArroyoExtension for JoinExtension {
@@ -45,6 +57,8 @@ impl ArroyoExtension for JoinExtension {
         )?;
         let operator_name = if self.is_instant {
             OperatorName::InstantJoin
+        } else if self.is_temporal {
+            OperatorName::TemporalJoin
         } else {
             OperatorName::Join
         };
@@ -54,10 +68,22 @@ impl ArroyoExtension for JoinExtension {
             right_schema: Some(right_schema.as_ref().clone().try_into()?),
             output_schema: Some(self.output_schema().try_into()?),
             join_plan: physical_plan_node.encode_to_vec(),
+            ttl_micros: self.ttl.map(|ttl| ttl.as_micros() as u64),
+            left_ttl_micros: self.interval.map(|ttls| ttls.left.as_micros() as u64),
+            right_ttl_micros: self.interval.map(|ttls| ttls.right.as_micros() as u64),
+        };
+        let description = match (self.interval, self.ttl) {
+            (None, Some(ttl)) if self.is_temporal => format!("temporal_join<ttl={:?}>", ttl),
+            (Some(ttls), _) => format!(
+                "interval_join<left_ttl={:?}, right_ttl={:?}>",
+                ttls.left, ttls.right
+            ),
+            (None, Some(ttl)) => format!("join<ttl={:?}>", ttl),
+            (None, None) => "join".to_string(),
         };
         let logical_node = LogicalNode {
             operator_id: format!("join_{}", index),
-            description: "join".to_string(),
+            description,
             operator_name,
             operator_config: config.encode_to_vec(),
             parallelism: 1,
@@ -111,6 +137,9 @@ impl UserDefinedLogicalNodeCore for JoinExtension {
         Self {
             rewritten_join: inputs[0].clone(),
             is_instant: self.is_instant,
+            is_temporal: self.is_temporal,
+            ttl: self.ttl,
+            interval: self.interval,
         }
     }
 }
@@ -0,0 +1,241 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{bail, Result};
+use arrow_schema::{DataType, TimeUnit};
+use arroyo_datastream::logical::{LogicalEdge, LogicalEdgeType, LogicalNode, OperatorName};
+use arroyo_rpc::grpc::api;
+use arroyo_rpc::{df::ArroyoSchema, TIMESTAMP_FIELD};
+use datafusion::common::{plan_err, DFField, DFSchema, DFSchemaRef, Result as DFResult};
+use datafusion::logical_expr::{LogicalPlan, UserDefinedLogicalNodeCore};
+use prost::Message;
+
+use crate::builder::{NamedNode, Planner};
+use crate::match_recognize::{MatchAggregate, Measure, PatternState};
+
+use super::{ArroyoExtension, NodeWithIncomingEdges};
+
+pub(crate) const MATCH_RECOGNIZE_EXTENSION_NAME: &str = "MatchRecognizeExtension";
+
+/// A variable of the pattern, with the boolean column of the input holding its condition
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct PatternVariable {
+    pub(crate) name: String,
+    pub(crate) condition: Option<usize>,
+}
+
+/// Matches the pattern against the rows of each key of its keyed input, in event time order,
+/// producing the keys and measures of each match, timestamped with its last row.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct MatchRecognizeExtension {
+    pub(crate) input: LogicalPlan,
+    pub(crate) key_count: usize,
+    pub(crate) variables: Vec<PatternVariable>,
+    pub(crate) states: Vec<PatternState>,
+    pub(crate) measures: Vec<Measure>,
+    pub(crate) within: Option<Duration>,
+    pub(crate) ttl: Duration,
+    // the input column each measure aggregates
+    arguments: Vec<Option<usize>>,
+    // the keys, under the names of the partition columns, then the measures and the timestamp
+    schema: DFSchemaRef,
+}
+
+impl MatchRecognizeExtension {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        input: LogicalPlan,
+        partition_by: &[String],
+        variables: Vec<PatternVariable>,
+        states: Vec<PatternState>,
+        measures: Vec<Measure>,
+        within: Option<Duration>,
+        ttl: Duration,
+    ) -> DFResult<Self> {
+        let input_schema = input.schema().clone();
+        let mut fields: Vec<_> = partition_by
+            .iter()
+            .zip(input_schema.fields())
+            .map(|(name, field)| DFField::new_unqualified(name, field.data_type().clone(), true))
+            .collect();
+
+        let mut arguments = vec![];
+        for measure in &measures {
+            let argument = match &measure.column {
+                Some(column) => match input_schema
+                    .fields()
+                    .iter()
+                    .position(|field| field.name() == column)
+                {
+                    Some(index) => Some(index),
+                    None => return plan_err!("MEASURES column {} not found", column),
+                },
+                None => None,
+            };
+            let argument_type = argument.map(|index| input_schema.field(index).data_type());
+            let Some(data_type) = measure.aggregate.output_type(argument_type) else {
+                return plan_err!(
+                    "{} in MEASURES can't aggregate {}",
+                    measure.aggregate,
+                    argument_type
+                        .map(|data_type| data_type.to_string())
+                        .unwrap_or_else(|| "*".to_string())
+                );
+            };
+            fields.push(DFField::new_unqualified(
+                &measure.name,
+                data_type,
+                measure.aggregate != MatchAggregate::Count,
+            ));
+            arguments.push(argument);
+        }
+        fields.push(DFField::new_unqualified(
+            TIMESTAMP_FIELD,
+            DataType::Timestamp(TimeUnit::Nanosecond, None),
+            false,
+        ));
+
+        Ok(Self {
+            input,
+            key_count: partition_by.len(),
+            variables,
+            states,
+            measures,
+            within,
+            ttl,
+            arguments,
+            schema: Arc::new(DFSchema::new_with_metadata(fields, Default::default())?),
+        })
+    }
+
+    fn aggregate(aggregate: MatchAggregate) -> api::MatchAggregate {
+        match aggregate {
+            MatchAggregate::First => api::MatchAggregate::First,
+            MatchAggregate::Last => api::MatchAggregate::Last,
+            MatchAggregate::Count => api::MatchAggregate::Count,
+            MatchAggregate::Min => api::MatchAggregate::Min,
+            MatchAggregate::Max => api::MatchAggregate::Max,
+            MatchAggregate::Sum => api::MatchAggregate::Sum,
+        }
+    }
+}
+
+impl UserDefinedLogicalNodeCore for MatchRecognizeExtension {
+    fn name(&self) -> &str {
+        MATCH_RECOGNIZE_EXTENSION_NAME
+    }
+
+    fn inputs(&self) -> Vec<&LogicalPlan> {
+        vec![&self.input]
+    }
+
+    fn schema(&self) -> &DFSchemaRef {
+        &self.schema
+    }
+
+    fn expressions(&self) -> Vec<datafusion::prelude::Expr> {
+        vec![]
+    }
+
+    fn fmt_for_explain(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "MatchRecognizeExtension<variables={}, states={}>",
+            self.variables
+                .iter()
+                .map(|variable| variable.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.states.len()
+        )
+    }
+
+    fn from_template(&self, _exprs: &[datafusion::prelude::Expr], inputs: &[LogicalPlan]) -> Self {
+        Self {
+            input: inputs[0].clone(),
+            ..self.clone()
+        }
+    }
+}
+
+impl ArroyoExtension for MatchRecognizeExtension {
+    fn node_name(&self) -> Option<NamedNode> {
+        None
+    }
+
+    fn plan_node(
+        &self,
+        _planner: &Planner,
+        index: usize,
+        input_schemas: Vec<arroyo_rpc::df::ArroyoSchemaRef>,
+    ) -> Result<NodeWithIncomingEdges> {
+        if input_schemas.len() != 1 {
+            bail!(
+                "MatchRecognizeExtension requires exactly one input schema, found {}",
+                input_schemas.len()
+            );
+        }
+        let input_schema = input_schemas[0].clone();
+
+        let config = api::MatchRecognizeOperator {
+            name: "MatchRecognize".to_string(),
+            input_schema: Some(input_schema.as_ref().clone().try_into()?),
+            output_schema: Some(self.output_schema().try_into()?),
+            variables: self
+                .variables
+                .iter()
+                .map(|variable| api::PatternVariable {
+                    name: variable.name.clone(),
+                    condition_index: variable.condition.map(|index| index as u32),
+                })
+                .collect(),
+            states: self
+                .states
+                .iter()
+                .map(|state| api::PatternState {
+                    transitions: state
+                        .transitions
+                        .iter()
+                        .map(|(variable, target)| api::PatternTransition {
+                            variable: *variable as u32,
+                            target: *target as u32,
+                        })
+                        .collect(),
+                    accepting: state.accepting,
+                })
+                .collect(),
+            measures: self
+                .measures
+                .iter()
+                .zip(self.arguments.iter())
+                .map(|(measure, argument)| api::MatchMeasure {
+                    name: measure.name.clone(),
+                    aggregate: Self::aggregate(measure.aggregate) as i32,
+                    variable: measure.variable.map(|variable| variable as u32),
+                    argument_index: argument.map(|index| index as u32),
+                })
+                .collect(),
+            within_micros: self.within.map(|within| within.as_micros() as u64),
+            ttl_micros: self.ttl.as_micros() as u64,
+        };
+        let description = match self.within {
+            Some(within) => format!("match_recognize<within={:?}>", within),
+            None => format!("match_recognize<ttl={:?}>", self.ttl),
+        };
+        let node = LogicalNode {
+            operator_id: format!("match_recognize_{}", index),
+            description,
+            operator_name: OperatorName::MatchRecognize,
+            operator_config: config.encode_to_vec(),
+            parallelism: 1,
+        };
+        let edge = LogicalEdge::project_all(LogicalEdgeType::Shuffle, (*input_schema).clone());
+        Ok(NodeWithIncomingEdges {
+            node,
+            edges: vec![edge],
+        })
+    }
+
+    fn output_schema(&self) -> ArroyoSchema {
+        ArroyoSchema::from_schema_unkeyed(Arc::new(self.schema().as_ref().into())).unwrap()
+    }
+}
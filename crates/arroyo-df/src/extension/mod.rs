@@ -29,6 +29,8 @@ use crate::ASYNC_RESULT_FIELD;
 use join::JoinExtension;
 
 use self::debezium::{DebeziumUnrollingExtension, ToDebeziumExtension};
+use self::deduplicate::DeduplicateExtension;
+use self::match_recognize::MatchRecognizeExtension;
 use self::updating_aggregate::UpdatingAggregateExtension;
 use self::{
     aggregate::AggregateExtension, key_calculation::KeyCalculationExtension,
@@ -38,8 +40,10 @@ use self::{
 
 pub(crate) mod aggregate;
 pub(crate) mod debezium;
+pub(crate) mod deduplicate;
 pub(crate) mod join;
 pub(crate) mod key_calculation;
+pub(crate) mod match_recognize;
 pub(crate) mod remote_table;
 pub(crate) mod sink;
 pub(crate) mod table_source;
@@ -91,6 +95,8 @@ impl<'a> TryFrom<&'a dyn UserDefinedLogicalNode> for &'a dyn ArroyoExtension {
             .or_else(|_| try_from_t::<ToDebeziumExtension>(node))
             .or_else(|_| try_from_t::<DebeziumUnrollingExtension>(node))
             .or_else(|_| try_from_t::<UpdatingAggregateExtension>(node))
+            .or_else(|_| try_from_t::<DeduplicateExtension>(node))
+            .or_else(|_| try_from_t::<MatchRecognizeExtension>(node))
             .map_err(|_| DataFusionError::Plan(format!("unexpected node: {}", node.name())))
     }
 }
@@ -22,6 +22,7 @@ pub(crate) struct UpdatingAggregateExtension {
     pub(crate) key_fields: Vec<usize>,
     pub(crate) final_calculation: LogicalPlan,
     pub(crate) timestamp_qualifier: Option<OwnedTableReference>,
+    pub(crate) ttl: Duration,
 }
 
 impl UpdatingAggregateExtension {
@@ -29,6 +30,7 @@ impl UpdatingAggregateExtension {
         aggregate: LogicalPlan,
         key_fields: Vec<usize>,
         timestamp_qualifier: Option<OwnedTableReference>,
+        ttl: Duration,
     ) -> Self {
         let final_calculation = LogicalPlan::Extension(Extension {
             node: Arc::new(IsRetractExtension::new(
@@ -41,6 +43,7 @@ impl UpdatingAggregateExtension {
             key_fields,
             final_calculation,
             timestamp_qualifier,
+            ttl,
         }
     }
 }
@@ -71,6 +74,7 @@ impl UserDefinedLogicalNodeCore for UpdatingAggregateExtension {
             inputs[0].clone(),
             self.key_fields.clone(),
             self.timestamp_qualifier.clone(),
+            self.ttl,
         )
     }
 }
@@ -168,10 +172,11 @@ impl ArroyoExtension for UpdatingAggregateExtension {
             combine_plan: combine_plan.encode_to_vec(),
             final_aggregation_plan: finish_plan.encode_to_vec(),
             flush_interval_micros: flush_interval.as_micros() as u64,
+            ttl_micros: Some(self.ttl.as_micros() as u64),
         };
         let node = LogicalNode {
             operator_id: format!("updating_aggregate_{}", index),
-            description: "UpdatingAggregate".to_string(),
+            description: format!("UpdatingAggregate<ttl={:?}>", self.ttl),
             operator_name: OperatorName::UpdatingAggregate,
             operator_config: config.encode_to_vec(),
             parallelism: 1,
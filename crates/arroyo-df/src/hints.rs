@@ -0,0 +1,153 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use arrow::compute::kernels::cast_utils::parse_interval_month_day_nano;
+use datafusion::sql::sqlparser::dialect::Dialect;
+use datafusion::sql::sqlparser::tokenizer::{Token, Tokenizer, Whitespace};
+
+use crate::types::interval_month_day_nanos_to_duration;
+
+/// Planner hints attached to a statement with an optimizer-hint style comment, e.g.
+/// `INSERT INTO sink /*+ parallelism(8) */ SELECT ...`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatementHints {
+    pub parallelism: Option<usize>,
+    /// How long after a window closes late rows may still update it, e.g.
+    /// `allowed_lateness('1 minute')`
+    pub allowed_lateness: Option<Duration>,
+    /// A table that rows too late to be aggregated are written to, e.g. `late_data_sink(late)`
+    pub late_data_sink: Option<String>,
+    /// How long updating aggregates and non-windowed joins keep state, e.g.
+    /// `updating_ttl('7 days')`; overrides `SET updating_ttl`
+    pub updating_ttl: Option<Duration>,
+}
+
+/// Parses an interval string like `'1 minute'` or `7 days`, with or without quotes
+pub(crate) fn parse_duration(value: &str) -> Option<Duration> {
+    parse_interval_month_day_nano(value.trim().trim_matches('\''))
+        .ok()
+        .map(interval_month_day_nanos_to_duration)
+}
+
+/// Extracts the hints for each statement in the query, in the same order as the statements
+/// returned by the parser. Comments are dropped by the parser, so we tokenize the query
+/// separately to find them.
+pub fn parse_statement_hints(dialect: &dyn Dialect, query: &str) -> Result<Vec<StatementHints>> {
+    let tokens = Tokenizer::new(dialect, query)
+        .tokenize()
+        .map_err(|e| anyhow!("failed to tokenize query: {}", e))?;
+
+    let mut statements = vec![];
+    let mut current = StatementHints::default();
+    let mut has_tokens = false;
+
+    for token in tokens {
+        match token {
+            Token::Whitespace(Whitespace::MultiLineComment(comment)) => {
+                if let Some(hints) = comment.strip_prefix('+') {
+                    parse_hints(hints, &mut current)?;
+                }
+            }
+            Token::Whitespace(_) | Token::EOF => {}
+            Token::SemiColon => {
+                // the parser skips empty statements, so we do as well
+                if has_tokens {
+                    statements.push(std::mem::take(&mut current));
+                    has_tokens = false;
+                }
+            }
+            _ => {
+                has_tokens = true;
+            }
+        }
+    }
+
+    if has_tokens {
+        statements.push(current);
+    }
+
+    Ok(statements)
+}
+
+fn parse_hints(hints: &str, statement: &mut StatementHints) -> Result<()> {
+    let mut rest = hints.trim();
+
+    while !rest.is_empty() {
+        let (name, after) = rest
+            .split_once('(')
+            .ok_or_else(|| anyhow!("invalid hint '{}'; expected hint(value)", rest))?;
+        let (arg, after) = after
+            .split_once(')')
+            .ok_or_else(|| anyhow!("invalid hint '{}'; missing ')'", rest))?;
+
+        let name = name.trim();
+        let arg = arg.trim();
+
+        match name.to_lowercase().as_str() {
+            "parallelism" => {
+                let parallelism: usize = arg.parse().map_err(|_| {
+                    anyhow!("parallelism hint must be a positive integer, not '{}'", arg)
+                })?;
+
+                if parallelism == 0 {
+                    bail!("parallelism hint must be a positive integer, not '{}'", arg);
+                }
+
+                if statement
+                    .parallelism
+                    .is_some_and(|existing| existing != parallelism)
+                {
+                    bail!("statement has multiple conflicting parallelism hints");
+                }
+
+                statement.parallelism = Some(parallelism);
+            }
+            "allowed_lateness" => {
+                let lateness = parse_duration(arg).ok_or_else(|| {
+                    anyhow!(
+                        "allowed_lateness hint must be an interval like '1 minute', not {}",
+                        arg
+                    )
+                })?;
+
+                if statement.allowed_lateness.is_some() {
+                    bail!("statement has multiple allowed_lateness hints");
+                }
+
+                statement.allowed_lateness = Some(lateness);
+            }
+            "late_data_sink" => {
+                if arg.is_empty() {
+                    bail!("late_data_sink hint must name a table");
+                }
+
+                if statement.late_data_sink.is_some() {
+                    bail!("statement has multiple late_data_sink hints");
+                }
+
+                statement.late_data_sink = Some(arg.trim_matches('"').to_string());
+            }
+            "updating_ttl" => {
+                let ttl = parse_duration(arg)
+                    .filter(|ttl| !ttl.is_zero())
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "updating_ttl hint must be a positive interval like '7 days', not {}",
+                            arg
+                        )
+                    })?;
+
+                if statement.updating_ttl.is_some() {
+                    bail!("statement has multiple updating_ttl hints");
+                }
+
+                statement.updating_ttl = Some(ttl);
+            }
+            _ => bail!("unknown hint '{}'", name),
+        }
+
+        rest = after.trim_start().trim_start_matches(',').trim_start();
+    }
+
+    Ok(())
+}
@@ -1,14 +1,18 @@
 #![allow(clippy::new_without_default)]
 
 pub mod builder;
+mod explain;
 pub(crate) mod extension;
 pub mod external;
+mod hints;
 mod json;
 pub mod logical;
+pub mod match_recognize;
 pub mod physical;
 mod plan;
 mod rewriters;
 pub mod schemas;
+pub mod system_time;
 mod tables;
 pub mod types;
 pub mod udafs;
@@ -29,6 +33,7 @@ use datafusion::physical_plan::functions::make_scalar_function;
 
 use datafusion::prelude::create_udf;
 
+use datafusion::sql::sqlparser::ast::{Expr as SqlExpr, ObjectName, Statement, Value as SqlValue};
 use datafusion::sql::sqlparser::dialect::PostgreSqlDialect;
 use datafusion::sql::sqlparser::parser::Parser;
 use datafusion::sql::{planner::ContextProvider, TableReference};
@@ -49,14 +54,24 @@ use tables::{Insert, Table};
 use crate::builder::PlanToGraphVisitor;
 use crate::extension::sink::SinkExtension;
 use crate::plan::ArroyoRewriter;
-use arroyo_datastream::logical::{DylibUdfConfig, ProgramConfig};
+use arroyo_datastream::logical::{
+    DylibUdfConfig, LogicalEdge, LogicalEdgeType, LogicalGraph, LogicalNode, OperatorName,
+    ProgramConfig,
+};
 use arroyo_rpc::api_types::connections::ConnectionProfile;
+use arroyo_rpc::api_types::pipelines::QueryExplanation;
+use arroyo_rpc::df::ArroyoSchema;
+use arroyo_types::sliding_window_pane;
 use datafusion::common::DataFusionError;
 use std::collections::HashSet;
 use std::fmt::Debug;
+use std::ops::Range;
 
+use crate::hints::parse_statement_hints;
 use crate::json::get_json_functions;
+use crate::match_recognize::extract_match_recognize_clauses;
 use crate::rewriters::{SourceMetadataVisitor, TimeWindowUdfChecker, UnnestRewriter};
+use crate::system_time::{extract_system_time_clauses, SystemTimeClause};
 use crate::types::interval_month_day_nanos_to_duration;
 
 use crate::udafs::EmptyUdaf;
@@ -67,6 +82,9 @@ use arroyo_udf_host::ParsedUdfFile;
 use datafusion::execution::FunctionRegistry;
 use datafusion::logical_expr;
 use datafusion::logical_expr::expr_rewriter::FunctionRewrite;
+use petgraph::graph::NodeIndex;
+use petgraph::Direction;
+use prost::Message;
 use std::time::{Duration, SystemTime};
 use std::{collections::HashMap, sync::Arc};
 use syn::Item;
@@ -74,12 +92,17 @@ use tracing::{info, warn};
 use unicase::UniCase;
 
 const DEFAULT_IDLE_TIME: Option<Duration> = Some(Duration::from_secs(5 * 60));
+/// The most panes a hop() window whose width is not a multiple of its slide may be split into;
+/// each pane is stored and combined separately for every key
+const MAX_UNALIGNED_HOP_PANES: u128 = 1000;
 pub const ASYNC_RESULT_FIELD: &str = "__async_result";
 
 #[derive(Clone, Debug)]
 pub struct CompiledSql {
     pub program: LogicalProgram,
     pub connection_ids: Vec<i64>,
+    /// Set if the query was `EXPLAIN`ed
+    pub explanation: Option<QueryExplanation>,
 }
 
 #[derive(Clone, Default)]
@@ -117,6 +140,20 @@ impl ArroyoSchemaProvider {
                 make_scalar_function(fn_impl),
             )),
         );
+        functions.insert(
+            "cumulate".to_string(),
+            Arc::new(create_udf(
+                "cumulate",
+                vec![
+                    DataType::Interval(datatypes::IntervalUnit::MonthDayNano),
+                    DataType::Interval(datatypes::IntervalUnit::MonthDayNano),
+                ],
+                window_return_type.clone(),
+                Volatility::Volatile,
+                #[allow(deprecated)]
+                make_scalar_function(fn_impl),
+            )),
+        );
         functions.insert(
             "tumble".to_string(),
             Arc::new(create_udf(
@@ -397,13 +434,18 @@ impl FunctionRegistry for ArroyoSchemaProvider {
 
 #[derive(Clone, Debug)]
 pub struct SqlConfig {
+    /// The parallelism for operators that are not configured with a parallelism hint
     pub default_parallelism: usize,
+    /// How long updating aggregates and non-windowed joins keep state, unless the query sets
+    /// it with `SET updating_ttl` or an `updating_ttl` hint; each operator has its own default
+    pub updating_ttl: Option<Duration>,
 }
 
 impl Default for SqlConfig {
     fn default() -> Self {
         Self {
             default_parallelism: 4,
+            updating_ttl: None,
         }
     }
 }
@@ -460,15 +502,41 @@ fn find_window(expression: &Expr) -> Result<Option<WindowType>> {
                 }
                 let slide = get_duration(&args[0])?;
                 let width = get_duration(&args[1])?;
-                if width.as_nanos() % slide.as_nanos() != 0 {
+                if slide.is_zero() || slide > width {
                     bail!(
-                        "hop() width {:?} currently must be a multiple of slide {:?}",
+                        "hop() slide {:?} must be positive and no larger than width {:?}",
+                        slide,
+                        width
+                    );
+                }
+                let panes = width.as_nanos() / sliding_window_pane(width, slide).as_nanos();
+                if width.as_nanos() % slide.as_nanos() != 0 && panes > MAX_UNALIGNED_HOP_PANES {
+                    bail!(
+                        "hop() width {:?} is not a multiple of slide {:?}, which splits each window into {} panes \
+                        (the limit is {}); use a width that is a multiple of the slide",
                         width,
-                        slide
+                        slide,
+                        panes,
+                        MAX_UNALIGNED_HOP_PANES
                     );
                 }
                 Ok(Some(WindowType::Sliding { width, slide }))
             }
+            "cumulate" => {
+                if args.len() != 2 {
+                    unreachable!("wrong number of arguments for cumulate(), expected two");
+                }
+                let step = get_duration(&args[0])?;
+                let max_size = get_duration(&args[1])?;
+                if step.is_zero() || max_size.as_nanos() % step.as_nanos() != 0 {
+                    bail!(
+                        "cumulate() max size {:?} must be a multiple of step {:?}",
+                        max_size,
+                        step
+                    );
+                }
+                Ok(Some(WindowType::Cumulating { step, max_size }))
+            }
             "tumble" => {
                 if args.len() != 1 {
                     unreachable!("wrong number of arguments for tumble(), expect one");
@@ -503,11 +571,21 @@ fn inspect_plan(logical_plan: LogicalPlan) -> LogicalPlan {
 pub fn rewrite_plan(
     plan: LogicalPlan,
     schema_provider: &ArroyoSchemaProvider,
+    allowed_lateness: Option<Duration>,
+    updating_ttl: Option<Duration>,
+    system_time_clauses: &[SystemTimeClause],
 ) -> DFResult<LogicalPlan> {
-    let rewritten_plan = plan
-        .rewrite(&mut UnnestRewriter {})?
-        .data
-        .rewrite(&mut ArroyoRewriter { schema_provider })?;
+    let rewritten_plan =
+        plan.rewrite(&mut UnnestRewriter {})?
+            .data
+            .rewrite(&mut ArroyoRewriter {
+                schema_provider,
+                allowed_lateness,
+                updating_ttl,
+                system_time_clauses,
+                pending_deduplications: vec![],
+                window_deduplications: vec![],
+            })?;
     // check for window functions
     rewritten_plan.data.visit(&mut TimeWindowUdfChecker {})?;
     Ok(rewritten_plan.data)
@@ -516,19 +594,68 @@ pub fn rewrite_plan(
 pub async fn parse_and_get_arrow_program(
     query: String,
     mut schema_provider: ArroyoSchemaProvider,
-    // TODO: use config
-    _config: SqlConfig,
+    config: SqlConfig,
 ) -> Result<CompiledSql> {
     let dialect = PostgreSqlDialect {};
+    let hints = parse_statement_hints(&dialect, &query)?;
+    let (tokens, system_time_clauses) = extract_system_time_clauses(&dialect, &query)?;
+    let (tokens, match_recognize_clauses) = extract_match_recognize_clauses(&dialect, tokens)?;
     let mut inserts = vec![];
-    for statement in Parser::parse_sql(&dialect, &query)? {
-        if let Some(table) = Table::try_from_statement(&statement, &schema_provider)? {
+    // SET statements apply to the statements that follow them
+    let mut updating_ttl = config.updating_ttl;
+    // set to whether it's verbose if any statement is EXPLAINed
+    let mut explain = None;
+    for (i, statement) in Parser::new(&dialect)
+        .with_tokens_with_locations(tokens)
+        .parse_statements()?
+        .iter()
+        .enumerate()
+    {
+        let statement_hints = hints.get(i).cloned().unwrap_or_default();
+        // the matches of each MATCH_RECOGNIZE are planned as a table the statement reads from
+        for clause in match_recognize_clauses.get(i).into_iter().flatten() {
+            let table = clause.plan(
+                &schema_provider,
+                statement_hints.updating_ttl.or(updating_ttl),
+            )?;
+            schema_provider.insert_table(table);
+        }
+        // EXPLAIN plans its statement as usual, so that the whole program can be described
+        let statement = match statement {
+            Statement::Explain { analyze: true, .. } => bail!("EXPLAIN ANALYZE is not supported"),
+            Statement::Explain {
+                verbose, statement, ..
+            } => {
+                // only statements that are planned into the pipeline can be explained
+                if !matches!(
+                    statement.as_ref(),
+                    Statement::Query(_) | Statement::Insert { .. }
+                ) {
+                    bail!(
+                        "only queries and INSERT statements can be EXPLAINed, not '{}'",
+                        statement
+                    );
+                }
+                explain = Some(explain.unwrap_or_default() || *verbose);
+                statement.as_ref()
+            }
+            statement => statement,
+        };
+        if let Statement::SetVariable {
+            variable, value, ..
+        } = statement
+        {
+            updating_ttl = Some(parse_set_updating_ttl(variable, value)?);
+        } else if let Some(table) = Table::try_from_statement(statement, &schema_provider)? {
             schema_provider.insert_table(table);
         } else {
-            inserts.push(Insert::try_from_statement(
-                &statement,
-                &mut schema_provider,
-            )?);
+            let mut hints = statement_hints;
+            hints.updating_ttl = hints.updating_ttl.or(updating_ttl);
+            inserts.push((
+                Insert::try_from_statement(statement, &mut schema_provider)?,
+                hints,
+                system_time_clauses.get(i).cloned().unwrap_or_default(),
+            ));
         };
     }
 
@@ -538,8 +665,9 @@ pub async fn parse_and_get_arrow_program(
 
     let mut used_connections = HashSet::new();
     let mut extensions = vec![];
+    let mut unrewritten_plans = vec![];
 
-    for insert in inserts {
+    for (insert, hints, system_time_clauses) in inserts {
         let (plan, sink_name) = match insert {
             // TODO: implement inserts
             Insert::InsertQuery {
@@ -548,8 +676,17 @@ pub async fn parse_and_get_arrow_program(
             } => (logical_plan, Some(sink_name)),
             Insert::Anonymous { logical_plan } => (logical_plan, None),
         };
+        if explain.is_some() {
+            unrewritten_plans.push(plan.clone());
+        }
 
-        let plan_rewrite = rewrite_plan(plan, &schema_provider)?;
+        let plan_rewrite = rewrite_plan(
+            plan,
+            &schema_provider,
+            hints.allowed_lateness,
+            hints.updating_ttl,
+            &system_time_clauses,
+        )?;
 
         let mut metadata = SourceMetadataVisitor::new(&schema_provider);
         plan_rewrite.visit(&mut metadata)?;
@@ -591,28 +728,198 @@ pub async fn parse_and_get_arrow_program(
                 Arc::new(plan_rewrite),
             ),
         };
-        extensions.push(LogicalPlan::Extension(Extension {
-            node: Arc::new(sink?),
-        }));
+        extensions.push((
+            LogicalPlan::Extension(Extension {
+                node: Arc::new(sink?),
+            }),
+            hints,
+        ));
     }
+    let rewritten_plans: Vec<_> = match explain {
+        Some(_) => extensions.iter().map(|(plan, _)| plan.clone()).collect(),
+        None => vec![],
+    };
     let mut plan_to_graph_visitor = PlanToGraphVisitor::new(&schema_provider);
-    for extension in extensions {
+    // a statement's hints apply to the nodes that were first planned for that statement
+    let mut hinted_nodes = vec![];
+    let mut late_nodes = vec![];
+    for (extension, hints) in extensions {
+        let start = plan_to_graph_visitor.node_count();
         plan_to_graph_visitor.add_plan(extension)?;
+        let nodes = start..plan_to_graph_visitor.node_count();
+        if let Some(parallelism) = hints.parallelism {
+            hinted_nodes.push((nodes.clone(), parallelism));
+        }
+        if hints.allowed_lateness.is_some() || hints.late_data_sink.is_some() {
+            late_nodes.push((nodes, hints));
+        }
     }
-    let graph = plan_to_graph_visitor.into_graph();
-    let program = LogicalProgram::new(
+    let mut graph = plan_to_graph_visitor.into_graph();
+    for (nodes, hints) in late_nodes {
+        let windows = window_aggregates(&graph, nodes);
+        if windows.is_empty() {
+            let hint = if hints.late_data_sink.is_some() {
+                "late_data_sink"
+            } else {
+                "allowed_lateness"
+            };
+            bail!(
+                "{} hint requires the statement to have a windowed aggregate",
+                hint
+            );
+        }
+        if let Some(sink_name) = hints.late_data_sink {
+            let sink = add_late_data_sink(&mut graph, windows, &sink_name, &schema_provider)?;
+            // the sink runs at the same parallelism as the windows it receives late rows from
+            if let Some(parallelism) = hints.parallelism {
+                hinted_nodes.push((sink.index()..sink.index() + 1, parallelism));
+            }
+        }
+    }
+    let mut overrides = HashMap::new();
+    for (range, parallelism) in hinted_nodes {
+        for idx in range {
+            overrides.insert(graph[NodeIndex::new(idx)].operator_id.clone(), parallelism);
+        }
+    }
+    for node in graph.node_weights_mut() {
+        node.parallelism = config.default_parallelism;
+    }
+    let mut program = LogicalProgram::new(
         graph,
         ProgramConfig {
             udf_dylibs: schema_provider.dylib_udfs.clone(),
         },
     );
+    program.apply_parallelism_overrides(&overrides)?;
+
+    let explanation = explain
+        .map(|verbose| explain::explain(verbose, &unrewritten_plans, &rewritten_plans, &program))
+        .transpose()?;
 
     Ok(CompiledSql {
         program,
         connection_ids: used_connections.into_iter().collect(),
+        explanation,
     })
 }
 
+fn parse_set_updating_ttl(variable: &ObjectName, value: &[SqlExpr]) -> Result<Duration> {
+    if !variable.to_string().eq_ignore_ascii_case("updating_ttl") {
+        bail!(
+            "unknown setting '{}'; only updating_ttl can be SET",
+            variable
+        );
+    }
+
+    let [SqlExpr::Value(SqlValue::SingleQuotedString(ttl))] = value else {
+        bail!("updating_ttl must be set to an interval like '7 days'");
+    };
+
+    hints::parse_duration(ttl)
+        .filter(|ttl| !ttl.is_zero())
+        .ok_or_else(|| {
+            anyhow!(
+                "updating_ttl must be a positive interval like '7 days', not '{}'",
+                ttl
+            )
+        })
+}
+
+/// The windowed aggregates among `nodes`, which are the nodes planned for a statement
+fn window_aggregates(graph: &LogicalGraph, nodes: Range<usize>) -> Vec<NodeIndex> {
+    nodes
+        .map(NodeIndex::new)
+        .filter(|idx| {
+            matches!(
+                graph[*idx].operator_name,
+                OperatorName::TumblingWindowAggregate
+                    | OperatorName::SlidingWindowAggregate
+                    | OperatorName::SessionWindowAggregate
+            )
+        })
+        .collect()
+}
+
+/// Routes the rows that the windowed aggregates planned for a statement were too late to
+/// aggregate to the table named by its late_data_sink hint, returning the sink's node
+fn add_late_data_sink(
+    graph: &mut LogicalGraph,
+    windows: Vec<NodeIndex>,
+    sink_name: &str,
+    schema_provider: &ArroyoSchemaProvider,
+) -> Result<NodeIndex> {
+    let table = schema_provider
+        .get_table(sink_name)
+        .ok_or_else(|| anyhow!("late_data_sink table {} not found", sink_name))?;
+    let Table::ConnectorTable(connector_table) = table else {
+        bail!(
+            "late_data_sink table {} must be a connector table",
+            sink_name
+        );
+    };
+    if connector_table.is_updating() {
+        bail!(
+            "late_data_sink table {} must not be an updating table, as late rows are append-only",
+            sink_name
+        );
+    }
+
+    let connector_op = table.connector_op()?;
+    let sink = graph.add_node(LogicalNode {
+        operator_id: format!("late_sink_{}_{}", sink_name, graph.node_count()),
+        description: connector_op.description.clone(),
+        operator_name: OperatorName::ConnectorSink,
+        operator_config: connector_op.encode_to_vec(),
+        parallelism: graph[windows[0]].parallelism,
+    });
+
+    let sink_schema = connector_table.physical_schema();
+    for window in windows {
+        let input_schema = graph
+            .edges_directed(window, Direction::Incoming)
+            .next()
+            .ok_or_else(|| anyhow!("windowed aggregate has no input"))?
+            .weight()
+            .schema
+            .clone();
+
+        // late rows have the window's input schema, so pick out the sink's columns by name
+        let mut fields = vec![];
+        let mut projection = vec![];
+        for field in sink_schema.fields() {
+            let (index, input_field) = input_schema
+                .schema
+                .column_with_name(field.name())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "late_data_sink table {} has column {} which is not in the input of the windowed aggregate",
+                        sink_name,
+                        field.name()
+                    )
+                })?;
+            fields.push(input_field.clone());
+            projection.push(index);
+        }
+        fields.push(
+            input_schema
+                .schema
+                .field(input_schema.timestamp_index)
+                .clone(),
+        );
+        projection.push(input_schema.timestamp_index);
+
+        let schema = ArroyoSchema::from_schema_unkeyed(Arc::new(Schema::new(fields)))?;
+        graph.add_edge(
+            window,
+            sink,
+            LogicalEdge::new(LogicalEdgeType::LateData, schema, Some(projection)),
+        );
+    }
+
+    Ok(sink)
+}
+
 #[derive(Clone)]
 pub struct TestStruct {
     pub non_nullable_i32: i32,
@@ -0,0 +1,947 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use arrow_schema::DataType;
+use arroyo_rpc::IS_RETRACT_FIELD;
+use arroyo_types::DEFAULT_MATCH_RECOGNIZE_TTL;
+use datafusion::common::Column;
+use datafusion::logical_expr::{Extension, LogicalPlan};
+use datafusion::sql::sqlparser::ast::{
+    Expr as SqlExpr, FunctionArg, FunctionArgExpr, Ident, Statement, Value,
+};
+use datafusion::sql::sqlparser::dialect::{Dialect, PostgreSqlDialect};
+use datafusion::sql::sqlparser::keywords::Keyword;
+use datafusion::sql::sqlparser::parser::Parser;
+use datafusion::sql::sqlparser::tokenizer::{Token, TokenWithLocation};
+
+use crate::extension::key_calculation::KeyCalculationExtension;
+use crate::extension::match_recognize::{MatchRecognizeExtension, PatternVariable};
+use crate::hints::parse_duration;
+use crate::plan::is_event_time;
+use crate::tables::{produce_optimized_plan, Table};
+use crate::{rewrite_plan, ArroyoSchemaProvider};
+
+/// A `MATCH_RECOGNIZE (...)` clause following a table in a FROM clause, which finds the rows of
+/// each partition that match a pattern, in event time order, and produces a row per match, e.g.
+///
+/// ```sql
+/// SELECT * FROM trades MATCH_RECOGNIZE (
+///     PARTITION BY symbol
+///     ORDER BY ts
+///     MEASURES FIRST(DOWN.price) AS peak, LAST(UP.price) AS recovered
+///     PATTERN (DOWN+ UP+) WITHIN INTERVAL '1' HOUR
+///     DEFINE DOWN AS price < 100, UP AS price >= 100
+/// ) AS m
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchRecognizeClause {
+    /// The table name the clause is replaced by in the query, under which its matches are planned
+    pub name: String,
+    /// The table the pattern is matched against
+    pub input: String,
+    pub partition_by: Vec<String>,
+    /// The event time column of the input
+    pub order_by: String,
+    pub measures: Vec<Measure>,
+    /// The pattern variables, in order of their first appearance in the pattern, along with
+    /// their DEFINE conditions; variables without one match every row
+    pub variables: Vec<(String, Option<String>)>,
+    pub pattern: Pattern,
+    /// How long after its first row a match must end
+    pub within: Option<Duration>,
+}
+
+/// A pattern of rows, in terms of the indices of the clause's variables
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    Variable(usize),
+    Concatenation(Vec<Pattern>),
+    Alternation(Vec<Pattern>),
+    Repetition {
+        pattern: Box<Pattern>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MatchAggregate {
+    First,
+    Last,
+    Count,
+    Min,
+    Max,
+    Sum,
+}
+
+impl MatchAggregate {
+    /// The type of the aggregate of a column of `argument` type, or of rows for `COUNT(*)`
+    pub(crate) fn output_type(&self, argument: Option<&DataType>) -> Option<DataType> {
+        match (self, argument) {
+            (MatchAggregate::Count, _) => Some(DataType::Int64),
+            (MatchAggregate::Sum, Some(argument)) if argument.is_signed_integer() => {
+                Some(DataType::Int64)
+            }
+            (MatchAggregate::Sum, Some(argument)) if argument.is_unsigned_integer() => {
+                Some(DataType::UInt64)
+            }
+            (MatchAggregate::Sum, Some(argument)) if argument.is_floating() => {
+                Some(DataType::Float64)
+            }
+            (MatchAggregate::Sum, _) => None,
+            (_, argument) => argument.cloned(),
+        }
+    }
+}
+
+impl Display for MatchAggregate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            MatchAggregate::First => "FIRST",
+            MatchAggregate::Last => "LAST",
+            MatchAggregate::Count => "COUNT",
+            MatchAggregate::Min => "MIN",
+            MatchAggregate::Max => "MAX",
+            MatchAggregate::Sum => "SUM",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A column of the clause's output, aggregating the rows of each match
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Measure {
+    pub name: String,
+    pub aggregate: MatchAggregate,
+    /// The variable whose rows are aggregated, or all the rows of the match if unset
+    pub variable: Option<usize>,
+    /// The column that is aggregated, unset for `COUNT(*)`
+    pub column: Option<String>,
+}
+
+/// A state of the automaton a pattern is compiled to, where each transition takes a row that
+/// meets the condition of its variable
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PatternState {
+    /// (variable, target state)
+    pub transitions: Vec<(usize, usize)>,
+    pub accepting: bool,
+}
+
+/// Replaces each `<table> MATCH_RECOGNIZE (...)` in the tokens with a placeholder table name,
+/// as the parser doesn't support the clause, returning the remaining tokens along with the
+/// clauses of each statement, in the same order as the statements returned by the parser.
+pub(crate) fn extract_match_recognize_clauses(
+    dialect: &dyn Dialect,
+    tokens: Vec<TokenWithLocation>,
+) -> Result<(Vec<TokenWithLocation>, Vec<Vec<MatchRecognizeClause>>)> {
+    let mut remaining: Vec<TokenWithLocation> = Vec::with_capacity(tokens.len());
+    let mut statements = vec![];
+    let mut current = vec![];
+    let mut has_tokens = false;
+
+    let mut index = 0;
+    while index < tokens.len() {
+        if is_word(&tokens[index].token, "MATCH_RECOGNIZE") {
+            let mut preceding = remaining
+                .iter()
+                .enumerate()
+                .rev()
+                .filter(|(_, token)| !matches!(token.token, Token::Whitespace(_)));
+            let (input_index, word) = match preceding.next() {
+                Some((
+                    input_index,
+                    TokenWithLocation {
+                        token: Token::Word(word),
+                        ..
+                    },
+                )) if !matches!(word.keyword, Keyword::FROM | Keyword::JOIN) => (input_index, word),
+                _ => bail!("MATCH_RECOGNIZE must follow the name of a table"),
+            };
+            if let Some((
+                _,
+                TokenWithLocation {
+                    token: Token::Period,
+                    ..
+                },
+            )) = preceding.next()
+            {
+                bail!("MATCH_RECOGNIZE must follow an unqualified table name");
+            }
+            let input = normalize(&word.to_ident());
+
+            let open = tokens[index + 1..]
+                .iter()
+                .position(|token| !matches!(token.token, Token::Whitespace(_)))
+                .map(|position| index + 1 + position)
+                .filter(|open| tokens[*open].token == Token::LParen)
+                .ok_or_else(|| {
+                    anyhow!("MATCH_RECOGNIZE must be followed by its clauses in parentheses")
+                })?;
+            let close = closing_paren(&tokens, open)?;
+
+            let name = format!(
+                "__match_recognize_{}",
+                statements.iter().map(Vec::len).sum::<usize>() + current.len()
+            );
+            current.push(parse_clause(
+                dialect,
+                name.clone(),
+                input,
+                &tokens[open + 1..close],
+            )?);
+            remaining[input_index].token = Token::make_word(&name, None);
+            index = close + 1;
+            continue;
+        }
+
+        match &tokens[index].token {
+            Token::Whitespace(_) | Token::EOF => {}
+            Token::SemiColon => {
+                // the parser skips empty statements, so we do as well
+                if has_tokens {
+                    statements.push(std::mem::take(&mut current));
+                    has_tokens = false;
+                }
+            }
+            _ => {
+                has_tokens = true;
+            }
+        }
+        remaining.push(tokens[index].clone());
+        index += 1;
+    }
+
+    if has_tokens {
+        statements.push(current);
+    }
+
+    Ok((remaining, statements))
+}
+
+fn is_word(token: &Token, expected: &str) -> bool {
+    matches!(token, Token::Word(word) if word.quote_style.is_none() && word.value.eq_ignore_ascii_case(expected))
+}
+
+/// Unquoted identifiers are case-insensitive, as in the planner
+fn normalize(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
+
+fn closing_paren(tokens: &[TokenWithLocation], open: usize) -> Result<usize> {
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(open) {
+        match token.token {
+            Token::LParen => depth += 1,
+            Token::RParen => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    bail!("MATCH_RECOGNIZE is missing a closing parenthesis")
+}
+
+/// Walks the tokens inside the parentheses of a MATCH_RECOGNIZE
+struct ClauseParser<'a> {
+    dialect: &'a dyn Dialect,
+    tokens: &'a [TokenWithLocation],
+    index: usize,
+}
+
+impl<'a> ClauseParser<'a> {
+    fn skip_whitespace(&mut self) {
+        while matches!(
+            self.tokens.get(self.index).map(|token| &token.token),
+            Some(Token::Whitespace(_))
+        ) {
+            self.index += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<&'a Token> {
+        self.skip_whitespace();
+        self.tokens.get(self.index).map(|token| &token.token)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.peek();
+        self.index += 1;
+        token
+    }
+
+    fn consume(&mut self, expected: &Token) -> bool {
+        if self.peek() == Some(expected) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes the words if the tokens start with them
+    fn parse_words(&mut self, words: &[&str]) -> bool {
+        let start = self.index;
+        for word in words {
+            if !self.peek().is_some_and(|token| is_word(token, word)) {
+                self.index = start;
+                return false;
+            }
+            self.index += 1;
+        }
+        true
+    }
+
+    fn expect_words(&mut self, words: &[&str]) -> Result<()> {
+        if !self.parse_words(words) {
+            bail!(
+                "expected {} in MATCH_RECOGNIZE, found {}",
+                words.join(" "),
+                self.peek()
+                    .map(|token| token.to_string())
+                    .unwrap_or_else(|| "the end of the clause".to_string())
+            );
+        }
+        Ok(())
+    }
+
+    fn ident(&mut self) -> Result<Ident> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word.to_ident()),
+            token => bail!(
+                "expected a name in MATCH_RECOGNIZE, found {}",
+                token
+                    .map(|token| token.to_string())
+                    .unwrap_or_else(|| "the end of the clause".to_string())
+            ),
+        }
+    }
+
+    /// Parses an expression, returning it along with the tokens it was parsed from
+    fn expr(&mut self) -> Result<(SqlExpr, &'a [TokenWithLocation])> {
+        let start = self.index;
+        let mut parser =
+            Parser::new(self.dialect).with_tokens_with_locations(self.tokens[start..].to_vec());
+        let expr = parser.parse_expr()?;
+        self.index += parser.index();
+        Ok((expr, &self.tokens[start..self.index]))
+    }
+
+    fn column(&mut self, clause: &str) -> Result<String> {
+        match self.expr()?.0 {
+            SqlExpr::Identifier(ident) => Ok(normalize(&ident)),
+            expr => bail!(
+                "{} in MATCH_RECOGNIZE must be a column, not {}",
+                clause,
+                expr
+            ),
+        }
+    }
+
+    /// The tokens of the pattern, up to its closing parenthesis
+    fn pattern_tokens(&mut self) -> Result<Vec<Token>> {
+        if !self.consume(&Token::LParen) {
+            bail!("PATTERN must be in parentheses");
+        }
+        let mut tokens = vec![];
+        let mut depth = 1;
+        loop {
+            let token = self
+                .next()
+                .ok_or_else(|| anyhow!("PATTERN is missing a closing parenthesis"))?;
+            match token {
+                Token::LParen => depth += 1,
+                Token::RParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(tokens);
+                    }
+                }
+                _ => {}
+            }
+            tokens.push(token.clone());
+        }
+    }
+}
+
+fn parse_clause(
+    dialect: &dyn Dialect,
+    name: String,
+    input: String,
+    tokens: &[TokenWithLocation],
+) -> Result<MatchRecognizeClause> {
+    let mut parser = ClauseParser {
+        dialect,
+        tokens,
+        index: 0,
+    };
+    let mut partition_by = vec![];
+    let mut order_by = None;
+    let mut measures = vec![];
+    let mut pattern_tokens = None;
+    let mut within = None;
+    let mut defines: Vec<(Ident, &[TokenWithLocation])> = vec![];
+
+    while let Some(token) = parser.peek() {
+        if parser.parse_words(&["PARTITION", "BY"]) {
+            loop {
+                partition_by.push(parser.column("PARTITION BY")?);
+                if !parser.consume(&Token::Comma) {
+                    break;
+                }
+            }
+        } else if parser.parse_words(&["ORDER", "BY"]) {
+            order_by = Some(parser.column("ORDER BY")?);
+            if parser.parse_words(&["DESC"]) || parser.consume(&Token::Comma) {
+                bail!("MATCH_RECOGNIZE can only be ordered by the event time ascending");
+            }
+            parser.parse_words(&["ASC"]);
+        } else if parser.parse_words(&["MEASURES"]) {
+            loop {
+                let (expr, _) = parser.expr()?;
+                parser.expect_words(&["AS"])?;
+                measures.push((expr, normalize(&parser.ident()?)));
+                if !parser.consume(&Token::Comma) {
+                    break;
+                }
+            }
+        } else if parser.parse_words(&["ONE", "ROW", "PER", "MATCH"]) {
+            // the default, and the only output mode we support
+        } else if parser.parse_words(&["ALL", "ROWS", "PER", "MATCH"]) {
+            bail!("only ONE ROW PER MATCH is supported in MATCH_RECOGNIZE");
+        } else if parser.parse_words(&["AFTER", "MATCH", "SKIP"]) {
+            if !parser.parse_words(&["PAST", "LAST", "ROW"]) {
+                bail!("only AFTER MATCH SKIP PAST LAST ROW is supported in MATCH_RECOGNIZE");
+            }
+        } else if parser.parse_words(&["PATTERN"]) {
+            pattern_tokens = Some(parser.pattern_tokens()?);
+        } else if parser.parse_words(&["WITHIN"]) {
+            within = Some(within_duration(&parser.expr()?.0)?);
+        } else if parser.parse_words(&["DEFINE"]) {
+            loop {
+                let variable = parser.ident()?;
+                parser.expect_words(&["AS"])?;
+                defines.push((variable, parser.expr()?.1));
+                if !parser.consume(&Token::Comma) {
+                    break;
+                }
+            }
+        } else {
+            bail!("unexpected {} in MATCH_RECOGNIZE", token);
+        }
+    }
+
+    let mut variables = vec![];
+    let pattern = PatternParser {
+        tokens: pattern_tokens.ok_or_else(|| anyhow!("MATCH_RECOGNIZE requires a PATTERN"))?,
+        index: 0,
+        variables: &mut variables,
+    }
+    .parse()?;
+    let order_by =
+        order_by.ok_or_else(|| anyhow!("MATCH_RECOGNIZE requires ORDER BY the event time"))?;
+    if partition_by.is_empty() {
+        bail!("MATCH_RECOGNIZE requires PARTITION BY the columns to match rows within");
+    }
+
+    let mut conditions = HashMap::new();
+    for (variable, tokens) in defines {
+        let name = normalize(&variable);
+        if !variables.contains(&name) {
+            bail!("DEFINE {} isn't a variable of the PATTERN", variable);
+        }
+        let condition = define_condition(dialect, &name, tokens, &variables)?;
+        if conditions.insert(name, condition).is_some() {
+            bail!("{} is defined more than once", variable);
+        }
+    }
+
+    let measures = measures
+        .into_iter()
+        .map(|(expr, name)| measure(&expr, name, &variables))
+        .collect::<Result<_>>()?;
+
+    Ok(MatchRecognizeClause {
+        name,
+        input,
+        partition_by,
+        order_by,
+        measures,
+        variables: variables
+            .into_iter()
+            .map(|variable| {
+                let condition = conditions.remove(&variable);
+                (variable, condition)
+            })
+            .collect(),
+        pattern,
+        within,
+    })
+}
+
+/// Conditions can only refer to the row they are evaluated for, so the variable being defined
+/// is dropped from qualified columns, as in `DOWN.price < 100`
+fn define_condition(
+    dialect: &dyn Dialect,
+    variable: &str,
+    tokens: &[TokenWithLocation],
+    variables: &[String],
+) -> Result<String> {
+    let mut unqualified = Vec::with_capacity(tokens.len());
+    let mut index = 0;
+    while index < tokens.len() {
+        if let (Token::Word(word), Some(Token::Period)) = (
+            &tokens[index].token,
+            tokens.get(index + 1).map(|token| &token.token),
+        ) {
+            let qualifier = normalize(&word.to_ident());
+            if qualifier == variable {
+                index += 2;
+                continue;
+            }
+            if variables.contains(&qualifier) {
+                bail!(
+                    "DEFINE {} can only refer to the row being matched, not to the rows of {}",
+                    variable,
+                    word
+                );
+            }
+        }
+        unqualified.push(tokens[index].clone());
+        index += 1;
+    }
+    let condition = Parser::new(dialect)
+        .with_tokens_with_locations(unqualified)
+        .parse_expr()?;
+    Ok(condition.to_string())
+}
+
+fn measure(expr: &SqlExpr, name: String, variables: &[String]) -> Result<Measure> {
+    let (aggregate, argument) = match expr {
+        SqlExpr::Function(function) => {
+            let aggregate = match function.name.to_string().to_uppercase().as_str() {
+                "FIRST" => MatchAggregate::First,
+                "LAST" => MatchAggregate::Last,
+                "COUNT" => MatchAggregate::Count,
+                "MIN" => MatchAggregate::Min,
+                "MAX" => MatchAggregate::Max,
+                "SUM" => MatchAggregate::Sum,
+                _ => bail!(
+                    "unsupported function {} in MEASURES; only FIRST, LAST, COUNT, MIN, MAX and SUM are supported",
+                    function.name
+                ),
+            };
+            let [FunctionArg::Unnamed(argument)] = function.args.as_slice() else {
+                bail!("{} in MEASURES takes a single argument", function.name);
+            };
+            (aggregate, argument.clone())
+        }
+        // a column on its own is its value at the last row of the match
+        expr => (MatchAggregate::Last, FunctionArgExpr::Expr(expr.clone())),
+    };
+
+    let variable = |ident: &Ident| {
+        let name = normalize(ident);
+        variables
+            .iter()
+            .position(|variable| *variable == name)
+            .ok_or_else(|| anyhow!("{} in MEASURES isn't a variable of the PATTERN", ident))
+    };
+    let (variable, column) = match &argument {
+        FunctionArgExpr::Wildcard => (None, None),
+        FunctionArgExpr::QualifiedWildcard(name) if name.0.len() == 1 => {
+            (Some(variable(&name.0[0])?), None)
+        }
+        FunctionArgExpr::Expr(SqlExpr::Identifier(column)) => (None, Some(normalize(column))),
+        FunctionArgExpr::Expr(SqlExpr::CompoundIdentifier(parts)) if parts.len() == 2 => {
+            (Some(variable(&parts[0])?), Some(normalize(&parts[1])))
+        }
+        _ => bail!(
+            "MEASURES can only aggregate a column, like FIRST(A.price), not {}",
+            expr
+        ),
+    };
+    if column.is_none() && aggregate != MatchAggregate::Count {
+        bail!(
+            "{} in MEASURES must be of a column, not {}",
+            aggregate,
+            expr
+        );
+    }
+
+    Ok(Measure {
+        name,
+        aggregate,
+        variable,
+        column,
+    })
+}
+
+fn within_duration(expr: &SqlExpr) -> Result<Duration> {
+    let duration = match expr {
+        SqlExpr::Interval(interval) => match (interval.value.as_ref(), &interval.leading_field) {
+            (SqlExpr::Value(Value::SingleQuotedString(value)), Some(field)) => {
+                parse_duration(&format!("{} {}", value, field))
+            }
+            (SqlExpr::Value(Value::SingleQuotedString(value)), None) => parse_duration(value),
+            _ => None,
+        },
+        _ => None,
+    };
+    duration
+        .filter(|duration| !duration.is_zero())
+        .ok_or_else(|| {
+            anyhow!(
+                "WITHIN must be a positive interval like INTERVAL '10' MINUTE, not {}",
+                expr
+            )
+        })
+}
+
+/// Parses the tokens of a PATTERN, in which variables are listed in order, `|` separates
+/// alternatives, and the quantifiers `*`, `+`, `?`, `{n}`, `{n,}`, `{,m}` and `{n,m}` repeat the
+/// variable or parenthesized group they follow
+struct PatternParser<'a> {
+    tokens: Vec<Token>,
+    index: usize,
+    variables: &'a mut Vec<String>,
+}
+
+impl<'a> PatternParser<'a> {
+    fn parse(mut self) -> Result<Pattern> {
+        let pattern = self.alternation()?;
+        match self.tokens.get(self.index) {
+            None => Ok(pattern),
+            Some(token) => bail!("unexpected {} in PATTERN", token),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn alternation(&mut self) -> Result<Pattern> {
+        let mut alternatives = vec![self.concatenation()?];
+        while self.peek() == Some(&Token::Pipe) {
+            self.index += 1;
+            alternatives.push(self.concatenation()?);
+        }
+        Ok(match alternatives.len() {
+            1 => alternatives.pop().unwrap(),
+            _ => Pattern::Alternation(alternatives),
+        })
+    }
+
+    fn concatenation(&mut self) -> Result<Pattern> {
+        let mut sequence = vec![];
+        while !matches!(self.peek(), None | Some(Token::Pipe) | Some(Token::RParen)) {
+            sequence.push(self.quantified()?);
+        }
+        Ok(match sequence.len() {
+            0 => bail!("PATTERN can't have empty alternatives"),
+            1 => sequence.pop().unwrap(),
+            _ => Pattern::Concatenation(sequence),
+        })
+    }
+
+    fn quantified(&mut self) -> Result<Pattern> {
+        let pattern = match self.tokens.get(self.index).cloned() {
+            Some(Token::Word(word)) => {
+                self.index += 1;
+                let name = normalize(&word.to_ident());
+                let index = match self.variables.iter().position(|variable| *variable == name) {
+                    Some(index) => index,
+                    None => {
+                        self.variables.push(name);
+                        self.variables.len() - 1
+                    }
+                };
+                Pattern::Variable(index)
+            }
+            Some(Token::LParen) => {
+                self.index += 1;
+                let pattern = self.alternation()?;
+                if self.peek() != Some(&Token::RParen) {
+                    bail!("PATTERN is missing a closing parenthesis");
+                }
+                self.index += 1;
+                pattern
+            }
+            Some(token) => bail!("unexpected {} in PATTERN", token),
+            None => bail!("PATTERN ended unexpectedly"),
+        };
+
+        let Some((min, max)) = self.quantifier()? else {
+            return Ok(pattern);
+        };
+        if matches!(self.peek(), Some(Token::Placeholder(p)) if p == "?") {
+            bail!("reluctant quantifiers aren't supported in PATTERN");
+        }
+        if max.is_some_and(|max| max < min || max == 0) {
+            bail!(
+                "PATTERN quantifier {{{},{}}} never matches",
+                min,
+                max.unwrap()
+            );
+        }
+        Ok(Pattern::Repetition {
+            pattern: Box::new(pattern),
+            min,
+            max,
+        })
+    }
+
+    fn quantifier(&mut self) -> Result<Option<(usize, Option<usize>)>> {
+        let quantifier = match self.peek() {
+            Some(Token::Mul) => (0, None),
+            Some(Token::Plus) => (1, None),
+            Some(Token::Placeholder(p)) if p == "?" => (0, Some(1)),
+            Some(Token::LBrace) => {
+                self.index += 1;
+                let min = self.bound()?;
+                let max = if self.peek() == Some(&Token::Comma) {
+                    self.index += 1;
+                    self.bound()?
+                } else {
+                    Some(min.ok_or_else(|| anyhow!("PATTERN quantifier {{}} needs a bound"))?)
+                };
+                if self.peek() != Some(&Token::RBrace) {
+                    bail!("PATTERN quantifier is missing a closing brace");
+                }
+                (min.unwrap_or(0), max)
+            }
+            _ => return Ok(None),
+        };
+        self.index += 1;
+        Ok(Some(quantifier))
+    }
+
+    fn bound(&mut self) -> Result<Option<usize>> {
+        match self.peek() {
+            Some(Token::Number(number, _)) => {
+                let bound = number
+                    .parse()
+                    .map_err(|_| anyhow!("invalid PATTERN quantifier bound {}", number))?;
+                self.index += 1;
+                Ok(Some(bound))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// A pattern automaton that may have transitions that don't take a row
+#[derive(Default)]
+struct Automaton {
+    transitions: Vec<Vec<(usize, usize)>>,
+    empty_transitions: Vec<Vec<usize>>,
+}
+
+impl Automaton {
+    fn add_state(&mut self) -> usize {
+        self.transitions.push(vec![]);
+        self.empty_transitions.push(vec![]);
+        self.transitions.len() - 1
+    }
+
+    /// Adds the states that match `pattern` from `from`, returning the state they end in
+    fn add(&mut self, pattern: &Pattern, from: usize) -> usize {
+        match pattern {
+            Pattern::Variable(variable) => {
+                let to = self.add_state();
+                self.transitions[from].push((*variable, to));
+                to
+            }
+            Pattern::Concatenation(sequence) => sequence
+                .iter()
+                .fold(from, |from, pattern| self.add(pattern, from)),
+            Pattern::Alternation(alternatives) => {
+                let to = self.add_state();
+                for pattern in alternatives {
+                    let end = self.add(pattern, from);
+                    self.empty_transitions[end].push(to);
+                }
+                to
+            }
+            Pattern::Repetition { pattern, min, max } => {
+                let mut current = from;
+                for _ in 0..*min {
+                    current = self.add(pattern, current);
+                }
+                let to = self.add_state();
+                self.empty_transitions[current].push(to);
+                match max {
+                    None => {
+                        let end = self.add(pattern, to);
+                        self.empty_transitions[end].push(to);
+                    }
+                    Some(max) => {
+                        for _ in *min..*max {
+                            current = self.add(pattern, current);
+                            self.empty_transitions[current].push(to);
+                        }
+                    }
+                }
+                to
+            }
+        }
+    }
+
+    /// The states reachable from `state` without taking a row
+    fn closure(&self, state: usize) -> BTreeSet<usize> {
+        let mut closure = BTreeSet::from([state]);
+        let mut stack = vec![state];
+        while let Some(state) = stack.pop() {
+            for next in &self.empty_transitions[state] {
+                if closure.insert(*next) {
+                    stack.push(*next);
+                }
+            }
+        }
+        closure
+    }
+}
+
+/// Compiles the pattern to an automaton in which every transition takes a row, starting from
+/// the first state
+pub(crate) fn compile_pattern(pattern: &Pattern) -> Result<Vec<PatternState>> {
+    let mut automaton = Automaton::default();
+    let start = automaton.add_state();
+    let end = automaton.add(pattern, start);
+
+    let mut states = vec![];
+    let mut numbering = HashMap::from([(start, 0)]);
+    let mut queue = vec![start];
+    while let Some(&state) = queue.get(states.len()) {
+        let closure = automaton.closure(state);
+        let mut transitions = vec![];
+        for (variable, target) in closure
+            .iter()
+            .flat_map(|state| automaton.transitions[*state].iter())
+        {
+            let target = *numbering.entry(*target).or_insert_with(|| {
+                queue.push(*target);
+                queue.len() - 1
+            });
+            if !transitions.contains(&(*variable, target)) {
+                transitions.push((*variable, target));
+            }
+        }
+        states.push(PatternState {
+            transitions,
+            accepting: closure.contains(&end),
+        });
+    }
+
+    if states[0].accepting {
+        bail!("PATTERN must not match an empty sequence of rows");
+    }
+    Ok(states)
+}
+
+impl MatchRecognizeClause {
+    /// Plans the matches of the clause as a table under its placeholder name. Conditions are
+    /// computed as columns of the input before it is keyed by the partition columns, so they
+    /// are type checked like any other expression.
+    pub(crate) fn plan(
+        &self,
+        schema_provider: &ArroyoSchemaProvider,
+        updating_ttl: Option<Duration>,
+    ) -> Result<Table> {
+        let mut projection: Vec<_> = self
+            .partition_by
+            .iter()
+            .enumerate()
+            .map(|(index, column)| format!("{} AS _key_{}", Ident::with_quote('"', column), index))
+            .collect();
+        projection.push("*".to_string());
+        for (index, (_, condition)) in self.variables.iter().enumerate() {
+            if let Some(condition) = condition {
+                projection.push(format!("({}) AS __define_{}", condition, index));
+            }
+        }
+        let query = format!(
+            "SELECT {} FROM {}",
+            projection.join(", "),
+            Ident::with_quote('"', &self.input)
+        );
+        let statement: Statement = Parser::new(&PostgreSqlDialect {})
+            .try_with_sql(&query)?
+            .parse_statement()?;
+        let plan = produce_optimized_plan(&statement, schema_provider)?;
+        let input = rewrite_plan(plan, schema_provider, None, updating_ttl, &[])?;
+
+        if input
+            .schema()
+            .has_column_with_unqualified_name(IS_RETRACT_FIELD)
+        {
+            bail!(
+                "MATCH_RECOGNIZE requires an append-only input, but {} is updating",
+                self.input
+            );
+        }
+        if !is_event_time(&input, &Column::new_unqualified(&self.order_by)) {
+            bail!(
+                "MATCH_RECOGNIZE must be ordered by the event time of {}, not {}",
+                self.input,
+                self.order_by
+            );
+        }
+
+        let mut variables = vec![];
+        for (index, (name, condition)) in self.variables.iter().enumerate() {
+            let condition = match condition {
+                Some(_) => {
+                    let column = input
+                        .schema()
+                        .index_of_column(&Column::new_unqualified(format!("__define_{}", index)))?;
+                    let data_type = input.schema().field(column).data_type();
+                    if *data_type != DataType::Boolean {
+                        bail!(
+                            "DEFINE {} must be a boolean condition, not {}",
+                            name,
+                            data_type
+                        );
+                    }
+                    Some(column)
+                }
+                None => None,
+            };
+            variables.push(PatternVariable {
+                name: name.clone(),
+                condition,
+            });
+        }
+
+        let key_plan = LogicalPlan::Extension(Extension {
+            node: Arc::new(KeyCalculationExtension::new(
+                input,
+                (0..self.partition_by.len()).collect(),
+            )),
+        });
+        let extension = MatchRecognizeExtension::new(
+            key_plan,
+            &self.partition_by,
+            variables,
+            compile_pattern(&self.pattern)?,
+            self.measures.clone(),
+            self.within,
+            updating_ttl.unwrap_or(DEFAULT_MATCH_RECOGNIZE_TTL),
+        )?;
+        Ok(Table::TableFromQuery {
+            name: self.name.clone(),
+            logical_plan: LogicalPlan::Extension(Extension {
+                node: Arc::new(extension),
+            }),
+        })
+    }
+}
@@ -4,6 +4,7 @@ use crate::extension::updating_aggregate::UpdatingAggregateExtension;
 use crate::plan::WindowDetectingVisitor;
 use crate::{find_window, WindowBehavior};
 use arroyo_rpc::{IS_RETRACT_FIELD, TIMESTAMP_FIELD};
+use arroyo_types::DEFAULT_UPDATING_AGGREGATE_TTL;
 use datafusion::common::tree_node::{Transformed, TreeNode, TreeNodeRewriter};
 use datafusion::common::{
     not_impl_err, plan_err, DFField, DFSchema, DataFusionError, Result as DFResult,
@@ -12,13 +13,20 @@ use datafusion::logical_expr;
 use datafusion::logical_expr::expr::AggregateFunction;
 use datafusion::logical_expr::{aggregate_function, Aggregate, Expr, Extension, LogicalPlan};
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::info;
 
 #[derive(Debug, Default)]
-pub struct AggregateRewriter {}
+pub struct AggregateRewriter {
+    // from the statement's allowed_lateness hint
+    pub allowed_lateness: Option<Duration>,
+    // from SET updating_ttl or the statement's updating_ttl hint
+    pub updating_ttl: Option<Duration>,
+}
 
 impl AggregateRewriter {
     pub fn rewrite_non_windowed_aggregate(
+        &self,
         input: Arc<LogicalPlan>,
         mut key_fields: Vec<DFField>,
         group_expr: Vec<Expr>,
@@ -114,6 +122,7 @@ impl AggregateRewriter {
             LogicalPlan::Aggregate(aggregate),
             (0..key_count).collect(),
             column.relation,
+            self.updating_ttl.unwrap_or(DEFAULT_UPDATING_AGGREGATE_TTL),
         );
         let final_plan = LogicalPlan::Extension(Extension {
             node: Arc::new(updating_aggregate_extension),
@@ -194,6 +203,14 @@ impl TreeNodeRewriter for AggregateRewriter {
                                 "can't reinvoke session window in nested aggregates. Need to pass the window struct up from the source query."
                             );
                         }
+                        if matches!(
+                            input_window,
+                            arroyo_datastream::WindowType::Cumulating { .. }
+                        ) {
+                            return plan_err!(
+                                "can't reinvoke cumulate window in nested aggregates. Need to pass the window struct up from the source query."
+                            );
+                        }
                         group_expr.remove(window_index);
                         key_fields.remove(window_index);
                         let window_field = schema.field(window_index).clone();
@@ -221,7 +238,7 @@ impl TreeNodeRewriter for AggregateRewriter {
                 }
             }
             (false, false) => {
-                return Self::rewrite_non_windowed_aggregate(
+                return self.rewrite_non_windowed_aggregate(
                     input, key_fields, group_expr, aggr_expr, schema,
                 );
             }
@@ -283,7 +300,8 @@ impl TreeNodeRewriter for AggregateRewriter {
             window_behavior,
             LogicalPlan::Aggregate(rewritten_aggregate),
             (0..key_count).collect(),
-        );
+            self.allowed_lateness,
+        )?;
         let final_plan = LogicalPlan::Extension(Extension {
             node: Arc::new(aggregate_extension),
         });
@@ -0,0 +1,217 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use arrow_schema::DataType;
+use arroyo_rpc::IS_RETRACT_FIELD;
+use arroyo_types::DEFAULT_DEDUPLICATION_TTL;
+use datafusion::common::tree_node::{Transformed, TreeNodeRewriter};
+use datafusion::common::{plan_err, Column, DFSchema, Result as DFResult, ScalarValue};
+use datafusion::logical_expr::expr::{self, WindowFunction};
+use datafusion::logical_expr::utils::split_conjunction;
+use datafusion::logical_expr::{
+    BinaryExpr, BuiltInWindowFunction, Expr, Extension, Filter, LogicalPlan, Operator, Projection,
+    Window, WindowFunctionDefinition,
+};
+
+use crate::extension::{
+    deduplicate::DeduplicateExtension, key_calculation::KeyCalculationExtension,
+};
+use crate::plan::is_event_time;
+
+/// Plans `ROW_NUMBER() OVER (PARTITION BY ... ORDER BY <event time>)` followed by a filter on
+/// `row_num = 1` over an unwindowed input as a deduplication, which passes on the earliest row
+/// of each key by event time and drops the rest until the key expires.
+pub(crate) struct DeduplicationRewriter {
+    // from SET updating_ttl or the statement's updating_ttl hint
+    pub updating_ttl: Option<Duration>,
+}
+
+impl DeduplicationRewriter {
+    /// If the filter only keeps the first row of each partition of a lone `ROW_NUMBER()` window
+    /// function, returns the window that computes it
+    pub(crate) fn deduplicated_window(filter: &Filter) -> Option<Window> {
+        split_conjunction(&filter.predicate)
+            .into_iter()
+            .find_map(|conjunct| {
+                let Expr::BinaryExpr(BinaryExpr { left, op, right }) = conjunct else {
+                    return None;
+                };
+                let (column, op, value) = match (Self::column(left), right.as_ref()) {
+                    (Some(column), Expr::Literal(value)) => (column, *op, value),
+                    _ => match (left.as_ref(), Self::column(right)) {
+                        (Expr::Literal(value), Some(column)) => (column, op.swap()?, value),
+                        _ => return None,
+                    },
+                };
+                let first_only = matches!(op, Operator::Eq | Operator::LtEq)
+                    && matches!(
+                        value.cast_to(&DataType::UInt64),
+                        Ok(ScalarValue::UInt64(Some(1)))
+                    );
+                if !first_only {
+                    return None;
+                }
+                let window = Self::find_window(&filter.input, column)?;
+                let [window_expr] = window.window_expr.as_slice() else {
+                    return None;
+                };
+                Self::row_number(window_expr).map(|_| window.clone())
+            })
+    }
+
+    fn column(expr: &Expr) -> Option<&Column> {
+        match expr {
+            Expr::Column(column) => Some(column),
+            // comparisons against other integer types cast the row number
+            Expr::Cast(expr::Cast { expr, .. }) | Expr::TryCast(expr::TryCast { expr, .. }) => {
+                Self::column(expr)
+            }
+            _ => None,
+        }
+    }
+
+    /// Follows `column` down to the window function that computes it
+    fn find_window<'a>(plan: &'a LogicalPlan, column: &Column) -> Option<&'a Window> {
+        let index = plan.schema().index_of_column(column).ok()?;
+        match plan {
+            LogicalPlan::Window(window) => {
+                (index >= window.input.schema().fields().len()).then_some(window)
+            }
+            LogicalPlan::Projection(projection) => match projection.expr[index].clone().unalias() {
+                Expr::Column(input_column) => Self::find_window(&projection.input, &input_column),
+                _ => None,
+            },
+            LogicalPlan::SubqueryAlias(alias) => {
+                let input_column = alias.input.schema().field(index).qualified_column();
+                Self::find_window(&alias.input, &input_column)
+            }
+            LogicalPlan::Filter(filter) => Self::find_window(&filter.input, column),
+            _ => None,
+        }
+    }
+
+    fn row_number(expr: &Expr) -> Option<&WindowFunction> {
+        match expr {
+            Expr::Alias(alias) => Self::row_number(&alias.expr),
+            Expr::WindowFunction(
+                window_function @ WindowFunction {
+                    fun:
+                        WindowFunctionDefinition::BuiltInWindowFunction(
+                            BuiltInWindowFunction::RowNumber,
+                        ),
+                    ..
+                },
+            ) => Some(window_function),
+            _ => None,
+        }
+    }
+}
+
+impl TreeNodeRewriter for DeduplicationRewriter {
+    type Node = LogicalPlan;
+
+    fn f_up(&mut self, node: Self::Node) -> DFResult<Transformed<Self::Node>> {
+        let LogicalPlan::Window(Window {
+            input, window_expr, ..
+        }) = node
+        else {
+            return Ok(Transformed::no(node));
+        };
+        let Some(row_number) = window_expr.first().and_then(Self::row_number) else {
+            return plan_err!("deduplication requires a ROW_NUMBER() window function");
+        };
+        if input
+            .schema()
+            .has_column_with_unqualified_name(IS_RETRACT_FIELD)
+        {
+            return plan_err!("can't deduplicate an updating input");
+        }
+        let partition_by = row_number.partition_by.clone();
+        if partition_by.is_empty() {
+            return plan_err!(
+                "deduplication requires PARTITION BY the columns that identify duplicate rows"
+            );
+        }
+        let ordered_by_event_time = match row_number.order_by.as_slice() {
+            [] => true,
+            [Expr::Sort(expr::Sort {
+                expr, asc: true, ..
+            })] => match expr.as_ref() {
+                Expr::Column(column) => is_event_time(&input, column),
+                _ => false,
+            },
+            _ => false,
+        };
+        if !ordered_by_event_time {
+            return plan_err!(
+                "deduplication keeps the first row of each key, so ROW_NUMBER() must be ordered by the event time ascending, not {}",
+                row_number
+                    .order_by
+                    .iter()
+                    .map(|expr| expr.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        let key_count = partition_by.len();
+        let mut key_projection_expressions: Vec<_> = partition_by
+            .iter()
+            .enumerate()
+            .map(|(index, expression)| expression.clone().alias(format!("_key_{}", index)))
+            .collect();
+        key_projection_expressions.extend(
+            input
+                .schema()
+                .fields()
+                .iter()
+                .map(|field| Expr::Column(field.qualified_column())),
+        );
+
+        // as for window functions, the types of the keys are needed to construct the schema
+        let auto_schema =
+            Projection::try_new(key_projection_expressions.clone(), input.clone())?.schema;
+        let mut key_fields = auto_schema
+            .fields()
+            .iter()
+            .take(key_count)
+            .cloned()
+            .collect::<Vec<_>>();
+        key_fields.extend(input.schema().fields().iter().cloned());
+        let key_schema = Arc::new(DFSchema::new_with_metadata(key_fields, HashMap::new())?);
+        let key_projection = LogicalPlan::Projection(Projection::try_new_with_schema(
+            key_projection_expressions,
+            input.clone(),
+            key_schema,
+        )?);
+        let key_plan = LogicalPlan::Extension(Extension {
+            node: Arc::new(KeyCalculationExtension::new(
+                key_projection,
+                (0..key_count).collect(),
+            )),
+        });
+
+        let deduplicate = LogicalPlan::Extension(Extension {
+            node: Arc::new(DeduplicateExtension::new(
+                key_plan,
+                (0..key_count).collect(),
+                self.updating_ttl.unwrap_or(DEFAULT_DEDUPLICATION_TTL),
+            )?),
+        });
+
+        // every row that makes it through is the first of its key, so the row number is always 1
+        let schema = Window::try_new(window_expr, input.clone())?.schema;
+        let row_number_field = schema.field(schema.fields().len() - 1);
+        let mut projection_expressions: Vec<_> = input
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| Expr::Column(field.qualified_column()))
+            .collect();
+        projection_expressions
+            .push(Expr::Literal(ScalarValue::UInt64(Some(1))).alias(row_number_field.name()));
+
+        Ok(Transformed::yes(LogicalPlan::Projection(
+            Projection::try_new_with_schema(projection_expressions, Arc::new(deduplicate), schema)?,
+        )))
+    }
+}
@@ -1,8 +1,13 @@
-use crate::extension::join::JoinExtension;
+use crate::extension::join::{IntervalJoinTtls, JoinExtension};
 use crate::extension::key_calculation::KeyCalculationExtension;
-use crate::plan::WindowDetectingVisitor;
+use crate::extension::watermark_node::WatermarkNode;
+use crate::plan::{is_event_time, WindowDetectingVisitor};
+use crate::system_time::SystemTimeClause;
+use crate::tables::Table;
+use crate::ArroyoSchemaProvider;
 use arroyo_datastream::WindowType;
-use arroyo_rpc::IS_RETRACT_FIELD;
+use arroyo_rpc::{IS_RETRACT_FIELD, TIMESTAMP_FIELD};
+use arroyo_types::{DEFAULT_JOIN_TTL, DEFAULT_TEMPORAL_JOIN_TTL};
 use datafusion::common::tree_node::{Transformed, TreeNodeRewriter};
 use datafusion::common::{
     plan_err, Column, DFSchema, DataFusionError, JoinConstraint, JoinType, OwnedTableReference,
@@ -10,14 +15,24 @@ use datafusion::common::{
 };
 use datafusion::logical_expr;
 use datafusion::logical_expr::expr::{Alias, ScalarFunction};
+use datafusion::logical_expr::utils::split_conjunction;
 use datafusion::logical_expr::{
-    BinaryExpr, BuiltinScalarFunction, Case, Expr, Extension, Join, LogicalPlan, Projection,
+    Between, BinaryExpr, BuiltinScalarFunction, Case, Expr, Extension, Join, LogicalPlan, Operator,
+    Projection,
 };
+use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Duration;
 
-pub(crate) struct JoinRewriter {}
+pub(crate) struct JoinRewriter<'a> {
+    pub schema_provider: &'a ArroyoSchemaProvider,
+    // from SET updating_ttl or the statement's updating_ttl hint
+    pub updating_ttl: Option<Duration>,
+    // the statement's FOR SYSTEM_TIME AS OF clauses, which make joins temporal joins
+    pub system_time_clauses: &'a [SystemTimeClause],
+}
 
-impl JoinRewriter {
+impl<'a> JoinRewriter<'a> {
     fn check_join_windowing(join: &Join) -> DFResult<bool> {
         let left_window = WindowDetectingVisitor::get_window(&join.left)?;
         let right_window = WindowDetectingVisitor::get_window(&join.right)?;
@@ -73,6 +88,328 @@ impl JoinRewriter {
         Ok(())
     }
 
+    /// Finds bounds of the form `lower <= left time - right time <= upper` in the join filter,
+    /// for example from `a.ts BETWEEN b.ts AND b.ts + INTERVAL '30' MINUTE`. When both are
+    /// present and the times are the rows' event times, neither side needs to keep rows longer
+    /// than the bounds allow behind the watermark.
+    fn interval_ttls(
+        left: &LogicalPlan,
+        right: &LogicalPlan,
+        filter: &Expr,
+    ) -> DFResult<Option<IntervalJoinTtls>> {
+        let mut lower: Option<i128> = None;
+        let mut upper: Option<i128> = None;
+        for expr in split_conjunction(filter) {
+            let comparisons = match expr {
+                Expr::Between(Between {
+                    expr,
+                    negated: false,
+                    low,
+                    high,
+                }) => vec![
+                    (expr.as_ref(), Operator::GtEq, low.as_ref()),
+                    (expr.as_ref(), Operator::LtEq, high.as_ref()),
+                ],
+                Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+                    vec![(left.as_ref(), *op, right.as_ref())]
+                }
+                _ => continue,
+            };
+            for (a, op, b) in comparisons {
+                let (Some((a_column, a_offset)), Some((b_column, b_offset))) =
+                    (Self::time_term(a), Self::time_term(b))
+                else {
+                    continue;
+                };
+                // a + a_offset op b + b_offset, so a - b op b_offset - a_offset
+                let (left_column, right_column, op, bound) = if Self::is_column_of(left, a_column)
+                    && Self::is_column_of(right, b_column)
+                {
+                    (a_column, b_column, op, b_offset - a_offset)
+                } else if Self::is_column_of(right, a_column) && Self::is_column_of(left, b_column)
+                {
+                    let Some(op) = op.swap() else {
+                        continue;
+                    };
+                    (b_column, a_column, op, a_offset - b_offset)
+                } else {
+                    continue;
+                };
+                if !is_event_time(left, left_column) || !is_event_time(right, right_column) {
+                    continue;
+                }
+                match op {
+                    Operator::Gt | Operator::GtEq => {
+                        lower = Some(lower.map_or(bound, |lower| lower.max(bound)))
+                    }
+                    Operator::Lt | Operator::LtEq => {
+                        upper = Some(upper.map_or(bound, |upper| upper.min(bound)))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        let (Some(lower), Some(upper)) = (lower, upper) else {
+            return Ok(None);
+        };
+        if lower > upper {
+            return plan_err!(
+                "interval join bounds are empty: the lower bound {:?} is after the upper bound {:?}",
+                Self::signed_duration(lower),
+                Self::signed_duration(upper)
+            );
+        }
+        Ok(Some(IntervalJoinTtls {
+            left: Duration::from_nanos((-lower).max(0) as u64),
+            right: Duration::from_nanos(upper.max(0) as u64),
+        }))
+    }
+
+    fn signed_duration(nanos: i128) -> String {
+        let duration = Duration::from_nanos(nanos.unsigned_abs() as u64);
+        if nanos < 0 {
+            format!("-{:?}", duration)
+        } else {
+            format!("{:?}", duration)
+        }
+    }
+
+    /// Splits `column`, `column + interval` or `column - interval` into the column and the
+    /// offset in nanoseconds
+    fn time_term(expr: &Expr) -> Option<(&Column, i128)> {
+        match expr {
+            Expr::Column(column) => Some((column, 0)),
+            Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+                let (column, interval, sign) = match (left.as_ref(), op, right.as_ref()) {
+                    (Expr::Column(column), Operator::Plus, interval)
+                    | (interval, Operator::Plus, Expr::Column(column)) => (column, interval, 1),
+                    (Expr::Column(column), Operator::Minus, interval) => (column, interval, -1),
+                    _ => return None,
+                };
+                let offset = crate::get_duration(interval).ok()?.as_nanos() as i128;
+                Some((column, sign * offset))
+            }
+            _ => None,
+        }
+    }
+
+    fn is_column_of(plan: &LogicalPlan, column: &Column) -> bool {
+        plan.schema().index_of_column(column).is_ok()
+    }
+
+    /// The `FOR SYSTEM_TIME AS OF` clause of the versioned table this join reads, if any
+    fn system_time_clause(&self, join: &Join) -> DFResult<Option<&'a SystemTimeClause>> {
+        for clause in self.system_time_clauses {
+            if Self::is_relation(&join.right, &clause.relation) {
+                return Ok(Some(clause));
+            }
+            if Self::is_relation(&join.left, &clause.relation) {
+                return plan_err!(
+                    "the versioned table {} of a temporal join must be on the right side of the join",
+                    clause.relation
+                );
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether every column of the plan comes from `relation`, i.e. it reads that table directly
+    fn is_relation(plan: &LogicalPlan, relation: &str) -> bool {
+        plan.schema().fields().iter().all(|field| {
+            field
+                .qualifier()
+                .is_some_and(|qualifier| qualifier.table() == relation)
+        })
+    }
+
+    /// The source table that a side of a join reads directly, through single-input nodes
+    fn source_table(plan: &LogicalPlan) -> Option<&OwnedTableReference> {
+        if let LogicalPlan::Extension(Extension { node }) = plan {
+            if let Some(watermark) = node.as_any().downcast_ref::<WatermarkNode>() {
+                return Some(&watermark.qualifier);
+            }
+        }
+        match plan.inputs().as_slice() {
+            [input] => Self::source_table(*input),
+            _ => None,
+        }
+    }
+
+    /// Checks that the join keys of the versioned side of a temporal join are exactly its
+    /// primary key, as versions are kept by join key
+    fn check_versioned_key(
+        &self,
+        right: &LogicalPlan,
+        right_expressions: &[Expr],
+        clause: &SystemTimeClause,
+    ) -> DFResult<()> {
+        let primary_keys = Self::source_table(right)
+            .and_then(
+                |table| match self.schema_provider.get_table(table.table()) {
+                    Some(Table::ConnectorTable(table)) => Some(&table.primary_keys),
+                    _ => None,
+                },
+            )
+            .filter(|keys| !keys.is_empty());
+        let Some(primary_keys) = primary_keys else {
+            return plan_err!(
+                "the versioned table {} of a temporal join must declare a PRIMARY KEY",
+                clause.relation
+            );
+        };
+
+        let join_keys: HashSet<_> = right_expressions
+            .iter()
+            .map(|expr| match expr {
+                Expr::Column(column) => Some(column.name.as_str()),
+                _ => None,
+            })
+            .collect();
+        let primary_key_set: HashSet<_> = primary_keys.iter().map(|k| Some(k.as_str())).collect();
+        if join_keys != primary_key_set {
+            return plan_err!(
+                "temporal joins must have equality conditions on exactly the primary key of {} ({})",
+                clause.relation,
+                primary_keys.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Plans a join of each left row with the version of the matching right row that was
+    /// current at the left row's event time. Versions are kept by join key, which must be the
+    /// versioned table's primary key.
+    fn temporal_join(
+        &self,
+        join: Join,
+        clause: &SystemTimeClause,
+    ) -> DFResult<Transformed<LogicalPlan>> {
+        let Join {
+            left,
+            right,
+            on,
+            filter,
+            join_type,
+            join_constraint: JoinConstraint::On,
+            schema,
+            null_equals_null: false,
+        } = join
+        else {
+            return Err(DataFusionError::NotImplemented(
+                "can't handle join constraint other than ON".into(),
+            ));
+        };
+        if !matches!(join_type, JoinType::Inner | JoinType::Left) {
+            return Err(DataFusionError::NotImplemented(
+                "temporal joins must be inner or left joins".into(),
+            ));
+        }
+        if WindowDetectingVisitor::get_window(&left)?.is_some()
+            || WindowDetectingVisitor::get_window(&right)?.is_some()
+        {
+            return Err(DataFusionError::NotImplemented(
+                "can't handle windowed inputs to temporal joins".into(),
+            ));
+        }
+        if left
+            .schema()
+            .has_column_with_unqualified_name(IS_RETRACT_FIELD)
+        {
+            return plan_err!("can't handle updating left side of temporal join");
+        }
+        if on.is_empty() {
+            return plan_err!(
+                "temporal joins must have an equality condition on the key of {}",
+                clause.relation
+            );
+        }
+        if !Self::is_column_of(&left, &clause.time_column) {
+            return plan_err!(
+                "FOR SYSTEM_TIME AS OF {} must reference a column of the left side of the join",
+                clause.time_column
+            );
+        }
+        if !is_event_time(&left, &clause.time_column) {
+            return plan_err!(
+                "temporal joins look up versions at the left side's event time, so FOR SYSTEM_TIME AS OF must reference it, not {}",
+                clause.time_column
+            );
+        }
+
+        let left_field_count = left.schema().fields().len();
+        let (left_expressions, right_expressions): (Vec<_>, Vec<_>) =
+            on.clone().into_iter().unzip();
+        self.check_versioned_key(&right, &right_expressions, clause)?;
+        let left_input = self.create_join_key_plan(left.clone(), left_expressions, "left")?;
+        let right_input = self.create_join_key_plan(right.clone(), right_expressions, "right")?;
+        let rewritten_join = LogicalPlan::Join(Join {
+            left: Arc::new(left_input),
+            right: Arc::new(right_input),
+            on,
+            join_type,
+            join_constraint: JoinConstraint::On,
+            schema: schema.clone(),
+            null_equals_null: false,
+            filter,
+        });
+
+        let final_logical_plan =
+            Self::post_temporal_join_projection(rewritten_join, left_field_count)?;
+
+        let join_extension = JoinExtension {
+            rewritten_join: final_logical_plan,
+            is_instant: false,
+            is_temporal: true,
+            // how long the version of a key that isn't updated is kept
+            ttl: Some(self.updating_ttl.unwrap_or(DEFAULT_TEMPORAL_JOIN_TTL)),
+            interval: None,
+        };
+
+        Ok(Transformed::yes(LogicalPlan::Extension(Extension {
+            node: Arc::new(join_extension),
+        })))
+    }
+
+    /// Each row of a temporal join is emitted at the time of its left row, so the right side's
+    /// `_timestamp` is dropped, along with its retraction flag as the output only has appends
+    fn post_temporal_join_projection(
+        input: LogicalPlan,
+        left_field_count: usize,
+    ) -> DFResult<LogicalPlan> {
+        let schema = input.schema().clone();
+        let mut fields = vec![];
+        let mut timestamp_field = None;
+        for (index, field) in schema.fields().iter().enumerate() {
+            let is_left = index < left_field_count;
+            if field.name() == TIMESTAMP_FIELD {
+                if is_left {
+                    timestamp_field = Some(field.clone());
+                }
+            } else if is_left || field.name() != IS_RETRACT_FIELD {
+                fields.push(field.clone());
+            }
+        }
+        let Some(timestamp_field) = timestamp_field else {
+            return plan_err!("left side of temporal join must have a timestamp field");
+        };
+        // add the _timestamp field last, as for other joins
+        fields.push(timestamp_field);
+
+        let projection_expr = fields
+            .iter()
+            .map(|field| Expr::Column(field.qualified_column()))
+            .collect();
+        let output_schema = Arc::new(DFSchema::new_with_metadata(
+            fields,
+            schema.metadata().clone(),
+        )?);
+        Ok(LogicalPlan::Projection(Projection::try_new_with_schema(
+            projection_expr,
+            Arc::new(input),
+            output_schema,
+        )?))
+    }
+
     fn create_join_key_plan(
         &self,
         input: Arc<LogicalPlan>,
@@ -185,13 +522,16 @@ impl JoinRewriter {
     }
 }
 
-impl TreeNodeRewriter for JoinRewriter {
+impl<'a> TreeNodeRewriter for JoinRewriter<'a> {
     type Node = LogicalPlan;
 
     fn f_up(&mut self, node: Self::Node) -> DFResult<Transformed<Self::Node>> {
         let LogicalPlan::Join(join) = node else {
             return Ok(Transformed::no(node));
         };
+        if let Some(clause) = self.system_time_clause(&join)? {
+            return self.temporal_join(join, clause);
+        }
         let is_instant = Self::check_join_windowing(&join)?;
 
         let Join {
@@ -211,6 +551,12 @@ impl TreeNodeRewriter for JoinRewriter {
         };
         Self::check_updating(&left, &right)?;
 
+        // instant joins already drop their state when the window closes
+        let interval = match &filter {
+            Some(filter) if !is_instant => Self::interval_ttls(&left, &right, filter)?,
+            _ => None,
+        };
+
         let (left_expressions, right_expressions): (Vec<_>, Vec<_>) =
             on.clone().into_iter().unzip();
         let left_input = self.create_join_key_plan(left.clone(), left_expressions, "left")?;
@@ -231,6 +577,11 @@ impl TreeNodeRewriter for JoinRewriter {
         let join_extension = JoinExtension {
             rewritten_join: final_logical_plan,
             is_instant,
+            is_temporal: false,
+            // instant joins only keep state until their window closes
+            ttl: (!is_instant && interval.is_none())
+                .then(|| self.updating_ttl.unwrap_or(DEFAULT_JOIN_TTL)),
+            interval,
         };
 
         Ok(Transformed::yes(LogicalPlan::Extension(Extension {
@@ -1,4 +1,4 @@
-use std::{collections::HashSet, sync::Arc};
+use std::{collections::HashSet, sync::Arc, time::Duration};
 
 use arroyo_datastream::WindowType;
 use arroyo_rpc::{IS_RETRACT_FIELD, TIMESTAMP_FIELD};
@@ -10,7 +10,8 @@ use datafusion::common::{
 };
 
 use aggregate::AggregateRewriter;
-use datafusion::logical_expr::{expr::Alias, Aggregate, Expr, Extension, LogicalPlan};
+use datafusion::logical_expr::{expr::Alias, Aggregate, Expr, Extension, LogicalPlan, Window};
+use deduplicate::DeduplicationRewriter;
 use join::JoinRewriter;
 
 use crate::{
@@ -21,6 +22,7 @@ use crate::{
     find_window,
     rewriters::SourceRewriter,
     schemas::{add_timestamp_field, has_timestamp_field},
+    system_time::SystemTimeClause,
     ArroyoSchemaProvider, WindowBehavior,
 };
 use crate::{
@@ -31,6 +33,7 @@ use crate::{
 use self::window_fn::WindowFunctionRewriter;
 
 mod aggregate;
+mod deduplicate;
 mod join;
 mod window_fn;
 
@@ -59,6 +62,48 @@ fn extract_column(expr: &Expr) -> Option<&Column> {
     }
 }
 
+/// Whether `column` of `plan` always holds the row's event time, which is what the watermark
+/// bounds. This follows the column back to the source projection, where the event time
+/// field is also aliased to `_timestamp`.
+pub(crate) fn is_event_time(plan: &LogicalPlan, column: &Column) -> bool {
+    if column.name == TIMESTAMP_FIELD {
+        return true;
+    }
+    let Ok(index) = plan.schema().index_of_column(column) else {
+        return false;
+    };
+    match plan {
+        LogicalPlan::Projection(projection) => {
+            let expr = projection.expr[index].clone().unalias();
+            let is_timestamp = projection
+                .schema
+                .fields()
+                .iter()
+                .zip(projection.expr.iter())
+                .any(|(field, timestamp_expr)| {
+                    field.name() == TIMESTAMP_FIELD && timestamp_expr.clone().unalias() == expr
+                });
+            if is_timestamp {
+                return true;
+            }
+            match &expr {
+                Expr::Column(input_column) => is_event_time(&projection.input, input_column),
+                _ => false,
+            }
+        }
+        LogicalPlan::SubqueryAlias(alias) => {
+            let input_column = alias.input.schema().field(index).qualified_column();
+            is_event_time(&alias.input, &input_column)
+        }
+        LogicalPlan::Filter(filter) => is_event_time(&filter.input, column),
+        LogicalPlan::Extension(extension) => match extension.node.inputs().as_slice() {
+            [input] => is_event_time(input, column),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
 impl TreeNodeVisitor for WindowDetectingVisitor {
     type Node = LogicalPlan;
 
@@ -250,11 +295,42 @@ impl TreeNodeVisitor for WindowDetectingVisitor {
 // ensuring they have _timestamp field, amongst other things.
 pub struct ArroyoRewriter<'a> {
     pub(crate) schema_provider: &'a ArroyoSchemaProvider,
+    pub(crate) allowed_lateness: Option<Duration>,
+    pub(crate) updating_ttl: Option<Duration>,
+    pub(crate) system_time_clauses: &'a [SystemTimeClause],
+    // the ROW_NUMBER() windows under `row_num = 1` filters that haven't been reached yet
+    pub(crate) pending_deduplications: Vec<Window>,
+    // whether each window being rewritten is planned as a deduplication, innermost last
+    pub(crate) window_deduplications: Vec<bool>,
 }
 
 impl<'a> TreeNodeRewriter for ArroyoRewriter<'a> {
     type Node = LogicalPlan;
 
+    fn f_down(&mut self, node: Self::Node) -> DFResult<Transformed<Self::Node>> {
+        // the filter is only reached after its input is rewritten, so note deduplications on the
+        // way down; the window is the first one under the filter that matches, as the path from
+        // the filter to it only has single-input nodes
+        match &node {
+            LogicalPlan::Filter(filter) => {
+                self.pending_deduplications
+                    .extend(DeduplicationRewriter::deduplicated_window(filter));
+            }
+            LogicalPlan::Window(window) => {
+                let pending = self
+                    .pending_deduplications
+                    .iter()
+                    .position(|pending| pending == window);
+                if let Some(index) = pending {
+                    self.pending_deduplications.remove(index);
+                }
+                self.window_deduplications.push(pending.is_some());
+            }
+            _ => {}
+        }
+        Ok(Transformed::no(node))
+    }
+
     fn f_up(&mut self, mut node: Self::Node) -> DFResult<Transformed<Self::Node>> {
         match node {
             LogicalPlan::Projection(ref mut projection) => {
@@ -299,10 +375,19 @@ impl<'a> TreeNodeRewriter for ArroyoRewriter<'a> {
                 return AsyncUdfRewriter::new(self.schema_provider).f_up(node);
             }
             LogicalPlan::Aggregate(aggregate) => {
-                return AggregateRewriter {}.f_up(LogicalPlan::Aggregate(aggregate));
+                return AggregateRewriter {
+                    allowed_lateness: self.allowed_lateness,
+                    updating_ttl: self.updating_ttl,
+                }
+                .f_up(LogicalPlan::Aggregate(aggregate));
             }
             LogicalPlan::Join(join) => {
-                return JoinRewriter {}.f_up(LogicalPlan::Join(join));
+                return JoinRewriter {
+                    schema_provider: self.schema_provider,
+                    updating_ttl: self.updating_ttl,
+                    system_time_clauses: self.system_time_clauses,
+                }
+                .f_up(LogicalPlan::Join(join));
             }
             LogicalPlan::TableScan(table_scan) => {
                 return SourceRewriter {
@@ -311,7 +396,14 @@ impl<'a> TreeNodeRewriter for ArroyoRewriter<'a> {
                 .f_up(LogicalPlan::TableScan(table_scan));
             }
             LogicalPlan::Filter(_) => {}
-            LogicalPlan::Window(_) => {
+            LogicalPlan::Window(ref window) => {
+                let deduplicated = self.window_deduplications.pop().unwrap_or_default();
+                if deduplicated && WindowDetectingVisitor::get_window(&window.input)?.is_none() {
+                    return DeduplicationRewriter {
+                        updating_ttl: self.updating_ttl,
+                    }
+                    .f_up(node);
+                }
                 return WindowFunctionRewriter {}.f_up(node);
             }
             LogicalPlan::Sort(_) => {
@@ -358,7 +450,12 @@ impl<'a> TreeNodeRewriter for ArroyoRewriter<'a> {
             }
             LogicalPlan::Values(_) => {}
             LogicalPlan::Explain(_) => {
-                return plan_err!("EXPLAIN is not supported ({})", node.display());
+                // compile_sql takes EXPLAIN off a statement before planning it, and rejects
+                // EXPLAINs of anything but queries and INSERTs, so an EXPLAIN can't be nested
+                return plan_err!(
+                    "EXPLAIN can only be used on a whole statement ({})",
+                    node.display()
+                );
             }
             LogicalPlan::Analyze(_) => {
                 return plan_err!("ANALYZE is not supported ({})", node.display());
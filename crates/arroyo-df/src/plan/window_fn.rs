@@ -52,31 +52,39 @@ impl TreeNodeRewriter for WindowFunctionRewriter {
         let Some(input_window) = window_detecting_visitor.window else {
             return plan_err!("Window functions require already windowed input");
         };
-        if matches!(input_window, WindowType::Session { .. }) {
-            return plan_err!("Window functions do not support session windows");
-        }
 
         let input_window_fields = window_detecting_visitor.fields;
 
         let Window {
             input, window_expr, ..
         } = window;
-        // error if there isn't exactly one window expr
-        if window_expr.len() != 1 {
-            return plan_err!("Window functions require exactly one window expression");
-        }
         // the window_expr can be renamed by optimizers, in which case there will be an alias
-        let (
-            WindowFunction {
-                fun,
-                args,
-                partition_by,
-                order_by,
-                window_frame,
-                null_treatment,
-            },
-            original_name,
-        ) = get_window_and_name(&window_expr[0])?;
+        let window_functions = window_expr
+            .iter()
+            .map(get_window_and_name)
+            .collect::<DFResult<Vec<_>>>()?;
+        let Some((first_function, _)) = window_functions.first() else {
+            return plan_err!("Window functions require at least one window expression");
+        };
+        let partition_by = first_function.partition_by.clone();
+        let order_by = first_function.order_by.clone();
+        if window_functions
+            .iter()
+            .any(|(window_function, _)| window_function.partition_by != partition_by)
+        {
+            return plan_err!(
+                "Window functions in the same projection must have the same PARTITION BY"
+            );
+        }
+        // the input is sorted once for all of the window functions
+        if window_functions
+            .iter()
+            .any(|(window_function, _)| window_function.order_by != order_by)
+        {
+            return plan_err!(
+                "Window functions in the same projection must have the same ORDER BY"
+            );
+        }
 
         let mut window_field: Vec<_> = partition_by
             .iter()
@@ -109,17 +117,15 @@ impl TreeNodeRewriter for WindowFunctionRewriter {
         let mut additional_keys = partition_by.clone();
         // because the operator will have grouped by the timestamp of each row,
         // don't need to shuffle or partition by the window.
-        additional_keys.remove(index);
+        let window_expression = additional_keys.remove(index);
         let key_count = additional_keys.len();
 
-        let new_window_func = WindowFunction {
-            fun,
-            args,
-            partition_by: additional_keys.clone(),
-            order_by,
-            window_frame,
-            null_treatment,
-        };
+        // the timestamp of a session is the end of the window, which different sessions can
+        // share, so they also need to be partitioned by their start
+        let mut window_partition_by = additional_keys.clone();
+        if matches!(input_window, WindowType::Session { .. }) {
+            window_partition_by.push(window_expression.field("start"));
+        }
 
         let mut key_projection_expressions: Vec<_> = additional_keys
             .iter()
@@ -169,7 +175,14 @@ impl TreeNodeRewriter for WindowFunctionRewriter {
                 })
             })
             .collect();
-        sort_expressions.extend(new_window_func.order_by.clone());
+        if window_partition_by.len() > key_count {
+            sort_expressions.push(Expr::Sort(logical_expr::expr::Sort {
+                expr: Box::new(window_partition_by[key_count].clone()),
+                asc: true,
+                nulls_first: false,
+            }));
+        }
+        sort_expressions.extend(order_by);
 
         // This sort seems to be necessary to not fail at execution time.
         let shuffle = LogicalPlan::Sort(Sort {
@@ -178,10 +191,19 @@ impl TreeNodeRewriter for WindowFunctionRewriter {
             fetch: None,
         });
 
-        let window_expr = Expr::WindowFunction(new_window_func).alias_if_changed(original_name)?;
+        let window_exprs = window_functions
+            .into_iter()
+            .map(|(window_function, original_name)| {
+                Expr::WindowFunction(WindowFunction {
+                    partition_by: window_partition_by.clone(),
+                    ..window_function
+                })
+                .alias_if_changed(original_name)
+            })
+            .collect::<DFResult<Vec<_>>>()?;
 
         let rewritten_window_plan =
-            LogicalPlan::Window(Window::try_new(vec![window_expr], Arc::new(shuffle))?);
+            LogicalPlan::Window(Window::try_new(window_exprs, Arc::new(shuffle))?);
 
         Ok(Transformed::yes(LogicalPlan::Extension(Extension {
             node: Arc::new(WindowFunctionExtension::new(
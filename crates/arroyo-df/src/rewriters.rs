@@ -612,7 +612,7 @@ impl TreeNodeVisitor for TimeWindowExprChecker {
     fn f_down(&mut self, node: &Self::Node) -> DFResult<TreeNodeRecursion> {
         if let Expr::ScalarFunction(ScalarFunction { func_def, args: _ }) = node {
             match func_def.name() {
-                "tumble" | "hop" | "session" => {
+                "tumble" | "hop" | "cumulate" | "session" => {
                     return plan_err!(
                         "time window function {} is not allowed in this context. Are you missing a GROUP BY clause?",
                         func_def.name()
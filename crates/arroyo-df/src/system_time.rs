@@ -0,0 +1,142 @@
+use anyhow::{anyhow, bail, Result};
+use datafusion::common::{Column, OwnedTableReference};
+use datafusion::sql::sqlparser::ast::{Expr as SqlExpr, Ident};
+use datafusion::sql::sqlparser::dialect::Dialect;
+use datafusion::sql::sqlparser::keywords::Keyword;
+use datafusion::sql::sqlparser::parser::Parser;
+use datafusion::sql::sqlparser::tokenizer::{Token, TokenWithLocation, Tokenizer};
+
+/// A `FOR SYSTEM_TIME AS OF <column>` clause following a table in a FROM clause, e.g.
+/// `JOIN rates FOR SYSTEM_TIME AS OF o.ts AS r`, which makes the join against that table a
+/// temporal join
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemTimeClause {
+    /// The name the versioned table is referred to by in the query, its alias if it has one
+    pub relation: String,
+    /// The column of the other side of the join giving the time to look up versions at
+    pub time_column: Column,
+}
+
+/// Removes the `FOR SYSTEM_TIME AS OF` clauses from the query, as the parser only supports
+/// them for other dialects, returning the remaining tokens along with the clauses of each
+/// statement, in the same order as the statements returned by the parser.
+pub(crate) fn extract_system_time_clauses(
+    dialect: &dyn Dialect,
+    query: &str,
+) -> Result<(Vec<TokenWithLocation>, Vec<Vec<SystemTimeClause>>)> {
+    let tokens = Tokenizer::new(dialect, query)
+        .tokenize_with_location()
+        .map_err(|e| anyhow!("failed to tokenize query: {}", e))?;
+
+    let mut remaining = Vec::with_capacity(tokens.len());
+    let mut statements = vec![];
+    let mut current = vec![];
+    let mut has_tokens = false;
+
+    let mut index = 0;
+    while index < tokens.len() {
+        if let Some(clause_length) = system_time_keywords(&tokens[index..]) {
+            let mut parser = Parser::new(dialect)
+                .with_tokens_with_locations(tokens[index + clause_length..].to_vec());
+            let time = parser.parse_expr()?;
+            index += clause_length + parser.index();
+
+            let relation = match next_word(&tokens[index..]) {
+                // an alias following the clause, as in `rates FOR SYSTEM_TIME AS OF o.ts AS r`
+                Some((Keyword::AS, _)) => {
+                    let alias_start = tokens[index..]
+                        .iter()
+                        .position(|token| matches!(token.token, Token::Word(_)))
+                        .map(|position| index + position + 1)
+                        .unwrap_or(tokens.len());
+                    next_word(&tokens[alias_start..]).map(|(_, alias)| alias)
+                }
+                Some((Keyword::NoKeyword, alias)) => Some(alias),
+                // otherwise the name, or alias, preceding the clause
+                _ => next_word(remaining.iter().rev()),
+            }
+            .map(|ident| normalize(&ident))
+            .ok_or_else(|| anyhow!("FOR SYSTEM_TIME AS OF must follow a table"))?;
+
+            current.push(SystemTimeClause {
+                relation,
+                time_column: time_column(&time)?,
+            });
+            continue;
+        }
+
+        match &tokens[index].token {
+            Token::Whitespace(_) | Token::EOF => {}
+            Token::SemiColon => {
+                // the parser skips empty statements, so we do as well
+                if has_tokens {
+                    statements.push(std::mem::take(&mut current));
+                    has_tokens = false;
+                }
+            }
+            _ => {
+                has_tokens = true;
+            }
+        }
+        remaining.push(tokens[index].clone());
+        index += 1;
+    }
+
+    if has_tokens {
+        statements.push(current);
+    }
+
+    Ok((remaining, statements))
+}
+
+/// If the tokens start with `FOR SYSTEM_TIME AS OF`, returns how many tokens that takes up
+fn system_time_keywords(tokens: &[TokenWithLocation]) -> Option<usize> {
+    let mut expected = [Keyword::FOR, Keyword::SYSTEM_TIME, Keyword::AS, Keyword::OF].iter();
+    let mut next = expected.next();
+    for (index, token) in tokens.iter().enumerate() {
+        match (&token.token, next) {
+            (Token::Whitespace(_), Some(_)) if index > 0 => {}
+            (Token::Word(word), Some(keyword)) if word.keyword == *keyword => {
+                next = expected.next();
+                if next.is_none() {
+                    return Some(index + 1);
+                }
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// The first word of the tokens, skipping whitespace
+fn next_word<'a>(
+    tokens: impl IntoIterator<Item = &'a TokenWithLocation>,
+) -> Option<(Keyword, Ident)> {
+    tokens.into_iter().find_map(|token| match &token.token {
+        Token::Whitespace(_) => None,
+        Token::Word(word) => Some(Some((word.keyword, word.to_ident()))),
+        _ => Some(None),
+    })?
+}
+
+/// Unquoted identifiers are case-insensitive, as in the planner
+fn normalize(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    }
+}
+
+fn time_column(time: &SqlExpr) -> Result<Column> {
+    match time {
+        SqlExpr::Identifier(name) => Ok(Column::new_unqualified(normalize(name))),
+        SqlExpr::CompoundIdentifier(parts) if parts.len() == 2 => Ok(Column::new(
+            Some(OwnedTableReference::bare(normalize(&parts[0]))),
+            normalize(&parts[1]),
+        )),
+        _ => bail!(
+            "FOR SYSTEM_TIME AS OF must reference a time column, like o.ts, not {}",
+            time
+        ),
+    }
+}
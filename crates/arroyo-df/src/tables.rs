@@ -47,7 +47,7 @@ use datafusion::{
     optimizer::{analyzer::Analyzer, optimizer::Optimizer, OptimizerContext},
     sql::{
         planner::SqlToRel,
-        sqlparser::ast::{ColumnDef, ColumnOption, Statement, Value},
+        sqlparser::ast::{ColumnDef, ColumnOption, Statement, TableConstraint, Value},
     },
 };
 
@@ -72,6 +72,8 @@ pub struct ConnectorTable {
     pub event_time_field: Option<String>,
     pub watermark_field: Option<String>,
     pub idle_time: Option<Duration>,
+    /// The columns of the table's PRIMARY KEY, if it declares one
+    pub primary_keys: Vec<String>,
 
     pub inferred_fields: Option<Vec<DFField>>,
 }
@@ -103,7 +105,7 @@ impl From<Field> for FieldSpec {
     }
 }
 
-fn produce_optimized_plan(
+pub(crate) fn produce_optimized_plan(
     statement: &Statement,
     schema_provider: &ArroyoSchemaProvider,
 ) -> Result<LogicalPlan> {
@@ -184,6 +186,7 @@ impl From<Connection> for ConnectorTable {
             event_time_field: None,
             watermark_field: None,
             idle_time: DEFAULT_IDLE_TIME,
+            primary_keys: vec![],
             inferred_fields: None,
         }
     }
@@ -374,6 +377,7 @@ impl ConnectorTable {
             connector: self.connector.clone(),
             config: self.config.clone(),
             description: self.description.clone(),
+            connection_id: self.id,
         }
     }
 
@@ -549,6 +553,53 @@ impl Table {
             .collect::<Result<Vec<_>>>()
     }
 
+    /// The columns of the table's PRIMARY KEY, which may be declared on a column or as a
+    /// constraint of the table
+    fn primary_keys(columns: &[ColumnDef], constraints: &[TableConstraint]) -> Result<Vec<String>> {
+        let mut primary_keys: Vec<Vec<String>> = columns
+            .iter()
+            .filter(|column| {
+                column.options.iter().any(|option| {
+                    matches!(
+                        option.option,
+                        ColumnOption::Unique {
+                            is_primary: true,
+                            ..
+                        }
+                    )
+                })
+            })
+            .map(|column| vec![column.name.value.to_string()])
+            .collect();
+
+        primary_keys.extend(
+            constraints
+                .iter()
+                .filter_map(|constraint| match constraint {
+                    TableConstraint::Unique {
+                        columns,
+                        is_primary: true,
+                        ..
+                    } => Some(columns.iter().map(|c| c.value.to_string()).collect()),
+                    _ => None,
+                }),
+        );
+
+        if primary_keys.len() > 1 {
+            bail!("a table can only have one PRIMARY KEY");
+        }
+
+        let primary_keys = primary_keys.pop().unwrap_or_default();
+        if let Some(key) = primary_keys
+            .iter()
+            .find(|key| !columns.iter().any(|c| c.name.value == **key))
+        {
+            bail!("PRIMARY KEY column '{}' is not a column of the table", key);
+        }
+
+        Ok(primary_keys)
+    }
+
     pub fn try_from_statement(
         statement: &Statement,
         schema_provider: &ArroyoSchemaProvider,
@@ -556,6 +607,7 @@ impl Table {
         if let Statement::CreateTable {
             name,
             columns,
+            constraints,
             with_options,
             query: None,
             ..
@@ -572,6 +624,7 @@ impl Table {
 
             let connector = with_map.remove("connector");
             let fields = Self::schema_from_columns(columns, schema_provider)?;
+            let primary_keys = Self::primary_keys(columns, constraints)?;
 
             match connector.as_deref() {
                 Some("memory") | None => {
@@ -579,6 +632,10 @@ impl Table {
                         bail!("Virtual fields are not supported in memory tables; instead write a query");
                     }
 
+                    if !primary_keys.is_empty() {
+                        bail!("PRIMARY KEY is only supported for connection tables");
+                    }
+
                     if !with_map.is_empty() {
                         if connector.is_some() {
                             bail!("Memory tables do not allow with options");
@@ -611,16 +668,16 @@ impl Table {
                         ),
                         None => None,
                     };
-                    Ok(Some(Table::ConnectorTable(
-                        ConnectorTable::from_options(
-                            &name,
-                            connector,
-                            fields,
-                            &mut with_map,
-                            connection_profile,
-                        )
-                        .map_err(|e| anyhow!("Failed to construct table '{}': {:?}", name, e))?,
-                    )))
+                    let mut table = ConnectorTable::from_options(
+                        &name,
+                        connector,
+                        fields,
+                        &mut with_map,
+                        connection_profile,
+                    )
+                    .map_err(|e| anyhow!("Failed to construct table '{}': {:?}", name, e))?;
+                    table.primary_keys = primary_keys;
+                    Ok(Some(Table::ConnectorTable(table)))
                 }
             }
         } else {
@@ -634,7 +691,8 @@ impl Table {
                     input,
                     ..
                 }))) => {
-                    let rewritten_plan = rewrite_plan(input.as_ref().clone(), schema_provider)?;
+                    let rewritten_plan =
+                        rewrite_plan(input.as_ref().clone(), schema_provider, None, None, &[])?;
                     let schema = rewritten_plan.schema().clone();
                     let remote_extension = RemoteTableExtension {
                         input: rewritten_plan,
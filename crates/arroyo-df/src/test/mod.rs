@@ -5,8 +5,10 @@ use arroyo_connectors::{
     nexmark::{NexmarkConnector, NexmarkTable},
     EmptyConfig,
 };
+use arroyo_datastream::logical::{LogicalEdgeType, OperatorName};
 use arroyo_operator::connector::Connector;
 use arroyo_udf_host::parse::NullableType;
+use petgraph::visit::EdgeRef;
 use test_log::test;
 
 use crate::{parse_and_get_program, ArroyoSchemaProvider, SqlConfig};
@@ -58,3 +60,547 @@ async fn test_udf() {
         .await
         .unwrap();
 }
+
+#[test(tokio::test)]
+async fn test_parallelism_hints() {
+    let schema_provider = get_test_schema_provider();
+
+    let sql = "
+    CREATE TABLE impulse WITH (
+        connector = 'impulse',
+        event_rate = '10'
+    );
+
+    SELECT /*+ parallelism(2) */ bid.auction FROM nexmark where bid is not null;
+    SELECT /*+ parallelism(3) */ counter, count(*) FROM impulse GROUP BY 1;";
+
+    let program = parse_and_get_program(
+        sql,
+        schema_provider,
+        SqlConfig {
+            default_parallelism: 1,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap()
+    .program;
+
+    let mut parallelisms: Vec<_> = program
+        .graph
+        .node_weights()
+        .map(|n| n.parallelism)
+        .collect();
+    parallelisms.sort();
+    parallelisms.dedup();
+    assert_eq!(parallelisms, vec![2, 3]);
+
+    for group in program.forward_groups() {
+        let p = program.graph[group[0]].parallelism;
+        assert!(group.iter().all(|idx| program.graph[*idx].parallelism == p));
+    }
+}
+
+#[test(tokio::test)]
+async fn test_join_unequal_parallelism_hints() {
+    let schema_provider = get_test_schema_provider();
+
+    // the first statement's hint applies to the shared impulse source, and so to the left side
+    // of the join in the second statement
+    let sql = "
+    CREATE TABLE impulse WITH (
+        connector = 'impulse',
+        event_rate = '10'
+    );
+
+    CREATE TABLE other_impulse WITH (
+        connector = 'impulse',
+        event_rate = '10'
+    );
+
+    SELECT /*+ parallelism(2) */ counter FROM impulse;
+    SELECT a.counter FROM impulse a JOIN other_impulse b ON a.counter = b.counter;";
+
+    let err = parse_and_get_program(
+        sql,
+        schema_provider,
+        SqlConfig {
+            default_parallelism: 1,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap_err();
+
+    assert!(
+        err.to_string()
+            .contains("left side has 2 and the right side has 1"),
+        "unexpected error: {}",
+        err
+    );
+}
+
+#[test(tokio::test)]
+async fn test_late_data_sink_hint() {
+    let schema_provider = get_test_schema_provider();
+
+    let sql = "
+    CREATE TABLE impulse WITH (
+        connector = 'impulse',
+        event_rate = '10'
+    );
+
+    CREATE TABLE late_rows (
+        counter BIGINT UNSIGNED
+    ) WITH (
+        connector = 'kafka',
+        bootstrap_servers = 'localhost:9092',
+        type = 'sink',
+        topic = 'late_rows',
+        format = 'json'
+    );
+
+    SELECT /*+ parallelism(3), allowed_lateness('10 seconds'), late_data_sink(late_rows) */
+        counter, tumble(interval '1 minute') as window, count(*)
+    FROM impulse
+    GROUP BY 1, 2;";
+
+    let program = parse_and_get_program(sql, schema_provider, SqlConfig::default())
+        .await
+        .unwrap()
+        .program;
+
+    let late_edges: Vec<_> = program
+        .graph
+        .edge_references()
+        .filter(|edge| edge.weight().edge_type == LogicalEdgeType::LateData)
+        .collect();
+    assert_eq!(late_edges.len(), 1);
+
+    // the sink runs at the parallelism of the window it receives late rows from
+    assert_eq!(program.graph[late_edges[0].source()].parallelism, 3);
+    assert_eq!(program.graph[late_edges[0].target()].parallelism, 3);
+
+    let fields: Vec<_> = late_edges[0]
+        .weight()
+        .schema
+        .schema
+        .fields()
+        .iter()
+        .map(|f| f.name().as_str())
+        .collect();
+    assert_eq!(fields, vec!["counter", "_timestamp"]);
+}
+
+#[test(tokio::test)]
+async fn test_updating_ttl() {
+    let schema_provider = get_test_schema_provider();
+
+    let sql = "
+    CREATE TABLE impulse WITH (
+        connector = 'impulse',
+        event_rate = '10'
+    );
+
+    SET updating_ttl = '7 days';
+
+    SELECT counter, count(*) FROM impulse GROUP BY 1;
+    SELECT /*+ updating_ttl('1 hour') */ subtask_index, count(*) FROM impulse GROUP BY 1;";
+
+    let program = parse_and_get_program(sql, schema_provider, SqlConfig::default())
+        .await
+        .unwrap()
+        .program;
+
+    let mut descriptions: Vec<_> = program
+        .graph
+        .node_weights()
+        .filter(|n| n.operator_name == OperatorName::UpdatingAggregate)
+        .map(|n| n.description.clone())
+        .collect();
+    descriptions.sort();
+    assert_eq!(
+        descriptions,
+        vec![
+            "UpdatingAggregate<ttl=3600s>",
+            "UpdatingAggregate<ttl=604800s>"
+        ]
+    );
+}
+
+#[test(tokio::test)]
+async fn test_interval_join() {
+    let schema_provider = get_test_schema_provider();
+
+    let sql = "
+    CREATE TABLE impressions (
+        ad_id bigint,
+        ts timestamp,
+        created_at timestamp
+    ) WITH (
+        connector = 'filesystem',
+        format = 'parquet',
+        type = 'source',
+        path = '/home/data/impressions',
+        event_time_field = 'ts'
+    );
+    CREATE TABLE clicks (
+        ad_id bigint,
+        ts timestamp,
+        created_at timestamp
+    ) WITH (
+        connector = 'filesystem',
+        format = 'parquet',
+        type = 'source',
+        path = '/home/data/clicks',
+        event_time_field = 'ts'
+    );
+
+    SELECT i.ad_id, c.ts FROM impressions i JOIN clicks c
+    ON i.ad_id = c.ad_id AND c.ts BETWEEN i.ts AND i.ts + INTERVAL '30' MINUTE;
+    SELECT i.ad_id, c.ts FROM impressions i JOIN clicks c
+    ON i.ad_id = c.ad_id AND i.ts >= c.ts - INTERVAL '1' MINUTE AND i.ts < c.ts + INTERVAL '5' MINUTE;
+    SELECT i.ad_id, c.ts FROM impressions i JOIN clicks c
+    ON i.ad_id = c.ad_id AND c.created_at BETWEEN i.created_at AND i.created_at + INTERVAL '30' MINUTE;";
+
+    let program = parse_and_get_program(sql, schema_provider, SqlConfig::default())
+        .await
+        .unwrap()
+        .program;
+
+    let mut descriptions: Vec<_> = program
+        .graph
+        .node_weights()
+        .filter(|n| n.operator_name == OperatorName::Join)
+        .map(|n| n.description.clone())
+        .collect();
+    descriptions.sort();
+    // created_at isn't the event time, so that join can't be pruned by the watermark
+    assert_eq!(
+        descriptions,
+        vec![
+            "interval_join<left_ttl=1800s, right_ttl=0ns>",
+            "interval_join<left_ttl=300s, right_ttl=60s>",
+            "join<ttl=3600s>"
+        ]
+    );
+}
+
+#[test(tokio::test)]
+async fn test_temporal_join() {
+    let schema_provider = get_test_schema_provider();
+
+    let sql = "
+    CREATE TABLE orders (
+        currency text,
+        amount double,
+        ts timestamp
+    ) WITH (
+        connector = 'filesystem',
+        format = 'parquet',
+        type = 'source',
+        path = '/home/data/orders',
+        event_time_field = 'ts'
+    );
+    CREATE TABLE rates (
+        currency text,
+        rate double,
+        PRIMARY KEY (currency)
+    ) WITH (
+        connector = 'kafka',
+        bootstrap_servers = 'localhost:9092',
+        type = 'source',
+        topic = 'rates',
+        format = 'debezium_json'
+    );
+
+    SELECT o.currency, o.amount * r.rate AS converted FROM orders o
+    LEFT JOIN rates FOR SYSTEM_TIME AS OF o.ts AS r ON o.currency = r.currency;";
+
+    let program = parse_and_get_program(sql, schema_provider, SqlConfig::default())
+        .await
+        .unwrap()
+        .program;
+
+    let descriptions: Vec<_> = program
+        .graph
+        .node_weights()
+        .filter(|n| n.operator_name == OperatorName::TemporalJoin)
+        .map(|n| n.description.clone())
+        .collect();
+    assert_eq!(descriptions, vec!["temporal_join<ttl=86400s>"]);
+    assert!(!program
+        .graph
+        .node_weights()
+        .any(|n| n.operator_name == OperatorName::Join));
+}
+
+#[test(tokio::test)]
+async fn test_deduplicate() {
+    let schema_provider = get_test_schema_provider();
+
+    let sql = "
+    CREATE TABLE events (
+        event_id text,
+        user_id bigint,
+        ts timestamp
+    ) WITH (
+        connector = 'filesystem',
+        format = 'parquet',
+        type = 'source',
+        path = '/home/data/events',
+        event_time_field = 'ts'
+    );
+
+    SELECT event_id, user_id FROM (
+        SELECT *, ROW_NUMBER() OVER (PARTITION BY event_id ORDER BY ts) AS row_num FROM events
+    ) WHERE row_num = 1;
+    SELECT /*+ updating_ttl('10 minutes') */ event_id, user_id FROM (
+        SELECT *, ROW_NUMBER() OVER (PARTITION BY event_id, user_id) AS row_num FROM events
+    ) WHERE row_num <= 1;";
+
+    let program = parse_and_get_program(sql, schema_provider, SqlConfig::default())
+        .await
+        .unwrap()
+        .program;
+
+    let mut descriptions: Vec<_> = program
+        .graph
+        .node_weights()
+        .filter(|n| n.operator_name == OperatorName::Deduplicate)
+        .map(|n| n.description.clone())
+        .collect();
+    descriptions.sort();
+    assert_eq!(
+        descriptions,
+        vec!["deduplicate<ttl=600s>", "deduplicate<ttl=86400s>"]
+    );
+    assert!(!program
+        .graph
+        .node_weights()
+        .any(|n| n.operator_name == OperatorName::WindowFunction));
+}
+
+#[test(tokio::test)]
+async fn test_deduplicate_only_filtered_window() {
+    let schema_provider = get_test_schema_provider();
+
+    // the second ROW_NUMBER() is identical to the first but isn't filtered, so it isn't a
+    // deduplication and still needs a windowed input
+    let sql = "
+    CREATE TABLE events (
+        event_id text,
+        user_id bigint,
+        ts timestamp
+    ) WITH (
+        connector = 'filesystem',
+        format = 'parquet',
+        type = 'source',
+        path = '/home/data/events',
+        event_time_field = 'ts'
+    );
+
+    SELECT event_id, row_num FROM (
+        SELECT *, ROW_NUMBER() OVER (PARTITION BY event_id ORDER BY ts) AS row_num FROM events
+    ) WHERE row_num = 1
+    UNION ALL
+    SELECT event_id, row_num FROM (
+        SELECT *, ROW_NUMBER() OVER (PARTITION BY event_id ORDER BY ts) AS row_num FROM events
+    );";
+
+    let err = parse_and_get_program(sql, schema_provider, SqlConfig::default())
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Window functions require already windowed input"),
+        "unexpected error: {}",
+        err
+    );
+}
+
+#[test(tokio::test)]
+async fn test_match_recognize() {
+    let schema_provider = get_test_schema_provider();
+
+    let sql = "
+    CREATE TABLE trades (
+        symbol text,
+        price double,
+        ts timestamp
+    ) WITH (
+        connector = 'filesystem',
+        format = 'parquet',
+        type = 'source',
+        path = '/home/data/trades',
+        event_time_field = 'ts'
+    );
+
+    SELECT symbol, peak, bottom, recovered, dips FROM trades MATCH_RECOGNIZE (
+        PARTITION BY symbol
+        ORDER BY ts
+        MEASURES
+            FIRST(DOWN.price) AS peak,
+            MIN(DOWN.price) AS bottom,
+            LAST(UP.price) AS recovered,
+            COUNT(DOWN.*) AS dips
+        ONE ROW PER MATCH
+        AFTER MATCH SKIP PAST LAST ROW
+        PATTERN (START DOWN{2,} UP+) WITHIN INTERVAL '1' HOUR
+        DEFINE
+            DOWN AS DOWN.price < 100,
+            UP AS price >= 100
+    ) AS m;
+    SELECT * FROM trades MATCH_RECOGNIZE (
+        PARTITION BY symbol
+        ORDER BY ts
+        MEASURES SUM(price) AS total
+        PATTERN ((A | B)+ C?)
+        DEFINE A AS price > 10, B AS price < 1, C AS price = 5
+    );";
+
+    let program = parse_and_get_program(sql, schema_provider, SqlConfig::default())
+        .await
+        .unwrap()
+        .program;
+
+    let mut descriptions: Vec<_> = program
+        .graph
+        .node_weights()
+        .filter(|n| n.operator_name == OperatorName::MatchRecognize)
+        .map(|n| n.description.clone())
+        .collect();
+    descriptions.sort();
+    assert_eq!(
+        descriptions,
+        vec![
+            "match_recognize<ttl=86400s>",
+            "match_recognize<within=3600s>"
+        ]
+    );
+}
+
+#[test(tokio::test)]
+async fn test_explain() {
+    let create = "
+    CREATE TABLE events (
+        event_id text,
+        user_id bigint,
+        ts timestamp
+    ) WITH (
+        connector = 'filesystem',
+        format = 'parquet',
+        type = 'source',
+        path = '/home/data/events',
+        event_time_field = 'ts'
+    );";
+
+    let sql = format!(
+        "{}
+        EXPLAIN SELECT /*+ updating_ttl('10 minutes') */ user_id, count(*) FROM events GROUP BY user_id;",
+        create
+    );
+    let explanation = parse_and_get_program(&sql, get_test_schema_provider(), SqlConfig::default())
+        .await
+        .unwrap()
+        .explanation
+        .unwrap();
+
+    assert!(!explanation.verbose);
+    assert!(explanation.unrewritten_logical_plans.is_none());
+    assert_eq!(explanation.logical_plans.len(), 1);
+    assert!(explanation
+        .extensions
+        .iter()
+        .any(|extension| extension.name == "SinkExtension"));
+    let aggregate = explanation
+        .operators
+        .iter()
+        .find(|operator| operator.operator == "UpdatingAggregate")
+        .unwrap();
+    assert_eq!(aggregate.ttl_micros, Some(600_000_000));
+    let mut tables: Vec<_> = aggregate
+        .state_tables
+        .iter()
+        .flatten()
+        .map(|table| (table.name.as_str(), table.retention_micros))
+        .collect();
+    tables.sort();
+    assert_eq!(
+        tables,
+        vec![("f", Some(600_000_000)), ("p", Some(600_000_000))]
+    );
+    assert!(explanation
+        .edges
+        .iter()
+        .any(|edge| edge.edge_type == "Shuffle" && !edge.key_fields.is_empty()));
+    assert!(explanation.edges.iter().all(|edge| edge.fields.is_none()));
+    assert!(explanation.text.contains("ttl: 600s"));
+
+    let sql = format!(
+        "{}
+        EXPLAIN VERBOSE SELECT user_id, count(*) FROM events GROUP BY user_id, tumble(interval '1 minute');",
+        create
+    );
+    let explanation = parse_and_get_program(&sql, get_test_schema_provider(), SqlConfig::default())
+        .await
+        .unwrap()
+        .explanation
+        .unwrap();
+
+    assert!(explanation.verbose);
+    assert_eq!(explanation.unrewritten_logical_plans.unwrap().len(), 1);
+    let window = explanation
+        .operators
+        .iter()
+        .find(|operator| operator.operator == "TumblingWindowAggregate")
+        .unwrap();
+    assert_eq!(window.ttl_micros, None);
+    assert_eq!(
+        window.state_tables.as_ref().unwrap()[0].retention_micros,
+        Some(60_000_000)
+    );
+    assert!(explanation
+        .operators
+        .iter()
+        .filter(|operator| operator.operator.starts_with("Connector"))
+        .all(|operator| operator.state_tables.is_none()));
+    assert!(explanation.edges.iter().all(|edge| edge.fields.is_some()));
+
+    let sql = format!("{}\nEXPLAIN ANALYZE SELECT * FROM events;", create);
+    let error = parse_and_get_program(&sql, get_test_schema_provider(), SqlConfig::default())
+        .await
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("EXPLAIN ANALYZE is not supported"));
+
+    let sql = format!("{}\nSELECT * FROM events;", create);
+    assert!(
+        parse_and_get_program(&sql, get_test_schema_provider(), SqlConfig::default())
+            .await
+            .unwrap()
+            .explanation
+            .is_none()
+    );
+}
+
+#[test(tokio::test)]
+async fn test_explain_create_table() {
+    let sql = "
+    EXPLAIN CREATE TABLE impulse WITH (
+        connector = 'impulse',
+        event_rate = '10'
+    );
+    SELECT * FROM impulse;";
+
+    let err = parse_and_get_program(sql, get_test_schema_provider(), SqlConfig::default())
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("only queries and INSERT statements can be EXPLAINed"),
+        "unexpected error: {}",
+        err
+    );
+}
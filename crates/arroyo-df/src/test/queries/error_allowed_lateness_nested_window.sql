@@ -0,0 +1,25 @@
+--fail=allowed_lateness hint is only supported for tumbling, sliding and session windows that are not nested in another window
+CREATE TABLE Nexmark WITH (
+    connector = 'nexmark',
+    event_rate = '10'
+);
+
+SELECT /*+ allowed_lateness('10 seconds') */
+    count(*) as auctions,
+    window
+FROM
+    (
+        SELECT
+            bid.auction as auction,
+            tumble(interval '1 minute') as window,
+            count(*) as count
+        FROM
+            nexmark
+        where
+            bid is not null
+        GROUP BY
+            1,
+            2
+    )
+GROUP BY
+    2
@@ -0,0 +1,7 @@
+--fail=allowed_lateness hint requires the statement to have a windowed aggregate
+CREATE TABLE Nexmark WITH (
+    connector = 'nexmark',
+    event_rate = '10'
+);
+
+SELECT /*+ allowed_lateness('10 seconds') */ bid.auction FROM nexmark where bid is not null;
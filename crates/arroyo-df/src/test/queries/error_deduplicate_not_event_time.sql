@@ -0,0 +1,18 @@
+--fail=ROW_NUMBER() must be ordered by the event time ascending
+CREATE TABLE events (
+  event_id TEXT,
+  user_id BIGINT,
+  ts TIMESTAMP,
+  created_at TIMESTAMP
+) WITH (
+  connector = 'filesystem',
+  format = 'parquet',
+  type = 'source',
+  path = '/home/data/events',
+  event_time_field = 'ts'
+);
+
+SELECT event_id, user_id FROM (
+  SELECT *, ROW_NUMBER() OVER (PARTITION BY event_id ORDER BY created_at) AS row_num
+  FROM events
+) WHERE row_num = 1
\ No newline at end of file
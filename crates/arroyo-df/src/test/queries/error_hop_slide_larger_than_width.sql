@@ -0,0 +1,16 @@
+--fail=Error during planning: hop() slide 600s must be positive and no larger than width 180s
+CREATE TABLE Nexmark WITH (
+    connector = 'nexmark',
+    event_rate = '10'
+);
+
+SELECT
+    bid.auction as auction,
+    hop(interval '10 minute', interval '3 minute') as window,
+    count(*) as count
+FROM
+    nexmark
+where
+    bid is not null
+GROUP BY
+1,2
\ No newline at end of file
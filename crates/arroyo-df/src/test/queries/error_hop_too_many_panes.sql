@@ -0,0 +1,16 @@
+--fail=Error during planning: hop() width 86400s is not a multiple of slide 7s, which splits each window into 86400 panes (the limit is 1000); use a width that is a multiple of the slide
+CREATE TABLE Nexmark WITH (
+    connector = 'nexmark',
+    event_rate = '10'
+);
+
+SELECT
+    bid.auction as auction,
+    hop(interval '7 second', interval '1 day') as window,
+    count(*) as count
+FROM
+    nexmark
+where
+    bid is not null
+GROUP BY
+1,2
\ No newline at end of file
@@ -0,0 +1,18 @@
+--fail=Error during planning: interval join bounds are empty: the lower bound 600s is after the upper bound 300s
+CREATE TABLE cars (
+  timestamp TIMESTAMP,
+  driver_id BIGINT,
+  event_type TEXT,
+  location TEXT
+) WITH (
+  connector = 'filesystem',
+  format = 'parquet',
+  type = 'source',
+  path = '/home/data/cars',
+  event_time_field = 'timestamp'
+);
+
+SELECT a.driver_id, b.timestamp
+FROM cars a JOIN cars b
+ON a.driver_id = b.driver_id
+AND a.timestamp BETWEEN b.timestamp + INTERVAL '10' MINUTE AND b.timestamp + INTERVAL '5' MINUTE
\ No newline at end of file
@@ -0,0 +1,17 @@
+--fail=late_data_sink hint requires the statement to have a windowed aggregate
+CREATE TABLE Nexmark WITH (
+    connector = 'nexmark',
+    event_rate = '10'
+);
+
+CREATE TABLE late_bids (
+    auction BIGINT
+) WITH (
+    connector = 'kafka',
+    bootstrap_servers = 'localhost:9092',
+    type = 'sink',
+    topic = 'late_bids',
+    format = 'json'
+);
+
+SELECT /*+ late_data_sink(late_bids) */ bid.auction FROM nexmark where bid is not null;
@@ -0,0 +1,21 @@
+--fail=must be ordered by the event time of trades, not created_at
+CREATE TABLE trades (
+  symbol TEXT,
+  price DOUBLE,
+  ts TIMESTAMP,
+  created_at TIMESTAMP
+) WITH (
+  connector = 'filesystem',
+  format = 'parquet',
+  type = 'source',
+  path = '/home/data/trades',
+  event_time_field = 'ts'
+);
+
+SELECT * FROM trades MATCH_RECOGNIZE (
+  PARTITION BY symbol
+  ORDER BY created_at
+  MEASURES LAST(UP.price) AS price
+  PATTERN (DOWN UP)
+  DEFINE DOWN AS price < 100, UP AS price >= 100
+)
\ No newline at end of file
@@ -0,0 +1,20 @@
+--fail=reluctant quantifiers aren't supported in PATTERN
+CREATE TABLE trades (
+  symbol TEXT,
+  price DOUBLE,
+  ts TIMESTAMP
+) WITH (
+  connector = 'filesystem',
+  format = 'parquet',
+  type = 'source',
+  path = '/home/data/trades',
+  event_time_field = 'ts'
+);
+
+SELECT * FROM trades MATCH_RECOGNIZE (
+  PARTITION BY symbol
+  ORDER BY ts
+  MEASURES LAST(UP.price) AS price
+  PATTERN (DOWN+? UP)
+  DEFINE DOWN AS price < 100, UP AS price >= 100
+)
\ No newline at end of file
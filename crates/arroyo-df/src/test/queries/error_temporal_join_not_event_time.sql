@@ -0,0 +1,29 @@
+--fail=FOR SYSTEM_TIME AS OF must reference it, not o.created_at
+CREATE TABLE orders (
+  currency TEXT,
+  amount DOUBLE,
+  ts TIMESTAMP,
+  created_at TIMESTAMP
+) WITH (
+  connector = 'filesystem',
+  format = 'parquet',
+  type = 'source',
+  path = '/home/data/orders',
+  event_time_field = 'ts'
+);
+
+CREATE TABLE rates (
+  currency TEXT PRIMARY KEY,
+  rate DOUBLE
+) WITH (
+  connector = 'kafka',
+  bootstrap_servers = 'localhost:9092',
+  type = 'source',
+  topic = 'rates',
+  format = 'debezium_json'
+);
+
+SELECT o.currency, o.amount * r.rate
+FROM orders o
+JOIN rates FOR SYSTEM_TIME AS OF o.created_at r
+ON o.currency = r.currency
\ No newline at end of file
@@ -0,0 +1,29 @@
+--fail=temporal joins must have equality conditions on exactly the primary key of r (currency)
+CREATE TABLE orders (
+  order_id BIGINT,
+  currency TEXT,
+  amount DOUBLE,
+  ts TIMESTAMP
+) WITH (
+  connector = 'filesystem',
+  format = 'parquet',
+  type = 'source',
+  path = '/home/data/orders',
+  event_time_field = 'ts'
+);
+
+CREATE TABLE rates (
+  currency TEXT PRIMARY KEY,
+  rate DOUBLE
+) WITH (
+  connector = 'kafka',
+  bootstrap_servers = 'localhost:9092',
+  type = 'source',
+  topic = 'rates',
+  format = 'debezium_json'
+);
+
+SELECT o.order_id, o.amount * r.rate AS converted
+FROM orders AS o
+JOIN rates FOR SYSTEM_TIME AS OF o.ts AS r
+ON o.currency = r.currency AND o.amount = r.rate
\ No newline at end of file
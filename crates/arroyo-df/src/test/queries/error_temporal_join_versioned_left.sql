@@ -0,0 +1,28 @@
+--fail=the versioned table r of a temporal join must be on the right side of the join
+CREATE TABLE orders (
+  currency TEXT,
+  amount DOUBLE,
+  ts TIMESTAMP
+) WITH (
+  connector = 'filesystem',
+  format = 'parquet',
+  type = 'source',
+  path = '/home/data/orders',
+  event_time_field = 'ts'
+);
+
+CREATE TABLE rates (
+  currency TEXT,
+  rate DOUBLE
+) WITH (
+  connector = 'kafka',
+  bootstrap_servers = 'localhost:9092',
+  type = 'source',
+  topic = 'rates',
+  format = 'debezium_json'
+);
+
+SELECT o.currency, o.amount * r.rate
+FROM rates FOR SYSTEM_TIME AS OF o.ts AS r
+JOIN orders o
+ON o.currency = r.currency
\ No newline at end of file
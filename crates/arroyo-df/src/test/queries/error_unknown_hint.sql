@@ -0,0 +1,7 @@
+--fail=unknown hint 'parallelsm'
+CREATE TABLE Nexmark WITH (
+    connector = 'nexmark',
+    event_rate = '10'
+);
+
+SELECT /*+ parallelsm(4) */ bid.auction FROM nexmark where bid is not null;
@@ -0,0 +1,18 @@
+CREATE TABLE cars (
+  timestamp TIMESTAMP,
+  driver_id BIGINT,
+  event_type TEXT,
+  location TEXT
+) WITH (
+  connector = 'filesystem',
+  format = 'parquet',
+  type = 'source',
+  path = '/home/data/cars',
+  event_time_field = 'timestamp'
+);
+
+SELECT pickups.driver_id, pickups.timestamp as pickup_time, dropoffs.timestamp as dropoff_time
+FROM (SELECT * FROM cars WHERE event_type = 'pickup') pickups
+JOIN (SELECT * FROM cars WHERE event_type = 'dropoff') dropoffs
+ON pickups.driver_id = dropoffs.driver_id
+AND dropoffs.timestamp BETWEEN pickups.timestamp AND pickups.timestamp + INTERVAL '30' MINUTE
\ No newline at end of file
@@ -1,4 +1,3 @@
---fail=Error during planning: hop() width 600s currently must be a multiple of slide 180s
 CREATE TABLE Nexmark WITH (
     connector = 'nexmark',
     event_rate = '10'
@@ -0,0 +1,12 @@
+SELECT * FROM (
+    SELECT *,
+        ROW_NUMBER() OVER (PARTITION BY window ORDER BY count DESC) as row_num,
+        RANK() OVER (PARTITION BY window ORDER BY count DESC) as rank,
+        LAG(count) OVER (PARTITION BY window ORDER BY count DESC) as previous_count,
+        MAX(count) OVER (PARTITION BY window ORDER BY count DESC) as max_count
+    FROM (SELECT count(*) as count,
+        bid.auction as auction,
+        tumble(interval '10 seconds') as window
+            FROM nexmark
+            WHERE bid is not null
+            group by window, auction)) WHERE row_num <= 5
\ No newline at end of file
@@ -0,0 +1,10 @@
+SELECT * FROM (
+    SELECT *, ROW_NUMBER() OVER (
+        PARTITION BY window
+        ORDER BY count DESC) as row_num
+    FROM (SELECT count(*) as count,
+        bid.auction as auction,
+        session(interval '10 seconds') as window
+            FROM nexmark
+            WHERE bid is not null
+            group by window, auction)) WHERE row_num <= 5
\ No newline at end of file
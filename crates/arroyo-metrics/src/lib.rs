@@ -3,7 +3,7 @@ use std::sync::{Arc, OnceLock, RwLock};
 
 use arroyo_types::{
     TaskInfo, BATCHES_RECV, BATCHES_SENT, BYTES_RECV, BYTES_SENT, DESERIALIZATION_ERRORS,
-    MESSAGES_RECV, MESSAGES_SENT,
+    LATE_ROWS_DROPPED, MESSAGES_RECV, MESSAGES_SENT,
 };
 use lazy_static::lazy_static;
 use prometheus::{
@@ -85,6 +85,12 @@ lazy_static! {
         &TASK_METRIC_LABELS
     )
     .unwrap();
+    pub static ref LATE_ROWS_DROPPED_COUNTER: IntCounterVec = register_int_counter_vec!(
+        LATE_ROWS_DROPPED,
+        "Count of rows that arrived after their window closed and were not aggregated",
+        &TASK_METRIC_LABELS
+    )
+    .unwrap();
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash)]
@@ -96,6 +102,7 @@ pub enum TaskCounters {
     BytesReceived,
     BytesSent,
     DeserializationErrors,
+    LateRowsDropped,
 }
 
 #[allow(clippy::type_complexity)]
@@ -109,6 +116,7 @@ impl TaskCounters {
             TaskCounters::BytesReceived => &BYTES_RECEIVED_COUNTER,
             TaskCounters::BytesSent => &BYTES_SENT_COUNTER,
             TaskCounters::DeserializationErrors => &DESERIALIZATION_ERRORS_COUNTER,
+            TaskCounters::LateRowsDropped => &LATE_ROWS_DROPPED_COUNTER,
         }
     }
 
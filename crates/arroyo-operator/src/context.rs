@@ -1,9 +1,10 @@
 use crate::{server_for_hash_array, RateLimiter};
-use arrow::array::{make_builder, Array, ArrayBuilder, PrimitiveArray, RecordBatch};
-use arrow::compute::{partition, sort_to_indices, take};
+use arrow::array::{make_builder, Array, ArrayBuilder, PrimitiveArray, RecordBatch, UInt64Array};
+use arrow::compute::kernels::cmp::eq;
+use arrow::compute::{filter_record_batch, partition, sort_to_indices, take};
 use arrow::datatypes::{SchemaRef, UInt64Type};
 use arroyo_formats::de::ArrowDeserializer;
-use arroyo_metrics::{register_queue_gauge, QueueGauges, TaskCounters};
+use arroyo_metrics::{gauge_for_task, register_queue_gauge, QueueGauges, TaskCounters};
 use arroyo_rpc::df::ArroyoSchema;
 use arroyo_rpc::formats::{BadData, Format, Framing};
 use arroyo_rpc::grpc::{CheckpointMetadata, TableConfig, TaskCheckpointEventType};
@@ -12,10 +13,11 @@ use arroyo_rpc::{get_hasher, CompactionResult, ControlMessage, ControlResp};
 use arroyo_state::tables::table_manager::TableManager;
 use arroyo_state::{BackingStore, StateBackend};
 use arroyo_types::{
-    from_micros, should_flush, ArrowMessage, CheckpointBarrier, SourceError, TaskInfo, UserError,
-    Watermark,
+    from_micros, should_flush, to_millis, ArrowMessage, CheckpointBarrier, SignalMessage,
+    SourceError, TaskInfo, UserError, Watermark, INPUT_WATERMARK, OUTPUT_WATERMARK,
 };
 use datafusion::common::hash_utils;
+use prometheus::IntGauge;
 use rand::Rng;
 use std::collections::HashMap;
 use std::mem::size_of_val;
@@ -35,6 +37,7 @@ pub struct WatermarkHolder {
     last_present_watermark: Option<SystemTime>,
     cur_watermark: Option<Watermark>,
     watermarks: Vec<Option<Watermark>>,
+    gauge: Option<IntGauge>,
 }
 
 impl WatermarkHolder {
@@ -43,6 +46,7 @@ impl WatermarkHolder {
             last_present_watermark: None,
             cur_watermark: None,
             watermarks,
+            gauge: None,
         };
         s.update_watermark();
 
@@ -57,6 +61,14 @@ impl WatermarkHolder {
         self.last_present_watermark
     }
 
+    /// Reports the current event-time watermark (in millis since the epoch) to `gauge`
+    pub fn report_to(&mut self, gauge: Option<IntGauge>) {
+        self.gauge = gauge;
+        if let (Some(gauge), Some(t)) = (&self.gauge, self.last_present_watermark) {
+            gauge.set(to_millis(t) as i64);
+        }
+    }
+
     fn update_watermark(&mut self) {
         self.cur_watermark =
             self.watermarks
@@ -72,6 +84,9 @@ impl WatermarkHolder {
 
         if let Some(Watermark::EventTime(t)) = self.cur_watermark {
             self.last_present_watermark = Some(t);
+            if let Some(gauge) = &self.gauge {
+                gauge.set(to_millis(t) as i64);
+            }
         }
     }
 
@@ -248,6 +263,8 @@ pub struct ArrowContext {
     pub error_reporter: ErrorReporter,
     pub watermarks: WatermarkHolder,
     pub in_schemas: Vec<ArroyoSchema>,
+    /// The number of partitions of each of the inputs in `in_schemas`
+    pub input_partitions: Vec<usize>,
     pub out_schema: Option<ArroyoSchema>,
     pub collector: ArrowCollector,
     buffer: Option<ContextBuffer>,
@@ -286,6 +303,17 @@ pub struct ArrowCollector {
     tx_queue_rem_gauges: QueueGauges,
     tx_queue_size_gauges: QueueGauges,
     tx_queue_bytes_gauges: QueueGauges,
+    output_watermark_gauge: Option<IntGauge>,
+    late_data: Option<LateDataOutput>,
+}
+
+/// The side output that an operator sends the rows it was too late to process to, if the
+/// pipeline routes them to a sink
+#[derive(Clone)]
+pub struct LateDataOutput {
+    schema: ArroyoSchema,
+    projection: Option<Vec<usize>>,
+    qs: Vec<BatchSender>,
 }
 
 fn repartition<'a>(
@@ -344,6 +372,23 @@ fn repartition<'a>(
     }
 }
 
+/// Returns the rows of the batch whose keys are routed to this subtask
+fn filter_for_subtask(batch: &RecordBatch, keys: &[usize], task_info: &TaskInfo) -> RecordBatch {
+    let keys: Vec<_> = keys.iter().map(|i| batch.column(*i).clone()).collect();
+    let mut hashes = vec![0; batch.num_rows()];
+    hash_utils::create_hashes(&keys[..], &get_hasher(), &mut hashes).unwrap();
+
+    let servers =
+        server_for_hash_array(&PrimitiveArray::from(hashes), task_info.parallelism).unwrap();
+    let mask = eq(
+        &servers,
+        &UInt64Array::new_scalar(task_info.task_index as u64),
+    )
+    .unwrap();
+
+    filter_record_batch(batch, &mask).unwrap()
+}
+
 impl ArrowCollector {
     pub async fn collect(&mut self, record: RecordBatch) {
         TaskCounters::MessagesSent
@@ -398,7 +443,48 @@ impl ArrowCollector {
         }
     }
 
+    /// Sends rows that were too late to be processed to the late-data side output, if there is one
+    pub async fn collect_late(&mut self, record: RecordBatch) {
+        let Some(late_data) = &self.late_data else {
+            return;
+        };
+
+        let record = if let Some(projection) = &late_data.projection {
+            record.project(projection).unwrap_or_else(|e| {
+                panic!(
+                    "failed to project late data for operator {}: {}",
+                    self.task_info.operator_id, e
+                )
+            })
+        } else {
+            record
+        };
+
+        let record = RecordBatch::try_new(late_data.schema.schema.clone(), record.columns().to_vec())
+            .unwrap_or_else(|e| {
+                panic!(
+                    "Late data does not match expected schema for {}: {:?}. expected schema:\n{:#?}\n, actual schema:\n{:#?}",
+                    self.task_info.operator_id, e, late_data.schema.schema, record.schema()
+                );
+            });
+
+        for (partition, batch) in repartition(&record, &None, late_data.qs.len()) {
+            late_data.qs[partition]
+                .send(ArrowMessage::Data(batch))
+                .await
+                .unwrap();
+        }
+    }
+
     pub async fn broadcast(&mut self, message: ArrowMessage) {
+        if let (
+            ArrowMessage::Signal(SignalMessage::Watermark(Watermark::EventTime(t))),
+            Some(gauge),
+        ) = (&message, &self.output_watermark_gauge)
+        {
+            gauge.set(to_millis(*t) as i64);
+        }
+
         for out_node in &self.out_qs {
             for q in out_node {
                 q.send(message.clone()).await.unwrap_or_else(|e| {
@@ -409,6 +495,16 @@ impl ArrowCollector {
                 });
             }
         }
+
+        // the late-data sink needs watermarks and barriers like any other downstream operator
+        for q in self.late_data.iter().flat_map(|l| &l.qs) {
+            q.send(message.clone()).await.unwrap_or_else(|e| {
+                panic!(
+                    "failed to broadcast message <{:?}> to late data output for operator {}: {}",
+                    message, self.task_info.operator_id, e
+                )
+            });
+        }
     }
 }
 
@@ -473,6 +569,22 @@ impl ArrowContext {
             &out_qs,
         );
 
+        let mut watermarks =
+            WatermarkHolder::new(vec![watermark.map(Watermark::EventTime); input_partitions]);
+        watermarks.report_to(gauge_for_task(
+            &task_info,
+            INPUT_WATERMARK,
+            "Current input watermark of this subtask, in milliseconds since the epoch",
+            HashMap::new(),
+        ));
+
+        let output_watermark_gauge = gauge_for_task(
+            &task_info,
+            OUTPUT_WATERMARK,
+            "Last watermark emitted by this subtask, in milliseconds since the epoch",
+            HashMap::new(),
+        );
+
         let task_info = Arc::new(task_info);
 
         let table_manager =
@@ -484,11 +596,9 @@ impl ArrowContext {
             task_info: task_info.clone(),
             control_rx,
             control_tx: control_tx.clone(),
-            watermarks: WatermarkHolder::new(vec![
-                watermark.map(Watermark::EventTime);
-                input_partitions
-            ]),
+            watermarks,
             in_schemas,
+            input_partitions: vec![input_partitions],
             out_schema: out_schema.clone(),
             collector: ArrowCollector {
                 task_info: task_info.clone(),
@@ -496,6 +606,8 @@ impl ArrowContext {
                 tx_queue_rem_gauges,
                 tx_queue_size_gauges,
                 tx_queue_bytes_gauges,
+                output_watermark_gauge,
+                late_data: None,
                 out_schema: out_schema.clone(),
                 projection,
             },
@@ -586,6 +698,37 @@ impl ArrowContext {
         self.collector.collect(record).await;
     }
 
+    /// Sets the number of partitions of each of the inputs in `in_schemas`
+    pub fn set_input_partitions(&mut self, input_partitions: Vec<usize>) {
+        assert_eq!(input_partitions.len(), self.in_schemas.len());
+        self.input_partitions = input_partitions;
+    }
+
+    /// Sets the queues that rows passed to `collect_late` are sent to
+    pub fn set_late_data_output(
+        &mut self,
+        schema: ArroyoSchema,
+        projection: Option<Vec<usize>>,
+        qs: Vec<BatchSender>,
+    ) {
+        self.collector.late_data = Some(LateDataOutput {
+            schema,
+            projection,
+            qs,
+        });
+    }
+
+    /// Records rows that arrived too late to be processed by this operator, sending them to the
+    /// late-data side output if the pipeline has one
+    pub async fn collect_late(&mut self, record: RecordBatch) {
+        if record.num_rows() == 0 {
+            return;
+        }
+        TaskCounters::LateRowsDropped
+            .for_task(&self.task_info, |c| c.inc_by(record.num_rows() as u64));
+        self.collector.collect_late(record).await;
+    }
+
     pub fn should_flush(&self) -> bool {
         self.buffer
             .as_ref()
@@ -640,6 +783,48 @@ impl ArrowContext {
             .unwrap();
     }
 
+    /// Returns the data that was in flight when the unaligned checkpoint this task was restored
+    /// from was taken, as (input index, batch) pairs. If the operator has been rescaled, only
+    /// the data that would now be routed to this subtask is returned.
+    pub async fn take_in_flight_data(&mut self) -> Vec<(usize, RecordBatch)> {
+        let restored = self
+            .table_manager
+            .take_in_flight_data(&self.input_partitions)
+            .await
+            .expect("should be able to read in-flight data");
+
+        if !restored.rescaled {
+            return restored
+                .batches
+                .into_iter()
+                .map(|b| (b.input_index, b.batch))
+                .collect();
+        }
+
+        restored
+            .batches
+            .into_iter()
+            .filter_map(|b| {
+                match self
+                    .in_schemas
+                    .get(b.input)
+                    .and_then(|s| s.key_indices.as_ref())
+                    .filter(|k| !k.is_empty())
+                {
+                    Some(keys) => {
+                        let batch = filter_for_subtask(&b.batch, keys, &self.task_info);
+                        (batch.num_rows() > 0).then_some((b.input_index, batch))
+                    }
+                    // unkeyed data can be processed by any subtask, so we just need to ensure
+                    // each batch is replayed exactly once
+                    None => (b.subtask_index % self.task_info.parallelism
+                        == self.task_info.task_index)
+                        .then_some((b.input_index, b.batch)),
+                }
+            })
+            .collect()
+    }
+
     pub async fn load_compacted(&mut self, compaction: CompactionResult) {
         //TODO: support compaction in the table manager
         self.table_manager
@@ -850,6 +1035,8 @@ mod tests {
             tx_queue_rem_gauges,
             tx_queue_size_gauges,
             tx_queue_bytes_gauges,
+            output_watermark_gauge: None,
+            late_data: None,
         };
 
         collector.collect(record).await;
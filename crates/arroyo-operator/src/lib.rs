@@ -6,11 +6,14 @@ use std::time::{Duration, Instant};
 use std::{collections::HashSet, time::SystemTime};
 
 use crate::inq_reader::InQReader;
-use arrow::array::types::{TimestampNanosecondType, UInt64Type};
-use arrow::array::{Array, PrimitiveArray, RecordBatch, UInt64Array};
-use arrow::compute::kernels::numeric::{div, rem};
-use arroyo_types::{ArrowMessage, CheckpointBarrier, Data, SignalMessage, TaskInfoRef};
+use arrow::array::types::TimestampNanosecondType;
+use arrow::array::{Array, PrimitiveArray, RecordBatch};
+use arroyo_state::in_flight::InFlightBatches;
+use arroyo_types::{
+    ArrowMessage, CheckpointAlignment, CheckpointBarrier, Data, SignalMessage, TaskInfoRef,
+};
 use bincode::{Decode, Encode};
+use tokio::sync::oneshot;
 
 use crate::context::ArrowContext;
 use crate::operator::Registry;
@@ -23,23 +26,12 @@ pub mod inq_reader;
 pub mod operator;
 pub mod udfs;
 
+pub use arroyo_rpc::df::server_for_hash_array;
+
 pub trait TimerT: Data + PartialEq + Eq + 'static {}
 
 impl<T: Data + PartialEq + Eq + 'static> TimerT for T {}
 
-pub fn server_for_hash_array(
-    hash: &PrimitiveArray<UInt64Type>,
-    n: usize,
-) -> anyhow::Result<PrimitiveArray<UInt64Type>> {
-    let range_size = u64::MAX / (n as u64);
-    let range_scalar = UInt64Array::new_scalar(range_size);
-    let server_scalar = UInt64Array::new_scalar(n as u64);
-    let division = div(hash, &range_scalar)?;
-    let mod_array = rem(&division, &server_scalar)?;
-    let result: &PrimitiveArray<UInt64Type> = mod_array.as_any().downcast_ref().unwrap();
-    Ok(result.clone())
-}
-
 pub enum SourceFinishType {
     // stop messages should be propagated through the dataflow
     Graceful,
@@ -70,6 +62,18 @@ pub enum ControlOutcome {
 pub struct CheckpointCounter {
     inputs: Vec<Option<u32>>,
     counter: Option<usize>,
+    // the barrier currently being aligned, and when its first instance arrived
+    aligning: Option<(CheckpointBarrier, Instant)>,
+    unaligned: Option<UnalignedCheckpoint>,
+}
+
+/// An unaligned checkpoint for which the task has already checkpointed its state, but has not yet
+/// received the barrier on all of its inputs. Data that arrives on those inputs before their
+/// barriers is recorded, as it is not reflected in the checkpointed state.
+#[derive(Debug)]
+struct UnalignedCheckpoint {
+    data: InFlightBatches,
+    tx: oneshot::Sender<InFlightBatches>,
 }
 
 impl CheckpointCounter {
@@ -77,17 +81,81 @@ impl CheckpointCounter {
         CheckpointCounter {
             inputs: vec![None; size],
             counter: None,
+            aligning: None,
+            unaligned: None,
         }
     }
 
     pub fn is_blocked(&self, idx: usize) -> bool {
-        self.inputs[idx].is_some()
+        self.unaligned.is_none() && self.inputs[idx].is_some()
     }
 
     pub fn all_clear(&self) -> bool {
         self.inputs.iter().all(|x| x.is_none())
     }
 
+    pub fn is_unaligned(&self) -> bool {
+        self.unaligned.is_some()
+    }
+
+    /// Whether a checkpoint started by this barrier should be unaligned
+    pub fn should_start_unaligned(&self, checkpoint: &CheckpointBarrier) -> bool {
+        self.inputs.len() > 1
+            && !checkpoint.then_stop
+            && checkpoint.alignment == CheckpointAlignment::Unaligned
+    }
+
+    /// Returns the time at which the checkpoint currently being aligned should switch to
+    /// unaligned, if its alignment has a timeout
+    pub fn alignment_deadline(&self) -> Option<Instant> {
+        if self.unaligned.is_some() {
+            return None;
+        }
+
+        let (barrier, started) = self.aligning?;
+        match barrier.alignment {
+            CheckpointAlignment::AlignedWithTimeout(timeout) if !barrier.then_stop => {
+                Some(started + timeout)
+            }
+            _ => None,
+        }
+    }
+
+    /// The barrier of the checkpoint that is currently being aligned
+    pub fn aligning_barrier(&self) -> Option<CheckpointBarrier> {
+        self.aligning.map(|(barrier, _)| barrier)
+    }
+
+    /// Switches the current checkpoint to unaligned. The returned receiver resolves to the data
+    /// received ahead of the barriers once they have arrived on all inputs. `input_partitions`
+    /// is the number of partitions of each of the task's inputs.
+    pub fn start_unaligned(
+        &mut self,
+        input_partitions: &[usize],
+    ) -> oneshot::Receiver<InFlightBatches> {
+        assert!(self.unaligned.is_none(), "checkpoint is already unaligned");
+        assert_eq!(input_partitions.iter().sum::<usize>(), self.inputs.len());
+        let (tx, rx) = oneshot::channel();
+        self.unaligned = Some(UnalignedCheckpoint {
+            data: InFlightBatches {
+                input_partitions: input_partitions.to_vec(),
+                batches: vec![],
+            },
+            tx,
+        });
+        rx
+    }
+
+    /// Records a batch received on an input; this is only retained if an unaligned checkpoint is
+    /// in progress and the barrier has not yet arrived on that input
+    pub fn record(&mut self, idx: usize, batch: &RecordBatch) {
+        if let Some(unaligned) = &mut self.unaligned {
+            if self.inputs[idx].is_none() {
+                unaligned.data.batches.push((idx, batch.clone()));
+            }
+        }
+    }
+
     pub fn mark(&mut self, idx: usize, checkpoint: &CheckpointBarrier) -> bool {
         assert!(self.inputs[idx].is_none());
 
@@ -97,11 +165,20 @@ impl CheckpointCounter {
 
         self.inputs[idx] = Some(checkpoint.epoch);
         self.counter = match self.counter {
-            None => Some(self.inputs.len() - 1),
+            None => {
+                self.aligning = Some((*checkpoint, Instant::now()));
+                Some(self.inputs.len() - 1)
+            }
             Some(1) => {
                 for v in self.inputs.iter_mut() {
                     *v = None;
                 }
+                self.aligning = None;
+                if let Some(unaligned) = self.unaligned.take() {
+                    // the receiver is dropped if the checkpoint failed, which will be reported
+                    // by the state backend
+                    let _ = unaligned.tx.send(unaligned.data);
+                }
                 None
             }
             Some(n) => Some(n - 1),
@@ -188,3 +265,101 @@ impl RateLimiter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::Int64Array;
+
+    fn barrier(alignment: CheckpointAlignment) -> CheckpointBarrier {
+        CheckpointBarrier {
+            epoch: 1,
+            min_epoch: 0,
+            timestamp: SystemTime::now(),
+            then_stop: false,
+            alignment,
+        }
+    }
+
+    fn batch(v: i64) -> RecordBatch {
+        RecordBatch::try_from_iter(vec![(
+            "v",
+            Arc::new(Int64Array::from(vec![v])) as Arc<dyn Array>,
+        )])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_aligned_checkpoint_blocks_inputs() {
+        let b = barrier(CheckpointAlignment::Aligned);
+        let mut counter = CheckpointCounter::new(2);
+        assert!(!counter.should_start_unaligned(&b));
+
+        assert!(!counter.mark(0, &b));
+        assert!(counter.is_blocked(0));
+        assert!(!counter.is_blocked(1));
+        assert_eq!(counter.alignment_deadline(), None);
+
+        assert!(counter.mark(1, &b));
+        assert!(counter.all_clear());
+        assert!(!counter.is_blocked(0));
+    }
+
+    #[test]
+    fn test_unaligned_checkpoint_records_in_flight_data() {
+        let b = barrier(CheckpointAlignment::Unaligned);
+        let mut counter = CheckpointCounter::new(3);
+        assert!(counter.should_start_unaligned(&b));
+
+        assert!(!counter.mark(1, &b));
+        let mut rx = counter.start_unaligned(&[1, 2]);
+        assert!(!counter.is_blocked(1));
+
+        // data after the barrier on input 1 is reflected in the next checkpoint
+        counter.record(1, &batch(1));
+        counter.record(0, &batch(2));
+        counter.record(2, &batch(3));
+
+        assert!(!counter.mark(0, &b));
+        counter.record(0, &batch(4));
+        counter.record(2, &batch(5));
+        assert!(rx.try_recv().is_err());
+
+        assert!(counter.mark(2, &b));
+        assert!(!counter.is_unaligned());
+
+        let in_flight = rx.try_recv().unwrap();
+        assert_eq!(in_flight.input_partitions, vec![1, 2]);
+        assert_eq!(
+            in_flight.batches,
+            vec![(0, batch(2)), (2, batch(3)), (2, batch(5))]
+        );
+    }
+
+    #[test]
+    fn test_alignment_timeout() {
+        let timeout = Duration::from_secs(10);
+        let b = barrier(CheckpointAlignment::AlignedWithTimeout(timeout));
+        let mut counter = CheckpointCounter::new(2);
+        assert!(!counter.should_start_unaligned(&b));
+
+        let before = Instant::now();
+        counter.mark(0, &b);
+        let deadline = counter.alignment_deadline().unwrap();
+        assert!(deadline >= before + timeout);
+        assert_eq!(counter.aligning_barrier(), Some(b));
+
+        let _rx = counter.start_unaligned(&[1, 1]);
+        assert_eq!(counter.alignment_deadline(), None);
+        assert!(!counter.is_blocked(0));
+
+        // stopping checkpoints are always aligned
+        let mut counter = CheckpointCounter::new(2);
+        let stopping = CheckpointBarrier {
+            then_stop: true,
+            ..b
+        };
+        counter.mark(0, &stopping);
+        assert_eq!(counter.alignment_deadline(), None);
+    }
+}
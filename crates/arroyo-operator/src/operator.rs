@@ -9,6 +9,7 @@ use arroyo_datastream::logical::DylibUdfConfig;
 use arroyo_metrics::TaskCounters;
 use arroyo_rpc::grpc::{TableConfig, TaskCheckpointEventType};
 use arroyo_rpc::{ControlMessage, ControlResp};
+use arroyo_state::in_flight::InFlightBatches;
 use arroyo_storage::StorageProvider;
 use arroyo_types::{ArrowMessage, CheckpointBarrier, SignalMessage, Watermark};
 use arroyo_udf_host::parse::inner_type;
@@ -29,7 +30,7 @@ use std::path::Path;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
-use tokio::sync::Barrier;
+use tokio::sync::{oneshot, Barrier};
 use tokio_stream::StreamExt;
 use tracing::{debug, error, info, warn, Instrument};
 
@@ -130,11 +131,15 @@ impl OperatorNode {
     }
 }
 
-async fn run_checkpoint(checkpoint_barrier: CheckpointBarrier, ctx: &mut ArrowContext) -> bool {
+async fn run_checkpoint(
+    checkpoint_barrier: CheckpointBarrier,
+    ctx: &mut ArrowContext,
+    in_flight: Option<oneshot::Receiver<InFlightBatches>>,
+) -> bool {
     let watermark = ctx.watermarks.last_present_watermark();
 
     ctx.table_manager
-        .checkpoint(checkpoint_barrier, watermark)
+        .checkpoint(checkpoint_barrier, watermark, in_flight)
         .await;
 
     ctx.send_checkpoint_event(checkpoint_barrier, TaskCheckpointEventType::FinishedSync)
@@ -175,10 +180,57 @@ pub trait SourceOperator: Send + 'static {
         )
         .await;
 
-        run_checkpoint(checkpoint_barrier, ctx).await
+        run_checkpoint(checkpoint_barrier, ctx, None).await
     }
 }
 
+/// Checkpoints the operator's state and forwards the barrier. For unaligned checkpoints this
+/// happens before the barrier has arrived on all inputs, and `in_flight` resolves to the data
+/// received ahead of the remaining barriers.
+async fn checkpoint_operator<O: ArrowOperator + ?Sized>(
+    operator: &mut O,
+    barrier: CheckpointBarrier,
+    in_flight: Option<oneshot::Receiver<InFlightBatches>>,
+    ctx: &mut ArrowContext,
+) -> bool {
+    debug!(
+        "Checkpointing {}-{}-{}",
+        operator.name(),
+        ctx.task_info.operator_id,
+        ctx.task_info.task_index
+    );
+
+    ctx.send_checkpoint_event(barrier, TaskCheckpointEventType::StartedCheckpointing)
+        .await;
+
+    operator.handle_checkpoint(barrier, ctx).await;
+
+    ctx.send_checkpoint_event(barrier, TaskCheckpointEventType::FinishedOperatorSetup)
+        .await;
+
+    run_checkpoint(barrier, ctx, in_flight).await
+}
+
+/// Switches a checkpoint that has been aligning for longer than its timeout to unaligned,
+/// checkpointing immediately
+async fn switch_to_unaligned<O: ArrowOperator + ?Sized>(
+    operator: &mut O,
+    counter: &mut CheckpointCounter,
+    ctx: &mut ArrowContext,
+) {
+    let Some(barrier) = counter.aligning_barrier() else {
+        return;
+    };
+
+    info!(
+        "Alignment for checkpoint {} in {}-{} timed out; switching to unaligned checkpoint",
+        barrier.epoch, ctx.task_info.operator_id, ctx.task_info.task_index
+    );
+
+    let in_flight = counter.start_unaligned(&ctx.input_partitions);
+    checkpoint_operator(operator, barrier, Some(in_flight), ctx).await;
+}
+
 async fn operator_run_behavior(
     this: &mut Box<dyn ArrowOperator + Send>,
     ctx: &mut ArrowContext,
@@ -216,8 +268,17 @@ async fn operator_run_behavior(
         tokio::time::interval(this.tick_interval().unwrap_or(Duration::from_secs(60)));
     interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
+    // replay any data that was in flight when the checkpoint we restored from was taken
+    for (idx, batch) in ctx.take_in_flight_data().await {
+        this.process_batch_index(idx, in_partitions, batch, ctx).await;
+    }
+
     loop {
         let operator_future: OptionFuture<_> = this.future_to_poll().into();
+        let alignment_timeout: OptionFuture<_> = counter
+            .alignment_deadline()
+            .map(|deadline| tokio::time::sleep_until(deadline.into()))
+            .into();
         tokio::select! {
             Some(control_message) = ctx.control_rx.recv() => {
                 this.handle_controller_message(control_message, ctx).await;
@@ -233,6 +294,7 @@ async fn operator_run_behavior(
 
                         match message {
                             ArrowMessage::Data(record) => {
+                                counter.record(idx, &record);
                                 TaskCounters::BatchesReceived.for_task(&ctx.task_info, |c| c.inc());
                                 TaskCounters::MessagesReceived.for_task(&ctx.task_info, |c| c.inc_by(record.num_rows() as u64));
                                 TaskCounters::BytesReceived.for_task(&ctx.task_info, |c| c.inc_by(record.get_array_memory_size() as u64));
@@ -283,6 +345,12 @@ async fn operator_run_behavior(
             Some(val) = operator_future => {
                 this.handle_future_result(val, ctx).await;
             }
+            Some(_) = alignment_timeout => {
+                switch_to_unaligned(this.as_mut(), &mut counter, ctx).await;
+                for q in blocked.drain(..) {
+                    sel.push(q);
+                }
+            }
             _ = interval.tick() => {
                 this.handle_tick(ticks, ctx).await;
                 ticks += 1;
@@ -359,7 +427,21 @@ pub trait ArrowOperator: Send + 'static {
                     idx
                 );
 
+                if counter.is_unaligned() {
+                    // we've already checkpointed; this barrier marks the end of the in-flight
+                    // data for this input
+                    counter.mark(idx, t);
+                    return ControlOutcome::Continue;
+                }
+
                 if counter.all_clear() {
+                    if counter.should_start_unaligned(t) {
+                        counter.mark(idx, t);
+                        let in_flight = counter.start_unaligned(&ctx.input_partitions);
+                        checkpoint_operator(self, *t, Some(in_flight), ctx).await;
+                        return ControlOutcome::Continue;
+                    }
+
                     ctx.control_tx
                         .send(ControlResp::CheckpointEvent(arroyo_rpc::CheckpointEvent {
                             checkpoint_epoch: t.epoch,
@@ -372,25 +454,8 @@ pub trait ArrowOperator: Send + 'static {
                         .unwrap();
                 }
 
-                if counter.mark(idx, t) {
-                    debug!(
-                        "Checkpointing {}-{}-{}",
-                        self.name(),
-                        ctx.task_info.operator_id,
-                        ctx.task_info.task_index
-                    );
-
-                    ctx.send_checkpoint_event(*t, TaskCheckpointEventType::StartedCheckpointing)
-                        .await;
-
-                    self.handle_checkpoint(*t, ctx).await;
-
-                    ctx.send_checkpoint_event(*t, TaskCheckpointEventType::FinishedOperatorSetup)
-                        .await;
-
-                    if run_checkpoint(*t, ctx).await {
-                        return ControlOutcome::Stop;
-                    }
+                if counter.mark(idx, t) && checkpoint_operator(self, *t, None, ctx).await {
+                    return ControlOutcome::Stop;
                 }
             }
             SignalMessage::Watermark(watermark) => {
@@ -0,0 +1,44 @@
+use crate::api_types::auth::Role;
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use utoipa::ToSchema;
+
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, ToSchema, Hash, PartialEq, Eq, Display, EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum AuditAction {
+    CreatePipeline,
+    UpdatePipeline,
+    RestartPipeline,
+    DeletePipeline,
+    UpdateCheckpoint,
+    RestoreCheckpoint,
+    CreateConnectionProfile,
+    UpdateConnectionProfile,
+    DeleteConnectionProfile,
+    CreateConnectionTable,
+    UpdateConnectionTable,
+    DeleteConnectionTable,
+    CreateUdf,
+    DeleteUdf,
+    CreateApiToken,
+    RevokeApiToken,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditLogEntry {
+    pub id: String,
+    /// The user that performed the action
+    pub actor: String,
+    pub actor_role: Role,
+    pub action: AuditAction,
+    /// The id of the resource the action was performed on
+    pub target_id: String,
+    /// The request that was made, if the action had a body, or for updates the fields that
+    /// changed with their values before and after; credentials are redacted
+    pub request: Option<serde_json::Value>,
+    pub created_at: u64,
+}
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+use utoipa::ToSchema;
+
+/// Roles are ordered by privilege; each role may do everything the roles below it can
+#[derive(
+    Serialize,
+    Deserialize,
+    Copy,
+    Clone,
+    Debug,
+    ToSchema,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Display,
+    EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Role {
+    /// May read pipelines, jobs, metrics, and output
+    Viewer,
+    /// May additionally create and modify pipelines, connection tables, and UDFs
+    Editor,
+    /// May additionally manage connection profiles and API tokens
+    Admin,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiTokenPost {
+    pub name: String,
+    /// Role granted to requests made with this token; defaults to the role of the creator
+    pub role: Option<Role>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub user_id: String,
+    pub role: Role,
+    /// The first few characters of the token, to help identify it
+    pub prefix: String,
+    pub created_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedApiToken {
+    pub token: ApiToken,
+    /// The secret token; this is only returned on creation and cannot be retrieved later
+    pub secret: String,
+}
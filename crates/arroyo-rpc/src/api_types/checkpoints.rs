@@ -4,10 +4,88 @@ use utoipa::ToSchema;
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Checkpoint {
+    pub id: String,
     pub epoch: u32,
     pub backend: String,
     pub start_time: u64,
     pub finish_time: Option<u64>,
+    /// Pinned checkpoints are never deleted by the retention policy
+    pub pinned: bool,
+    /// The number of bytes of state written by this checkpoint
+    pub bytes: u64,
+    /// The location of the checkpoint in the checkpoint storage
+    pub storage_url: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointPatch {
+    pub pinned: bool,
+}
+
+/// Determines which checkpoints are retained for a pipeline; older checkpoints are deleted
+/// once they are no longer needed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointRetention {
+    /// The number of most recent checkpoints to keep
+    #[serde(default = "default_keep_last")]
+    pub keep_last: u32,
+    /// If set, the first checkpoint of each hour is also kept for this many days
+    pub keep_hourly_days: Option<u32>,
+}
+
+fn default_keep_last() -> u32 {
+    4
+}
+
+impl Default for CheckpointRetention {
+    fn default() -> Self {
+        Self {
+            keep_last: default_keep_last(),
+            keep_hourly_days: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckpointAlignmentMode {
+    /// Operators with multiple inputs wait for the barrier to arrive on all inputs before
+    /// checkpointing
+    #[default]
+    Aligned,
+    /// Operators checkpoint as soon as the first barrier arrives, persisting data received ahead
+    /// of the barriers on their other inputs
+    Unaligned,
+    /// Checkpoints are aligned, but switch to unaligned if alignment takes longer than
+    /// `alignment_timeout_micros`
+    Auto,
+}
+
+/// Determines how operators with multiple inputs handle checkpoint barriers. Unaligned
+/// checkpoints don't wait for the barriers on the slower inputs, at the cost of writing the data
+/// received on them. Barriers don't overtake data that is already queued ahead of them, so the
+/// first barrier to arrive still waits behind the data queued on its input.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointAlignmentConfig {
+    pub mode: CheckpointAlignmentMode,
+    #[serde(default = "default_alignment_timeout_micros")]
+    pub alignment_timeout_micros: u64,
+}
+
+fn default_alignment_timeout_micros() -> u64 {
+    30_000_000
+}
+
+impl Default for CheckpointAlignmentConfig {
+    fn default() -> Self {
+        Self {
+            mode: CheckpointAlignmentMode::default(),
+            alignment_timeout_micros: default_alignment_timeout_micros(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -46,6 +46,36 @@ pub struct ConnectionProfilePost {
     pub config: serde_json::Value,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionProfilePatch {
+    /// The new config; redacted values are replaced with the currently stored values
+    pub config: serde_json::Value,
+    /// Whether running pipelines that use the profile should be restarted from a checkpoint to
+    /// pick up the new config
+    pub restart_pipelines: Option<bool>,
+}
+
+/// A pipeline that uses an updated connection profile or table
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AffectedPipeline {
+    pub id: String,
+    pub name: String,
+    pub state: Option<String>,
+    /// Whether the pipeline was updated to use the new config, which takes effect the next
+    /// time it is started or restarted
+    pub updated: bool,
+    pub restarted: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionProfileUpdate {
+    pub profile: ConnectionProfile,
+    pub pipelines: Vec<AffectedPipeline>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum ConnectionType {
@@ -326,6 +356,23 @@ pub struct ConnectionTablePost {
     pub schema: Option<ConnectionSchema>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionTablePatch {
+    pub config: Option<serde_json::Value>,
+    pub schema: Option<ConnectionSchema>,
+    /// Whether running pipelines that use the table should be restarted from a checkpoint to
+    /// pick up the new config
+    pub restart_pipelines: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionTableUpdate {
+    pub table: ConnectionTable,
+    pub pipelines: Vec<AffectedPipeline>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ConnectionAutocompleteResp {
@@ -15,6 +15,14 @@ pub enum MetricName {
     Backpressure,
     TxQueueSize,
     TxQueueRem,
+    /// The subtask's current input watermark, in microseconds since the epoch
+    InputWatermark,
+    /// The last watermark emitted by the subtask, in microseconds since the epoch
+    OutputWatermark,
+    /// The maximum event time observed by a source, in microseconds since the epoch
+    MaxEventTime,
+    /// 1 if the source has been marked idle, otherwise 0
+    SourceIdle,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -1,3 +1,5 @@
+use audit::*;
+use auth::*;
 use checkpoints::*;
 use connections::*;
 use metrics::*;
@@ -7,9 +9,12 @@ use udfs::*;
 use serde::{Deserialize, Serialize};
 use utoipa::{IntoParams, ToSchema};
 
+pub mod audit;
+pub mod auth;
 pub mod checkpoints;
 pub mod connections;
 pub mod metrics;
+pub mod notifications;
 pub mod pipelines;
 pub mod udfs;
 
@@ -19,6 +24,7 @@ pub mod udfs;
     PipelineCollection = PaginatedCollection<Pipeline>,
     JobLogMessageCollection = PaginatedCollection<JobLogMessage>,
     ConnectionTableCollection = PaginatedCollection<ConnectionTable>,
+    AuditLogCollection = PaginatedCollection<AuditLogEntry>,
 )]
 pub struct PaginatedCollection<T> {
     pub data: Vec<T>,
@@ -35,6 +41,7 @@ pub struct PaginatedCollection<T> {
     ConnectorCollection = NonPaginatedCollection<Connector>,
     ConnectionProfileCollection = NonPaginatedCollection<ConnectionProfile>,
     GlobalUdfCollection = NonPaginatedCollection<GlobalUdf>,
+    ApiTokenCollection = NonPaginatedCollection<ApiToken>,
 )]
 pub struct NonPaginatedCollection<T> {
     pub data: Vec<T>,
@@ -47,3 +54,11 @@ pub struct PaginationQueryParams {
     pub starting_after: Option<String>,
     pub limit: Option<u32>,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, IntoParams, ToSchema)]
+#[into_params(parameter_in = Query)]
+#[serde(rename_all = "snake_case")]
+pub struct PipelineEventsQueryParams {
+    /// How often to send metric snapshots while the job is running; 0 disables them
+    pub metrics_interval_secs: Option<u64>,
+}
@@ -0,0 +1,200 @@
+use crate::api_types::pipelines::JobLogMessage;
+use crate::secrets::REDACTED;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// Configures where notifications are sent when a pipeline's job fails, restarts or recovers
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationConfig {
+    #[serde(default)]
+    pub channels: Vec<NotificationChannel>,
+    /// The events that trigger a notification; defaults to all of them
+    #[serde(default = "default_notification_events")]
+    pub events: Vec<JobNotificationEvent>,
+    /// Repeated notifications for the same event are suppressed for this long; the next
+    /// notification that is sent reports how many were suppressed
+    #[serde(default = "default_notification_debounce_secs")]
+    pub debounce_secs: u64,
+}
+
+fn default_notification_events() -> Vec<JobNotificationEvent> {
+    vec![
+        JobNotificationEvent::Failed,
+        JobNotificationEvent::Restarting,
+        JobNotificationEvent::Recovering,
+    ]
+}
+
+fn default_notification_debounce_secs() -> u64 {
+    5 * 60
+}
+
+impl NotificationConfig {
+    /// Replaces the credentials of the channels, Slack webhook urls and webhook header values,
+    /// with [`REDACTED`] before the config is returned from the API
+    pub fn redact(&mut self) {
+        for channel in &mut self.channels {
+            match channel {
+                NotificationChannel::Webhook { headers, .. } => {
+                    headers
+                        .values_mut()
+                        .for_each(|value| *value = REDACTED.to_string());
+                }
+                NotificationChannel::Slack { webhook_url } => *webhook_url = REDACTED.to_string(),
+            }
+        }
+    }
+
+    /// Restores values that were redacted by [`NotificationConfig::redact`] from `stored`,
+    /// matching channels by their position
+    pub fn restore_redacted(&mut self, stored: &NotificationConfig) {
+        for (channel, stored) in self.channels.iter_mut().zip(&stored.channels) {
+            match (channel, stored) {
+                (
+                    NotificationChannel::Webhook { headers, .. },
+                    NotificationChannel::Webhook {
+                        headers: stored_headers,
+                        ..
+                    },
+                ) => {
+                    for (name, value) in headers.iter_mut() {
+                        if *value == REDACTED {
+                            if let Some(stored) = stored_headers.get(name) {
+                                value.clone_from(stored);
+                            }
+                        }
+                    }
+                }
+                (
+                    NotificationChannel::Slack { webhook_url },
+                    NotificationChannel::Slack {
+                        webhook_url: stored_url,
+                    },
+                ) if *webhook_url == REDACTED => {
+                    webhook_url.clone_from(stored_url);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            channels: vec![],
+            events: default_notification_events(),
+            debounce_secs: default_notification_debounce_secs(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum NotificationChannel {
+    /// POSTs a `JobNotification` as JSON to the url
+    #[serde(rename_all = "camelCase")]
+    Webhook {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+    /// Posts a message to a Slack incoming webhook (or any service that accepts the same format)
+    #[serde(rename_all = "camelCase")]
+    Slack { webhook_url: String },
+}
+
+impl NotificationChannel {
+    pub fn url(&self) -> &str {
+        match self {
+            NotificationChannel::Webhook { url, .. } => url,
+            NotificationChannel::Slack { webhook_url } => webhook_url,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum JobNotificationEvent {
+    Failed,
+    Restarting,
+    Recovering,
+}
+
+impl JobNotificationEvent {
+    /// The event for a transition into the controller state `state`, if it has one
+    pub fn for_state(state: &str) -> Option<Self> {
+        match state {
+            "Failed" => Some(JobNotificationEvent::Failed),
+            "Restarting" => Some(JobNotificationEvent::Restarting),
+            "Recovering" => Some(JobNotificationEvent::Recovering),
+            _ => None,
+        }
+    }
+}
+
+/// The payload that is sent to webhook channels
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobNotification {
+    pub event: JobNotificationEvent,
+    pub pipeline_id: String,
+    pub pipeline_name: String,
+    pub job_id: String,
+    pub run_id: u64,
+    pub previous_state: String,
+    pub state: String,
+    pub failure_message: Option<String>,
+    pub restarts: u64,
+    /// The number of notifications for this event that were suppressed by debouncing since the
+    /// last one was sent
+    pub suppressed: u64,
+    pub timestamp: u64,
+    /// The most recent log messages for the job, newest first
+    pub recent_logs: Vec<JobLogMessage>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_and_restore() {
+        let stored = NotificationConfig {
+            channels: vec![
+                NotificationChannel::Webhook {
+                    url: "https://example.com/hook".to_string(),
+                    headers: [("Authorization".to_string(), "Bearer secret".to_string())]
+                        .into_iter()
+                        .collect(),
+                },
+                NotificationChannel::Slack {
+                    webhook_url: "https://hooks.slack.com/services/secret".to_string(),
+                },
+            ],
+            ..Default::default()
+        };
+
+        let mut redacted = stored.clone();
+        redacted.redact();
+        assert_eq!(
+            redacted.channels,
+            vec![
+                NotificationChannel::Webhook {
+                    url: "https://example.com/hook".to_string(),
+                    headers: [("Authorization".to_string(), REDACTED.to_string())]
+                        .into_iter()
+                        .collect(),
+                },
+                NotificationChannel::Slack {
+                    webhook_url: REDACTED.to_string(),
+                },
+            ]
+        );
+
+        redacted.restore_redacted(&stored);
+        assert_eq!(redacted, stored);
+    }
+}
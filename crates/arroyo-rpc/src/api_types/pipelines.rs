@@ -1,6 +1,10 @@
+use crate::api_types::checkpoints::{Checkpoint, CheckpointAlignmentConfig, CheckpointRetention};
+use crate::api_types::metrics::OperatorMetricGroup;
+use crate::api_types::notifications::NotificationConfig;
 use crate::api_types::udfs::Udf;
 use crate::grpc as grpc_proto;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use utoipa::ToSchema;
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -8,6 +12,8 @@ use utoipa::ToSchema;
 pub struct ValidateQueryPost {
     pub query: String,
     pub udfs: Option<Vec<Udf>>, // needed for query validation but are not themselves validated
+    /// The `updating_ttl_micros` the pipeline will be created with
+    pub updating_ttl_micros: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -15,6 +21,70 @@ pub struct ValidateQueryPost {
 pub struct QueryValidationResult {
     pub graph: Option<PipelineGraph>,
     pub errors: Vec<String>,
+    /// Set if the query was prefixed with `EXPLAIN` or `EXPLAIN VERBOSE`
+    pub explanation: Option<QueryExplanation>,
+}
+
+/// How an `EXPLAIN`ed query is planned, as a streaming dataflow
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryExplanation {
+    pub verbose: bool,
+    /// The logical plan of each query, after it has been rewritten into Arroyo extensions
+    pub logical_plans: Vec<String>,
+    /// The logical plan of each query as DataFusion produced it; only set for `EXPLAIN VERBOSE`
+    pub unrewritten_logical_plans: Option<Vec<String>>,
+    pub extensions: Vec<ExplainedExtension>,
+    pub operators: Vec<ExplainedOperator>,
+    pub edges: Vec<ExplainedEdge>,
+    /// All of the above, rendered as text
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplainedExtension {
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplainedOperator {
+    pub node_id: String,
+    pub operator: String,
+    pub description: String,
+    pub parallelism: u32,
+    /// How long the operator keeps state for keys or rows that aren't updated, for operators
+    /// whose state is bounded by a TTL rather than by the watermark
+    pub ttl_micros: Option<u64>,
+    /// The state tables of the operator; unset for connectors, which manage their own state
+    pub state_tables: Option<Vec<ExplainedStateTable>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplainedStateTable {
+    pub name: String,
+    pub description: String,
+    /// `global` or `expiring_time_key`
+    pub table_type: String,
+    /// How long rows are kept behind the watermark, for expiring tables
+    pub retention_micros: Option<u64>,
+    /// Whether only the latest row of each key is kept
+    pub generational: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplainedEdge {
+    pub src_id: String,
+    pub dest_id: String,
+    pub edge_type: String,
+    /// The fields the data is keyed by; empty for unkeyed data
+    pub key_fields: Vec<String>,
+    /// The fields of the data; only set for `EXPLAIN VERBOSE`
+    pub fields: Option<Vec<String>>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -25,15 +95,68 @@ pub struct PipelinePost {
     pub udfs: Option<Vec<Udf>>,
     pub preview: Option<bool>,
     pub parallelism: u64,
+    /// Parallelism for individual operators, keyed by node id, overriding `parallelism`
+    pub parallelism_overrides: Option<HashMap<String, u64>>,
     pub checkpoint_interval_micros: Option<u64>,
+    pub checkpoint_retention: Option<CheckpointRetention>,
+    pub checkpoint_alignment: Option<CheckpointAlignmentConfig>,
+    pub autoscaler: Option<AutoscalerConfig>,
+    pub notifications: Option<NotificationConfig>,
+    /// How long updating aggregates and non-windowed joins keep state; queries can override it
+    /// with `SET updating_ttl`
+    pub updating_ttl_micros: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PipelinePatch {
     pub parallelism: Option<u64>,
+    /// Parallelism for individual operators, keyed by node id; applied after `parallelism`
+    pub parallelism_overrides: Option<HashMap<String, u64>>,
     pub checkpoint_interval_micros: Option<u64>,
+    pub checkpoint_retention: Option<CheckpointRetention>,
+    pub checkpoint_alignment: Option<CheckpointAlignmentConfig>,
     pub stop: Option<StopType>,
+    pub autoscaler: Option<AutoscalerConfig>,
+    pub notifications: Option<NotificationConfig>,
+}
+
+/// Configuration for automatically adjusting the parallelism of a running pipeline based on
+/// the backpressure observed in its operators
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoscalerConfig {
+    pub enabled: bool,
+    pub min_parallelism: u64,
+    pub max_parallelism: u64,
+    /// The length of the window of metrics that each scaling decision is based on
+    #[serde(default = "default_autoscaler_window_secs")]
+    pub window_secs: u64,
+    /// The minimum amount of time after the job starts or is rescaled before it may be rescaled again
+    #[serde(default = "default_autoscaler_cooldown_secs")]
+    pub cooldown_secs: u64,
+    /// Scale up when the average backpressure of any operator over the window exceeds this value
+    #[serde(default = "default_scale_up_backpressure")]
+    pub scale_up_backpressure: f64,
+    /// Scale down when the average backpressure of all operators over the window is below this value
+    #[serde(default = "default_scale_down_backpressure")]
+    pub scale_down_backpressure: f64,
+}
+
+fn default_autoscaler_window_secs() -> u64 {
+    5 * 60
+}
+
+fn default_autoscaler_cooldown_secs() -> u64 {
+    10 * 60
+}
+
+fn default_scale_up_backpressure() -> f64 {
+    0.5
+}
+
+fn default_scale_down_backpressure() -> f64 {
+    0.05
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -50,6 +173,8 @@ pub struct Pipeline {
     pub query: String,
     pub udfs: Vec<Udf>,
     pub checkpoint_interval_micros: u64,
+    pub checkpoint_retention: CheckpointRetention,
+    pub checkpoint_alignment: CheckpointAlignmentConfig,
     pub stop: StopType,
     pub created_at: u64,
     pub action: Option<StopType>,
@@ -57,6 +182,11 @@ pub struct Pipeline {
     pub action_in_progress: bool,
     pub graph: PipelineGraph,
     pub preview: bool,
+    pub autoscaler: Option<AutoscalerConfig>,
+    pub notifications: NotificationConfig,
+    /// How long updating aggregates and non-windowed joins keep state, if it was set when the
+    /// pipeline was created
+    pub updating_ttl_micros: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
@@ -95,7 +225,7 @@ pub enum StopType {
     Force,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Job {
     pub id: String,
@@ -129,6 +259,37 @@ pub struct JobLogMessage {
     pub details: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobMetricsSnapshot {
+    pub job_id: String,
+    pub timestamp: u64,
+    pub data: Vec<OperatorMetricGroup>,
+}
+
+/// An event on a pipeline's event stream. The variant is sent as the SSE event type (in
+/// snake_case) and its contents as the event data.
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineEvent {
+    /// Sent when the stream is opened and whenever one of the pipeline's jobs changes
+    JobState(Job),
+    CheckpointCompleted(Checkpoint),
+    JobError(JobLogMessage),
+    Metrics(JobMetricsSnapshot),
+}
+
+impl PipelineEvent {
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            PipelineEvent::JobState(_) => "job_state",
+            PipelineEvent::CheckpointCompleted(_) => "checkpoint_completed",
+            PipelineEvent::JobError(_) => "job_error",
+            PipelineEvent::Metrics(_) => "metrics",
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct OutputData {
@@ -1,17 +1,19 @@
 use crate::grpc::api;
 use crate::{grpc, Converter, TIMESTAMP_FIELD};
 use anyhow::{anyhow, Result};
-use arrow::compute::kernels::numeric::div;
+use arrow::compute::kernels::numeric::{div, mul};
 use arrow::compute::{filter_record_batch, take};
 use arrow::datatypes::{DataType, Field, Schema, SchemaBuilder, TimeUnit};
 use arrow::row::SortField;
 use arrow_array::builder::{make_builder, ArrayBuilder};
 use arrow_array::types::UInt64Type;
-use arrow_array::{Array, PrimitiveArray, RecordBatch, TimestampNanosecondArray, UInt64Array};
-use arrow_ord::cmp::gt_eq;
+use arrow_array::{
+    Array, PrimitiveArray, RecordBatch, TimestampNanosecondArray, UInt32Array, UInt64Array,
+};
+use arrow_ord::cmp::{gt_eq, lt};
 use arrow_ord::partition::partition;
 use arrow_ord::sort::{lexsort_to_indices, SortColumn};
-use arroyo_types::to_nanos;
+use arroyo_types::{to_nanos, KEY_GROUPS};
 use std::ops::Range;
 use std::sync::Arc;
 use std::time::SystemTime;
@@ -226,16 +228,43 @@ impl ArroyoSchema {
             return Ok(batch);
         };
         // filter out late data
-        let timestamp_column = batch
-            .column(self.timestamp_index)
-            .as_any()
-            .downcast_ref::<TimestampNanosecondArray>()
-            .ok_or_else(|| anyhow!("failed to downcast column {} of {:?} to timestamp. Schema is supposed to be {:?}", self.timestamp_index, batch, self.schema))?;
+        let timestamp_column = self.try_timestamp_column(&batch)?;
         let cutoff_scalar = TimestampNanosecondArray::new_scalar(to_nanos(cutoff) as i64);
         let on_time = gt_eq(timestamp_column, &cutoff_scalar).unwrap();
         Ok(filter_record_batch(&batch, &on_time)?)
     }
 
+    /// Splits the batch into the rows at or after `cutoff` and the rows before it, so that
+    /// callers can account for the late rows rather than silently dropping them
+    pub fn split_by_time(
+        &self,
+        batch: RecordBatch,
+        cutoff: Option<SystemTime>,
+    ) -> anyhow::Result<(RecordBatch, RecordBatch)> {
+        let Some(cutoff) = cutoff else {
+            return Ok((batch.clone(), batch.slice(0, 0)));
+        };
+        let timestamp_column = self.try_timestamp_column(&batch)?;
+        let cutoff_scalar = TimestampNanosecondArray::new_scalar(to_nanos(cutoff) as i64);
+        let on_time = gt_eq(timestamp_column, &cutoff_scalar).unwrap();
+        let late = lt(timestamp_column, &cutoff_scalar).unwrap();
+        Ok((
+            filter_record_batch(&batch, &on_time)?,
+            filter_record_batch(&batch, &late)?,
+        ))
+    }
+
+    fn try_timestamp_column<'a>(
+        &self,
+        batch: &'a RecordBatch,
+    ) -> anyhow::Result<&'a TimestampNanosecondArray> {
+        batch
+            .column(self.timestamp_index)
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            .ok_or_else(|| anyhow!("failed to downcast column {} of {:?} to timestamp. Schema is supposed to be {:?}", self.timestamp_index, batch, self.schema))
+    }
+
     pub fn sort_columns(&self, batch: &RecordBatch, with_timestamp: bool) -> Vec<SortColumn> {
         let mut columns = vec![];
         if let Some(keys) = &self.key_indices {
@@ -335,6 +364,27 @@ impl ArroyoSchema {
         Ok(RecordBatch::try_new(batch.schema(), columns)?)
     }
 
+    /// Like `sort`, but rows that are equal on the sort columns keep their order in the batch
+    pub fn sort_stable(&self, batch: RecordBatch, with_timestamp: bool) -> Result<RecordBatch> {
+        if self.key_indices.is_none() && !with_timestamp {
+            return Ok(batch);
+        }
+        // lexsort isn't stable, so ties are broken by each row's position
+        let mut sort_columns = self.sort_columns(&batch, with_timestamp);
+        sort_columns.push(SortColumn {
+            values: Arc::new(UInt32Array::from_iter_values(0..batch.num_rows() as u32)),
+            options: None,
+        });
+        let sort_indices = lexsort_to_indices(&sort_columns, None)?;
+        let columns = batch
+            .columns()
+            .iter()
+            .map(|c| take(c, &sort_indices, None))
+            .collect::<std::result::Result<_, _>>()?;
+
+        Ok(RecordBatch::try_new(batch.schema(), columns)?)
+    }
+
     pub fn partition(
         &self,
         batch: &RecordBatch,
@@ -391,9 +441,12 @@ pub fn server_for_hash_array(
     hash: &PrimitiveArray<UInt64Type>,
     n: usize,
 ) -> anyhow::Result<PrimitiveArray<UInt64Type>> {
-    let range_size = u64::MAX / (n as u64) + 1;
-    let range_scalar = UInt64Array::new_scalar(range_size);
-    let division = div(hash, &range_scalar)?;
-    let result: &PrimitiveArray<UInt64Type> = division.as_any().downcast_ref().unwrap();
+    // first compute the key group for each hash, then map key groups onto servers; this must
+    // stay consistent with arroyo_types::server_for_hash
+    let group_size = u64::MAX / (KEY_GROUPS as u64) + 1;
+    let groups = div(hash, &UInt64Array::new_scalar(group_size))?;
+    let scaled = mul(&groups, &UInt64Array::new_scalar(n as u64))?;
+    let servers = div(&scaled, &UInt64Array::new_scalar(KEY_GROUPS as u64))?;
+    let result: &PrimitiveArray<UInt64Type> = servers.as_any().downcast_ref().unwrap();
     Ok(result.clone())
 }
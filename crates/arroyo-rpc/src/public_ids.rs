@@ -22,6 +22,7 @@ pub enum IdTypes {
     ConnectionTable,
     ConnectionTablePipeline,
     Udf,
+    AuditLogEntry,
 }
 
 pub fn generate_id(id_type: IdTypes) -> String {
@@ -38,6 +39,7 @@ pub fn generate_id(id_type: IdTypes) -> String {
         IdTypes::ConnectionTable => "ct",
         IdTypes::ConnectionTablePipeline => "ctp",
         IdTypes::Udf => "udf",
+        IdTypes::AuditLogEntry => "al",
     };
     let id = nanoid!(ID_LENGTH, &ALPHABET);
     format!("{}_{}", prefix, id)
@@ -0,0 +1,472 @@
+//! Pluggable resolution of secrets referenced from connection configs.
+//!
+//! Fields of type [`VarStr`](crate::var_str::VarStr) may contain references of the form
+//! `{{ scheme:name }}`, which are resolved through the [`SecretResolver`] registered for that
+//! scheme when the config is used (generally when operators are constructed in the worker).
+//! Two resolvers are provided out of the box:
+//!
+//! * `encrypted`: values encrypted with the cluster master key (`SECRETS_MASTER_KEY`), which is
+//!   how sensitive connection profile fields are stored in the database
+//! * `file`: files under `SECRETS_DIR`, for example a mounted Kubernetes secret volume
+//!
+//! Other backends can be added with [`register_secret_resolver`].
+
+use anyhow::{anyhow, bail, Context};
+use arroyo_types::{SECRETS_DIR_DEFAULT, SECRETS_DIR_ENV, SECRETS_MASTER_KEY_ENV};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+use tracing::error;
+
+pub const ENCRYPTED_SCHEME: &str = "encrypted";
+pub const FILE_SCHEME: &str = "file";
+
+/// Replaces sensitive values in API responses
+pub const REDACTED: &str = "********";
+
+pub trait SecretResolver: Send + Sync {
+    /// Returns the secret value for `name`, which is everything after the `scheme:` prefix
+    fn resolve(&self, name: &str) -> anyhow::Result<String>;
+}
+
+/// Secrets encrypted with AES-256-GCM using the cluster master key. References contain the
+/// encrypted value itself, so they can be stored in the database alongside the rest of a config.
+pub struct EncryptedSecretResolver {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl EncryptedSecretResolver {
+    pub fn new(master_key: &[u8]) -> anyhow::Result<Self> {
+        let key = UnboundKey::new(&AES_256_GCM, master_key)
+            .map_err(|_| anyhow!("secrets master key must be 32 bytes"))?;
+
+        Ok(Self {
+            key: LessSafeKey::new(key),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    fn from_env() -> anyhow::Result<Option<Self>> {
+        Self::from_base64(std::env::var(SECRETS_MASTER_KEY_ENV).ok().as_deref())
+    }
+
+    /// Parses a base64-encoded master key. No key means secrets aren't encrypted, but a key that
+    /// is set and invalid is an error.
+    fn from_base64(key: Option<&str>) -> anyhow::Result<Option<Self>> {
+        let Some(key) = key else {
+            return Ok(None);
+        };
+
+        let key = STANDARD
+            .decode(key.trim())
+            .with_context(|| format!("{} is not valid base64", SECRETS_MASTER_KEY_ENV))?;
+
+        Ok(Some(Self::new(&key)?))
+    }
+
+    /// Encrypts `plaintext`, returning a reference that can be stored in a config
+    pub fn encrypt(&self, plaintext: &str) -> anyhow::Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("failed to generate nonce"))?;
+
+        let mut data = plaintext.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .map_err(|_| anyhow!("failed to encrypt secret"))?;
+
+        let mut out = nonce.to_vec();
+        out.extend(data);
+
+        Ok(format!(
+            "{{{{ {}:{} }}}}",
+            ENCRYPTED_SCHEME,
+            URL_SAFE_NO_PAD.encode(out)
+        ))
+    }
+}
+
+impl SecretResolver for EncryptedSecretResolver {
+    fn resolve(&self, name: &str) -> anyhow::Result<String> {
+        let data = URL_SAFE_NO_PAD
+            .decode(name)
+            .context("encrypted secret is not valid base64")?;
+
+        if data.len() < NONCE_LEN {
+            bail!("encrypted secret is too short");
+        }
+
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let mut ciphertext = ciphertext.to_vec();
+        let plaintext = self
+            .key
+            .open_in_place(
+                Nonce::try_assume_unique_for_key(nonce).unwrap(),
+                Aad::empty(),
+                &mut ciphertext,
+            )
+            .map_err(|_| {
+                anyhow!("failed to decrypt secret; was it encrypted with a different master key?")
+            })?;
+
+        Ok(String::from_utf8(plaintext.to_vec())?)
+    }
+}
+
+/// Secrets stored as files within a directory, such as a mounted Kubernetes secret
+pub struct FileSecretResolver {
+    dir: PathBuf,
+}
+
+impl FileSecretResolver {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl SecretResolver for FileSecretResolver {
+    fn resolve(&self, name: &str) -> anyhow::Result<String> {
+        let path = Path::new(name);
+        if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+            bail!("invalid secret file name '{}'", name);
+        }
+
+        let contents = std::fs::read_to_string(self.dir.join(path))
+            .with_context(|| format!("failed to read secret file '{}'", name))?;
+
+        // secret files often end with a newline that isn't part of the secret
+        Ok(contents.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+/// The resolver for the master key, or None if no master key is set
+fn master_key_resolver() -> anyhow::Result<Option<&'static Arc<EncryptedSecretResolver>>> {
+    static RESOLVER: OnceLock<Result<Option<Arc<EncryptedSecretResolver>>, String>> =
+        OnceLock::new();
+    match RESOLVER.get_or_init(|| {
+        EncryptedSecretResolver::from_env()
+            .map(|resolver| resolver.map(Arc::new))
+            .map_err(|e| format!("{:#}", e))
+    }) {
+        Ok(resolver) => Ok(resolver.as_ref()),
+        Err(e) => Err(anyhow!("invalid secrets master key: {}", e)),
+    }
+}
+
+/// Checks that the master key is valid if one is set, so that a misconfigured key is reported
+/// on startup rather than when a secret is first stored
+pub fn validate_master_key() -> anyhow::Result<()> {
+    master_key_resolver().map(|_| ())
+}
+
+fn resolvers() -> &'static RwLock<HashMap<String, Arc<dyn SecretResolver>>> {
+    static RESOLVERS: OnceLock<RwLock<HashMap<String, Arc<dyn SecretResolver>>>> = OnceLock::new();
+    RESOLVERS.get_or_init(|| {
+        let mut resolvers: HashMap<String, Arc<dyn SecretResolver>> = HashMap::new();
+        resolvers.insert(
+            FILE_SCHEME.to_string(),
+            Arc::new(FileSecretResolver::new(
+                std::env::var(SECRETS_DIR_ENV).unwrap_or_else(|_| SECRETS_DIR_DEFAULT.to_string()),
+            )),
+        );
+        match master_key_resolver() {
+            Ok(Some(resolver)) => {
+                resolvers.insert(ENCRYPTED_SCHEME.to_string(), resolver.clone());
+            }
+            Ok(None) => {}
+            Err(e) => error!("{:?}", e),
+        }
+        RwLock::new(resolvers)
+    })
+}
+
+/// Registers a resolver for `{{ scheme:name }}` references, replacing any existing resolver
+/// for that scheme
+pub fn register_secret_resolver(scheme: &str, resolver: Arc<dyn SecretResolver>) {
+    resolvers()
+        .write()
+        .unwrap()
+        .insert(scheme.to_string(), resolver);
+}
+
+pub fn resolve_secret(scheme: &str, name: &str) -> anyhow::Result<String> {
+    let resolver = resolvers()
+        .read()
+        .unwrap()
+        .get(scheme)
+        .cloned()
+        .ok_or_else(|| {
+            if scheme == ENCRYPTED_SCHEME {
+                master_key_resolver().err().unwrap_or_else(|| {
+                    anyhow!(
+                        "cannot resolve encrypted secret because {} is not set",
+                        SECRETS_MASTER_KEY_ENV
+                    )
+                })
+            } else {
+                anyhow!("no secret resolver registered for '{}'", scheme)
+            }
+        })?;
+
+    resolver
+        .resolve(name)
+        .with_context(|| format!("failed to resolve {} secret", scheme))
+}
+
+/// Collects the names of fields marked as `sensitive` anywhere in a connector's JSON schema. If
+/// `var_str_only` is set, only fields that support secret references are returned.
+fn sensitive_fields(schema: &Value, var_str_only: bool, fields: &mut HashSet<String>) {
+    match schema {
+        Value::Object(obj) => {
+            if let Some(Value::Array(sensitive)) = obj.get("sensitive") {
+                for field in sensitive.iter().filter_map(|f| f.as_str()) {
+                    let is_var_str = obj
+                        .get("properties")
+                        .and_then(|p| p.get(field))
+                        .and_then(|p| p.get("format"))
+                        .and_then(|f| f.as_str())
+                        == Some("var-str");
+
+                    if is_var_str || !var_str_only {
+                        fields.insert(field.to_string());
+                    }
+                }
+            }
+
+            for v in obj.values() {
+                sensitive_fields(v, var_str_only, fields);
+            }
+        }
+        Value::Array(values) => {
+            for v in values {
+                sensitive_fields(v, var_str_only, fields);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn is_reference(value: &str) -> bool {
+    let value = value.trim();
+    value.starts_with("{{") && value.ends_with("}}")
+}
+
+fn map_sensitive(
+    config: &mut Value,
+    fields: &HashSet<String>,
+    f: &mut impl FnMut(&mut String) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    match config {
+        Value::Object(obj) => {
+            for (k, v) in obj.iter_mut() {
+                match v {
+                    Value::String(s) if fields.contains(k) && !is_reference(s) => f(s)?,
+                    _ => map_sensitive(v, fields, f)?,
+                }
+            }
+        }
+        Value::Array(values) => {
+            for v in values {
+                map_sensitive(v, fields, f)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Replaces plaintext values of sensitive fields in `config` with [`REDACTED`]. Secret
+/// references are left as-is, as they do not reveal the secret.
+pub fn redact_sensitive(schema: &Value, config: &mut Value) {
+    let mut fields = HashSet::new();
+    sensitive_fields(schema, false, &mut fields);
+
+    map_sensitive(config, &fields, &mut |s| {
+        *s = REDACTED.to_string();
+        Ok(())
+    })
+    .unwrap();
+}
+
+/// Replaces [`REDACTED`] values in `config` with the values at the same position in `existing`,
+/// so that a config read back from the API can be updated without re-supplying credentials
+pub fn restore_redacted(config: &mut Value, existing: &Value) {
+    if config.as_str() == Some(REDACTED) {
+        *config = existing.clone();
+        return;
+    }
+
+    match (config, existing) {
+        (Value::Object(obj), Value::Object(existing)) => {
+            for (k, v) in obj.iter_mut() {
+                if let Some(e) = existing.get(k) {
+                    restore_redacted(v, e);
+                }
+            }
+        }
+        (Value::Array(values), Value::Array(existing)) => {
+            for (v, e) in values.iter_mut().zip(existing) {
+                restore_redacted(v, e);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Encrypts plaintext values of sensitive fields in `config` with the master key, so that they
+/// are not stored in plaintext. Does nothing if no master key is set, and fails if the master key
+/// is invalid.
+pub fn encrypt_sensitive(schema: &Value, config: &mut Value) -> anyhow::Result<()> {
+    let Some(resolver) = master_key_resolver()? else {
+        return Ok(());
+    };
+
+    let mut fields = HashSet::new();
+    sensitive_fields(schema, true, &mut fields);
+
+    map_sensitive(config, &fields, &mut |s| {
+        *s = resolver.encrypt(s)?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "authentication": {
+                    "oneOf": [{
+                        "type": "object",
+                        "sensitive": ["password", "user"],
+                        "properties": {
+                            "user": { "type": "string" },
+                            "password": { "type": "string", "format": "var-str" }
+                        }
+                    }]
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_encrypted_roundtrip() {
+        let resolver = EncryptedSecretResolver::new(&[7u8; 32]).unwrap();
+        let reference = resolver.encrypt("hunter2").unwrap();
+
+        let name = reference
+            .strip_prefix("{{ encrypted:")
+            .and_then(|s| s.strip_suffix(" }}"))
+            .unwrap();
+        assert_eq!(resolver.resolve(name).unwrap(), "hunter2");
+
+        let other = EncryptedSecretResolver::new(&[8u8; 32]).unwrap();
+        assert!(other.resolve(name).is_err());
+    }
+
+    #[test]
+    fn test_master_key_from_base64() {
+        assert!(EncryptedSecretResolver::from_base64(None)
+            .unwrap()
+            .is_none());
+
+        let key = STANDARD.encode([7u8; 32]);
+        assert!(EncryptedSecretResolver::from_base64(Some(&key))
+            .unwrap()
+            .is_some());
+
+        // a key that is set but invalid must not fall back to storing secrets in plaintext
+        assert!(EncryptedSecretResolver::from_base64(Some("not base64!")).is_err());
+        assert!(EncryptedSecretResolver::from_base64(Some(&STANDARD.encode([7u8; 16]))).is_err());
+        assert!(EncryptedSecretResolver::from_base64(Some("")).is_err());
+    }
+
+    #[test]
+    fn test_file_resolver() {
+        let dir = std::env::temp_dir().join(format!("arroyo-secrets-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("kafka")).unwrap();
+        std::fs::write(dir.join("kafka/password"), "s3cret\n").unwrap();
+
+        let resolver = FileSecretResolver::new(&dir);
+        assert_eq!(resolver.resolve("kafka/password").unwrap(), "s3cret");
+        assert!(resolver.resolve("../kafka/password").is_err());
+        assert!(resolver.resolve("/etc/passwd").is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_redact_sensitive() {
+        let mut config = json!({
+            "authentication": {
+                "user": "bob",
+                "password": "{{ file:kafka/password }}"
+            },
+            "bootstrapServers": "localhost:9092"
+        });
+
+        redact_sensitive(&schema(), &mut config);
+
+        assert_eq!(
+            config,
+            json!({
+                "authentication": {
+                    "user": REDACTED,
+                    "password": "{{ file:kafka/password }}"
+                },
+                "bootstrapServers": "localhost:9092"
+            })
+        );
+    }
+
+    #[test]
+    fn test_restore_redacted() {
+        let existing = json!({
+            "authentication": {
+                "user": "bob",
+                "password": "{{ encrypted:abcd }}"
+            },
+            "bootstrapServers": "localhost:9092"
+        });
+
+        let mut config = json!({
+            "authentication": {
+                "user": REDACTED,
+                "password": "hunter2"
+            },
+            "bootstrapServers": "localhost:9093"
+        });
+
+        restore_redacted(&mut config, &existing);
+
+        assert_eq!(
+            config,
+            json!({
+                "authentication": {
+                    "user": "bob",
+                    "password": "hunter2"
+                },
+                "bootstrapServers": "localhost:9093"
+            })
+        );
+    }
+
+    #[test]
+    fn test_sensitive_var_str_fields() {
+        let mut fields = HashSet::new();
+        sensitive_fields(&schema(), true, &mut fields);
+        assert_eq!(fields, HashSet::from(["password".to_string()]));
+    }
+}
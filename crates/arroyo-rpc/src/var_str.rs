@@ -1,3 +1,4 @@
+use crate::secrets;
 use anyhow::bail;
 use regex::Regex;
 use serde::de::Visitor;
@@ -52,23 +53,33 @@ impl VarStr {
         VarStr { raw_val }
     }
 
+    /// Substitutes `{{ VAR }}` placeholders with environment variables and `{{ scheme:name }}`
+    /// placeholders with secrets from the resolver registered for `scheme` (see
+    /// [`crate::secrets`])
     pub fn sub_env_vars(&self) -> anyhow::Result<String> {
-        // Regex to match patterns like {{ VAR_NAME }}
+        // Regex to match patterns like {{ VAR_NAME }} or {{ scheme:secret/name }}
         static RE: OnceLock<Regex> = OnceLock::new();
-        let re = RE.get_or_init(|| Regex::new(r"\{\{\s*(\w+)\s*}}").unwrap());
+        let re =
+            RE.get_or_init(|| Regex::new(r"\{\{\s*(?:(\w+):([\w./+=-]+)|(\w+))\s*}}").unwrap());
 
         let mut result = self.raw_val.to_string();
 
         for caps in re.captures_iter(&self.raw_val) {
-            let var_name = caps.get(1).unwrap().as_str();
             let full_match = caps.get(0).unwrap().as_str();
 
-            match env::var(var_name) {
-                Ok(value) => {
-                    result = result.replace(full_match, &value);
+            let value = if let Some(var_name) = caps.get(3) {
+                let var_name = var_name.as_str();
+                match env::var(var_name) {
+                    Ok(value) => value,
+                    Err(_) => bail!("Environment variable {} not found", var_name),
                 }
-                Err(_) => bail!("Environment variable {} not found", var_name),
-            }
+            } else {
+                let scheme = caps.get(1).unwrap().as_str();
+                let name = caps.get(2).unwrap().as_str();
+                secrets::resolve_secret(scheme, name)?
+            };
+
+            result = result.replace(full_match, &value);
         }
 
         Ok(result)
@@ -99,6 +110,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_secret_placeholders() {
+        struct StaticResolver;
+
+        impl secrets::SecretResolver for StaticResolver {
+            fn resolve(&self, name: &str) -> anyhow::Result<String> {
+                Ok(format!("secret-{}", name))
+            }
+        }
+
+        secrets::register_secret_resolver("test", std::sync::Arc::new(StaticResolver));
+
+        let input = "user:{{ test:kafka/password }}";
+        assert_eq!(
+            VarStr::new(input.to_string()).sub_env_vars().unwrap(),
+            "user:secret-kafka/password"
+        );
+
+        assert!(VarStr::new("{{ unknown:password }}".to_string())
+            .sub_env_vars()
+            .is_err());
+    }
+
     #[test]
     fn test_multiple_placeholders() {
         env::set_var("VAR1", "first");
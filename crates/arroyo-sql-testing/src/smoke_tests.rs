@@ -18,7 +18,7 @@ use crate::udfs::get_udfs;
 use arroyo_rpc::grpc::{StopMode, TaskCheckpointCompletedReq, TaskCheckpointEventReq};
 use arroyo_rpc::{CompactionResult, ControlMessage, ControlResp};
 use arroyo_state::checkpoint_state::CheckpointState;
-use arroyo_types::{to_micros, CheckpointBarrier};
+use arroyo_types::{to_micros, CheckpointAlignment, CheckpointBarrier};
 use arroyo_udf_host::LocalUdf;
 use arroyo_worker::engine::{Engine, StreamConfig};
 use arroyo_worker::engine::{Program, RunningEngine};
@@ -105,6 +105,7 @@ async fn checkpoint(ctx: &mut SmokeTestContext<'_>, epoch: u32) {
         min_epoch: 0,
         timestamp: SystemTime::now(),
         then_stop: false,
+        alignment: CheckpointAlignment::Aligned,
     };
 
     for source in ctx.engine.source_controls() {
@@ -567,6 +568,7 @@ async fn get_graph(query_string: String, udfs: &[LocalUdf]) -> Result<LogicalPro
         schema_provider,
         SqlConfig {
             default_parallelism: 1,
+            ..Default::default()
         },
     )
     .await?
@@ -0,0 +1,29 @@
+CREATE TABLE cars(
+  timestamp TIMESTAMP,
+  driver_id BIGINT,
+  event_type TEXT,
+  location TEXT
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/cars.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp'
+);
+CREATE TABLE daily_running_count (
+  event_type TEXT,
+  day TIMESTAMP,
+  end TIMESTAMP,
+  count BIGINT
+) WITH (
+  connector = 'single_file',
+  path = '$output_path',
+  format = 'json',
+  type = 'sink'
+);
+INSERT INTO daily_running_count
+SELECT event_type, window.start as day, window.end as end, count
+FROM (
+SELECT event_type, CUMULATE(INTERVAL '1' HOUR, INTERVAL '1' DAY) as window, COUNT(*) as count
+FROM cars
+GROUP BY 1,2);
\ No newline at end of file
@@ -0,0 +1,28 @@
+CREATE TABLE impulse_source (
+      timestamp TIMESTAMP,
+      counter bigint unsigned not null,
+      subtask_index bigint unsigned not null
+    ) WITH (
+      connector = 'single_file',
+      path = '$input_dir/impulse.json',
+      format = 'json',
+      event_time_field = 'timestamp',
+      type = 'source'
+    );
+CREATE TABLE deduplicate_sink (
+    key bigint,
+    counter bigint
+) WITH (
+    connector = 'single_file',
+    path = '$output_path',
+    format = 'json',
+    type = 'sink'
+);
+
+INSERT INTO deduplicate_sink
+SELECT key, counter FROM (
+  SELECT counter % 7 as key, counter,
+    ROW_NUMBER() OVER (PARTITION BY counter % 7 ORDER BY timestamp) as row_num
+  FROM impulse_source
+) first_rows
+WHERE row_num = 1;
\ No newline at end of file
@@ -0,0 +1,30 @@
+CREATE TABLE events (
+  timestamp TIMESTAMP,
+  key BIGINT,
+  label TEXT,
+  watermark TIMESTAMP GENERATED ALWAYS AS (timestamp - INTERVAL '10 second') STORED
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/out_of_order.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp',
+  watermark_field = 'watermark'
+);
+CREATE TABLE deduplicate_sink (
+  key BIGINT,
+  label TEXT
+) WITH (
+  connector = 'single_file',
+  path = '$output_path',
+  format = 'json',
+  type = 'sink'
+);
+
+INSERT INTO deduplicate_sink
+SELECT key, label FROM (
+  SELECT key, label,
+    ROW_NUMBER() OVER (PARTITION BY key ORDER BY timestamp) as row_num
+  FROM events
+) first_rows
+WHERE row_num = 1;
@@ -0,0 +1,26 @@
+CREATE TABLE impulse_source (
+      timestamp TIMESTAMP,
+      counter bigint unsigned not null,
+      subtask_index bigint unsigned not null
+    ) WITH (
+      connector = 'single_file',
+      path = '$input_dir/impulse.json',
+      format = 'json',
+      event_time_field = 'timestamp',
+      type = 'source'
+    );
+CREATE TABLE interval_join_sink (
+    counter bigint,
+    nearby_counter bigint
+) WITH (
+    connector = 'single_file',
+    path = '$output_path',
+    format = 'json',
+    type = 'sink'
+);
+
+INSERT INTO interval_join_sink
+SELECT a.counter as counter, b.counter as nearby_counter
+FROM impulse_source a JOIN impulse_source b
+ON a.counter % 5 = b.counter % 5
+AND b.timestamp BETWEEN a.timestamp - INTERVAL '1' SECOND AND a.timestamp + INTERVAL '1' SECOND;
\ No newline at end of file
@@ -0,0 +1,42 @@
+CREATE TABLE impulse_source (
+      timestamp TIMESTAMP,
+      counter bigint unsigned not null,
+      subtask_index bigint unsigned not null
+    ) WITH (
+      connector = 'single_file',
+      path = '$input_dir/impulse.json',
+      format = 'json',
+      event_time_field = 'timestamp',
+      type = 'source'
+    );
+CREATE TABLE match_recognize_sink (
+    subtask_index bigint,
+    start_counter bigint,
+    end_counter bigint,
+    b_rows bigint,
+    total bigint
+) WITH (
+    connector = 'single_file',
+    path = '$output_path',
+    format = 'json',
+    type = 'sink'
+);
+
+INSERT INTO match_recognize_sink
+SELECT subtask_index, start_counter, end_counter, b_rows, total
+FROM impulse_source MATCH_RECOGNIZE (
+  PARTITION BY subtask_index
+  ORDER BY timestamp
+  MEASURES
+    FIRST(A.counter) AS start_counter,
+    LAST(C.counter) AS end_counter,
+    COUNT(B.*) AS b_rows,
+    SUM(counter) AS total
+  ONE ROW PER MATCH
+  AFTER MATCH SKIP PAST LAST ROW
+  PATTERN (A B+ C) WITHIN INTERVAL '10' SECOND
+  DEFINE
+    A AS A.counter % 10 = 0,
+    B AS counter % 10 > 0 AND counter % 10 < 5,
+    C AS counter % 10 = 5
+) AS m;
\ No newline at end of file
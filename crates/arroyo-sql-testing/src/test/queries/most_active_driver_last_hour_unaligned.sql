@@ -1,4 +1,3 @@
---fail=Error during planning: hop() width 3600s currently must be a multiple of slide 2400s
 CREATE TABLE cars (
   timestamp TIMESTAMP,
   driver_id BIGINT,
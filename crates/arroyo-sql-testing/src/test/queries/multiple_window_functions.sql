@@ -0,0 +1,35 @@
+CREATE TABLE cars (
+  timestamp TIMESTAMP,
+  driver_id BIGINT,
+  event_type TEXT,
+  location TEXT
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/cars.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp'
+);
+CREATE TABLE top_drivers (
+  driver_id BIGINT,
+  count BIGINT,
+  hour TIMESTAMP,
+  row_number BIGINT,
+  rank BIGINT,
+  previous_driver_id BIGINT
+) WITH (
+  connector = 'single_file',
+  path = '$output_path',
+  format = 'json',
+  type = 'sink'
+);
+INSERT INTO top_drivers
+SELECT driver_id, count, window.start, row_number, rank, previous_driver_id FROM (
+  SELECT *,
+    ROW_NUMBER() OVER (PARTITION BY window ORDER BY count DESC, driver_id DESC) as row_number,
+    RANK() OVER (PARTITION BY window ORDER BY count DESC, driver_id DESC) as rank,
+    LAG(driver_id) OVER (PARTITION BY window ORDER BY count DESC, driver_id DESC) as previous_driver_id
+  FROM (
+    SELECT driver_id, TUMBLE(INTERVAL '1' HOUR) as window, count(*) as count
+    FROM cars
+    GROUP BY 1, 2)) WHERE row_number <= 3;
\ No newline at end of file
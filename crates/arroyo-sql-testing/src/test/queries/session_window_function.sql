@@ -0,0 +1,34 @@
+CREATE TABLE impulse_source (
+  timestamp TIMESTAMP,
+  counter bigint unsigned not null,
+  subtask_index bigint unsigned not null
+) WITH (
+  connector = 'single_file',
+  path = '$input_dir/impulse.json',
+  format = 'json',
+  type = 'source',
+  event_time_field = 'timestamp'
+);
+
+CREATE TABLE session_window_function_output (
+  start timestamp,
+  end timestamp,
+  user_id bigint,
+  rows bigint,
+  row_num bigint,
+  previous_rows bigint
+) WITH (
+  connector = 'single_file',
+  path = '$output_path',
+  format = 'json',
+  type = 'sink'
+);
+
+INSERT INTO session_window_function_output
+SELECT window.start, window.end, user_id, rows, row_num, previous_rows FROM (
+  SELECT *,
+    ROW_NUMBER() OVER (PARTITION BY window ORDER BY rows DESC) as row_num,
+    LAG(rows) OVER (PARTITION BY window ORDER BY rows DESC) as previous_rows
+  FROM (
+    SELECT SESSION(interval '20 seconds') as window, CASE WHEN counter % 10 = 0 THEN 0 ELSE counter END as user_id, count(*) as rows
+    FROM impulse_source GROUP BY window, user_id));
\ No newline at end of file
@@ -0,0 +1,27 @@
+CREATE TABLE impulse_source (
+      timestamp TIMESTAMP,
+      counter bigint unsigned not null,
+      subtask_index bigint unsigned not null,
+      PRIMARY KEY (counter)
+    ) WITH (
+      connector = 'single_file',
+      path = '$input_dir/impulse.json',
+      format = 'json',
+      event_time_field = 'timestamp',
+      type = 'source'
+    );
+CREATE TABLE temporal_join_sink (
+    counter bigint,
+    version_counter bigint
+) WITH (
+    connector = 'single_file',
+    path = '$output_path',
+    format = 'json',
+    type = 'sink'
+);
+
+INSERT INTO temporal_join_sink
+SELECT i.counter as counter, v.counter as version_counter
+FROM impulse_source i
+LEFT JOIN impulse_source FOR SYSTEM_TIME AS OF i.timestamp AS v
+ON i.counter = v.counter;
\ No newline at end of file
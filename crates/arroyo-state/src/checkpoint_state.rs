@@ -8,11 +8,11 @@ use anyhow::{anyhow, bail, Result};
 use arroyo_rpc::grpc::{
     self,
     api::{self, OperatorCheckpointDetail},
-    CheckpointMetadata, OperatorCheckpointMetadata, OperatorMetadata, SubtaskCheckpointMetadata,
-    TableCheckpointMetadata, TableConfig, TableEnum, TableSubtaskCheckpointMetadata,
-    TaskCheckpointCompletedReq, TaskCheckpointEventReq,
+    CheckpointMetadata, InFlightData, OperatorCheckpointMetadata, OperatorMetadata,
+    SubtaskCheckpointMetadata, TableCheckpointMetadata, TableConfig, TableEnum,
+    TableSubtaskCheckpointMetadata, TaskCheckpointCompletedReq, TaskCheckpointEventReq,
 };
-use arroyo_types::{from_micros, to_micros};
+use arroyo_types::{from_micros, to_micros, KEY_GROUPS};
 use tracing::{debug, warn};
 
 use crate::{
@@ -51,6 +51,7 @@ pub struct OperatorState {
     pub finish_time: Option<SystemTime>,
     table_state: HashMap<String, TableState>,
     watermarks: Vec<Option<SystemTime>>,
+    in_flight_data: Vec<InFlightData>,
 }
 
 impl OperatorState {
@@ -62,6 +63,7 @@ impl OperatorState {
             finish_time: None,
             table_state: HashMap::new(),
             watermarks: vec![],
+            in_flight_data: vec![],
         }
     }
 
@@ -84,6 +86,7 @@ impl OperatorState {
             }
             None => Some(from_micros(c.finish_time)),
         };
+        self.in_flight_data.extend(c.in_flight_data);
         for (table, table_metadata) in c.table_metadata {
             self.table_state
                 .entry(table)
@@ -327,6 +330,7 @@ impl CheckpointState {
                 finish_time: to_micros(operator_state.finish_time.unwrap()),
                 table_checkpoint_metadata,
                 table_configs,
+                in_flight_data: std::mem::take(&mut operator_state.in_flight_data),
                 operator_metadata: Some(OperatorMetadata {
                     job_id: self.job_id.to_string(),
                     operator_id: c.operator_id,
@@ -334,6 +338,7 @@ impl CheckpointState {
                     min_watermark,
                     max_watermark,
                     parallelism: operator_state.subtasks_checkpointed as u64,
+                    key_groups: KEY_GROUPS,
                 }),
             })
             .await
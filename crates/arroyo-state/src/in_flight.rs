@@ -0,0 +1,146 @@
+use crate::tables::in_flight_data_path;
+use anyhow::{bail, Result};
+use arrow_array::RecordBatch;
+use arroyo_rpc::grpc::InFlightData;
+use arroyo_storage::StorageProviderRef;
+use arroyo_types::TaskInfoRef;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::{
+    arrow::ArrowWriter,
+    basic::ZstdLevel,
+    file::properties::{EnabledStatistics, WriterProperties},
+};
+use std::collections::BTreeMap;
+
+/// The data a subtask received during an unaligned checkpoint on inputs whose barriers had not yet
+/// arrived when it checkpointed its state. This data is not reflected in the checkpointed state,
+/// so it must be replayed when restoring.
+#[derive(Debug, Default)]
+pub struct InFlightBatches {
+    /// The number of partitions of each of the task's inputs
+    pub input_partitions: Vec<usize>,
+    pub batches: Vec<(usize, RecordBatch)>,
+}
+
+/// Splits an index into all of a task's input partitions into the input it belongs to and the
+/// partition within that input
+pub fn split_input_index(input_partitions: &[usize], mut index: usize) -> (usize, usize) {
+    for (input, partitions) in input_partitions.iter().enumerate() {
+        if index < *partitions {
+            return (input, index);
+        }
+        index -= partitions;
+    }
+    panic!(
+        "input index is out of range for inputs with {:?} partitions",
+        input_partitions
+    );
+}
+
+/// In-flight data restored from an unaligned checkpoint
+#[derive(Debug, Default)]
+pub struct RestoredInFlightData {
+    /// Whether the checkpoint was written with a different parallelism; if so, the data from all
+    /// subtasks is returned and must be filtered by the caller
+    pub rescaled: bool,
+    pub batches: Vec<RestoredInFlightBatch>,
+}
+
+#[derive(Debug)]
+pub struct RestoredInFlightBatch {
+    /// The subtask that persisted the data
+    pub subtask_index: usize,
+    /// The input the data was received on
+    pub input: usize,
+    /// The input partition the data was received on, mapped onto the current partitions of the
+    /// operator's inputs
+    pub input_index: usize,
+    pub batch: RecordBatch,
+}
+
+/// Writes the in-flight data for each input to its own file, returning the metadata for the files
+/// and the total number of bytes written
+pub(crate) async fn write_in_flight_batches(
+    storage: &StorageProviderRef,
+    task_info: &TaskInfoRef,
+    epoch: u32,
+    data: InFlightBatches,
+) -> Result<(Vec<InFlightData>, usize)> {
+    let mut by_input: BTreeMap<usize, Vec<RecordBatch>> = BTreeMap::new();
+    for (input, batch) in data.batches {
+        by_input.entry(input).or_default().push(batch);
+    }
+
+    let mut metadata = vec![];
+    let mut bytes = 0;
+    for (input, batches) in by_input {
+        let props = WriterProperties::builder()
+            .set_compression(parquet::basic::Compression::ZSTD(ZstdLevel::default()))
+            .set_statistics_enabled(EnabledStatistics::None)
+            .build();
+        let mut writer = ArrowWriter::try_new(Vec::new(), batches[0].schema(), Some(props))?;
+        let mut rows = 0;
+        for batch in &batches {
+            writer.write(batch)?;
+            rows += batch.num_rows();
+        }
+        let parquet_bytes = writer.into_inner()?;
+        bytes += parquet_bytes.len();
+
+        let path = in_flight_data_path(
+            &task_info.job_id,
+            &task_info.operator_id,
+            task_info.task_index,
+            input,
+            epoch,
+        );
+        storage.put(&path, parquet_bytes).await?;
+
+        let (logical_input, input_index) = split_input_index(&data.input_partitions, input);
+        metadata.push(InFlightData {
+            subtask_index: task_info.task_index as u32,
+            input: logical_input as u32,
+            input_index: input_index as u32,
+            input_partitions: data.input_partitions[logical_input] as u32,
+            file: path,
+            rows: rows as u64,
+        });
+    }
+
+    Ok((metadata, bytes))
+}
+
+/// Reads the batches in the given in-flight data files, mapping the partitions they were received
+/// on onto an operator whose inputs have `input_partitions` partitions
+pub(crate) async fn read_in_flight_batches(
+    storage: &StorageProviderRef,
+    data: &[InFlightData],
+    input_partitions: &[usize],
+) -> Result<Vec<RestoredInFlightBatch>> {
+    let mut result = vec![];
+    for d in data {
+        let input = d.input as usize;
+        let Some(partitions) = input_partitions.get(input) else {
+            bail!(
+                "in-flight data was received on input {}, but the operator only has {} inputs",
+                input,
+                input_partitions.len()
+            );
+        };
+
+        let contents = storage.get(d.file.as_str()).await?;
+        let input_index = input_partitions[..input].iter().sum::<usize>()
+            + d.input_index as usize * partitions / (d.input_partitions as usize).max(1);
+
+        for batch in ParquetRecordBatchReaderBuilder::try_new(contents)?.build()? {
+            result.push(RestoredInFlightBatch {
+                subtask_index: d.subtask_index as usize,
+                input,
+                input_index,
+                batch: batch?,
+            });
+        }
+    }
+
+    Ok(result)
+}
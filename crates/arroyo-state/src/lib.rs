@@ -10,15 +10,18 @@ use bincode::config::Configuration;
 use bincode::{Decode, Encode};
 
 use arroyo_rpc::df::ArroyoSchema;
+use in_flight::InFlightBatches;
 use prost::Message;
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::ops::RangeInclusive;
 use std::time::{Duration, SystemTime};
+use tokio::sync::oneshot;
 
 pub mod checkpoint_state;
 pub mod committing_state;
+pub mod in_flight;
 mod metrics;
 pub mod parquet;
 pub(crate) mod schemas;
@@ -39,6 +42,8 @@ pub struct CheckpointMessage {
     time: SystemTime,
     watermark: Option<SystemTime>,
     then_stop: bool,
+    // for unaligned checkpoints, resolves once the barriers have arrived on all inputs
+    in_flight: Option<oneshot::Receiver<InFlightBatches>>,
 }
 
 #[derive(Debug)]
@@ -147,12 +152,17 @@ pub trait BackingStore {
     /// writes the checkpoint metadata to the backing store
     async fn write_checkpoint_metadata(metadata: CheckpointMetadata) -> Result<()>;
 
-    /// cleans up a checkpoint by deleting data that is no longer needed
+    /// cleans up a checkpoint by deleting the epochs in `epochs_to_delete`, along with any data
+    /// that is not referenced by one of the `epochs_to_keep`
     async fn cleanup_checkpoint(
         metadata: CheckpointMetadata,
-        old_min_epoch: u32,
+        epochs_to_delete: &[u32],
+        epochs_to_keep: &[u32],
         new_min_epoch: u32,
     ) -> Result<()>;
+
+    /// returns the storage URLs of the given checkpoint epochs
+    async fn checkpoint_urls(job_id: &str, epochs: &[u32]) -> Result<Vec<String>>;
 }
 
 pub fn hash_key<K: Hash>(key: &K) -> u64 {
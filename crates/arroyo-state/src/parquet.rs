@@ -2,7 +2,7 @@ use crate::tables::expiring_time_key_map::ExpiringTimeKeyTable;
 use crate::tables::global_keyed_map::GlobalKeyedTable;
 use crate::tables::{CompactionConfig, ErasedTable};
 use crate::BackingStore;
-use anyhow::{bail, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use arroyo_rpc::grpc;
 use arroyo_rpc::grpc::{CheckpointMetadata, OperatorCheckpointMetadata, TableCheckpointMetadata};
 use arroyo_storage::StorageProvider;
@@ -110,7 +110,8 @@ impl BackingStore for ParquetBackend {
 
     async fn cleanup_checkpoint(
         mut metadata: CheckpointMetadata,
-        old_min_epoch: u32,
+        epochs_to_delete: &[u32],
+        epochs_to_keep: &[u32],
         min_epoch: u32,
     ) -> Result<()> {
         info!(
@@ -126,8 +127,8 @@ impl BackingStore for ParquetBackend {
                 Self::cleanup_operator(
                     metadata.job_id.clone(),
                     operator_id.clone(),
-                    old_min_epoch,
-                    min_epoch,
+                    epochs_to_delete,
+                    epochs_to_keep,
                 )
             })
             .collect();
@@ -138,10 +139,10 @@ impl BackingStore for ParquetBackend {
         while let Some(result) = futures.next().await {
             let operator_id = result?;
 
-            for epoch_to_remove in old_min_epoch..min_epoch {
+            for epoch_to_remove in epochs_to_delete {
                 let path = metadata_path(&operator_path(
                     &metadata.job_id,
-                    epoch_to_remove,
+                    *epoch_to_remove,
                     &operator_id,
                 ));
                 storage_client.lock().await.delete_if_present(path).await?;
@@ -154,17 +155,64 @@ impl BackingStore for ParquetBackend {
             );
         }
 
-        for epoch_to_remove in old_min_epoch..min_epoch {
+        for epoch_to_remove in epochs_to_delete {
             storage_client
                 .lock()
                 .await
-                .delete_if_present(metadata_path(&base_path(&metadata.job_id, epoch_to_remove)))
+                .delete_if_present(metadata_path(&base_path(
+                    &metadata.job_id,
+                    *epoch_to_remove,
+                )))
                 .await?;
         }
         metadata.min_epoch = min_epoch;
         Self::write_checkpoint_metadata(metadata).await?;
         Ok(())
     }
+
+    async fn checkpoint_urls(job_id: &str, epochs: &[u32]) -> Result<Vec<String>> {
+        let storage_client = get_storage_provider().await?;
+        Ok(epochs
+            .iter()
+            .map(|epoch| storage_client.canonical_url_for(&base_path(job_id, *epoch)))
+            .collect())
+    }
+}
+
+fn files_to_keep(operator_metadata: &OperatorCheckpointMetadata) -> Result<Vec<String>> {
+    let mut files = vec![];
+    for (table_name, metadata) in &operator_metadata.table_checkpoint_metadata {
+        let table_config = operator_metadata
+            .table_configs
+            .get(table_name)
+            .ok_or_else(|| {
+                anyhow!(
+                    "missing table config for table {} in operator metadata {:?}",
+                    table_name,
+                    operator_metadata
+                )
+            })?
+            .clone();
+
+        files.extend(match table_config.table_type() {
+            grpc::TableEnum::MissingTableType => bail!("should have table type"),
+            grpc::TableEnum::GlobalKeyValue => {
+                GlobalKeyedTable::files_to_keep(table_config, metadata.clone())?
+            }
+            grpc::TableEnum::ExpiringKeyedTimeTable => {
+                ExpiringTimeKeyTable::files_to_keep(table_config, metadata.clone())?
+            }
+        });
+    }
+
+    files.extend(
+        operator_metadata
+            .in_flight_data
+            .iter()
+            .map(|d| d.file.clone()),
+    );
+
+    Ok(files)
 }
 
 impl ParquetBackend {
@@ -225,73 +273,35 @@ impl ParquetBackend {
         Ok(result)
     }
 
-    /// Delete files no longer referenced by the new min epoch
+    /// Delete files from `epochs_to_delete` that are no longer referenced by any of the
+    /// `epochs_to_keep`
     pub async fn cleanup_operator(
         job_id: String,
         operator_id: String,
-        old_min_epoch: u32,
-        new_min_epoch: u32,
+        epochs_to_delete: &[u32],
+        epochs_to_keep: &[u32],
     ) -> Result<String> {
-        let operator_metadata = Self::load_operator_metadata(&job_id, &operator_id, new_min_epoch)
-            .await?
-            .expect("expect new_min_epoch metadata to still be present");
-        let paths_to_keep: HashSet<String> = operator_metadata
-            .table_checkpoint_metadata
-            .iter()
-            .flat_map(|(table_name, metadata)| {
-                let table_config = operator_metadata
-                    .table_configs
-                    .get(table_name)
-                    .unwrap()
-                    .clone();
-
-                match table_config.table_type() {
-                    grpc::TableEnum::MissingTableType => todo!("should handle error"),
-                    grpc::TableEnum::GlobalKeyValue => {
-                        GlobalKeyedTable::files_to_keep(table_config, metadata.clone()).unwrap()
-                    }
-                    grpc::TableEnum::ExpiringKeyedTimeTable => {
-                        ExpiringTimeKeyTable::files_to_keep(table_config, metadata.clone()).unwrap()
-                    }
-                }
-            })
-            .collect();
+        let mut paths_to_keep = HashSet::new();
+        for epoch in epochs_to_keep {
+            if let Some(metadata) =
+                Self::load_operator_metadata(&job_id, &operator_id, *epoch).await?
+            {
+                paths_to_keep.extend(files_to_keep(&metadata)?);
+            }
+        }
 
         let mut deleted_paths = HashSet::new();
         let storage_client = get_storage_provider().await?;
 
-        for epoch_to_remove in old_min_epoch..new_min_epoch {
+        for epoch_to_remove in epochs_to_delete {
             let Some(metadata) =
-                Self::load_operator_metadata(&job_id, &operator_id, epoch_to_remove).await?
+                Self::load_operator_metadata(&job_id, &operator_id, *epoch_to_remove).await?
             else {
                 continue;
             };
 
-            // delete any files that are not in the new min epoch
-            for file in metadata
-                .table_checkpoint_metadata
-                .iter()
-                // TODO: factor this out
-                .flat_map(|(table_name, metadata)| {
-                    let table_config = operator_metadata
-                        .table_configs
-                        .get(table_name)
-                        .ok_or_else(|| anyhow::anyhow!("missing table config for operator {}, table {}, metadata is {:?}, operator_metadata is {:?}",
-                         operator_id, table_name, metadata, operator_metadata)).unwrap()
-                        .clone();
-
-                    match table_config.table_type() {
-                        grpc::TableEnum::MissingTableType => todo!("should handle error"),
-                        grpc::TableEnum::GlobalKeyValue => {
-                            GlobalKeyedTable::files_to_keep(table_config, metadata.clone()).unwrap()
-                        }
-                        grpc::TableEnum::ExpiringKeyedTimeTable => {
-                            ExpiringTimeKeyTable::files_to_keep(table_config, metadata.clone())
-                                .unwrap()
-                        }
-                    }
-                })
-            {
+            // delete any files that are not referenced by a retained epoch
+            for file in files_to_keep(&metadata)? {
                 if !paths_to_keep.contains(&file) && !deleted_paths.contains(&file) {
                     deleted_paths.insert(file.clone());
                     storage_client.delete_if_present(file).await?;
@@ -1,6 +1,7 @@
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
     mem,
+    ops::RangeInclusive,
     sync::Arc,
     time::{Duration, SystemTime},
 };
@@ -11,9 +12,11 @@ use arrow::row::OwnedRow;
 use arrow_array::{
     cast::AsArray,
     types::{TimestampNanosecondType, UInt64Type},
-    BooleanArray, PrimitiveArray, RecordBatch, TimestampNanosecondArray, UInt64Array,
+    ArrayRef, BooleanArray, PrimitiveArray, RecordBatch, TimestampNanosecondArray, UInt32Array,
+    UInt64Array,
 };
 use arrow_ord::{partition::partition, sort::sort_to_indices};
+use arrow_schema::Schema;
 use arroyo_rpc::{
     df::server_for_hash_array,
     grpc::{
@@ -24,7 +27,8 @@ use arroyo_rpc::{
 };
 use arroyo_storage::StorageProviderRef;
 use arroyo_types::{
-    from_micros, from_nanos, print_time, server_for_hash, to_micros, to_nanos, TaskInfoRef,
+    from_micros, from_nanos, key_group_for_hash, print_time, server_for_hash, to_micros, to_nanos,
+    TaskInfoRef,
 };
 
 use futures::{StreamExt, TryStreamExt};
@@ -165,14 +169,14 @@ impl ExpiringTimeKeyTable {
         self.checkpoint_files
             .iter()
             .filter_map(|file| {
-                // file must have some data greater than the cutoff and routing keys within the range.
+                // file must have some data greater than the cutoff and key groups within the range.
                 if cutoff <= from_micros(file.max_timestamp_micros)
-                    && (file.max_routing_key >= *self.task_info.key_range.start()
-                        && *self.task_info.key_range.end() >= file.min_routing_key)
+                    && overlaps_key_groups(file, &self.task_info.key_groups())
                 {
-                    let needs_hash_filtering = *self.task_info.key_range.end()
-                        < file.max_routing_key
-                        || *self.task_info.key_range.start() > file.min_routing_key;
+                    // files that lie entirely within our key groups can be read without
+                    // checking the hash of each row
+                    let needs_hash_filtering =
+                        !contained_in_key_groups(file, &self.task_info.key_groups());
                     Some((file.file.clone(), needs_hash_filtering))
                 } else {
                     None
@@ -247,6 +251,25 @@ impl ExpiringTimeKeyTable {
     }
 }
 
+/// Returns the range of key groups that may have data in this file
+fn file_key_groups(file: &ParquetTimeFile) -> RangeInclusive<u32> {
+    match (file.min_key_group, file.max_key_group) {
+        (Some(min), Some(max)) => min..=max,
+        // files written before key groups were recorded
+        _ => key_group_for_hash(file.min_routing_key)..=key_group_for_hash(file.max_routing_key),
+    }
+}
+
+fn overlaps_key_groups(file: &ParquetTimeFile, key_groups: &RangeInclusive<u32>) -> bool {
+    let file_groups = file_key_groups(file);
+    *file_groups.end() >= *key_groups.start() && *key_groups.end() >= *file_groups.start()
+}
+
+fn contained_in_key_groups(file: &ParquetTimeFile, key_groups: &RangeInclusive<u32>) -> bool {
+    let file_groups = file_key_groups(file);
+    key_groups.contains(file_groups.start()) && key_groups.contains(file_groups.end())
+}
+
 #[async_trait::async_trait]
 impl Table for ExpiringTimeKeyTable {
     type Checkpointer = ExpiringTimeKeyTableCheckpointer;
@@ -343,10 +366,17 @@ impl Table for ExpiringTimeKeyTable {
         &self,
         table_metadata: Self::TableCheckpointMessage,
     ) -> anyhow::Result<Option<Self::TableSubtaskCheckpointMetadata>> {
+        // only the files that overlap with this subtask's key groups are relevant; after
+        // rescaling this will be a subset of the files written by the previous subtasks
+        let key_groups = self.task_info.key_groups();
         Ok(Some(ExpiringKeyedTimeSubtaskCheckpointMetadata {
             subtask_index: self.task_info.task_index as u32,
             watermark: None,
-            files: table_metadata.files,
+            files: table_metadata
+                .files
+                .into_iter()
+                .filter(|file| overlaps_key_groups(file, &key_groups))
+                .collect(),
         }))
     }
 
@@ -626,6 +656,8 @@ impl CompactedFileWriter {
             max_routing_key: stats.max_routing_key,
             max_timestamp_micros: to_micros(stats.max_timestamp),
             generation,
+            min_key_group: Some(key_group_for_hash(stats.min_routing_key)),
+            max_key_group: Some(key_group_for_hash(stats.max_routing_key)),
         })
     }
 }
@@ -714,10 +746,9 @@ impl TableEpochCheckpointer for ExpiringTimeKeyTableCheckpointer {
             .prior_files
             .into_iter()
             .filter(|file| {
-                // file must have some data greater than the cutoff and routing keys within the range.
+                // file must have some data greater than the cutoff and key groups within the range.
                 cutoff <= file.max_timestamp_micros
-                    && (file.max_routing_key >= *self.parent.task_info.key_range.start()
-                        && *self.parent.task_info.key_range.end() >= file.min_routing_key)
+                    && overlaps_key_groups(file, &self.parent.task_info.key_groups())
             })
             .collect();
         let mut bytes = 0;
@@ -739,6 +770,8 @@ impl TableEpochCheckpointer for ExpiringTimeKeyTableCheckpointer {
                 max_routing_key: stats.max_routing_key,
                 max_timestamp_micros: to_micros(stats.max_timestamp),
                 generation: 0,
+                min_key_group: Some(key_group_for_hash(stats.min_routing_key)),
+                max_key_group: Some(key_group_for_hash(stats.max_routing_key)),
             };
             files.push(file)
         }
@@ -900,6 +933,9 @@ pub struct KeyTimeView {
     // indices of schema that aren't keys, used for projection
     value_indices: Vec<usize>,
     state_tx: Sender<StateMessage>,
+    // the earliest timestamp of the retained rows, so expiration can skip scanning the table
+    // until some of them are old enough to drop
+    earliest: Option<SystemTime>,
 }
 
 #[derive(Debug)]
@@ -908,6 +944,15 @@ enum BatchData {
     BatchVec(Vec<RecordBatch>),
 }
 
+/// The earliest timestamp in `batch`, if it has any rows
+fn min_timestamp(schema: &ArroyoSchema, batch: &RecordBatch) -> Result<Option<SystemTime>> {
+    let timestamp_array: &PrimitiveArray<TimestampNanosecondType> = batch
+        .column(schema.timestamp_index)
+        .as_primitive_opt()
+        .ok_or_else(|| anyhow!("failed to find timestamp column"))?;
+    Ok(aggregate::min(timestamp_array).map(|min| from_nanos(min as u128)))
+}
+
 impl KeyTimeView {
     fn new(parent: ExpiringTimeKeyTable, state_tx: Sender<StateMessage>) -> Result<Self> {
         let schema = parent.schema.memory_schema();
@@ -922,6 +967,7 @@ impl KeyTimeView {
             value_indices,
             value_schema,
             state_tx,
+            earliest: None,
         })
     }
 
@@ -962,9 +1008,60 @@ impl KeyTimeView {
         Ok(self.insert_internal(batch)?)
     }
 
+    /// Drops the rows that are further behind the watermark than the table's retention, along
+    /// with any keys that no longer have rows. The table is only scanned once its earliest row
+    /// is past the retention.
+    pub fn expire(&mut self, watermark: Option<SystemTime>) -> Result<()> {
+        let Some(watermark) = watermark else {
+            return Ok(());
+        };
+        let cutoff = watermark - self.parent.retention;
+        if self.earliest.map_or(true, |earliest| earliest >= cutoff) {
+            return Ok(());
+        }
+
+        let mut earliest = None;
+        let mut expired_keys = vec![];
+        for (key, data) in self.keyed_data.iter_mut() {
+            let batches = match data {
+                BatchData::SingleBatch(batch) => vec![batch.clone()],
+                BatchData::BatchVec(batches) => mem::take(batches),
+            };
+            let mut remaining = vec![];
+            for batch in batches {
+                let batch = self.value_schema.filter_by_time(batch, Some(cutoff))?;
+                if batch.num_rows() > 0 {
+                    earliest = earliest
+                        .into_iter()
+                        .chain(min_timestamp(&self.value_schema, &batch)?)
+                        .min();
+                    remaining.push(batch);
+                }
+            }
+            *data = match remaining.len() {
+                0 => {
+                    expired_keys.push(key.clone());
+                    continue;
+                }
+                1 => BatchData::SingleBatch(remaining.pop().unwrap()),
+                _ => BatchData::BatchVec(remaining),
+            };
+        }
+        for key in expired_keys {
+            self.keyed_data.remove(&key);
+        }
+        self.earliest = earliest;
+        Ok(())
+    }
+
     fn insert_internal(&mut self, batch: RecordBatch) -> Result<Vec<OwnedRow>> {
         let sorted_batch = self.schema.sort(batch, false)?;
         let value_batch = sorted_batch.project(&self.value_indices)?;
+        self.earliest = self
+            .earliest
+            .into_iter()
+            .chain(min_timestamp(&self.value_schema, &value_batch)?)
+            .min();
         let mut rows = vec![];
         for range in self.schema.partition(&sorted_batch, false)? {
             let value_batch = value_batch.slice(range.start, range.end - range.start);
@@ -1099,6 +1196,79 @@ impl LastKeyValueView {
         )))
     }
 
+    /// Returns the current value of each distinct key in `key_columns` that has one, as a batch
+    /// with the table's schema
+    pub fn get_current_values_for_keys(
+        &self,
+        key_columns: &[ArrayRef],
+        num_rows: usize,
+    ) -> Result<Option<RecordBatch>> {
+        if self.backing_map.is_empty() {
+            return Ok(None);
+        }
+        let key_rows = self
+            .key_converter
+            .convert_all_columns(key_columns, num_rows)?;
+        let mut seen_keys = HashSet::new();
+        let mut indices = vec![];
+        let mut values = vec![];
+        let mut timestamp_builder = TimestampNanosecondArray::builder(num_rows);
+        for i in 0..num_rows {
+            let key_row = key_rows.row(i);
+            if !seen_keys.insert(key_row) {
+                continue;
+            }
+            if let Some(Value {
+                value_row_bytes,
+                timestamp,
+                generation: _,
+            }) = self.backing_map.get(key_row.as_ref())
+            {
+                indices.push(i as u32);
+                values.push(value_row_bytes.as_slice());
+                timestamp_builder.append_value(to_nanos(*timestamp) as i64);
+            }
+        }
+        if indices.is_empty() {
+            return Ok(None);
+        }
+        let indices = UInt32Array::from(indices);
+        let mut key_values = key_columns
+            .iter()
+            .map(|column| take(column, &indices, None))
+            .collect::<std::result::Result<Vec<_>, _>>()?
+            .into_iter();
+        let mut value_values = self.value_converter.convert_raw_rows(values)?.into_iter();
+        let mut timestamps = Some(Arc::new(timestamp_builder.finish()) as ArrayRef);
+
+        let memory_schema = self.parent.schema.memory_schema();
+        let generation_index = self
+            .parent
+            .schema
+            .generation_index()
+            .ok_or_else(|| anyhow!("should have generation index"))?;
+        let mut fields = vec![];
+        let mut columns = vec![];
+        for (index, field) in memory_schema.schema.fields().iter().enumerate() {
+            if index == generation_index {
+                continue;
+            }
+            let column = if self.key_indices.contains(&index) {
+                key_values.next()
+            } else if index == memory_schema.timestamp_index {
+                timestamps.take()
+            } else {
+                value_values.next()
+            };
+            columns.push(column.ok_or_else(|| anyhow!("missing column {}", field.name()))?);
+            fields.push(field.clone());
+        }
+        Ok(Some(RecordBatch::try_new(
+            Arc::new(Schema::new(fields)),
+            columns,
+        )?))
+    }
+
     async fn insert_batch_internal(&mut self, batch: RecordBatch, is_backfill: bool) -> Result<()> {
         if batch.num_rows() == 0 {
             return Ok(());
@@ -33,6 +33,21 @@ pub(crate) fn table_checkpoint_path(
     )
 }
 
+pub(crate) fn in_flight_data_path(
+    job_id: &str,
+    operator_id: &str,
+    subtask_index: usize,
+    input_index: usize,
+    epoch: u32,
+) -> String {
+    format!(
+        "{}/in-flight-{:0>3}-{:0>3}",
+        operator_path(job_id, epoch, operator_id),
+        subtask_index,
+        input_index
+    )
+}
+
 fn operator_path(job_id: &str, epoch: u32, operator: &str) -> String {
     format!("{}/operator-{}", base_path(job_id, epoch), operator)
 }
@@ -6,13 +6,15 @@ use anyhow::{anyhow, bail, Context, Result};
 use arroyo_rpc::CompactionResult;
 use arroyo_rpc::{
     grpc::{
-        OperatorCheckpointMetadata, SubtaskCheckpointMetadata, TableConfig, TableEnum,
-        TableSubtaskCheckpointMetadata,
+        InFlightData, OperatorCheckpointMetadata, SubtaskCheckpointMetadata, TableConfig,
+        TableEnum, TableSubtaskCheckpointMetadata,
     },
     CheckpointCompleted, ControlResp,
 };
 use arroyo_storage::{StorageProvider, StorageProviderRef};
-use arroyo_types::{to_micros, CheckpointBarrier, Data, Key, TaskInfoRef, CHECKPOINT_URL_ENV};
+use arroyo_types::{
+    to_micros, CheckpointBarrier, Data, Key, TaskInfoRef, CHECKPOINT_URL_ENV, KEY_GROUPS,
+};
 use tokio::sync::{
     mpsc::{self, Receiver, Sender},
     oneshot,
@@ -20,6 +22,9 @@ use tokio::sync::{
 
 use tracing::{debug, error, info, warn};
 
+use crate::in_flight::{
+    read_in_flight_batches, write_in_flight_batches, InFlightBatches, RestoredInFlightData,
+};
 use crate::{tables::global_keyed_map::GlobalKeyedTable, StateMessage};
 use crate::{CheckpointMessage, TableData};
 
@@ -39,6 +44,9 @@ pub struct TableManager {
     task_info: TaskInfoRef,
     storage: StorageProviderRef,
     caches: HashMap<String, Box<dyn Any + Send>>,
+    // in-flight data from the unaligned checkpoint we restored from, along with the parallelism
+    // it was written with
+    restored_in_flight: Option<(Vec<InFlightData>, u64)>,
 }
 
 pub struct BackendWriter {
@@ -59,6 +67,26 @@ pub struct BackendFlusher {
     table_checkpointers: HashMap<String, Box<dyn ErasedCheckpointer>>,
     current_epoch: u32,
     last_epoch_checkpoints: HashMap<String, TableSubtaskCheckpointMetadata>,
+    pending_checkpoint: Option<PendingCheckpoint>,
+}
+
+/// An unaligned checkpoint whose tables have been written, which completes once the operator
+/// has received the barriers on all of its inputs and hands over the data that arrived ahead of
+/// them. State messages for the next epoch keep being processed in the meantime.
+struct PendingCheckpoint {
+    checkpoint: CheckpointMessage,
+    metadatas: HashMap<String, TableSubtaskCheckpointMetadata>,
+    bytes: usize,
+    in_flight: oneshot::Receiver<InFlightBatches>,
+}
+
+async fn pending_in_flight(
+    pending: &mut Option<PendingCheckpoint>,
+) -> std::result::Result<InFlightBatches, oneshot::error::RecvError> {
+    match pending {
+        Some(pending) => (&mut pending.in_flight).await,
+        None => std::future::pending().await,
+    }
 }
 
 impl BackendFlusher {
@@ -105,9 +133,23 @@ impl BackendFlusher {
         // accumulate writes in the RecordBatchBuilders until we get a checkpoint
         while checkpoint_epoch.is_none() {
             tokio::select! {
+                in_flight = pending_in_flight(&mut self.pending_checkpoint),
+                    if self.pending_checkpoint.is_some() => {
+                    let pending = self.pending_checkpoint.take().unwrap();
+                    if !self.complete_unaligned_checkpoint(pending, in_flight).await? {
+                        return Ok(false);
+                    }
+                }
                 op = self.queue.recv() => {
                     match op {
                         Some(StateMessage::Checkpoint(checkpoint)) => {
+                            // checkpoints complete in order
+                            if let Some(mut pending) = self.pending_checkpoint.take() {
+                                let in_flight = (&mut pending.in_flight).await;
+                                if !self.complete_unaligned_checkpoint(pending, in_flight).await? {
+                                    return Ok(false);
+                                }
+                            }
                             checkpoint_epoch = Some(checkpoint);
                         }
                         Some(StateMessage::Compaction(compacted_tables_message)) => {
@@ -126,7 +168,7 @@ impl BackendFlusher {
                 }
             }
         }
-        let Some(cp) = checkpoint_epoch else {
+        let Some(mut cp) = checkpoint_epoch else {
             bail!("somehow exited loop without checkpoint_epoch being set");
         };
         let mut metadatas = HashMap::new();
@@ -161,6 +203,60 @@ impl BackendFlusher {
         self.last_epoch_checkpoints = metadatas.clone();
         self.current_epoch += 1;
 
+        // for unaligned checkpoints, the data that arrived ahead of the barriers has to be
+        // persisted as well; we wait for it alongside the next epoch's state messages, unless
+        // the operator is stopping
+        if let Some(in_flight) = cp.in_flight.take() {
+            let mut pending = PendingCheckpoint {
+                checkpoint: cp,
+                metadatas,
+                bytes,
+                in_flight,
+            };
+            if pending.checkpoint.then_stop {
+                let in_flight = (&mut pending.in_flight).await;
+                return self.complete_unaligned_checkpoint(pending, in_flight).await;
+            }
+            self.pending_checkpoint = Some(pending);
+            return Ok(true);
+        }
+
+        self.complete_checkpoint(cp, metadatas, bytes, vec![]).await
+    }
+
+    async fn complete_unaligned_checkpoint(
+        &mut self,
+        pending: PendingCheckpoint,
+        in_flight: std::result::Result<InFlightBatches, oneshot::error::RecvError>,
+    ) -> Result<bool> {
+        let PendingCheckpoint {
+            checkpoint,
+            metadatas,
+            mut bytes,
+            ..
+        } = pending;
+        let batches = in_flight.map_err(|_| {
+            anyhow!(
+                "operator stopped before completing unaligned checkpoint {}",
+                checkpoint.epoch
+            )
+        })?;
+        let (in_flight_data, size) =
+            write_in_flight_batches(&self.storage, &self.task_info, checkpoint.epoch, batches)
+                .await?;
+        bytes += size;
+        self.complete_checkpoint(checkpoint, metadatas, bytes, in_flight_data)
+            .await
+    }
+
+    /// Reports the checkpoint to the controller, returning whether to keep flushing
+    async fn complete_checkpoint(
+        &mut self,
+        cp: CheckpointMessage,
+        metadatas: HashMap<String, TableSubtaskCheckpointMetadata>,
+        bytes: usize,
+        in_flight_data: Vec<InFlightData>,
+    ) -> Result<bool> {
         // send controller the subtask metadata
         let subtask_metadata = SubtaskCheckpointMetadata {
             subtask_index: self.task_info.task_index as u32,
@@ -170,6 +266,7 @@ impl BackendFlusher {
             table_metadata: metadatas,
             table_configs: self.table_configs.clone(),
             bytes: bytes as u64,
+            in_flight_data,
         };
         self.control_tx
             .send(ControlResp::CheckpointCompleted(CheckpointCompleted {
@@ -214,6 +311,7 @@ impl BackendWriter {
             current_epoch,
             table_checkpointers: HashMap::new(),
             last_epoch_checkpoints,
+            pending_checkpoint: None,
         })
         .start();
 
@@ -281,14 +379,31 @@ impl TableManager {
         let epoch;
         let min_epoch;
         let mut last_epoch_checkpoints = HashMap::new();
+        let mut restored_in_flight = None;
         match checkpoint_metadata {
             Some(metadata) => {
                 // TODO: validate this logic.
                 let Some(operator_metadata) = metadata.operator_metadata else {
                     bail!("missing operator metadata");
                 };
+                // checkpoints from before key groups were introduced have key_groups = 0, and are
+                // restored by filtering on the key hash
+                if operator_metadata.key_groups != 0 && operator_metadata.key_groups != KEY_GROUPS {
+                    bail!(
+                        "checkpoint for operator {} was written with {} key groups, but this version of Arroyo uses {}",
+                        operator_metadata.operator_id,
+                        operator_metadata.key_groups,
+                        KEY_GROUPS
+                    );
+                }
                 epoch = operator_metadata.epoch + 1;
                 min_epoch = operator_metadata.epoch;
+                if !metadata.in_flight_data.is_empty() {
+                    restored_in_flight = Some((
+                        metadata.in_flight_data.clone(),
+                        operator_metadata.parallelism,
+                    ));
+                }
                 for (table, table_metadata) in metadata.table_checkpoint_metadata.clone() {
                     let table_implementation = tables
                         .get(&table)
@@ -323,10 +438,19 @@ impl TableManager {
             task_info,
             storage,
             caches: HashMap::new(),
+            restored_in_flight,
         })
     }
 
-    pub async fn checkpoint(&mut self, barrier: CheckpointBarrier, watermark: Option<SystemTime>) {
+    /// Checkpoints all tables. For unaligned checkpoints, `in_flight` should resolve to the data
+    /// received ahead of the barriers on the remaining inputs; the checkpoint does not complete
+    /// until it does.
+    pub async fn checkpoint(
+        &mut self,
+        barrier: CheckpointBarrier,
+        watermark: Option<SystemTime>,
+        in_flight: Option<oneshot::Receiver<InFlightBatches>>,
+    ) {
         self.writer
             .sender
             .send(StateMessage::Checkpoint(CheckpointMessage {
@@ -334,6 +458,7 @@ impl TableManager {
                 time: barrier.timestamp,
                 watermark,
                 then_stop: barrier.then_stop,
+                in_flight,
             }))
             .await
             .expect("should be able to send checkpoint");
@@ -346,6 +471,29 @@ impl TableManager {
         }
     }
 
+    /// Takes the in-flight data persisted by the unaligned checkpoint this task was restored from,
+    /// which must be processed before any new input. `input_partitions` is the current number of
+    /// partitions of each of the task's inputs.
+    pub async fn take_in_flight_data(
+        &mut self,
+        input_partitions: &[usize],
+    ) -> Result<RestoredInFlightData> {
+        let Some((data, parallelism)) = self.restored_in_flight.take() else {
+            return Ok(RestoredInFlightData::default());
+        };
+
+        let rescaled = parallelism != self.task_info.parallelism as u64;
+        let data: Vec<_> = data
+            .into_iter()
+            .filter(|d| rescaled || d.subtask_index as usize == self.task_info.task_index)
+            .collect();
+
+        Ok(RestoredInFlightData {
+            rescaled,
+            batches: read_in_flight_batches(&self.storage, &data, input_partitions).await?,
+        })
+    }
+
     pub async fn load_compacted(&mut self, compacted: CompactionResult) -> Result<()> {
         if compacted.operator_id != self.task_info.operator_id {
             bail!("shouldn't be loading compaction for other operator");
@@ -80,6 +80,16 @@ pub const DEFAULT_LINGER: Duration = Duration::from_millis(100);
 pub const DEFAULT_BATCH_SIZE: usize = 512;
 pub const QUEUE_SIZE_ENV: &str = "QUEUE_SIZE";
 pub const DEFAULT_QUEUE_SIZE: u32 = 8 * 1024;
+/// How long updating aggregates keep state for keys that haven't been updated, unless configured
+pub const DEFAULT_UPDATING_AGGREGATE_TTL: Duration = Duration::from_secs(60 * 60 * 24);
+/// How long non-windowed joins keep rows from each side, unless configured
+pub const DEFAULT_JOIN_TTL: Duration = Duration::from_secs(60 * 60);
+/// How long temporal joins keep the version of a key that hasn't been updated, unless configured
+pub const DEFAULT_TEMPORAL_JOIN_TTL: Duration = Duration::from_secs(60 * 60 * 24);
+/// How long deduplication remembers a key after its first row, unless configured
+pub const DEFAULT_DEDUPLICATION_TTL: Duration = Duration::from_secs(60 * 60 * 24);
+/// How long MATCH_RECOGNIZE keeps a partial match without a WITHIN, unless configured
+pub const DEFAULT_MATCH_RECOGNIZE_TTL: Duration = Duration::from_secs(60 * 60 * 24);
 pub const TASK_SLOTS_ENV: &str = "TASK_SLOTS";
 pub const CONTROLLER_ADDR_ENV: &str = "CONTROLLER_ADDR";
 pub const API_ADDR_ENV: &str = "API_ADDR";
@@ -120,6 +130,23 @@ pub const API_ENDPOINT_ENV: &str = "API_ENDPOINT";
 // be at least 4x the configured scrape interval for your prometheus config
 pub const API_METRICS_RATE_ENV: &str = "API_METRICS_RATE";
 
+// API authentication: one of "none" (default), "token", or "jwt"
+pub const API_AUTH_MODE_ENV: &str = "API_AUTH_MODE";
+// A static admin token that is always accepted when auth is enabled, used to bootstrap API tokens
+pub const API_ADMIN_TOKEN_ENV: &str = "API_ADMIN_TOKEN";
+// JWKS file containing the public keys used to verify JWTs in "jwt" mode
+pub const API_JWKS_FILE_ENV: &str = "API_JWKS_FILE";
+pub const API_JWT_ISSUER_ENV: &str = "API_JWT_ISSUER";
+pub const API_JWT_AUDIENCE_ENV: &str = "API_JWT_AUDIENCE";
+// Organization for JWTs without an org_id claim; if unset, those tokens are rejected
+pub const API_JWT_DEFAULT_ORG_ENV: &str = "API_JWT_DEFAULT_ORG";
+
+// secrets: base64-encoded 256-bit key used to encrypt connection profile secrets at rest
+pub const SECRETS_MASTER_KEY_ENV: &str = "SECRETS_MASTER_KEY";
+// directory that file-based secrets (e.g., kubernetes secret volumes) are mounted under
+pub const SECRETS_DIR_ENV: &str = "SECRETS_DIR";
+pub const SECRETS_DIR_DEFAULT: &str = "/var/run/secrets/arroyo";
+
 // storage configuration
 pub const S3_ENDPOINT_ENV: &str = "S3_ENDPOINT";
 pub const S3_REGION_ENV: &str = "S3_REGION";
@@ -322,6 +349,16 @@ pub fn from_nanos(ts: u128) -> SystemTime {
         + Duration::from_nanos((ts % 1_000_000_000) as u64)
 }
 
+/// The pane size for a sliding window, the largest duration that evenly divides both the
+/// width and the slide. Sliding windows aggregate each pane once and combine panes into windows.
+pub fn sliding_window_pane(width: Duration, slide: Duration) -> Duration {
+    let (mut a, mut b) = (width.as_nanos(), slide.as_nanos());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    Duration::from_nanos(a as u64)
+}
+
 pub fn print_time(time: SystemTime) -> String {
     chrono::DateTime::<chrono::Utc>::from(time)
         .format("%Y-%m-%d %H:%M:%S%.3f")
@@ -598,6 +635,10 @@ impl TaskInfo {
         }
     }
 
+    pub fn key_groups(&self) -> RangeInclusive<u32> {
+        key_groups_for_server(self.task_index, self.parallelism)
+    }
+
     pub fn metric_label_map(&self) -> HashMap<String, String> {
         let mut labels = HashMap::new();
         labels.insert("operator_id".to_string(), self.operator_id.clone());
@@ -648,6 +689,27 @@ pub static BATCHES_SENT: &str = "arroyo_worker_batches_sent";
 pub static TX_QUEUE_SIZE: &str = "arroyo_worker_tx_queue_size";
 pub static TX_QUEUE_REM: &str = "arroyo_worker_tx_queue_rem";
 pub static DESERIALIZATION_ERRORS: &str = "arroyo_worker_deserialization_errors";
+pub static LATE_ROWS_DROPPED: &str = "arroyo_worker_late_rows_dropped";
+pub static INPUT_WATERMARK: &str = "arroyo_worker_input_watermark";
+pub static OUTPUT_WATERMARK: &str = "arroyo_worker_output_watermark";
+pub static MAX_EVENT_TIME: &str = "arroyo_worker_max_event_time";
+pub static SOURCE_IDLE: &str = "arroyo_worker_source_idle";
+
+/// Determines how operators with multiple inputs handle checkpoint barriers
+#[derive(Debug, Copy, Clone, Encode, Decode, PartialEq, Eq, Default)]
+pub enum CheckpointAlignment {
+    /// Inputs are blocked once their barrier arrives until the barrier has been received on all
+    /// inputs, at which point the operator checkpoints
+    #[default]
+    Aligned,
+    /// The operator checkpoints as soon as the first barrier arrives, and data received on the
+    /// other inputs before their barriers is persisted as part of the checkpoint. Barriers are
+    /// still processed in order with the data queued ahead of them on their input.
+    Unaligned,
+    /// Checkpoints start aligned, but switch to unaligned if alignment takes longer than the
+    /// timeout
+    AlignedWithTimeout(Duration),
+}
 
 #[derive(Debug, Copy, Clone, Encode, Decode, PartialEq, Eq)]
 pub struct CheckpointBarrier {
@@ -655,6 +717,7 @@ pub struct CheckpointBarrier {
     pub min_epoch: u32,
     pub timestamp: SystemTime,
     pub then_stop: bool,
+    pub alignment: CheckpointAlignment,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Hash, Serialize)]
@@ -759,12 +822,51 @@ impl TryFrom<&str> for DateTruncPrecision {
     }
 }
 
+/// The key space is divided into this many key groups, which are the unit of state assignment
+/// between subtasks. Each subtask owns a contiguous range of key groups, so state can be
+/// redistributed on rescaling without re-partitioning individual keys. This also bounds the
+/// maximum parallelism of any operator.
+pub const KEY_GROUPS: u32 = 1024;
+
+pub fn key_group_for_hash(x: u64) -> u32 {
+    let group_size = (u64::MAX / (KEY_GROUPS as u64)) + 1;
+    (x / group_size) as u32
+}
+
+/// Returns the (inclusive) range of key groups owned by subtask `i` of `n`
+pub fn key_groups_for_server(i: usize, n: usize) -> RangeInclusive<u32> {
+    assert!(
+        n <= KEY_GROUPS as usize,
+        "parallelism {} exceeds the maximum of {}",
+        n,
+        KEY_GROUPS
+    );
+    let start = (i as u64 * KEY_GROUPS as u64).div_ceil(n as u64) as u32;
+    let end = ((i as u64 + 1) * KEY_GROUPS as u64).div_ceil(n as u64) as u32 - 1;
+    start..=end
+}
+
+/// Returns the range of hashes covered by the (inclusive) range of key groups
+pub fn range_for_key_groups(groups: &RangeInclusive<u32>) -> RangeInclusive<u64> {
+    let group_size = (u64::MAX / (KEY_GROUPS as u64)) + 1;
+    let start = group_size * (*groups.start() as u64);
+    let end = if *groups.end() + 1 == KEY_GROUPS {
+        u64::MAX
+    } else {
+        group_size * (*groups.end() as u64 + 1) - 1
+    };
+    start..=end
+}
+
+pub fn server_for_key_group(group: u32, n: usize) -> usize {
+    (group as u64 * n as u64 / KEY_GROUPS as u64) as usize
+}
+
 pub fn server_for_hash(x: u64, n: usize) -> usize {
     if n == 1 {
         0
     } else {
-        let range_size = (u64::MAX / (n as u64)) + 1;
-        (x / range_size) as usize
+        server_for_key_group(key_group_for_hash(x), n)
     }
 }
 
@@ -772,14 +874,7 @@ pub fn range_for_server(i: usize, n: usize) -> RangeInclusive<u64> {
     if n == 1 {
         return 0..=u64::MAX;
     }
-    let range_size = (u64::MAX / (n as u64)) + 1;
-    let start = range_size * (i as u64);
-    let end = if i + 1 == n {
-        u64::MAX
-    } else {
-        start + range_size - 1
-    };
-    start..=end
+    range_for_key_groups(&key_groups_for_server(i, n))
 }
 
 pub fn should_flush(size: usize, time: Instant) -> bool {
@@ -832,6 +927,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_key_groups_for_server() {
+        for n in [1, 3, 7, 64, KEY_GROUPS as usize] {
+            let mut next_group = 0;
+            for i in 0..n {
+                let groups = key_groups_for_server(i, n);
+                assert_eq!(*groups.start(), next_group, "key groups not adjacent");
+                for group in groups.clone() {
+                    assert_eq!(i, server_for_key_group(group, n));
+                }
+                let range = range_for_key_groups(&groups);
+                assert_eq!(i, server_for_hash(*range.start(), n));
+                assert_eq!(i, server_for_hash(*range.end(), n));
+                next_group = *groups.end() + 1;
+            }
+            assert_eq!(next_group, KEY_GROUPS, "not all key groups assigned");
+        }
+    }
+
+    #[test]
+    fn test_sliding_window_pane() {
+        let minutes = |m| Duration::from_secs(m * 60);
+        assert_eq!(sliding_window_pane(minutes(60), minutes(1)), minutes(1));
+        assert_eq!(sliding_window_pane(minutes(10), minutes(3)), minutes(1));
+        assert_eq!(sliding_window_pane(minutes(60), minutes(40)), minutes(20));
+        assert_eq!(
+            sliding_window_pane(Duration::from_millis(2500), Duration::from_secs(1)),
+            Duration::from_millis(500)
+        );
+    }
+
     #[test]
     fn test_server_for_hash() {
         let n = 2;
@@ -3,6 +3,7 @@ use arrow::row::{OwnedRow, RowConverter, SortField};
 use arrow_array::{make_array, Array, RecordBatch, UInt64Array};
 use arrow_schema::{Field, Schema};
 use arroyo_datastream::logical::DylibUdfConfig;
+use arroyo_datastream::state_tables::ASYNC_UDF;
 use arroyo_df::ASYNC_RESULT_FIELD;
 use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::{ArrowOperator, OperatorConstructor, OperatorNode, Registry};
@@ -107,7 +108,7 @@ impl ArrowOperator for AsyncUdfOperator {
     }
 
     fn tables(&self) -> HashMap<String, TableConfig> {
-        global_table_config("a", "AsyncMapOperator state")
+        global_table_config(ASYNC_UDF.name, ASYNC_UDF.description)
     }
 
     async fn on_start(&mut self, ctx: &mut ArrowContext) {
@@ -187,7 +188,7 @@ impl ArrowOperator for AsyncUdfOperator {
 
         let gs = ctx
             .table_manager
-            .get_global_keyed_state::<usize, AsyncUdfState>("a")
+            .get_global_keyed_state::<usize, AsyncUdfState>(ASYNC_UDF.name)
             .await
             .unwrap();
 
@@ -344,7 +345,11 @@ impl ArrowOperator for AsyncUdfOperator {
     }
 
     async fn handle_checkpoint(&mut self, _: CheckpointBarrier, ctx: &mut ArrowContext) {
-        let gs = ctx.table_manager.get_global_keyed_state("a").await.unwrap();
+        let gs = ctx
+            .table_manager
+            .get_global_keyed_state(ASYNC_UDF.name)
+            .await
+            .unwrap();
 
         let state = AsyncUdfState {
             inputs: self
@@ -0,0 +1,257 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Result};
+use arrow::compute::{concat_batches, filter_record_batch, not, take};
+use arrow_array::{RecordBatch, UInt32Array};
+use arroyo_datastream::state_tables::{DEDUPLICATE_PENDING, DEDUPLICATE_SEEN_KEYS};
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::{ArrowOperator, OperatorConstructor, OperatorNode, Registry};
+use arroyo_rpc::{
+    df::ArroyoSchema,
+    grpc::{api, TableConfig},
+};
+use arroyo_state::timestamp_table_config;
+use arroyo_types::{from_nanos, CheckpointBarrier, Watermark};
+
+/// Passes on the earliest row of each key by event time, remembering the key in a table that
+/// expires it `ttl` after that row, so that state stays bounded and the output only has appends.
+/// Rows are buffered until the watermark passes them, as an earlier row of their key may still
+/// arrive until then.
+pub struct DeduplicateOperator {
+    input_schema: ArroyoSchema,
+    // the input without keys, used for splitting batches by time
+    unkeyed_schema: ArroyoSchema,
+    // the keys and timestamp of the input, which is all the seen keys table needs to keep
+    state_schema: ArroyoSchema,
+    state_indices: Vec<usize>,
+    ttl: Duration,
+    pending: BTreeMap<SystemTime, Vec<RecordBatch>>,
+}
+
+impl DeduplicateOperator {
+    /// Splits the batch into the rows at each timestamp, keeping rows at the same timestamp in
+    /// arrival order
+    fn split_by_timestamp(&self, batch: RecordBatch) -> Result<Vec<(SystemTime, RecordBatch)>> {
+        let sorted_batch = self.unkeyed_schema.sort_stable(batch, true)?;
+        let timestamps = self.unkeyed_schema.timestamp_column(&sorted_batch);
+        Ok(self
+            .unkeyed_schema
+            .partition(&sorted_batch, true)?
+            .into_iter()
+            .map(|range| {
+                (
+                    from_nanos(timestamps.value(range.start) as u128),
+                    sorted_batch.slice(range.start, range.end - range.start),
+                )
+            })
+            .collect())
+    }
+
+    /// The earliest row of each key of the batch; of rows at the same time, the first to arrive
+    fn first_rows(&self, batch: RecordBatch) -> Result<RecordBatch> {
+        let sorted_batch = self.input_schema.sort_stable(batch, true)?;
+        let indices: UInt32Array = self
+            .input_schema
+            .partition(&sorted_batch, false)?
+            .into_iter()
+            .map(|range| range.start as u32)
+            .collect();
+        let columns = sorted_batch
+            .columns()
+            .iter()
+            .map(|column| take(column, &indices, None))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(RecordBatch::try_new(sorted_batch.schema(), columns)?)
+    }
+
+    async fn deduplicate(
+        &mut self,
+        batch: RecordBatch,
+        ctx: &mut ArrowContext,
+    ) -> Result<Option<RecordBatch>> {
+        let first_rows = self.first_rows(batch)?;
+        let table = ctx
+            .table_manager
+            .get_last_key_value_table(DEDUPLICATE_SEEN_KEYS.name, ctx.last_present_watermark())
+            .await?;
+        let state_batch = first_rows.project(&self.state_indices)?;
+        // drop the rows whose keys have already been seen
+        let (new_rows, new_state) = match table.get_current_matching_values(&state_batch)? {
+            Some((_, seen)) => {
+                let unseen = not(&seen)?;
+                (
+                    filter_record_batch(&first_rows, &unseen)?,
+                    filter_record_batch(&state_batch, &unseen)?,
+                )
+            }
+            None => (first_rows, state_batch),
+        };
+        if new_rows.num_rows() == 0 {
+            return Ok(None);
+        }
+        table.insert_batch(new_state).await?;
+        Ok(Some(self.input_schema.unkeyed_batch(&new_rows)?))
+    }
+}
+
+#[async_trait::async_trait]
+impl ArrowOperator for DeduplicateOperator {
+    fn name(&self) -> String {
+        "Deduplicate".to_string()
+    }
+
+    async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        let watermark = ctx.last_present_watermark();
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table(DEDUPLICATE_PENDING.name, watermark)
+            .await
+            .expect("should have pending table");
+        for (timestamp, batches) in table.all_batches_for_watermark(watermark) {
+            self.pending
+                .entry(*timestamp)
+                .or_default()
+                .extend(batches.iter().cloned());
+        }
+        // fetch the table so the seen keys are ready to be queried
+        ctx.table_manager
+            .get_last_key_value_table(DEDUPLICATE_SEEN_KEYS.name, watermark)
+            .await
+            .expect("should have seen keys table");
+    }
+
+    async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
+        let current_watermark = ctx.last_present_watermark();
+        let (batch, late) = self
+            .input_schema
+            .split_by_time(batch, current_watermark)
+            .expect("should split by time");
+        ctx.collect_late(late).await;
+        if batch.num_rows() == 0 {
+            return;
+        }
+        let batches = self.split_by_timestamp(batch).expect("should split batch");
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table(DEDUPLICATE_PENDING.name, current_watermark)
+            .await
+            .expect("should have pending table");
+        for (timestamp, batch) in batches {
+            table.insert(timestamp, batch.clone());
+            self.pending.entry(timestamp).or_default().push(batch);
+        }
+    }
+
+    async fn handle_watermark(
+        &mut self,
+        watermark_message: Watermark,
+        ctx: &mut ArrowContext,
+    ) -> Option<Watermark> {
+        let Some(watermark) = ctx.last_present_watermark() else {
+            return Some(watermark_message);
+        };
+        // no earlier rows can arrive for the rows the watermark has passed
+        let pending = self.pending.split_off(&watermark);
+        let ready = std::mem::replace(&mut self.pending, pending);
+        if !ready.is_empty() {
+            let batch = concat_batches(&self.input_schema.schema, ready.values().flatten())
+                .expect("should concat pending rows");
+            if let Some(batch) = self
+                .deduplicate(batch, ctx)
+                .await
+                .expect("should deduplicate rows")
+            {
+                ctx.collect(batch).await;
+            }
+        }
+        ctx.table_manager
+            .get_last_key_value_table(DEDUPLICATE_SEEN_KEYS.name, Some(watermark))
+            .await
+            .expect("should have seen keys table")
+            .expire(Some(watermark))
+            .expect("should expire seen keys");
+        Some(watermark_message)
+    }
+
+    async fn handle_checkpoint(&mut self, _cb: CheckpointBarrier, ctx: &mut ArrowContext) {
+        let watermark = ctx.last_present_watermark();
+        ctx.table_manager
+            .get_expiring_time_key_table(DEDUPLICATE_PENDING.name, watermark)
+            .await
+            .expect("should have pending table")
+            .flush(watermark)
+            .await
+            .expect("should flush");
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        vec![
+            (
+                DEDUPLICATE_PENDING.name.to_string(),
+                timestamp_table_config(
+                    DEDUPLICATE_PENDING.name,
+                    DEDUPLICATE_PENDING.description,
+                    Duration::ZERO,
+                    false,
+                    self.input_schema.clone(),
+                ),
+            ),
+            (
+                DEDUPLICATE_SEEN_KEYS.name.to_string(),
+                timestamp_table_config(
+                    DEDUPLICATE_SEEN_KEYS.name,
+                    DEDUPLICATE_SEEN_KEYS.description,
+                    self.ttl,
+                    true,
+                    self.state_schema.clone(),
+                ),
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+}
+
+pub struct DeduplicateConstructor;
+
+impl OperatorConstructor for DeduplicateConstructor {
+    type ConfigT = api::DeduplicateOperator;
+
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        _registry: Arc<Registry>,
+    ) -> anyhow::Result<OperatorNode> {
+        let input_schema: ArroyoSchema = config
+            .input_schema
+            .ok_or_else(|| anyhow!("requires input schema"))?
+            .try_into()?;
+        let key_indices = input_schema
+            .key_indices
+            .clone()
+            .ok_or_else(|| anyhow!("deduplicate input should be keyed"))?;
+
+        let mut state_indices = key_indices.clone();
+        state_indices.push(input_schema.timestamp_index);
+        let state_schema = ArroyoSchema::new_keyed(
+            Arc::new(input_schema.schema.project(&state_indices)?),
+            key_indices.len(),
+            (0..key_indices.len()).collect(),
+        );
+
+        let unkeyed_schema = ArroyoSchema::from_schema_unkeyed(input_schema.schema.clone())?;
+
+        Ok(OperatorNode::from_operator(Box::new(DeduplicateOperator {
+            input_schema,
+            unkeyed_schema,
+            state_schema,
+            state_indices,
+            ttl: Duration::from_micros(config.ttl_micros),
+            pending: BTreeMap::new(),
+        })))
+    }
+}
@@ -9,6 +9,7 @@ use std::{
 use anyhow::Result;
 use arrow::compute::{max, min, partition, sort_to_indices, take};
 use arrow_array::{RecordBatch, TimestampNanosecondArray};
+use arroyo_datastream::state_tables::{JOIN_LEFT, JOIN_RIGHT};
 use arroyo_df::physical::{ArroyoPhysicalExtensionCodec, DecodingContext};
 use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::{ArrowOperator, OperatorConstructor, OperatorNode, Registry};
@@ -59,8 +60,8 @@ enum Side {
 impl Side {
     fn name(&self) -> &'static str {
         match self {
-            Side::Left => "left",
-            Side::Right => "right",
+            Side::Left => JOIN_LEFT.name,
+            Side::Right => JOIN_RIGHT.name,
         }
     }
 }
@@ -196,7 +197,7 @@ impl ArrowOperator for InstantJoin {
         let watermark = ctx.last_present_watermark();
         let left_table = ctx
             .table_manager
-            .get_expiring_time_key_table("left", watermark)
+            .get_expiring_time_key_table(JOIN_LEFT.name, watermark)
             .await
             .expect("should have left table");
         let left_batches: Vec<_> = left_table
@@ -210,7 +211,7 @@ impl ArrowOperator for InstantJoin {
         }
         let right_table = ctx
             .table_manager
-            .get_expiring_time_key_table("right", watermark)
+            .get_expiring_time_key_table(JOIN_RIGHT.name, watermark)
             .await
             .expect("should have right table");
         let right_batches: Vec<_> = right_table
@@ -285,14 +286,14 @@ impl ArrowOperator for InstantJoin {
     async fn handle_checkpoint(&mut self, _b: CheckpointBarrier, ctx: &mut ArrowContext) {
         let watermark = ctx.last_present_watermark();
         ctx.table_manager
-            .get_expiring_time_key_table("left", watermark)
+            .get_expiring_time_key_table(JOIN_LEFT.name, watermark)
             .await
             .expect("should have left table")
             .flush(watermark)
             .await
             .expect("should flush");
         ctx.table_manager
-            .get_expiring_time_key_table("right", watermark)
+            .get_expiring_time_key_table(JOIN_RIGHT.name, watermark)
             .await
             .expect("should have right table")
             .flush(watermark)
@@ -303,20 +304,20 @@ impl ArrowOperator for InstantJoin {
     fn tables(&self) -> HashMap<String, TableConfig> {
         let mut tables = HashMap::new();
         tables.insert(
-            "left".to_string(),
+            JOIN_LEFT.name.to_string(),
             timestamp_table_config(
-                "left",
-                "left join data",
+                JOIN_LEFT.name,
+                JOIN_LEFT.description,
                 Duration::ZERO,
                 false,
                 self.left_input_schema.as_ref().clone(),
             ),
         );
         tables.insert(
-            "right".to_string(),
+            JOIN_RIGHT.name.to_string(),
             timestamp_table_config(
-                "right",
-                "right join data",
+                JOIN_RIGHT.name,
+                JOIN_RIGHT.description,
                 Duration::ZERO,
                 false,
                 self.right_input_schema.as_ref().clone(),
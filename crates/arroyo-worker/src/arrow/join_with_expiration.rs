@@ -7,6 +7,7 @@ use std::{
 use anyhow::Result;
 use arrow::compute::concat_batches;
 use arrow_array::RecordBatch;
+use arroyo_datastream::state_tables::{join_retention, JOIN_LEFT, JOIN_RIGHT};
 use arroyo_df::physical::{ArroyoPhysicalExtensionCodec, DecodingContext};
 use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::{ArrowOperator, OperatorConstructor, OperatorNode, Registry};
@@ -15,6 +16,7 @@ use arroyo_rpc::{
     grpc::{api, TableConfig},
 };
 use arroyo_state::timestamp_table_config;
+use arroyo_types::{Watermark, DEFAULT_JOIN_TTL};
 use datafusion::execution::context::SessionContext;
 use datafusion::execution::runtime_env::{RuntimeConfig, RuntimeEnv};
 use datafusion::physical_plan::ExecutionPlan;
@@ -42,7 +44,7 @@ impl JoinWithExpiration {
     ) -> Result<()> {
         let left_table = ctx
             .table_manager
-            .get_key_time_table("left", ctx.last_present_watermark())
+            .get_key_time_table(JOIN_LEFT.name, ctx.last_present_watermark())
             .await
             .expect("should have left table");
         let left_rows = left_table
@@ -51,7 +53,7 @@ impl JoinWithExpiration {
             .expect("should insert");
         let right_table = ctx
             .table_manager
-            .get_key_time_table("right", ctx.last_present_watermark())
+            .get_key_time_table(JOIN_RIGHT.name, ctx.last_present_watermark())
             .await
             .expect("should have right table");
         let mut right_batches = vec![];
@@ -80,7 +82,7 @@ impl JoinWithExpiration {
     ) -> Result<()> {
         let right_table = ctx
             .table_manager
-            .get_key_time_table("right", ctx.last_present_watermark())
+            .get_key_time_table(JOIN_RIGHT.name, ctx.last_present_watermark())
             .await
             .expect("should have right table");
         let right_rows = right_table
@@ -89,7 +91,7 @@ impl JoinWithExpiration {
             .expect("should insert");
         let left_table = ctx
             .table_manager
-            .get_key_time_table("left", ctx.last_present_watermark())
+            .get_key_time_table(JOIN_LEFT.name, ctx.last_present_watermark())
             .await
             .expect("should have left table");
         let mut left_batches = vec![];
@@ -162,23 +164,40 @@ impl ArrowOperator for JoinWithExpiration {
         }
     }
 
+    async fn handle_watermark(
+        &mut self,
+        watermark: Watermark,
+        ctx: &mut ArrowContext,
+    ) -> Option<Watermark> {
+        let current_watermark = ctx.last_present_watermark();
+        for table in [JOIN_LEFT.name, JOIN_RIGHT.name] {
+            ctx.table_manager
+                .get_key_time_table(table, current_watermark)
+                .await
+                .expect("should have join table")
+                .expire(current_watermark)
+                .expect("should expire join table");
+        }
+        Some(watermark)
+    }
+
     fn tables(&self) -> HashMap<String, TableConfig> {
         let mut tables = HashMap::new();
         tables.insert(
-            "left".to_string(),
+            JOIN_LEFT.name.to_string(),
             timestamp_table_config(
-                "left",
-                "left join data",
+                JOIN_LEFT.name,
+                JOIN_LEFT.description,
                 self.left_expiration,
                 false,
                 self.left_input_schema.clone(),
             ),
         );
         tables.insert(
-            "right".to_string(),
+            JOIN_RIGHT.name.to_string(),
             timestamp_table_config(
-                "right",
-                "right join data",
+                JOIN_RIGHT.name,
+                JOIN_RIGHT.description,
                 self.right_expiration,
                 false,
                 self.right_input_schema.clone(),
@@ -216,10 +235,20 @@ impl OperatorConstructor for JoinWithExpirationConstructor {
         let right_input_schema: ArroyoSchema = config.right_schema.unwrap().try_into()?;
         let left_schema = left_input_schema.schema_without_keys()?;
         let right_schema = right_input_schema.schema_without_keys()?;
+        let ttl = config
+            .ttl_micros
+            .map(Duration::from_micros)
+            .unwrap_or(DEFAULT_JOIN_TTL);
+
+        let (left_expiration, right_expiration) = join_retention(
+            ttl,
+            config.left_ttl_micros.map(Duration::from_micros),
+            config.right_ttl_micros.map(Duration::from_micros),
+        );
 
         Ok(OperatorNode::from_operator(Box::new(JoinWithExpiration {
-            left_expiration: Duration::from_secs(3600),
-            right_expiration: Duration::from_secs(3600),
+            left_expiration,
+            right_expiration,
             left_input_schema,
             right_input_schema,
             left_schema,
@@ -0,0 +1,673 @@
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Result};
+use arrow::compute::{concat_batches, filter_record_batch, not};
+use arrow::row::OwnedRow;
+use arrow_array::{cast::AsArray, ArrayRef, BooleanArray, RecordBatch, TimestampNanosecondArray};
+use arrow_schema::DataType;
+use arroyo_datastream::state_tables::{
+    match_recognize_retention, MATCH_RECOGNIZE_MATCHES, MATCH_RECOGNIZE_ROWS,
+};
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::{ArrowOperator, OperatorConstructor, OperatorNode, Registry};
+use arroyo_rpc::{
+    df::ArroyoSchema,
+    grpc::{api, TableConfig},
+    Converter,
+};
+use arroyo_state::timestamp_table_config;
+use arroyo_types::{from_nanos, to_nanos, CheckpointBarrier, Watermark};
+use datafusion::common::ScalarValue;
+use tracing::warn;
+
+/// The most partial matches a key may have at once. Without a WITHIN, runs are only ended by the
+/// TTL, so patterns like `A+ B` over rows that never meet `B` would otherwise start a run per row
+/// and keep every one of them; past the limit, the oldest runs are ended early.
+const MAX_RUNS_PER_KEY: usize = 1000;
+
+struct PatternState {
+    // (variable, target state)
+    transitions: Vec<(usize, usize)>,
+    accepting: bool,
+}
+
+struct Measure {
+    aggregate: api::MatchAggregate,
+    variable: Option<usize>,
+    argument: Option<usize>,
+    data_type: DataType,
+}
+
+/// A row of the input, holding just what the measures need
+struct MatchRow {
+    // the position of the row in its partition, which orders matches
+    sequence: u64,
+    timestamp: SystemTime,
+    // the value of each measure's argument at this row
+    arguments: Vec<ScalarValue>,
+}
+
+/// The rows of a match, along with the variable each row was mapped to
+type MatchRows = Vec<(Arc<MatchRow>, usize)>;
+
+/// A partial match of the pattern, which later rows may extend
+#[derive(Clone)]
+struct Run {
+    state: usize,
+    // the sequence number of the row the run started at
+    start: u64,
+    start_time: SystemTime,
+    rows: MatchRows,
+    // the number of rows of the longest complete match the run has passed through
+    matched: Option<usize>,
+}
+
+/// The partial matches of a single key
+struct PartitionState {
+    keys: Vec<ScalarValue>,
+    next_sequence: u64,
+    runs: Vec<Run>,
+    // complete matches by the row they start at, waiting on the runs from earlier rows, which
+    // take precedence, and on longer matches from the same row
+    finished: BTreeMap<u64, Vec<MatchRows>>,
+    // rows up to this sequence number are part of an emitted match, so can't start another
+    skip_through: Option<u64>,
+}
+
+impl PartitionState {
+    fn new(keys: Vec<ScalarValue>) -> Self {
+        Self {
+            keys,
+            next_sequence: 0,
+            runs: vec![],
+            finished: BTreeMap::new(),
+            skip_through: None,
+        }
+    }
+
+    /// Moves the runs of the partition past `row`, where `satisfied` holds whether the row
+    /// meets the condition of each variable
+    fn advance(
+        &mut self,
+        states: &[PatternState],
+        within: Option<Duration>,
+        row: Arc<MatchRow>,
+        satisfied: &[bool],
+    ) {
+        let mut candidates = std::mem::take(&mut self.runs);
+        // any row may start a match
+        candidates.push(Run {
+            state: 0,
+            start: row.sequence,
+            start_time: row.timestamp,
+            rows: vec![],
+            matched: None,
+        });
+
+        // runs from the same row that reach the same state have the same future, so only the
+        // first is kept
+        let mut seen = HashSet::new();
+        for run in candidates {
+            let expired = within.is_some_and(|within| row.timestamp > run.start_time + within);
+            let mut extended = false;
+            if !expired {
+                for (variable, target) in &states[run.state].transitions {
+                    if !satisfied[*variable] {
+                        continue;
+                    }
+                    extended = true;
+                    if !seen.insert((run.start, *target)) {
+                        continue;
+                    }
+                    let mut rows = run.rows.clone();
+                    rows.push((row.clone(), *variable));
+                    let matched = if states[*target].accepting {
+                        Some(rows.len())
+                    } else {
+                        run.matched
+                    };
+                    self.runs.push(Run {
+                        state: *target,
+                        start: run.start,
+                        start_time: run.start_time,
+                        rows,
+                        matched,
+                    });
+                }
+            }
+            if !extended {
+                self.finish(run);
+            }
+        }
+
+        // runs are kept in the order they started, so the oldest are at the front
+        if self.runs.len() > MAX_RUNS_PER_KEY {
+            let excess = self.runs.len() - MAX_RUNS_PER_KEY;
+            warn!(
+                "ending {} partial matches of MATCH_RECOGNIZE early, as a key has more than {}",
+                excess, MAX_RUNS_PER_KEY
+            );
+            let oldest: Vec<_> = self.runs.drain(..excess).collect();
+            for run in oldest {
+                self.finish(run);
+            }
+        }
+    }
+
+    /// Ends the runs that started more than `limit` before the watermark, as no later row can
+    /// extend them
+    fn expire(&mut self, watermark: SystemTime, limit: Duration) {
+        let (expired, runs): (Vec<_>, Vec<_>) = std::mem::take(&mut self.runs)
+            .into_iter()
+            .partition(|run| run.start_time + limit < watermark);
+        self.runs = runs;
+        for run in expired {
+            self.finish(run);
+        }
+    }
+
+    fn finish(&mut self, mut run: Run) {
+        if let Some(matched) = run.matched {
+            run.rows.truncate(matched);
+            self.finished.entry(run.start).or_default().push(run.rows);
+        }
+    }
+
+    /// Returns the matches that are no longer waiting on other runs, in order, dropping those
+    /// that overlap an earlier match
+    fn take_matches(&mut self) -> Vec<MatchRows> {
+        let mut matches = vec![];
+        loop {
+            let earliest_run = self.runs.iter().map(|run| run.start).min();
+            let Some(mut entry) = self.finished.first_entry() else {
+                break;
+            };
+            if earliest_run.is_some_and(|earliest_run| earliest_run <= *entry.key()) {
+                break;
+            }
+            let start = *entry.key();
+            // the longest of the matches from a row wins
+            let rows = std::mem::take(entry.get_mut())
+                .into_iter()
+                .rev()
+                .max_by_key(|rows| rows.len())
+                .expect("finished entries aren't empty");
+            entry.remove();
+            if self
+                .skip_through
+                .is_some_and(|skip_through| start <= skip_through)
+            {
+                continue;
+            }
+
+            // after a match, matching picks up again at the row past its last row
+            let skip_through = rows.last().expect("matches aren't empty").0.sequence;
+            self.skip_through = Some(skip_through);
+            self.runs.retain(|run| run.start > skip_through);
+            self.finished = self.finished.split_off(&(skip_through + 1));
+            matches.push(rows);
+        }
+        matches
+    }
+
+    fn is_empty(&self) -> bool {
+        self.runs.is_empty() && self.finished.is_empty()
+    }
+}
+
+/// Finds the rows of each key that match a pattern, in event time order, and produces a row
+/// per match. Rows are buffered until the watermark passes them, then run through the
+/// automaton the planner compiled the pattern to, with a run for every row a match could
+/// start at. Matches are emitted once no run from an earlier row could still match.
+pub struct MatchRecognize {
+    input_schema: ArroyoSchema,
+    unkeyed_input_schema: ArroyoSchema,
+    output_schema: ArroyoSchema,
+    // the keys and timestamp of the input, used to remember the end of each key's last match
+    state_schema: ArroyoSchema,
+    key_converter: Converter,
+    conditions: Vec<Option<usize>>,
+    states: Vec<PatternState>,
+    measures: Vec<Measure>,
+    within: Option<Duration>,
+    // how long rows and partial matches are kept
+    limit: Duration,
+    pending: BTreeMap<SystemTime, Vec<RecordBatch>>,
+    partitions: HashMap<OwnedRow, PartitionState>,
+}
+
+impl MatchRecognize {
+    /// Splits the batch into the rows at each timestamp, keeping rows at the same timestamp in
+    /// arrival order, which is the order they are matched in
+    fn split_by_timestamp(&self, batch: RecordBatch) -> Result<Vec<(SystemTime, RecordBatch)>> {
+        let sorted_batch = self.unkeyed_input_schema.sort_stable(batch, true)?;
+        let timestamps = self.unkeyed_input_schema.timestamp_column(&sorted_batch);
+        Ok(self
+            .unkeyed_input_schema
+            .partition(&sorted_batch, true)?
+            .into_iter()
+            .map(|range| {
+                (
+                    from_nanos(timestamps.value(range.start) as u128),
+                    sorted_batch.slice(range.start, range.end - range.start),
+                )
+            })
+            .collect())
+    }
+
+    /// Runs the rows of a batch, which all have the same timestamp, through the pattern,
+    /// returning the matches they complete along with their keys
+    fn match_rows(&mut self, batch: &RecordBatch) -> Result<Vec<(OwnedRow, MatchRows)>> {
+        let key_indices = self
+            .input_schema
+            .key_indices
+            .as_ref()
+            .ok_or_else(|| anyhow!("match_recognize input should be keyed"))?;
+        let key_columns: Vec<_> = key_indices
+            .iter()
+            .map(|index| batch.column(*index).clone())
+            .collect();
+        let key_rows = self
+            .key_converter
+            .convert_all_columns(&key_columns, batch.num_rows())?;
+        let timestamps = self.input_schema.timestamp_column(batch);
+        let conditions: Vec<Option<&BooleanArray>> = self
+            .conditions
+            .iter()
+            .map(|index| index.map(|index| batch.column(index).as_boolean()))
+            .collect();
+
+        let mut advanced_keys = HashSet::new();
+        for i in 0..batch.num_rows() {
+            let key = key_rows.row(i).owned();
+            let partition = match self.partitions.entry(key.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let keys = key_columns
+                        .iter()
+                        .map(|column| ScalarValue::try_from_array(column, i))
+                        .collect::<datafusion::common::Result<_>>()?;
+                    entry.insert(PartitionState::new(keys))
+                }
+            };
+            let arguments = self
+                .measures
+                .iter()
+                .map(|measure| match measure.argument {
+                    Some(index) => ScalarValue::try_from_array(batch.column(index), i),
+                    None => Ok(ScalarValue::Null),
+                })
+                .collect::<datafusion::common::Result<_>>()?;
+            let row = Arc::new(MatchRow {
+                sequence: partition.next_sequence,
+                timestamp: from_nanos(timestamps.value(i) as u128),
+                arguments,
+            });
+            partition.next_sequence += 1;
+            // rows that don't meet a condition, including those where it is null, don't match
+            // its variable
+            let satisfied: Vec<bool> = conditions
+                .iter()
+                .map(|condition| {
+                    condition.map_or(true, |condition| {
+                        condition.is_valid(i) && condition.value(i)
+                    })
+                })
+                .collect();
+            partition.advance(&self.states, self.within, row, &satisfied);
+            advanced_keys.insert(key);
+        }
+
+        let mut matches = vec![];
+        for key in advanced_keys {
+            let partition = self.partitions.get_mut(&key).unwrap();
+            for rows in partition.take_matches() {
+                matches.push((key.clone(), rows));
+            }
+        }
+        Ok(matches)
+    }
+
+    fn measure(&self, measure: &Measure, index: usize, rows: &MatchRows) -> Result<ScalarValue> {
+        let mut values = rows
+            .iter()
+            .filter(|(_, variable)| measure.variable.map_or(true, |v| v == *variable))
+            .map(|(row, _)| &row.arguments[index]);
+        let value = match measure.aggregate {
+            api::MatchAggregate::First => values.next().cloned(),
+            api::MatchAggregate::Last => values.last().cloned(),
+            api::MatchAggregate::Count => {
+                let count = values
+                    .filter(|value| measure.argument.is_none() || !value.is_null())
+                    .count();
+                Some(ScalarValue::Int64(Some(count as i64)))
+            }
+            api::MatchAggregate::Min | api::MatchAggregate::Max => {
+                let keep = if measure.aggregate == api::MatchAggregate::Min {
+                    std::cmp::Ordering::Less
+                } else {
+                    std::cmp::Ordering::Greater
+                };
+                values
+                    .filter(|value| !value.is_null())
+                    .fold(None, |best: Option<&ScalarValue>, value| match best {
+                        Some(best) if value.partial_cmp(best) != Some(keep) => Some(best),
+                        _ => Some(value),
+                    })
+                    .cloned()
+            }
+            api::MatchAggregate::Sum => {
+                let mut sum: Option<ScalarValue> = None;
+                for value in values.filter(|value| !value.is_null()) {
+                    let value = value.cast_to(&measure.data_type)?;
+                    sum = Some(match sum {
+                        Some(sum) => sum.add(&value)?,
+                        None => value,
+                    });
+                }
+                sum
+            }
+        };
+        match value {
+            Some(value) if !value.is_null() => Ok(value.cast_to(&measure.data_type)?),
+            _ => Ok(ScalarValue::try_from(&measure.data_type)?),
+        }
+    }
+
+    /// Builds the output rows for the matches, returning them along with the state rows that
+    /// record where each key's last match ended
+    fn output(&self, matches: &[(OwnedRow, MatchRows)]) -> Result<(RecordBatch, RecordBatch)> {
+        let key_count = self.state_schema.schema.fields().len() - 1;
+        let mut columns: Vec<Vec<ScalarValue>> = vec![vec![]; key_count + self.measures.len()];
+        let mut ends = Vec::with_capacity(matches.len());
+        for (key, rows) in matches {
+            let partition = &self.partitions[key];
+            for (column, value) in columns.iter_mut().zip(partition.keys.iter()) {
+                column.push(value.clone());
+            }
+            for (index, measure) in self.measures.iter().enumerate() {
+                columns[key_count + index].push(self.measure(measure, index, rows)?);
+            }
+            let end = rows.last().expect("matches aren't empty").0.timestamp;
+            ends.push(to_nanos(end) as i64);
+        }
+        let mut columns = columns
+            .into_iter()
+            .map(ScalarValue::iter_to_array)
+            .collect::<datafusion::common::Result<Vec<ArrayRef>>>()?;
+        let ends: ArrayRef = Arc::new(TimestampNanosecondArray::from(ends));
+
+        let mut state_columns = columns[..key_count].to_vec();
+        state_columns.push(ends.clone());
+        columns.push(ends);
+        Ok((
+            RecordBatch::try_new(self.output_schema.schema.clone(), columns)?,
+            RecordBatch::try_new(self.state_schema.schema.clone(), state_columns)?,
+        ))
+    }
+
+    async fn emit(&mut self, matches: Vec<(OwnedRow, MatchRows)>, ctx: &mut ArrowContext) {
+        if matches.is_empty() {
+            return;
+        }
+        let (output, state) = self.output(&matches).expect("should build matches");
+        ctx.table_manager
+            .get_last_key_value_table(MATCH_RECOGNIZE_MATCHES.name, ctx.last_present_watermark())
+            .await
+            .expect("should have last match table")
+            .insert_batch(state)
+            .await
+            .expect("should record last matches");
+        ctx.collect(output).await;
+    }
+
+    /// Rebuilds the partial matches from the buffered rows the watermark has already passed,
+    /// skipping the rows of matches that were emitted before the restore
+    async fn replay(&mut self, batches: Vec<RecordBatch>, ctx: &mut ArrowContext) -> Result<()> {
+        let last_matches = ctx
+            .table_manager
+            .get_last_key_value_table(MATCH_RECOGNIZE_MATCHES.name, ctx.last_present_watermark())
+            .await?;
+        let mut state_indices = self.input_schema.key_indices.clone().unwrap_or_default();
+        state_indices.push(self.input_schema.timestamp_index);
+        for batch in batches {
+            let batch =
+                match last_matches.get_current_matching_values(&batch.project(&state_indices)?)? {
+                    Some((ends, has_match)) => {
+                        let timestamps = self.input_schema.timestamp_column(&batch);
+                        let ends = self.state_schema.timestamp_column(&ends);
+                        let mut end_index = 0;
+                        let matched: BooleanArray = (0..batch.num_rows())
+                            .map(|i| {
+                                if !has_match.value(i) {
+                                    return Some(false);
+                                }
+                                let end = ends.value(end_index);
+                                end_index += 1;
+                                Some(timestamps.value(i) <= end)
+                            })
+                            .collect();
+                        filter_record_batch(&batch, &not(&matched)?)?
+                    }
+                    None => batch,
+                };
+            if batch.num_rows() > 0 {
+                // any matches the rows complete were emitted before the restore
+                self.match_rows(&batch)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl ArrowOperator for MatchRecognize {
+    fn name(&self) -> String {
+        "MatchRecognize".to_string()
+    }
+
+    async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        let watermark = ctx.last_present_watermark();
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table(MATCH_RECOGNIZE_ROWS.name, watermark)
+            .await
+            .expect("should have buffered rows table");
+        let mut replayed = BTreeMap::new();
+        for (timestamp, batches) in table.all_batches_for_watermark(watermark) {
+            let buffer = if watermark.is_some_and(|watermark| *timestamp < watermark) {
+                &mut replayed
+            } else {
+                &mut self.pending
+            };
+            buffer
+                .entry(*timestamp)
+                .or_default()
+                .extend(batches.iter().cloned());
+        }
+        self.replay(replayed.into_values().flatten().collect(), ctx)
+            .await
+            .expect("should replay buffered rows");
+    }
+
+    async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
+        let current_watermark = ctx.last_present_watermark();
+        let (batch, late) = self
+            .input_schema
+            .split_by_time(batch, current_watermark)
+            .expect("should split by time");
+        ctx.collect_late(late).await;
+        if batch.num_rows() == 0 {
+            return;
+        }
+        let batches = self.split_by_timestamp(batch).expect("should split batch");
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table(MATCH_RECOGNIZE_ROWS.name, current_watermark)
+            .await
+            .expect("should have buffered rows table");
+        for (timestamp, batch) in batches {
+            table.insert(timestamp, batch.clone());
+            self.pending.entry(timestamp).or_default().push(batch);
+        }
+    }
+
+    async fn handle_watermark(
+        &mut self,
+        watermark_message: Watermark,
+        ctx: &mut ArrowContext,
+    ) -> Option<Watermark> {
+        let Some(watermark) = ctx.last_present_watermark() else {
+            return Some(watermark_message);
+        };
+        while let Some(entry) = self.pending.first_entry() {
+            if watermark <= *entry.key() {
+                break;
+            }
+            let batches = entry.remove();
+            let batch = concat_batches(&self.input_schema.schema, batches.iter())
+                .expect("should concat buffered rows");
+            let matches = self.match_rows(&batch).expect("should match rows");
+            self.emit(matches, ctx).await;
+        }
+
+        let mut matches = vec![];
+        for (key, partition) in self.partitions.iter_mut() {
+            partition.expire(watermark, self.limit);
+            for rows in partition.take_matches() {
+                matches.push((key.clone(), rows));
+            }
+        }
+        self.emit(matches, ctx).await;
+        self.partitions.retain(|_, partition| !partition.is_empty());
+
+        ctx.table_manager
+            .get_last_key_value_table(MATCH_RECOGNIZE_MATCHES.name, Some(watermark))
+            .await
+            .expect("should have last match table")
+            .expire(Some(watermark))
+            .expect("should expire last matches");
+        Some(watermark_message)
+    }
+
+    async fn handle_checkpoint(&mut self, _cb: CheckpointBarrier, ctx: &mut ArrowContext) {
+        let watermark = ctx.last_present_watermark();
+        ctx.table_manager
+            .get_expiring_time_key_table(MATCH_RECOGNIZE_ROWS.name, watermark)
+            .await
+            .expect("should have buffered rows table")
+            .flush(watermark)
+            .await
+            .expect("should flush");
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        let mut tables = HashMap::new();
+        tables.insert(
+            MATCH_RECOGNIZE_ROWS.name.to_string(),
+            timestamp_table_config(
+                MATCH_RECOGNIZE_ROWS.name,
+                MATCH_RECOGNIZE_ROWS.description,
+                self.limit,
+                false,
+                self.input_schema.clone(),
+            ),
+        );
+        tables.insert(
+            MATCH_RECOGNIZE_MATCHES.name.to_string(),
+            timestamp_table_config(
+                MATCH_RECOGNIZE_MATCHES.name,
+                MATCH_RECOGNIZE_MATCHES.description,
+                self.limit,
+                true,
+                self.state_schema.clone(),
+            ),
+        );
+        tables
+    }
+}
+
+pub struct MatchRecognizeConstructor;
+
+impl OperatorConstructor for MatchRecognizeConstructor {
+    type ConfigT = api::MatchRecognizeOperator;
+
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        _registry: Arc<Registry>,
+    ) -> anyhow::Result<OperatorNode> {
+        let input_schema: ArroyoSchema = config
+            .input_schema
+            .ok_or_else(|| anyhow!("requires input schema"))?
+            .try_into()?;
+        let output_schema: ArroyoSchema = config
+            .output_schema
+            .ok_or_else(|| anyhow!("requires output schema"))?
+            .try_into()?;
+        let key_indices = input_schema
+            .key_indices
+            .clone()
+            .ok_or_else(|| anyhow!("match_recognize input should be keyed"))?;
+
+        let mut state_indices = key_indices.clone();
+        state_indices.push(input_schema.timestamp_index);
+        let state_schema = ArroyoSchema::new_keyed(
+            Arc::new(input_schema.schema.project(&state_indices)?),
+            key_indices.len(),
+            (0..key_indices.len()).collect(),
+        );
+        let unkeyed_input_schema = ArroyoSchema::from_schema_unkeyed(input_schema.schema.clone())?;
+        let key_converter = input_schema.converter(false)?;
+
+        let states = config
+            .states
+            .into_iter()
+            .map(|state| PatternState {
+                transitions: state
+                    .transitions
+                    .into_iter()
+                    .map(|transition| (transition.variable as usize, transition.target as usize))
+                    .collect(),
+                accepting: state.accepting,
+            })
+            .collect();
+        let measures = config
+            .measures
+            .iter()
+            .map(|measure| {
+                let field = output_schema.schema.field_with_name(&measure.name)?;
+                Ok(Measure {
+                    aggregate: measure.aggregate(),
+                    variable: measure.variable.map(|variable| variable as usize),
+                    argument: measure.argument_index.map(|index| index as usize),
+                    data_type: field.data_type().clone(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        let within = config.within_micros.map(Duration::from_micros);
+
+        Ok(OperatorNode::from_operator(Box::new(MatchRecognize {
+            input_schema,
+            unkeyed_input_schema,
+            output_schema,
+            state_schema,
+            key_converter,
+            conditions: config
+                .variables
+                .iter()
+                .map(|variable| variable.condition_index.map(|index| index as usize))
+                .collect(),
+            states,
+            measures,
+            within,
+            limit: match_recognize_retention(Duration::from_micros(config.ttl_micros), within),
+            pending: BTreeMap::new(),
+            partitions: HashMap::new(),
+        })))
+    }
+}
@@ -22,11 +22,14 @@ use std::sync::Arc;
 use std::sync::RwLock;
 
 pub mod async_udf;
+pub mod deduplicate;
 pub mod instant_join;
 pub mod join_with_expiration;
+pub mod match_recognize;
 pub mod session_aggregating_window;
 pub mod sliding_aggregating_window;
 pub(crate) mod sync;
+pub mod temporal_join;
 pub mod tumbling_aggregating_window;
 pub mod updating_aggregator;
 pub mod watermark_generator;
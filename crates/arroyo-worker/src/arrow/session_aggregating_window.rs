@@ -17,6 +17,9 @@ use arrow_array::{
     TimestampNanosecondArray,
 };
 use arrow_schema::{DataType, Field, FieldRef};
+use arroyo_datastream::state_tables::{
+    session_window_retention, SESSION_WINDOW, SESSION_WINDOW_EARLIEST,
+};
 use arroyo_df::schemas::window_arrow_struct;
 use arroyo_operator::{
     context::ArrowContext,
@@ -54,6 +57,17 @@ pub struct SessionAggregatingWindowFunc {
     key_computations: HashMap<OwnedRow, KeyComputingHolder>,
     keys_by_start_time: BTreeMap<SystemTime, HashSet<OwnedRow>>,
     row_converter: Converter,
+    // sessions that have been emitted but may still be updated by late rows, by key
+    emitted: HashMap<OwnedRow, Vec<EmittedSession>>,
+}
+
+struct EmittedSession {
+    window_start: SystemTime,
+    window_end: SystemTime,
+    // the input rows of the session, so it can be recomputed when late rows arrive
+    rows: Vec<RecordBatch>,
+    // the output row as it was emitted
+    output: RecordBatch,
 }
 
 impl SessionAggregatingWindowFunc {
@@ -82,15 +96,183 @@ impl SessionAggregatingWindowFunc {
             .context("results at watermark")?;
         if !results.is_empty() {
             let result_batch = self
-                .to_record_batch(results, ctx)
+                .to_record_batch(&results, ctx)
                 .context("should convert to record batch")?;
             debug!("emitting session batch of size {}", result_batch.num_rows());
+            if self.config.allowed_lateness.is_some() {
+                self.remember_emitted(results, &result_batch);
+            }
             ctx.collect(result_batch).await;
         }
 
+        if let Some(lateness) = self.config.allowed_lateness {
+            self.emitted.retain(|_, sessions| {
+                sessions.retain(|session| session.window_end + lateness > watermark);
+                !sessions.is_empty()
+            });
+        }
+
+        Ok(())
+    }
+
+    fn remember_emitted(
+        &mut self,
+        results: Vec<(OwnedRow, Vec<SessionWindowResult>)>,
+        result_batch: &RecordBatch,
+    ) {
+        let mut index = 0;
+        for (key, session_results) in results {
+            let sessions = self.emitted.entry(key).or_default();
+            for result in session_results {
+                sessions.push(EmittedSession {
+                    window_start: result.window_start,
+                    window_end: result.window_end,
+                    rows: result.rows,
+                    output: result_batch.slice(index, 1),
+                });
+                index += 1;
+            }
+            sessions.sort_by_key(|session| session.window_start);
+        }
+    }
+
+    /// Handles rows that arrived behind the watermark but within the allowed lateness. Rows that
+    /// reach a session that is still open are added to it; otherwise the emitted sessions they
+    /// touch are merged with them, retracted and re-emitted.
+    async fn add_late_rows(
+        &mut self,
+        sorted_batch: RecordBatch,
+        watermark: SystemTime,
+        ctx: &mut ArrowContext,
+    ) -> Result<()> {
+        for (key, key_batch) in self.key_batches(&sorted_batch)? {
+            for group in self.split_into_sessions(&key_batch) {
+                self.add_late_session(key.clone(), group, watermark, ctx)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    // splits a sorted batch for a single key wherever consecutive rows are at least gap apart
+    fn split_into_sessions(&self, batch: &RecordBatch) -> Vec<RecordBatch> {
+        let timestamps = self.config.input_schema_ref.timestamp_column(batch);
+        let gap = self.config.gap.as_nanos() as i64;
+        let mut groups = vec![];
+        let mut start = 0;
+        for index in 1..batch.num_rows() {
+            if timestamps.value(index) >= timestamps.value(index - 1) + gap {
+                groups.push(batch.slice(start, index - start));
+                start = index;
+            }
+        }
+        groups.push(batch.slice(start, batch.num_rows() - start));
+        groups
+    }
+
+    async fn add_late_session(
+        &mut self,
+        key: OwnedRow,
+        group: RecordBatch,
+        watermark: SystemTime,
+        ctx: &mut ArrowContext,
+    ) -> Result<()> {
+        let gap = self.config.gap;
+        let timestamps = self.config.input_schema_ref.timestamp_column(&group);
+        let mut data_start = from_nanos(timestamps.value(0) as u128);
+        let mut data_end = from_nanos(timestamps.value(group.num_rows() - 1) as u128);
+
+        // pull in every emitted session that the late rows connect to, directly or transitively
+        let sessions = self.emitted.entry(key.clone()).or_default();
+        let mut merged = vec![];
+        loop {
+            let Some(index) = sessions.iter().position(|session| {
+                session.window_start < data_end + gap && data_start < session.window_end
+            }) else {
+                break;
+            };
+            let session = sessions.remove(index);
+            data_start = data_start.min(session.window_start);
+            data_end = data_end.max(session.window_end - gap);
+            merged.push(session);
+        }
+        let mut rows: Vec<_> = merged
+            .iter()
+            .flat_map(|session| session.rows.iter().cloned())
+            .collect();
+        rows.push(group);
+
+        for session in &merged {
+            ctx.collect(Self::set_is_retract(&session.output, true)?)
+                .await;
+        }
+
+        let reaches_active_session = self
+            .key_computations
+            .get(&key)
+            .and_then(|computation| computation.active_session.as_ref())
+            .map(|active_session| active_session.data_start < data_end + gap);
+        // If there's an active session the rows don't reach, they are treated as a closed
+        // session even if the watermark hasn't passed its end, as the active session can't
+        // take rows from before its start - gap.
+        if reaches_active_session.unwrap_or(data_end + gap >= watermark) {
+            // The session isn't over yet, so hand the rows to the live computation. This always
+            // succeeds as every late row is within gap of the active session's end.
+            let input_schema = self.config.input_schema_ref.schema.clone();
+            let batch = self.sort_batch(&concat_batches(&input_schema, &rows)?)?;
+            return self.add_at_watermark(batch, Some(watermark)).await;
+        }
+
+        let (sender, receiver) = unbounded_channel();
+        {
+            let mut internal_receiver = self.config.receiver.write().unwrap();
+            *internal_receiver = Some(receiver);
+        }
+        self.config.final_physical_exec.reset()?;
+        let mut exec = self
+            .config
+            .final_physical_exec
+            .execute(0, SessionContext::new().task_ctx())?;
+        for batch in &rows {
+            sender.send(batch.clone())?;
+        }
+        drop(sender);
+        let mut batches = vec![];
+        while let Some(batch) = exec.next().await {
+            batches.push(batch?);
+        }
+        let [batch] = batches.try_into().map_err(|batches| {
+            anyhow!(
+                "expect session result to be exactly one batch, not {:?}",
+                batches
+            )
+        })?;
+
+        let results = vec![(
+            key,
+            vec![SessionWindowResult {
+                window_start: data_start,
+                window_end: data_end + gap,
+                batch,
+                rows,
+            }],
+        )];
+        let result_batch = self.to_record_batch(&results, ctx)?;
+        self.remember_emitted(results, &result_batch);
+        ctx.collect(result_batch).await;
         Ok(())
     }
 
+    fn set_is_retract(batch: &RecordBatch, is_retract: bool) -> Result<RecordBatch> {
+        let mut columns = batch.columns().to_vec();
+        columns.pop();
+        columns.push(Arc::new(BooleanArray::from(vec![
+            is_retract;
+            batch.num_rows()
+        ])));
+        Ok(RecordBatch::try_new(batch.schema(), columns)?)
+    }
+
     async fn results_at_watermark(
         &mut self,
         watermark: SystemTime,
@@ -146,55 +328,57 @@ impl SessionAggregatingWindowFunc {
     }
 
     fn earliest_batch_time(&self) -> Option<SystemTime> {
+        // emitted sessions count too, so that their rows are restored along with the live ones
+        let earliest_emitted = self
+            .emitted
+            .values()
+            .filter_map(|sessions| sessions.first())
+            .map(|session| session.window_start)
+            .min();
         self.keys_by_start_time
             .first_key_value()
             .map(|(start_time, _keys)| *start_time)
+            .into_iter()
+            .chain(earliest_emitted)
+            .min()
     }
 
     #[allow(clippy::single_range_in_vec_init)]
-    async fn add_at_watermark(
-        &mut self,
-        sorted_batch: RecordBatch,
-        watermark: Option<SystemTime>,
-    ) -> Result<()> {
-        let has_keys = self.config.input_schema_ref.key_indices.is_some();
-        let partition = if !has_keys {
+    fn key_batches(&self, sorted_batch: &RecordBatch) -> Result<Vec<(OwnedRow, RecordBatch)>> {
+        let key_count = self
+            .config
+            .input_schema_ref
+            .key_indices
+            .as_ref()
+            .map(|keys| keys.len())
+            .unwrap_or(0);
+        let partition = if key_count == 0 {
             // if we don't have keys, we can just partition by the whole batch.
             vec![0..sorted_batch.num_rows()]
         } else {
-            partition(
-                sorted_batch
-                    .columns()
-                    .iter()
-                    // Keys are first in the schema, because of how DataFusion structures aggregates.
-                    .take(
-                        self.config
-                            .input_schema_ref
-                            .key_indices
-                            .as_ref()
-                            .unwrap()
-                            .len(),
-                    )
-                    .cloned()
-                    .collect::<Vec<_>>()
-                    .as_slice(),
-            )?
-            .ranges()
+            // Keys are first in the schema, because of how DataFusion structures aggregates.
+            partition(&sorted_batch.columns()[0..key_count])?.ranges()
         };
 
-        for range in partition {
-            let key_batch = sorted_batch.slice(range.start, range.end - range.start);
-            let key_count = self
-                .config
-                .input_schema_ref
-                .key_indices
-                .as_ref()
-                .map(|keys| keys.len())
-                .unwrap_or(0);
-            let row = self
-                .row_converter
-                .convert_columns(&key_batch.slice(0, 1).columns()[0..key_count])
-                .context("failed to convert rows")?;
+        partition
+            .into_iter()
+            .map(|range| {
+                let key_batch = sorted_batch.slice(range.start, range.end - range.start);
+                let row = self
+                    .row_converter
+                    .convert_columns(&key_batch.slice(0, 1).columns()[0..key_count])
+                    .context("failed to convert rows")?;
+                Ok((row, key_batch))
+            })
+            .collect()
+    }
+
+    async fn add_at_watermark(
+        &mut self,
+        sorted_batch: RecordBatch,
+        watermark: Option<SystemTime>,
+    ) -> Result<()> {
+        for (row, key_batch) in self.key_batches(&sorted_batch)? {
             let key_computation =
                 self.key_computations
                     .entry(row.clone())
@@ -280,6 +464,29 @@ impl SessionAggregatingWindowFunc {
         Ok(filter_record_batch(&batch, &on_time)?)
     }
 
+    // send to state backend.
+    // TODO: pre-aggregate data before sending to state backend.
+    async fn insert_into_table(
+        &self,
+        sorted: &RecordBatch,
+        watermark: Option<SystemTime>,
+        ctx: &mut ArrowContext,
+    ) {
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table(SESSION_WINDOW.name, watermark)
+            .await
+            .expect("should get table");
+
+        let max_timestamp = max(sorted
+            .column(self.config.input_schema_ref.timestamp_index)
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            .expect("should have max timestamp"))
+        .unwrap();
+        table.insert(from_nanos(max_timestamp as u128), sorted.clone());
+    }
+
     fn sort_batch(&self, batch: &RecordBatch) -> Result<RecordBatch> {
         let sort_columns = self.sort_columns(batch);
         let sort_indices = lexsort_to_indices(&sort_columns, None).expect("should be able to sort");
@@ -293,7 +500,7 @@ impl SessionAggregatingWindowFunc {
 
     fn to_record_batch(
         &self,
-        results: Vec<(OwnedRow, Vec<SessionWindowResult>)>,
+        results: &[(OwnedRow, Vec<SessionWindowResult>)],
         ctx: &mut ArrowContext,
     ) -> Result<RecordBatch> {
         debug!("first result is {:#?}", results[0]);
@@ -345,6 +552,9 @@ impl SessionAggregatingWindowFunc {
         columns.insert(self.config.window_index, Arc::new(window_struct_array));
         columns.extend_from_slice(merged_batch.columns());
         columns.push(Arc::new(timestamp_array));
+        if self.config.allowed_lateness.is_some() {
+            columns.push(Arc::new(BooleanArray::from(vec![false; results.len()])));
+        }
         RecordBatch::try_new(
             ctx.out_schema.as_ref().unwrap().schema.clone(),
             columns.clone(),
@@ -358,6 +568,7 @@ impl SessionAggregatingWindowFunc {
 
 struct SessionWindowConfig {
     gap: Duration,
+    allowed_lateness: Option<Duration>,
     input_schema_ref: ArroyoSchemaRef,
     window_field: FieldRef,
     window_index: usize,
@@ -375,6 +586,8 @@ struct ActiveSession {
     sender: Option<UnboundedSender<RecordBatch>>,
     // the next batch's execution plan
     result_stream: SendableRecordBatchStream,
+    // the input rows, kept when the session may need to be recomputed for late rows
+    rows: Option<Vec<RecordBatch>>,
 }
 
 impl ActiveSession {
@@ -382,6 +595,7 @@ impl ActiveSession {
         aggregation_plan: Arc<dyn ExecutionPlan>,
         initial_timestamp: SystemTime,
         sender: UnboundedSender<RecordBatch>,
+        keep_rows: bool,
     ) -> Result<Self> {
         aggregation_plan.reset()?;
         let result_exec = aggregation_plan.execute(0, SessionContext::new().task_ctx())?;
@@ -390,8 +604,17 @@ impl ActiveSession {
             data_end: initial_timestamp,
             sender: Some(sender),
             result_stream: result_exec,
+            rows: keep_rows.then(Vec::new),
         })
     }
+
+    fn send(&mut self, batch: RecordBatch) -> Result<()> {
+        if let Some(rows) = &mut self.rows {
+            rows.push(batch.clone());
+        }
+        self.sender.as_ref().unwrap().send(batch)?;
+        Ok(())
+    }
     // Add all data in the batch that is within gap of the current session interval,
     // updating gap as more data is added.
     // The batch is sorted and it will never be the case that the start of batch is less than data_start - gap.
@@ -415,7 +638,7 @@ impl ActiveSession {
             // add it to the current session and update the gap
             self.data_end = self.data_end.max(from_nanos(end as u128));
             self.data_start = self.data_start.min(from_nanos(start as u128));
-            self.sender.as_ref().unwrap().send(batch)?;
+            self.send(batch)?;
             return Ok(None);
         }
 
@@ -452,10 +675,10 @@ impl ActiveSession {
         if index == batch.num_rows() {
             // all data in the batch is within the current session interval
             // we've already updated the gap, so we can just add it to the current session
-            self.sender.as_ref().unwrap().send(batch)?;
+            self.send(batch)?;
             return Ok(None);
         }
-        self.sender.as_ref().unwrap().send(batch.slice(0, index))?;
+        self.send(batch.slice(0, index))?;
 
         let batch = batch.slice(index, batch.num_rows() - index);
         let start_time = from_nanos(timestamp_column.value(index) as u128);
@@ -490,6 +713,7 @@ impl ActiveSession {
             window_start: self.data_start,
             window_end: self.data_end + gap,
             batch,
+            rows: self.rows.take().unwrap_or_default(),
         })
     }
 }
@@ -499,6 +723,7 @@ struct SessionWindowResult {
     window_start: SystemTime,
     window_end: SystemTime,
     batch: RecordBatch,
+    rows: Vec<RecordBatch>,
 }
 
 struct KeyComputingHolder {
@@ -571,6 +796,7 @@ impl KeyComputingHolder {
                         self.session_window_config.final_physical_exec.clone(),
                         *initial_timestamp,
                         sender,
+                        self.session_window_config.allowed_lateness.is_some(),
                     )
                     .await?,
                 );
@@ -728,6 +954,7 @@ impl OperatorConstructor for SessionAggregatingWindowConstructor {
 
         let config = SessionWindowConfig {
             gap: Duration::from_micros(config.gap_micros),
+            allowed_lateness: config.allowed_lateness_micros.map(Duration::from_micros),
             window_field,
             window_index: config.window_index as usize,
             input_schema_ref: Arc::new(input_schema),
@@ -742,6 +969,7 @@ impl OperatorConstructor for SessionAggregatingWindowConstructor {
                 keys_by_start_time: BTreeMap::new(),
                 key_computations: HashMap::new(),
                 row_converter,
+                emitted: HashMap::new(),
             },
         )))
     }
@@ -755,8 +983,11 @@ impl ArrowOperator for SessionAggregatingWindowFunc {
     }
 
     async fn on_start(&mut self, ctx: &mut ArrowContext) {
-        let start_times_map: &mut GlobalKeyedView<usize, Option<SystemTime>> =
-            ctx.table_manager.get_global_keyed_state("e").await.unwrap();
+        let start_times_map: &mut GlobalKeyedView<usize, Option<SystemTime>> = ctx
+            .table_manager
+            .get_global_keyed_state(SESSION_WINDOW_EARLIEST.name)
+            .await
+            .unwrap();
         let start_time = start_times_map
             .get_all()
             .values()
@@ -771,7 +1002,7 @@ impl ArrowOperator for SessionAggregatingWindowFunc {
             .table_manager
             // TODO: this will subtract the retention from start time, so hold more than it should,
             // but we plan to overhaul it all anyway.
-            .get_expiring_time_key_table("s", start_time)
+            .get_expiring_time_key_table(SESSION_WINDOW.name, start_time)
             .await
             .expect("should be able to load table");
         let all_batches = table.all_batches_for_watermark(start_time);
@@ -800,7 +1031,25 @@ impl ArrowOperator for SessionAggregatingWindowFunc {
             .results_at_watermark(watermark)
             .await
             .expect("should be able to get results");
-        if !evicted_results.is_empty() {
+        if let Some(lateness) = self.config.allowed_lateness {
+            // sessions we emitted before restoring may still be updated by late rows
+            let evicted_results: Vec<_> = evicted_results
+                .into_iter()
+                .filter_map(|(key, results)| {
+                    let results: Vec<_> = results
+                        .into_iter()
+                        .filter(|result| result.window_end + lateness > watermark)
+                        .collect();
+                    (!results.is_empty()).then_some((key, results))
+                })
+                .collect();
+            if !evicted_results.is_empty() {
+                let result_batch = self
+                    .to_record_batch(&evicted_results, ctx)
+                    .expect("should convert to record batch");
+                self.remember_emitted(evicted_results, &result_batch);
+            }
+        } else if !evicted_results.is_empty() {
             warn!(
                 "evicted {} results when restoring from state.",
                 evicted_results.len()
@@ -808,46 +1057,49 @@ impl ArrowOperator for SessionAggregatingWindowFunc {
         }
     }
 
-    // TODO: filter out late data
     async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
         debug!("received batch {:?}", batch);
         let current_watermark = ctx.last_present_watermark();
-        let batch = if let Some(watermark) = current_watermark {
-            // filter out late data
-            let timestamp_column = batch
-                .column(self.config.input_schema_ref.timestamp_index)
-                .as_any()
-                .downcast_ref::<TimestampNanosecondArray>()
-                .unwrap();
-            let watermark_scalar = TimestampNanosecondArray::new_scalar(to_nanos(watermark) as i64);
-            let on_time = gt_eq(timestamp_column, &watermark_scalar).unwrap();
-            filter_record_batch(&batch, &on_time).unwrap()
-        } else {
-            batch
+        let (batch, late) = self
+            .config
+            .input_schema_ref
+            .split_by_time(batch, current_watermark)
+            .unwrap();
+        let late = match (self.config.allowed_lateness, current_watermark) {
+            (Some(lateness), Some(watermark)) if late.num_rows() > 0 => {
+                // a row can still change a session if the session's end is within the lateness
+                let cutoff = watermark
+                    .checked_sub(self.config.gap + lateness)
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                let (accepted, too_late) = self
+                    .config
+                    .input_schema_ref
+                    .split_by_time(late, Some(cutoff))
+                    .unwrap();
+                if accepted.num_rows() > 0 {
+                    let sorted = self
+                        .sort_batch(&accepted)
+                        .expect("should be able to sort batch");
+                    self.insert_into_table(&sorted, current_watermark, ctx)
+                        .await;
+                    self.add_late_rows(sorted, watermark, ctx)
+                        .await
+                        .expect("should be able to add late rows");
+                }
+                too_late
+            }
+            _ => late,
         };
+        ctx.collect_late(late).await;
         if batch.num_rows() == 0 {
-            warn!("fully filtered out a batch");
             return;
         }
         let sorted = self
             .sort_batch(&batch)
             .expect("should be able to sort batch");
 
-        // send to state backend.
-        // TODO: pre-aggregate data before sending to state backend.
-        let table = ctx
-            .table_manager
-            .get_expiring_time_key_table("s", current_watermark)
-            .await
-            .expect("should get table");
-
-        let max_timestamp = max(sorted
-            .column(self.config.input_schema_ref.timestamp_index)
-            .as_any()
-            .downcast_ref::<TimestampNanosecondArray>()
-            .expect("should have max timestamp"))
-        .unwrap();
-        table.insert(from_nanos(max_timestamp as u128), sorted.clone());
+        self.insert_into_table(&sorted, current_watermark, ctx)
+            .await;
 
         self.add_at_watermark(sorted, current_watermark)
             .await
@@ -867,12 +1119,12 @@ impl ArrowOperator for SessionAggregatingWindowFunc {
         let watermark = ctx.last_present_watermark();
         let table = ctx
             .table_manager
-            .get_expiring_time_key_table("s", watermark)
+            .get_expiring_time_key_table(SESSION_WINDOW.name, watermark)
             .await
             .expect("should get table");
         table.flush(watermark).await.unwrap();
         ctx.table_manager
-            .get_global_keyed_state("e")
+            .get_global_keyed_state(SESSION_WINDOW_EARLIEST.name)
             .await
             .unwrap()
             .insert(ctx.task_info.task_index, self.earliest_batch_time())
@@ -880,14 +1132,16 @@ impl ArrowOperator for SessionAggregatingWindowFunc {
     }
 
     fn tables(&self) -> HashMap<String, TableConfig> {
-        let mut tables = global_table_config("e", "earliest start time of all active batches.");
+        let mut tables = global_table_config(
+            SESSION_WINDOW_EARLIEST.name,
+            SESSION_WINDOW_EARLIEST.description,
+        );
         tables.insert(
-            "s".to_string(),
+            SESSION_WINDOW.name.to_string(),
             timestamp_table_config(
-                "s",
-                "session",
-                // TODO: something better
-                self.config.gap * 100,
+                SESSION_WINDOW.name,
+                SESSION_WINDOW.description,
+                session_window_retention(self.config.gap, self.config.allowed_lateness),
                 false,
                 self.config.input_schema_ref.as_ref().clone(),
             ),
@@ -7,15 +7,22 @@ use std::{
 
 use anyhow::{anyhow, bail, Result};
 use arrow::compute::{partition, sort_to_indices, take};
-use arrow_array::{types::TimestampNanosecondType, Array, PrimitiveArray, RecordBatch};
+use arrow_array::{
+    types::TimestampNanosecondType, Array, BooleanArray, PrimitiveArray, RecordBatch,
+};
 use arrow_schema::SchemaRef;
+use arroyo_datastream::state_tables::{
+    sliding_window_lateness, sliding_window_retention, SLIDING_WINDOW,
+};
 use arroyo_operator::{
     context::ArrowContext,
     operator::{ArrowOperator, OperatorConstructor, OperatorNode},
 };
 use arroyo_rpc::grpc::{api, TableConfig};
 use arroyo_state::timestamp_table_config;
-use arroyo_types::{from_nanos, print_time, to_nanos, CheckpointBarrier, Watermark};
+use arroyo_types::{
+    from_nanos, print_time, sliding_window_pane, to_nanos, CheckpointBarrier, Watermark,
+};
 use datafusion::common::ScalarValue;
 use datafusion::{execution::context::SessionContext, physical_plan::ExecutionPlan};
 
@@ -45,6 +52,9 @@ use super::sync::streams::KeyedCloneableStreamFuture;
 pub struct SlidingAggregatingWindowFunc<K: Copy> {
     slide: Duration,
     width: Duration,
+    // rows are partially aggregated into panes of this size, which evenly divides
+    // both the slide and the width
+    pane: Duration,
     binning_function: Arc<dyn PhysicalExpr>,
     partial_aggregation_plan: Arc<dyn ExecutionPlan>,
     partial_schema: ArroyoSchema,
@@ -59,6 +69,11 @@ pub struct SlidingAggregatingWindowFunc<K: Copy> {
     projection_input_schema: SchemaRef,
     final_projection: Arc<dyn ExecutionPlan>,
     state: SlidingWindowState,
+    // how long windows are kept after they've been emitted so that late rows can update them;
+    // always a multiple of the slide
+    allowed_lateness: Option<Duration>,
+    // the results of emitted windows that are still within the allowed lateness, by window end
+    emitted: BTreeMap<SystemTime, Vec<RecordBatch>>,
 }
 
 #[allow(clippy::enum_variant_names)]
@@ -69,7 +84,7 @@ enum SlidingWindowState {
     // We've received data, but don't have any data in the memory_view.
     OnlyBufferedData { earliest_bin_time: SystemTime },
     // There is data in memory_view waiting to be emitted.
-    // will trigger on a watermark after next_window_start + self.slide
+    // will trigger on a watermark after next_window_start + self.pane
     InMemoryData { next_window_start: SystemTime },
 }
 
@@ -89,14 +104,32 @@ impl Display for SlidingWindowState {
 
 impl<K: Copy> SlidingAggregatingWindowFunc<K> {
     fn bin_start(&self, timestamp: SystemTime) -> SystemTime {
-        if self.slide == Duration::ZERO {
+        if self.pane == Duration::ZERO {
             return timestamp;
         }
         let mut nanos = to_nanos(timestamp);
-        nanos -= nanos % self.slide.as_nanos();
+        nanos -= nanos % self.pane.as_nanos();
 
         from_nanos(nanos)
     }
+
+    // windows start at multiples of the slide, so they end wherever the width lands after that
+    fn is_window_end(&self, timestamp: SystemTime) -> bool {
+        to_nanos(timestamp) % self.slide.as_nanos() == self.width.as_nanos() % self.slide.as_nanos()
+    }
+
+    /// The start of the first window that ends after `timestamp`
+    fn next_window_start(&self, timestamp: SystemTime) -> SystemTime {
+        let slide = self.slide.as_nanos();
+        let nanos = (to_nanos(timestamp) + slide).saturating_sub(self.width.as_nanos());
+        from_nanos(nanos - nanos % slide)
+    }
+
+    /// The end of the last window that contains `timestamp`
+    fn last_window_end_containing(&self, timestamp: SystemTime) -> SystemTime {
+        let nanos = to_nanos(timestamp);
+        from_nanos(nanos - nanos % self.slide.as_nanos()) + self.width
+    }
 }
 
 impl SlidingAggregatingWindowFunc<SystemTime> {
@@ -105,10 +138,10 @@ impl SlidingAggregatingWindowFunc<SystemTime> {
         match self.state {
             SlidingWindowState::NoData => false,
             SlidingWindowState::OnlyBufferedData { earliest_bin_time } => {
-                earliest_bin_time + self.slide <= watermark_bin
+                earliest_bin_time + self.pane <= watermark_bin
             }
             SlidingWindowState::InMemoryData { next_window_start } => {
-                next_window_start + self.slide <= watermark_bin
+                next_window_start + self.pane <= watermark_bin
             }
         }
     }
@@ -121,10 +154,11 @@ impl SlidingAggregatingWindowFunc<SystemTime> {
         };
         let partial_table = ctx
             .table_manager
-            .get_expiring_time_key_table("t", ctx.last_present_watermark())
+            .get_expiring_time_key_table(SLIDING_WINDOW.name, ctx.last_present_watermark())
             .await?;
 
-        let bin_end = bin_start + self.slide;
+        let bin_end = bin_start + self.pane;
+        let lateness = self.allowed_lateness.unwrap_or_default();
         partial_table.flush(Some(bin_end)).await?;
 
         if let Some(mut bin_exec) = self.execs.remove(&bin_start) {
@@ -155,9 +189,60 @@ impl SlidingAggregatingWindowFunc<SystemTime> {
             }
         }
         partial_table.flush_timestamp(bin_end).await?;
-        partial_table.expire_timestamp(bin_end - self.width + self.slide);
-        let interval_start = bin_end - self.width;
-        let interval_end = bin_end;
+
+        let next_window_start = self.next_window_start(bin_end);
+        self.state = if self.tiered_record_batches.is_empty_from(next_window_start) {
+            match self.execs.keys().next() {
+                Some(bin) => SlidingWindowState::OnlyBufferedData {
+                    earliest_bin_time: *bin,
+                },
+                None => SlidingWindowState::NoData,
+            }
+        } else {
+            SlidingWindowState::InMemoryData {
+                next_window_start: bin_end,
+            }
+        };
+
+        if !self.is_window_end(bin_end) {
+            return Ok(());
+        }
+
+        let window_start = bin_end - self.width;
+        let results = self.compute_window(window_start, bin_end).await?;
+        // the panes in the first slide of this window aren't part of any later window
+        let mut pane = window_start - lateness;
+        while pane < next_window_start - lateness {
+            partial_table.expire_timestamp(pane);
+            pane += self.pane;
+        }
+        self.tiered_record_batches
+            .delete_before(next_window_start - lateness)?;
+
+        if self.allowed_lateness.is_some() {
+            let out_schema = ctx.out_schema.as_ref().unwrap().schema.clone();
+            for batch in &results {
+                ctx.collector
+                    .collect(Self::add_is_retract(batch, false, out_schema.clone())?)
+                    .await;
+            }
+            self.emitted.insert(bin_end, results);
+        } else {
+            for batch in results {
+                ctx.collector.collect(batch).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Computes the results of the window covering [interval_start, interval_end) from the
+    /// panes held in memory
+    async fn compute_window(
+        &self,
+        interval_start: SystemTime,
+        interval_end: SystemTime,
+    ) -> Result<Vec<RecordBatch>> {
         {
             let mut batches = self.final_batches_passer.write().unwrap();
             *batches = self
@@ -169,21 +254,6 @@ impl SlidingAggregatingWindowFunc<SystemTime> {
             .finish_execution_plan
             .execute(0, SessionContext::new().task_ctx())
             .unwrap();
-        self.tiered_record_batches
-            .delete_before(bin_end + self.slide - self.width)?;
-
-        self.state = if self.tiered_record_batches.is_empty() {
-            match partial_table.get_min_time() {
-                Some(min_time) => SlidingWindowState::OnlyBufferedData {
-                    earliest_bin_time: self.bin_start(min_time),
-                },
-                None => SlidingWindowState::NoData,
-            }
-        } else {
-            SlidingWindowState::InMemoryData {
-                next_window_start: bin_end,
-            }
-        };
         let mut aggregate_results = Vec::new();
         while let Some(batch) = final_exec.next().await {
             let batch = batch.expect("should be able to compute batch");
@@ -202,13 +272,115 @@ impl SlidingAggregatingWindowFunc<SystemTime> {
         let mut final_projection_exec = self
             .final_projection
             .execute(0, SessionContext::new().task_ctx())?;
+        let mut results = vec![];
         while let Some(batch) = final_projection_exec.next().await {
-            let batch = batch.expect("should be able to compute batch");
-            ctx.collector.collect(batch).await;
+            results.push(batch.expect("should be able to compute batch"));
         }
+        Ok(results)
+    }
+
+    fn accepts_late_rows(&self, bin_start: SystemTime, watermark: SystemTime) -> bool {
+        self.allowed_lateness.is_some_and(|lateness| {
+            self.last_window_end_containing(bin_start) + lateness > watermark
+        })
+    }
 
+    /// Computes the partial aggregates for rows that arrived after their bin was moved into the
+    /// tiered record batches
+    async fn compute_late_partials(&self, batch: RecordBatch) -> Result<Vec<RecordBatch>> {
+        let (sender, receiver) = unbounded_channel();
+        {
+            let mut internal_receiver = self.receiver.write().unwrap();
+            *internal_receiver = Some(receiver);
+        }
+        self.partial_aggregation_plan.reset()?;
+        let mut exec = self
+            .partial_aggregation_plan
+            .execute(0, SessionContext::new().task_ctx())?;
+        sender.send(batch)?;
+        drop(sender);
+
+        let mut partials = vec![];
+        while let Some(batch) = exec.next().await {
+            partials.push(batch?);
+        }
+        Ok(partials)
+    }
+
+    /// Adds rows that arrived within the allowed lateness to their bin, then retracts and
+    /// re-emits every already-emitted window that contains that bin
+    async fn update_emitted_windows(
+        &mut self,
+        bin_start: SystemTime,
+        batch: RecordBatch,
+        watermark: SystemTime,
+        ctx: &mut ArrowContext,
+    ) -> Result<()> {
+        let partials = self.compute_late_partials(batch).await?;
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table(SLIDING_WINDOW.name, Some(watermark))
+            .await?;
+        for partial in partials {
+            table.insert(
+                bin_start,
+                Self::add_bin_start_as_timestamp(
+                    &partial,
+                    bin_start,
+                    self.partial_schema.schema.clone(),
+                )?,
+            );
+            self.tiered_record_batches.insert(partial, bin_start)?;
+        }
+
+        let watermark_bin = self.bin_start(watermark);
+        if bin_start + self.width > watermark_bin
+            && !matches!(self.state, SlidingWindowState::InMemoryData { .. })
+        {
+            // there are windows containing this bin that haven't been emitted yet
+            self.state = SlidingWindowState::InMemoryData {
+                next_window_start: watermark_bin,
+            };
+        }
+
+        let out_schema = ctx.out_schema.as_ref().unwrap().schema.clone();
+        let window_ends: Vec<_> = self
+            .emitted
+            .range(bin_start + self.pane..=bin_start + self.width)
+            .map(|(window_end, _)| *window_end)
+            .collect();
+        for window_end in window_ends {
+            let results = self
+                .compute_window(window_end - self.width, window_end)
+                .await?;
+            let previous = self
+                .emitted
+                .insert(window_end, results.clone())
+                .unwrap_or_default();
+            for batch in &previous {
+                ctx.collect(Self::add_is_retract(batch, true, out_schema.clone())?)
+                    .await;
+            }
+            for batch in &results {
+                ctx.collect(Self::add_is_retract(batch, false, out_schema.clone())?)
+                    .await;
+            }
+        }
         Ok(())
     }
+
+    fn add_is_retract(
+        batch: &RecordBatch,
+        is_retract: bool,
+        schema: SchemaRef,
+    ) -> Result<RecordBatch> {
+        let mut columns = batch.columns().to_vec();
+        columns.push(Arc::new(BooleanArray::from(vec![
+            is_retract;
+            batch.num_rows()
+        ])));
+        Ok(RecordBatch::try_new(schema, columns)?)
+    }
     // TODO: don't repeat this
     fn add_bin_start_as_timestamp(
         batch: &RecordBatch,
@@ -261,8 +433,13 @@ impl RecordBatchTier {
             self.panes.push_back(pane);
             return Ok(());
         }
-        // if the bin_start is before start_time, error out
-        let start_time = self.start_time.unwrap();
+        // late rows may belong before any we've seen so far, so add panes at the front
+        let mut start_time = self.start_time.unwrap();
+        while bin_start < start_time {
+            start_time -= self.width;
+            self.panes.push_front(RecordBatchPane::default());
+        }
+        self.start_time = Some(start_time);
         let bin_index =
             (bin_start.duration_since(start_time)?.as_nanos() / self.width.as_nanos()) as usize;
         while self.panes.len() <= bin_index {
@@ -425,6 +602,23 @@ impl TieredRecordBatchHolder {
             .iter()
             .all(|entry| entry.batches.is_empty())
     }
+
+    /// Whether there is no data at or after `start`; data before it is only held so that late
+    /// rows can update windows that have already been emitted
+    fn is_empty_from(&self, start: SystemTime) -> bool {
+        let tier = &self.tiers[0];
+        let Some(start_time) = tier.start_time else {
+            return true;
+        };
+        let skip = start
+            .duration_since(start_time)
+            .map(|d| (d.as_nanos() / tier.width.as_nanos()) as usize)
+            .unwrap_or(0);
+        tier.panes
+            .iter()
+            .skip(skip)
+            .all(|entry| entry.batches.is_empty())
+    }
 }
 
 struct BinComputingHolder<K: Copy> {
@@ -460,6 +654,10 @@ impl OperatorConstructor for SlidingAggregatingWindowConstructor {
             .ok_or_else(|| anyhow!("missing input schema"))?
             .try_into()?;
         let slide = Duration::from_micros(config.slide_micros);
+        let pane = sliding_window_pane(width, slide);
+        let allowed_lateness = config
+            .allowed_lateness_micros
+            .map(|lateness| sliding_window_lateness(Duration::from_micros(lateness), slide));
         let binning_function = PhysicalExprNode::decode(&mut config.binning_function.as_slice())?;
         let binning_function = parse_physical_expr(
             &binning_function,
@@ -509,6 +707,7 @@ impl OperatorConstructor for SlidingAggregatingWindowConstructor {
             SlidingAggregatingWindowFunc {
                 slide,
                 width,
+                pane,
                 binning_function,
                 partial_aggregation_plan,
                 partial_schema,
@@ -517,12 +716,12 @@ impl OperatorConstructor for SlidingAggregatingWindowConstructor {
                 final_batches_passer,
                 futures: FuturesUnordered::new(),
                 execs: BTreeMap::new(),
-                tiered_record_batches: TieredRecordBatchHolder::new(vec![Duration::from_micros(
-                    config.slide_micros,
-                )])?,
+                tiered_record_batches: TieredRecordBatchHolder::new(vec![pane])?,
                 projection_input_schema: final_projection.children()[0].schema().clone(),
                 final_projection,
                 state: SlidingWindowState::NoData,
+                allowed_lateness,
+                emitted: BTreeMap::new(),
             },
         )))
     }
@@ -539,7 +738,7 @@ impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
         let watermark = ctx.last_present_watermark();
         let table = ctx
             .table_manager
-            .get_expiring_time_key_table("t", watermark)
+            .get_expiring_time_key_table(SLIDING_WINDOW.name, watermark)
             .await
             .expect("should be able to load table");
         // bins before the watermark should be put into the TieredRecordBatchHolder, those after in the exec.
@@ -560,11 +759,14 @@ impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
                 .for_each(|batch| holder.finished_batches.push(batch.clone()));
         }
 
-        if self.tiered_record_batches.is_empty() {
-            match table.get_min_time() {
-                Some(min_time) => {
+        if self
+            .tiered_record_batches
+            .is_empty_from(self.next_window_start(watermark_bin))
+        {
+            match self.execs.keys().next() {
+                Some(bin) => {
                     self.state = SlidingWindowState::OnlyBufferedData {
-                        earliest_bin_time: self.bin_start(min_time),
+                        earliest_bin_time: *bin,
                     }
                 }
                 None => self.state = SlidingWindowState::NoData,
@@ -574,9 +776,21 @@ impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
                 next_window_start: watermark_bin,
             };
         }
+
+        // recompute the windows emitted before we restored that late rows may still retract
+        if let (Some(lateness), Some(watermark)) = (self.allowed_lateness, watermark) {
+            let mut window_end = self.next_window_start(watermark_bin) + self.width - self.slide;
+            while window_end + lateness > watermark && !self.tiered_record_batches.is_empty() {
+                let results = self
+                    .compute_window(window_end - self.width, window_end)
+                    .await
+                    .expect("should be able to compute window");
+                self.emitted.insert(window_end, results);
+                window_end -= self.slide;
+            }
+        }
     }
 
-    // TODO: filter out late data
     async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
         let bin = self
             .binning_function
@@ -604,9 +818,19 @@ impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
             let bin_start = from_nanos(typed_bin.value(range.start) as u128);
 
             let watermark = ctx.last_present_watermark();
+            let bin_batch = sorted.slice(range.start, range.end - range.start);
 
-            if watermark.is_some() && bin_start < self.bin_start(watermark.unwrap()) {
-                return;
+            if let Some(watermark) = watermark {
+                if bin_start < self.bin_start(watermark) {
+                    if self.accepts_late_rows(bin_start, watermark) {
+                        self.update_emitted_windows(bin_start, bin_batch, watermark, ctx)
+                            .await
+                            .expect("should be able to update emitted windows");
+                    } else {
+                        ctx.collect_late(bin_batch).await;
+                    }
+                    continue;
+                }
             }
 
             self.state = match self.state {
@@ -622,7 +846,6 @@ impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
                     SlidingWindowState::InMemoryData { next_window_start }
                 }
             };
-            let bin_batch = sorted.slice(range.start, range.end - range.start);
             let bin_exec = self.execs.entry(bin_start).or_default();
             if bin_exec.active_exec.is_none() {
                 let (unbounded_sender, unbounded_receiver) = unbounded_channel();
@@ -660,6 +883,11 @@ impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
             self.advance(ctx).await.unwrap();
         }
 
+        if let Some(lateness) = self.allowed_lateness {
+            self.emitted
+                .retain(|window_end, _| *window_end + lateness > last_watermark);
+        }
+
         Some(watermark)
     }
 
@@ -672,7 +900,7 @@ impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
             });
         let table = ctx
             .table_manager
-            .get_expiring_time_key_table("t", watermark)
+            .get_expiring_time_key_table(SLIDING_WINDOW.name, watermark)
             .await
             .expect("should get table");
 
@@ -706,11 +934,11 @@ impl ArrowOperator for SlidingAggregatingWindowFunc<SystemTime> {
 
     fn tables(&self) -> HashMap<String, TableConfig> {
         vec![(
-            "t".to_string(),
+            SLIDING_WINDOW.name.to_string(),
             timestamp_table_config(
-                "t",
-                "Sliding_intermediate",
-                self.width,
+                SLIDING_WINDOW.name,
+                SLIDING_WINDOW.description,
+                sliding_window_retention(self.width, self.allowed_lateness),
                 false,
                 self.partial_schema.clone(),
             ),
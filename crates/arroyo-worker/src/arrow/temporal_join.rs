@@ -0,0 +1,386 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Result};
+use arrow::compute::{concat_batches, filter_record_batch, not};
+use arrow_array::{cast::AsArray, RecordBatch};
+use arroyo_datastream::state_tables::{
+    TEMPORAL_JOIN_LEFT, TEMPORAL_JOIN_RIGHT, TEMPORAL_JOIN_VERSIONS,
+};
+use arroyo_df::physical::{ArroyoPhysicalExtensionCodec, DecodingContext};
+use arroyo_operator::context::ArrowContext;
+use arroyo_operator::operator::{ArrowOperator, OperatorConstructor, OperatorNode, Registry};
+use arroyo_rpc::{
+    df::ArroyoSchema,
+    grpc::{api, TableConfig},
+    IS_RETRACT_FIELD,
+};
+use arroyo_state::timestamp_table_config;
+use arroyo_types::{from_nanos, CheckpointBarrier, Watermark, DEFAULT_TEMPORAL_JOIN_TTL};
+use datafusion::execution::context::SessionContext;
+use datafusion::execution::runtime_env::{RuntimeConfig, RuntimeEnv};
+use datafusion::physical_plan::ExecutionPlan;
+use datafusion_proto::{physical_plan::AsExecutionPlan, protobuf::PhysicalPlanNode};
+use futures::StreamExt;
+use prost::Message;
+
+/// Joins each left row with the version of the matching right row that was current at the left
+/// row's time. Both sides are buffered until the watermark passes them, then applied in time
+/// order, so that a left row sees exactly the right rows at or before its time.
+pub struct TemporalJoin {
+    // how long the version of a key that isn't updated is kept
+    ttl: Duration,
+    left_input_schema: ArroyoSchema,
+    right_input_schema: ArroyoSchema,
+    // the left input without keys, used for splitting batches by time
+    left_unkeyed_schema: ArroyoSchema,
+    right_unkeyed_schema: ArroyoSchema,
+    right_schema: ArroyoSchema,
+    // index of the retraction flag in the right input, if it is updating
+    right_retract_index: Option<usize>,
+    pending_left: BTreeMap<SystemTime, Vec<RecordBatch>>,
+    pending_right: BTreeMap<SystemTime, Vec<RecordBatch>>,
+    left_passer: Arc<RwLock<Option<RecordBatch>>>,
+    right_passer: Arc<RwLock<Option<RecordBatch>>>,
+    join_execution_plan: Arc<dyn ExecutionPlan>,
+}
+
+impl TemporalJoin {
+    /// Splits the batch into the rows at each timestamp. Rows at the same timestamp keep their
+    /// arrival order, so that later versions of a key replace earlier ones.
+    fn split_by_timestamp(
+        unkeyed_schema: &ArroyoSchema,
+        batch: RecordBatch,
+    ) -> Result<Vec<(SystemTime, RecordBatch)>> {
+        let sorted_batch = unkeyed_schema.sort_stable(batch, true)?;
+        let timestamps = unkeyed_schema.timestamp_column(&sorted_batch);
+        Ok(unkeyed_schema
+            .partition(&sorted_batch, true)?
+            .into_iter()
+            .map(|range| {
+                (
+                    from_nanos(timestamps.value(range.start) as u128),
+                    sorted_batch.slice(range.start, range.end - range.start),
+                )
+            })
+            .collect())
+    }
+
+    async fn buffer(&mut self, batch: RecordBatch, is_left: bool, ctx: &mut ArrowContext) {
+        let current_watermark = ctx.last_present_watermark();
+        let (input_schema, unkeyed_schema, table_name) = if is_left {
+            (
+                &self.left_input_schema,
+                &self.left_unkeyed_schema,
+                TEMPORAL_JOIN_LEFT.name,
+            )
+        } else {
+            (
+                &self.right_input_schema,
+                &self.right_unkeyed_schema,
+                TEMPORAL_JOIN_RIGHT.name,
+            )
+        };
+        let (batch, late) = input_schema
+            .split_by_time(batch, current_watermark)
+            .expect("should split by time");
+        ctx.collect_late(late).await;
+        if batch.num_rows() == 0 {
+            return;
+        }
+        let batches = Self::split_by_timestamp(unkeyed_schema, batch).expect("should split batch");
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table(table_name, current_watermark)
+            .await
+            .expect("should have pending table");
+        let pending = if is_left {
+            &mut self.pending_left
+        } else {
+            &mut self.pending_right
+        };
+        for (timestamp, batch) in batches {
+            table.insert(timestamp, batch.clone());
+            pending.entry(timestamp).or_default().push(batch);
+        }
+    }
+
+    /// Makes the right rows at `timestamp` the current versions of their keys
+    async fn apply_versions(
+        &mut self,
+        batches: Vec<RecordBatch>,
+        ctx: &mut ArrowContext,
+    ) -> Result<()> {
+        let versions = ctx
+            .table_manager
+            .get_last_key_value_table(TEMPORAL_JOIN_VERSIONS.name, ctx.last_present_watermark())
+            .await?;
+        // rows are in arrival order, so later rows for a key replace earlier ones
+        for batch in batches {
+            versions.insert_batch(batch).await?;
+        }
+        Ok(())
+    }
+
+    /// Joins the left rows at a single timestamp with the current versions of their keys
+    async fn join_left(&mut self, batches: Vec<RecordBatch>, ctx: &mut ArrowContext) -> Result<()> {
+        let left_batch = concat_batches(&self.left_input_schema.schema, batches.iter())?;
+        let key_columns: Vec<_> = self
+            .left_input_schema
+            .key_indices
+            .as_ref()
+            .ok_or_else(|| anyhow!("left input should be keyed"))?
+            .iter()
+            .map(|index| left_batch.column(*index).clone())
+            .collect();
+        let versions = ctx
+            .table_manager
+            .get_last_key_value_table(TEMPORAL_JOIN_VERSIONS.name, ctx.last_present_watermark())
+            .await?
+            .get_current_values_for_keys(&key_columns, left_batch.num_rows())?;
+        let right_batch = match versions {
+            Some(versions) => {
+                // a key whose current version is a retraction has been deleted
+                let versions = match self.right_retract_index {
+                    Some(index) => {
+                        filter_record_batch(&versions, &not(versions.column(index).as_boolean())?)?
+                    }
+                    None => versions,
+                };
+                self.right_input_schema.unkeyed_batch(&versions)?
+            }
+            None => RecordBatch::new_empty(self.right_schema.schema.clone()),
+        };
+        self.compute_pair(
+            self.left_input_schema.unkeyed_batch(&left_batch)?,
+            right_batch,
+            ctx,
+        )
+        .await;
+        Ok(())
+    }
+
+    async fn compute_pair(
+        &mut self,
+        left: RecordBatch,
+        right: RecordBatch,
+        ctx: &mut ArrowContext,
+    ) {
+        {
+            self.right_passer.write().unwrap().replace(right);
+            self.left_passer.write().unwrap().replace(left);
+        }
+        self.join_execution_plan.reset().unwrap();
+        let mut records = self
+            .join_execution_plan
+            .execute(0, SessionContext::new().task_ctx())
+            .expect("successfully computed?");
+        while let Some(batch) = records.next().await {
+            let batch = batch.expect("should be able to compute batch");
+            ctx.collect(batch).await;
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ArrowOperator for TemporalJoin {
+    fn name(&self) -> String {
+        "TemporalJoin".to_string()
+    }
+
+    async fn on_start(&mut self, ctx: &mut ArrowContext) {
+        let watermark = ctx.last_present_watermark();
+        for (table_name, pending) in [
+            (TEMPORAL_JOIN_LEFT.name, &mut self.pending_left),
+            (TEMPORAL_JOIN_RIGHT.name, &mut self.pending_right),
+        ] {
+            let table = ctx
+                .table_manager
+                .get_expiring_time_key_table(table_name, watermark)
+                .await
+                .expect("should have pending table");
+            for (timestamp, batches) in table.all_batches_for_watermark(watermark) {
+                pending
+                    .entry(*timestamp)
+                    .or_default()
+                    .extend(batches.iter().cloned());
+            }
+        }
+        // fetch the versions so they are ready to be queried
+        ctx.table_manager
+            .get_last_key_value_table(TEMPORAL_JOIN_VERSIONS.name, watermark)
+            .await
+            .expect("should have versions table");
+    }
+
+    async fn process_batch(&mut self, _record_batch: RecordBatch, _ctx: &mut ArrowContext) {
+        unreachable!();
+    }
+
+    async fn process_batch_index(
+        &mut self,
+        index: usize,
+        total_inputs: usize,
+        record_batch: RecordBatch,
+        ctx: &mut ArrowContext,
+    ) {
+        match index / (total_inputs / 2) {
+            0 => self.buffer(record_batch, true, ctx).await,
+            1 => self.buffer(record_batch, false, ctx).await,
+            _ => unreachable!(),
+        }
+    }
+
+    async fn handle_watermark(
+        &mut self,
+        watermark_message: Watermark,
+        ctx: &mut ArrowContext,
+    ) -> Option<Watermark> {
+        let Some(watermark) = ctx.last_present_watermark() else {
+            return Some(watermark_message);
+        };
+        // walk both sides in time order, applying the versions at each time before joining the
+        // left rows at that time
+        loop {
+            let next_timestamp = match (
+                self.pending_left.keys().next(),
+                self.pending_right.keys().next(),
+            ) {
+                (Some(left), Some(right)) => *left.min(right),
+                (Some(timestamp), None) | (None, Some(timestamp)) => *timestamp,
+                (None, None) => break,
+            };
+            if watermark <= next_timestamp {
+                break;
+            }
+            if let Some(batches) = self.pending_right.remove(&next_timestamp) {
+                self.apply_versions(batches, ctx)
+                    .await
+                    .expect("should apply versions");
+            }
+            if let Some(batches) = self.pending_left.remove(&next_timestamp) {
+                self.join_left(batches, ctx)
+                    .await
+                    .expect("should join left rows");
+            }
+        }
+        ctx.table_manager
+            .get_last_key_value_table(TEMPORAL_JOIN_VERSIONS.name, Some(watermark))
+            .await
+            .expect("should have versions table")
+            .expire(Some(watermark))
+            .expect("should expire versions");
+        Some(watermark_message)
+    }
+
+    async fn handle_checkpoint(&mut self, _cb: CheckpointBarrier, ctx: &mut ArrowContext) {
+        let watermark = ctx.last_present_watermark();
+        for table_name in [TEMPORAL_JOIN_LEFT.name, TEMPORAL_JOIN_RIGHT.name] {
+            ctx.table_manager
+                .get_expiring_time_key_table(table_name, watermark)
+                .await
+                .expect("should have pending table")
+                .flush(watermark)
+                .await
+                .expect("should flush");
+        }
+    }
+
+    fn tables(&self) -> HashMap<String, TableConfig> {
+        let mut tables = HashMap::new();
+        tables.insert(
+            TEMPORAL_JOIN_LEFT.name.to_string(),
+            timestamp_table_config(
+                TEMPORAL_JOIN_LEFT.name,
+                TEMPORAL_JOIN_LEFT.description,
+                Duration::ZERO,
+                false,
+                self.left_input_schema.clone(),
+            ),
+        );
+        tables.insert(
+            TEMPORAL_JOIN_RIGHT.name.to_string(),
+            timestamp_table_config(
+                TEMPORAL_JOIN_RIGHT.name,
+                TEMPORAL_JOIN_RIGHT.description,
+                Duration::ZERO,
+                false,
+                self.right_input_schema.clone(),
+            ),
+        );
+        tables.insert(
+            TEMPORAL_JOIN_VERSIONS.name.to_string(),
+            timestamp_table_config(
+                TEMPORAL_JOIN_VERSIONS.name,
+                TEMPORAL_JOIN_VERSIONS.description,
+                self.ttl,
+                true,
+                self.right_input_schema.clone(),
+            ),
+        );
+        tables
+    }
+}
+
+pub struct TemporalJoinConstructor;
+impl OperatorConstructor for TemporalJoinConstructor {
+    type ConfigT = api::JoinOperator;
+    fn with_config(
+        &self,
+        config: Self::ConfigT,
+        registry: Arc<Registry>,
+    ) -> anyhow::Result<OperatorNode> {
+        let left_passer = Arc::new(RwLock::new(None));
+        let right_passer = Arc::new(RwLock::new(None));
+
+        let codec = ArroyoPhysicalExtensionCodec {
+            context: DecodingContext::LockedJoinPair {
+                left: left_passer.clone(),
+                right: right_passer.clone(),
+            },
+        };
+        let join_physical_plan_node = PhysicalPlanNode::decode(&mut config.join_plan.as_slice())?;
+        let join_execution_plan = join_physical_plan_node.try_into_physical_plan(
+            registry.as_ref(),
+            &RuntimeEnv::new(RuntimeConfig::new())?,
+            &codec,
+        )?;
+
+        let left_input_schema: ArroyoSchema = config
+            .left_schema
+            .ok_or_else(|| anyhow!("missing left schema"))?
+            .try_into()?;
+        let right_input_schema: ArroyoSchema = config
+            .right_schema
+            .ok_or_else(|| anyhow!("missing right schema"))?
+            .try_into()?;
+        let left_unkeyed_schema =
+            ArroyoSchema::from_schema_unkeyed(left_input_schema.schema.clone())?;
+        let right_unkeyed_schema =
+            ArroyoSchema::from_schema_unkeyed(right_input_schema.schema.clone())?;
+        let right_schema = right_input_schema.schema_without_keys()?;
+        let right_retract_index = right_input_schema.schema.index_of(IS_RETRACT_FIELD).ok();
+        let ttl = config
+            .ttl_micros
+            .map(Duration::from_micros)
+            .unwrap_or(DEFAULT_TEMPORAL_JOIN_TTL);
+
+        Ok(OperatorNode::from_operator(Box::new(TemporalJoin {
+            ttl,
+            left_input_schema,
+            right_input_schema,
+            left_unkeyed_schema,
+            right_unkeyed_schema,
+            right_schema,
+            right_retract_index,
+            pending_left: BTreeMap::new(),
+            pending_right: BTreeMap::new(),
+            left_passer,
+            right_passer,
+            join_execution_plan,
+        })))
+    }
+}
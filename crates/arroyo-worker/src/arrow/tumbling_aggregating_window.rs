@@ -3,15 +3,17 @@ use std::future::Future;
 use std::pin::Pin;
 use std::{
     collections::{BTreeMap, HashMap},
-    mem,
     sync::{Arc, RwLock},
     time::SystemTime,
 };
 
 use anyhow::{anyhow, Result};
 use arrow::compute::{partition, sort_to_indices, take};
-use arrow_array::{types::TimestampNanosecondType, Array, PrimitiveArray, RecordBatch};
+use arrow_array::{
+    types::TimestampNanosecondType, Array, BooleanArray, PrimitiveArray, RecordBatch,
+};
 use arrow_schema::SchemaRef;
+use arroyo_datastream::state_tables::{tumbling_window_retention, TUMBLING_WINDOW};
 use arroyo_df::schemas::add_timestamp_field_arrow;
 use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::{ArrowOperator, OperatorConstructor, OperatorNode, Registry};
@@ -38,7 +40,7 @@ use prost::Message;
 use std::time::Duration;
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 use tokio::sync::Mutex;
-use tracing::{debug, warn};
+use tracing::debug;
 
 use super::sync::streams::KeyedCloneableStreamFuture;
 type NextBatchFuture<K> = KeyedCloneableStreamFuture<K, SendableRecordBatchStream>;
@@ -57,6 +59,13 @@ pub struct TumblingAggregatingWindowFunc<K: Copy> {
     final_batches_passer: Arc<RwLock<Vec<RecordBatch>>>,
     futures: Arc<Mutex<FuturesUnordered<NextBatchFuture<K>>>>,
     execs: BTreeMap<K, BinComputingHolder<K>>,
+    // how long bins are kept after they've been emitted so that late rows can update them
+    allowed_lateness: Option<Duration>,
+    emitted: BTreeMap<K, EmittedBin>,
+    // for cumulating windows, the size of the period that windows grow within, one bin at a time
+    cumulate_max_size: Option<Duration>,
+    // the closed bins of each cumulating period that hasn't reached its max size yet
+    cumulating: BTreeMap<K, CumulatingPeriod>,
 }
 
 impl<K: Copy> TumblingAggregatingWindowFunc<K> {
@@ -69,6 +78,17 @@ impl<K: Copy> TumblingAggregatingWindowFunc<K> {
 
         from_nanos(nanos)
     }
+
+    fn period_start(&self, bin: SystemTime, max_size: Duration) -> SystemTime {
+        let nanos = to_nanos(bin);
+        from_nanos(nanos - nanos % max_size.as_nanos())
+    }
+
+    /// Whether the partial aggregates of closed bins need to be kept in state, either because
+    /// late rows may update them or because later cumulating windows include them
+    fn keeps_closed_bins(&self) -> bool {
+        self.allowed_lateness.is_some() || self.cumulate_max_size.is_some()
+    }
 }
 
 struct BinComputingHolder<K: Copy> {
@@ -87,6 +107,21 @@ impl<K: Copy> Default for BinComputingHolder<K> {
     }
 }
 
+/// A bin whose results have been emitted but that is still within the allowed lateness, along
+/// with what was emitted for it so that it can be retracted if late rows arrive
+#[derive(Default)]
+struct EmittedBin {
+    partials: Vec<RecordBatch>,
+    results: Vec<RecordBatch>,
+}
+
+/// A cumulating period that has been emitted through `next_bin`, along with the partial aggregates
+/// of all of its closed bins
+struct CumulatingPeriod {
+    partials: Vec<RecordBatch>,
+    next_bin: SystemTime,
+}
+
 type PolledFutureT = <NextBatchFuture<SystemTime> as Future>::Output;
 
 impl TumblingAggregatingWindowFunc<SystemTime> {
@@ -102,6 +137,213 @@ impl TumblingAggregatingWindowFunc<SystemTime> {
         RecordBatch::try_new(schema.clone(), columns)
             .map_err(|err| anyhow::anyhow!("schema: {:?}\nbatch:{:?}\nerr:{}", schema, batch, err))
     }
+
+    fn add_is_retract(batch: &RecordBatch, is_retract: bool, schema: SchemaRef) -> RecordBatch {
+        let mut columns = batch.columns().to_vec();
+        columns.push(Arc::new(BooleanArray::from(vec![
+            is_retract;
+            batch.num_rows()
+        ])));
+        RecordBatch::try_new(schema, columns).expect("should be able to add is_retract column")
+    }
+
+    fn accepts_late_rows(&self, bin_start: SystemTime, watermark: SystemTime) -> bool {
+        self.allowed_lateness
+            .is_some_and(|lateness| bin_start + self.width + lateness > watermark)
+    }
+
+    /// Runs the final aggregation, and the final projection if there is one, over a bin's
+    /// partial aggregates
+    async fn compute_results(
+        &self,
+        bin: SystemTime,
+        partials: Vec<RecordBatch>,
+    ) -> Vec<RecordBatch> {
+        {
+            let mut batches = self.final_batches_passer.write().unwrap();
+            *batches = partials;
+        }
+        self.finish_execution_plan
+            .reset()
+            .expect("reset execution plan");
+        let mut final_exec = self
+            .finish_execution_plan
+            .execute(0, SessionContext::new().task_ctx())
+            .unwrap();
+        let mut aggregate_results = vec![];
+        while let Some(batch) = final_exec.next().await {
+            let batch = batch.expect("should be able to compute batch");
+            let with_timestamp = Self::add_bin_start_as_timestamp(
+                &batch,
+                bin,
+                self.aggregate_with_timestamp_schema.clone(),
+            )
+            .expect("should be able to add timestamp");
+            aggregate_results.push(with_timestamp);
+        }
+
+        let Some(final_projection) = self.final_projection.as_ref() else {
+            return aggregate_results;
+        };
+
+        {
+            let mut batches = self.final_batches_passer.write().unwrap();
+            *batches = aggregate_results;
+        }
+        final_projection.reset().expect("reset execution plan");
+        let mut final_projection_exec = final_projection
+            .execute(0, SessionContext::new().task_ctx())
+            .unwrap();
+        let mut results = vec![];
+        while let Some(batch) = final_projection_exec.next().await {
+            results.push(batch.expect("should be able to compute batch"));
+        }
+        results
+    }
+
+    /// Computes the partial aggregates for rows that arrived after their bin was emitted
+    async fn compute_late_partials(&self, batch: RecordBatch) -> Vec<RecordBatch> {
+        let (sender, receiver) = unbounded_channel();
+        {
+            let mut internal_receiver = self.receiver.write().unwrap();
+            *internal_receiver = Some(receiver);
+        }
+        self.partial_aggregation_plan.reset().unwrap();
+        let mut exec = self
+            .partial_aggregation_plan
+            .execute(0, SessionContext::new().task_ctx())
+            .unwrap();
+        sender.send(batch).unwrap();
+        drop(sender);
+
+        let mut partials = vec![];
+        while let Some(batch) = exec.next().await {
+            partials.push(batch.expect("should be able to compute batch"));
+        }
+        partials
+    }
+
+    /// Finishes the partial aggregation of a bin that's being closed, returning all of its partials
+    async fn close_bin(
+        &self,
+        bin: SystemTime,
+        mut exec: BinComputingHolder<SystemTime>,
+        ctx: &mut ArrowContext,
+    ) -> Vec<RecordBatch> {
+        if let Some(mut active_exec) = exec.active_exec.take() {
+            exec.sender.take();
+            let mut new_partials = vec![];
+            while let (_bin, Some((batch, new_exec))) = active_exec.await {
+                active_exec = new_exec;
+                let batch = batch.expect("should be able to compute batch");
+                new_partials.push(batch);
+            }
+            if self.keeps_closed_bins() {
+                // the bin's state needs to outlive it
+                self.persist_partials(bin, &new_partials, ctx).await;
+            }
+            exec.finished_batches.extend(new_partials);
+        }
+        exec.finished_batches
+    }
+
+    /// Closes every bin before `watermark_bin`, emitting for each one the window that runs from
+    /// the start of its period through the end of the bin. Bins without new rows still emit if
+    /// earlier bins in their period had data.
+    async fn advance_cumulating(
+        &mut self,
+        max_size: Duration,
+        watermark_bin: SystemTime,
+        ctx: &mut ArrowContext,
+    ) {
+        loop {
+            let next_exec = self.execs.keys().next().copied();
+            let next_period_bin = self
+                .cumulating
+                .values()
+                .next()
+                .map(|period| period.next_bin);
+            let bin = match (next_exec, next_period_bin) {
+                (Some(exec_bin), Some(period_bin)) => exec_bin.min(period_bin),
+                (Some(bin), None) | (None, Some(bin)) => bin,
+                (None, None) => break,
+            };
+            if bin >= watermark_bin {
+                break;
+            }
+
+            let partials = match self.execs.remove(&bin) {
+                Some(exec) => self.close_bin(bin, exec, ctx).await,
+                None => vec![],
+            };
+            let period_start = self.period_start(bin, max_size);
+            let period = self
+                .cumulating
+                .entry(period_start)
+                .or_insert_with(|| CumulatingPeriod {
+                    partials: vec![],
+                    next_bin: bin,
+                });
+            period.partials.extend(partials);
+            period.next_bin = bin + self.width;
+            let period_partials = period.partials.clone();
+            if period_start + max_size <= bin + self.width {
+                self.cumulating.remove(&period_start);
+            }
+
+            for batch in self.compute_results(bin, period_partials).await {
+                ctx.collect(batch).await;
+            }
+        }
+    }
+
+    async fn persist_partials(
+        &self,
+        bin: SystemTime,
+        partials: &[RecordBatch],
+        ctx: &mut ArrowContext,
+    ) {
+        let table = ctx
+            .table_manager
+            .get_expiring_time_key_table(TUMBLING_WINDOW.name, ctx.last_present_watermark())
+            .await
+            .expect("should get table");
+        for batch in partials {
+            let state_batch =
+                Self::add_bin_start_as_timestamp(batch, bin, self.partial_schema.schema.clone())
+                    .expect("should be able to add timestamp");
+            table.insert(bin, state_batch);
+        }
+    }
+
+    /// Folds rows that arrived within the allowed lateness into a bin that has already been
+    /// emitted, retracting the bin's previous results and emitting the updated ones
+    async fn update_emitted_bin(
+        &mut self,
+        bin: SystemTime,
+        batch: RecordBatch,
+        ctx: &mut ArrowContext,
+    ) {
+        let partials = self.compute_late_partials(batch).await;
+        self.persist_partials(bin, &partials, ctx).await;
+
+        let mut emitted = self.emitted.remove(&bin).unwrap_or_default();
+        emitted.partials.extend(partials);
+        let results = self.compute_results(bin, emitted.partials.clone()).await;
+
+        let out_schema = ctx.out_schema.as_ref().unwrap().schema.clone();
+        for batch in &emitted.results {
+            ctx.collect(Self::add_is_retract(batch, true, out_schema.clone()))
+                .await;
+        }
+        for batch in &results {
+            ctx.collect(Self::add_is_retract(batch, false, out_schema.clone()))
+                .await;
+        }
+
+        emitted.results = results;
+        self.emitted.insert(bin, emitted);
+    }
 }
 
 pub struct TumblingAggregateWindowConstructor;
@@ -193,6 +435,10 @@ impl OperatorConstructor for TumblingAggregateWindowConstructor {
                 final_batches_passer,
                 futures: Arc::new(Mutex::new(FuturesUnordered::new())),
                 execs: BTreeMap::new(),
+                allowed_lateness: config.allowed_lateness_micros.map(Duration::from_micros),
+                emitted: BTreeMap::new(),
+                cumulate_max_size: config.cumulate_max_size_micros.map(Duration::from_micros),
+                cumulating: BTreeMap::new(),
             },
         )))
     }
@@ -208,16 +454,54 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
         let watermark = ctx.last_present_watermark();
         let table = ctx
             .table_manager
-            .get_expiring_time_key_table("t", watermark)
+            .get_expiring_time_key_table(TUMBLING_WINDOW.name, watermark)
             .await
             .expect("should be able to load table");
         for (timestamp, batch) in table.all_batches_for_watermark(watermark) {
             let bin = self.bin_start(*timestamp);
+            if let (Some(max_size), Some(watermark)) = (self.cumulate_max_size, watermark) {
+                let watermark_bin = self.bin_start(watermark);
+                if bin < watermark_bin {
+                    // this bin was closed before we restored, but the rest of its period's
+                    // windows still include it
+                    let period_start = self.period_start(bin, max_size);
+                    if period_start + max_size > watermark_bin {
+                        self.cumulating
+                            .entry(period_start)
+                            .or_insert_with(|| CumulatingPeriod {
+                                partials: vec![],
+                                next_bin: watermark_bin,
+                            })
+                            .partials
+                            .extend(batch.iter().cloned());
+                    }
+                    continue;
+                }
+            }
+            if self.allowed_lateness.is_some()
+                && watermark.is_some_and(|watermark| bin < self.bin_start(watermark))
+            {
+                // this bin was emitted before we restored, but may still receive late rows
+                self.emitted
+                    .entry(bin)
+                    .or_default()
+                    .partials
+                    .extend(batch.iter().cloned());
+                continue;
+            }
             let holder = self.execs.entry(bin).or_default();
             batch
                 .iter()
                 .for_each(|batch| holder.finished_batches.push(batch.clone()));
         }
+
+        // recompute what was emitted for the restored bins so that it can be retracted
+        let emitted_bins: Vec<_> = self.emitted.keys().copied().collect();
+        for bin in emitted_bins {
+            let partials = self.emitted[&bin].partials.clone();
+            let results = self.compute_results(bin, partials).await;
+            self.emitted.get_mut(&bin).unwrap().results = results;
+        }
     }
 
     async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
@@ -247,16 +531,24 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
             let bin_start = from_nanos(typed_bin.value(range.start) as u128);
             let watermark = ctx.last_present_watermark();
 
-            if watermark.is_some() && bin_start < self.bin_start(watermark.unwrap()) {
-                warn!(
-                    "bin start {} is before watermark {}, skipping",
-                    print_time(bin_start),
-                    print_time(watermark.unwrap())
-                );
-                continue;
+            let bin_batch = sorted.slice(range.start, range.end - range.start);
+
+            if let Some(watermark) = watermark {
+                if bin_start < self.bin_start(watermark) {
+                    if self.accepts_late_rows(bin_start, watermark) {
+                        self.update_emitted_bin(bin_start, bin_batch, ctx).await;
+                    } else {
+                        debug!(
+                            "bin start {} is before watermark {}, dropping late rows",
+                            print_time(bin_start),
+                            print_time(watermark)
+                        );
+                        ctx.collect_late(bin_batch).await;
+                    }
+                    continue;
+                }
             }
 
-            let bin_batch = sorted.slice(range.start, range.end - range.start);
             let bin_exec = self.execs.entry(bin_start).or_default();
             if bin_exec.active_exec.is_none() {
                 let (unbounded_sender, unbounded_receiver) = unbounded_channel();
@@ -290,6 +582,10 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
     ) -> Option<Watermark> {
         if let Some(watermark) = ctx.last_present_watermark() {
             let bin = self.bin_start(watermark);
+            if let Some(max_size) = self.cumulate_max_size {
+                // this closes every bin before the watermark, so there's nothing left to pop below
+                self.advance_cumulating(max_size, bin, ctx).await;
+            }
             while !self.execs.is_empty() {
                 let should_pop = {
                     let Some((first_bin, _exec)) = self.execs.first_key_value() else {
@@ -298,55 +594,22 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
                     *first_bin < bin
                 };
                 if should_pop {
-                    let Some((popped_bin, mut exec)) = self.execs.pop_first() else {
+                    let Some((popped_bin, exec)) = self.execs.pop_first() else {
                         unreachable!("should have an entry")
                     };
-                    if let Some(mut active_exec) = exec.active_exec.take() {
-                        exec.sender.take();
-                        while let (_bin, Some((batch, new_exec))) = active_exec.await {
-                            active_exec = new_exec;
-                            let batch = batch.expect("should be able to compute batch");
-                            exec.finished_batches.push(batch);
-                        }
-                    }
-                    {
-                        let mut batches = self.final_batches_passer.write().unwrap();
-                        let finished_batches = mem::take(&mut exec.finished_batches);
-                        *batches = finished_batches;
-                    }
-                    self.finish_execution_plan
-                        .reset()
-                        .expect("reset execution plan");
-                    let mut final_exec = self
-                        .finish_execution_plan
-                        .execute(0, SessionContext::new().task_ctx())
-                        .unwrap();
-                    let mut aggregate_results = vec![];
-                    while let Some(batch) = final_exec.next().await {
-                        let batch = batch.expect("should be able to compute batch");
-                        let with_timestamp = Self::add_bin_start_as_timestamp(
-                            &batch,
-                            popped_bin,
-                            self.aggregate_with_timestamp_schema.clone(),
-                        )
-                        .expect("should be able to add timestamp");
-                        if self.final_projection.is_some() {
-                            aggregate_results.push(with_timestamp);
-                        } else {
-                            ctx.collect(with_timestamp).await;
+                    let partials = self.close_bin(popped_bin, exec, ctx).await;
+
+                    if self.allowed_lateness.is_some() {
+                        let results = self.compute_results(popped_bin, partials.clone()).await;
+                        let out_schema = ctx.out_schema.as_ref().unwrap().schema.clone();
+                        for batch in &results {
+                            ctx.collect(Self::add_is_retract(batch, false, out_schema.clone()))
+                                .await;
                         }
-                    }
-                    if let Some(final_projection) = self.final_projection.as_ref() {
-                        {
-                            let mut batches = self.final_batches_passer.write().unwrap();
-                            *batches = aggregate_results;
-                        }
-                        final_projection.reset().expect("reset execution plan");
-                        let mut final_projection_exec = final_projection
-                            .execute(0, SessionContext::new().task_ctx())
-                            .unwrap();
-                        while let Some(batch) = final_projection_exec.next().await {
-                            let batch = batch.expect("should be able to compute batch");
+                        self.emitted
+                            .insert(popped_bin, EmittedBin { partials, results });
+                    } else {
+                        for batch in self.compute_results(popped_bin, partials).await {
                             ctx.collect(batch).await;
                         }
                     }
@@ -354,6 +617,12 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
                     break;
                 }
             }
+
+            if let Some(lateness) = self.allowed_lateness {
+                let width = self.width;
+                self.emitted
+                    .retain(|bin, _| *bin + width + lateness > watermark);
+            }
         }
         Some(watermark)
     }
@@ -373,7 +642,7 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
         }))
     }
 
-    async fn handle_future_result(&mut self, result: Box<dyn Any + Send>, _: &mut ArrowContext) {
+    async fn handle_future_result(&mut self, result: Box<dyn Any + Send>, ctx: &mut ArrowContext) {
         let data: Box<Option<PolledFutureT>> = result.downcast().expect("invalid data in future");
         if let Some((bin, batch_option)) = *data {
             match batch_option {
@@ -382,9 +651,12 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
                 }
                 Some((batch, future)) => match self.execs.get_mut(&bin) {
                     Some(exec) => {
-                        exec.finished_batches
-                            .push(batch.expect("should've been able to compute a batch"));
+                        let batch = batch.expect("should've been able to compute a batch");
+                        exec.finished_batches.push(batch.clone());
                         self.futures.lock().await.push(future);
+                        if self.keeps_closed_bins() {
+                            self.persist_partials(bin, &[batch], ctx).await;
+                        }
                     }
                     None => unreachable!(
                         "FuturesUnordered returned a batch, but we can't find the exec"
@@ -403,7 +675,7 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
             });
         let table = ctx
             .table_manager
-            .get_expiring_time_key_table("t", watermark)
+            .get_expiring_time_key_table(TUMBLING_WINDOW.name, watermark)
             .await
             .expect("should get table");
 
@@ -431,11 +703,15 @@ impl ArrowOperator for TumblingAggregatingWindowFunc<SystemTime> {
 
     fn tables(&self) -> HashMap<String, TableConfig> {
         vec![(
-            "t".to_string(),
+            TUMBLING_WINDOW.name.to_string(),
             timestamp_table_config(
-                "t",
-                "tumbling_intermediate",
-                self.width,
+                TUMBLING_WINDOW.name,
+                TUMBLING_WINDOW.description,
+                tumbling_window_retention(
+                    self.width,
+                    self.cumulate_max_size,
+                    self.allowed_lateness,
+                ),
                 false,
                 self.partial_schema.clone(),
             ),
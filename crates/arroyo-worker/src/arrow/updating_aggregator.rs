@@ -9,13 +9,14 @@ use anyhow::{anyhow, Result};
 use arrow::compute::concat_batches;
 use arrow_array::RecordBatch;
 
+use arroyo_datastream::state_tables::{UPDATING_AGGREGATE_FINAL, UPDATING_AGGREGATE_PARTIAL};
 use arroyo_operator::{
     context::ArrowContext,
     operator::{ArrowOperator, OperatorConstructor, OperatorNode},
 };
 use arroyo_rpc::grpc::{api::UpdatingAggregateOperator, TableConfig};
 use arroyo_state::timestamp_table_config;
-use arroyo_types::{CheckpointBarrier, SignalMessage, Watermark};
+use arroyo_types::{CheckpointBarrier, SignalMessage, Watermark, DEFAULT_UPDATING_AGGREGATE_TTL};
 use datafusion::{execution::context::SessionContext, physical_plan::ExecutionPlan};
 
 use arroyo_df::physical::{ArroyoPhysicalExtensionCodec, DecodingContext};
@@ -40,6 +41,8 @@ pub struct UpdatingAggregatingFunc {
     state_partial_schema: ArroyoSchemaRef,
     state_final_schema: ArroyoSchemaRef,
     flush_interval: Duration,
+    // how long state is kept for keys that haven't been updated
+    ttl: Duration,
     combine_plan: Arc<dyn ExecutionPlan>,
     finish_execution_plan: Arc<dyn ExecutionPlan>,
     receiver: Arc<RwLock<Option<UnboundedReceiver<RecordBatch>>>>,
@@ -67,7 +70,10 @@ impl UpdatingAggregatingFunc {
             concat_batches(&self.state_partial_schema.schema, &partial_batches)?;
         let prior_partials = ctx
             .table_manager
-            .get_last_key_value_table("p", ctx.last_present_watermark())
+            .get_last_key_value_table(
+                UPDATING_AGGREGATE_PARTIAL.name,
+                ctx.last_present_watermark(),
+            )
             .await?;
         let mut final_input_batches = vec![];
         if let Some((prior_partial_batch, _filter)) =
@@ -109,7 +115,7 @@ impl UpdatingAggregatingFunc {
         };
         let final_output_table = ctx
             .table_manager
-            .get_last_key_value_table("f", ctx.last_present_watermark())
+            .get_last_key_value_table(UPDATING_AGGREGATE_FINAL.name, ctx.last_present_watermark())
             .await?;
         let mut batches_to_write = vec![];
         while let Some(results) = final_exec.next().await {
@@ -182,21 +188,21 @@ impl ArrowOperator for UpdatingAggregatingFunc {
     fn tables(&self) -> HashMap<String, TableConfig> {
         vec![
             (
-                "f".to_string(),
+                UPDATING_AGGREGATE_FINAL.name.to_string(),
                 timestamp_table_config(
-                    "f",
-                    "final_table",
-                    Duration::from_secs(60 * 60 * 24),
+                    UPDATING_AGGREGATE_FINAL.name,
+                    UPDATING_AGGREGATE_FINAL.description,
+                    self.ttl,
                     true,
                     self.state_final_schema.as_ref().clone(),
                 ),
             ),
             (
-                "p".to_string(),
+                UPDATING_AGGREGATE_PARTIAL.name.to_string(),
                 timestamp_table_config(
-                    "p",
-                    "partial_table",
-                    Duration::from_secs(60 * 60 * 24),
+                    UPDATING_AGGREGATE_PARTIAL.name,
+                    UPDATING_AGGREGATE_PARTIAL.description,
+                    self.ttl,
                     true,
                     self.state_partial_schema.as_ref().clone(),
                 ),
@@ -221,7 +227,7 @@ impl ArrowOperator for UpdatingAggregatingFunc {
         let last_watermark = ctx.last_present_watermark();
         let partial_table = ctx
             .table_manager
-            .get_last_key_value_table("p", last_watermark)
+            .get_last_key_value_table(UPDATING_AGGREGATE_PARTIAL.name, last_watermark)
             .await
             .expect("should have partial table");
         if partial_table.would_expire(last_watermark) {
@@ -229,7 +235,7 @@ impl ArrowOperator for UpdatingAggregatingFunc {
         }
         let partial_table = ctx
             .table_manager
-            .get_last_key_value_table("p", last_watermark)
+            .get_last_key_value_table(UPDATING_AGGREGATE_PARTIAL.name, last_watermark)
             .await
             .expect("should have partial table");
         partial_table
@@ -237,7 +243,7 @@ impl ArrowOperator for UpdatingAggregatingFunc {
             .expect("should expire partial table");
         let final_table = ctx
             .table_manager
-            .get_last_key_value_table("f", last_watermark)
+            .get_last_key_value_table(UPDATING_AGGREGATE_FINAL.name, last_watermark)
             .await
             .expect("should have final table");
         final_table
@@ -270,11 +276,14 @@ impl ArrowOperator for UpdatingAggregatingFunc {
     async fn on_start(&mut self, ctx: &mut ArrowContext) {
         // fetch the tables so they are ready to be queried.
         ctx.table_manager
-            .get_last_key_value_table("f", ctx.last_present_watermark())
+            .get_last_key_value_table(UPDATING_AGGREGATE_FINAL.name, ctx.last_present_watermark())
             .await
             .unwrap();
         ctx.table_manager
-            .get_last_key_value_table("p", ctx.last_present_watermark())
+            .get_last_key_value_table(
+                UPDATING_AGGREGATE_PARTIAL.name,
+                ctx.last_present_watermark(),
+            )
             .await
             .unwrap();
     }
@@ -344,6 +353,10 @@ impl OperatorConstructor for UpdatingAggregatingConstructor {
                         .try_into()?,
                 ),
                 flush_interval: Duration::from_micros(config.flush_interval_micros),
+                ttl: config
+                    .ttl_micros
+                    .map(Duration::from_micros)
+                    .unwrap_or(DEFAULT_UPDATING_AGGREGATE_TTL),
                 finish_execution_plan,
                 receiver,
                 sender: None,
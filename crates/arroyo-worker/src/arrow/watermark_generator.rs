@@ -1,5 +1,7 @@
 use arrow::compute::kernels;
 use arrow_array::RecordBatch;
+use arroyo_datastream::state_tables::EXPRESSION_WATERMARK;
+use arroyo_metrics::gauge_for_task;
 use arroyo_operator::context::ArrowContext;
 use arroyo_operator::get_timestamp_col;
 use arroyo_operator::operator::{ArrowOperator, OperatorConstructor, OperatorNode, Registry};
@@ -9,6 +11,7 @@ use arroyo_rpc::grpc::TableConfig;
 use arroyo_state::global_table_config;
 use arroyo_types::{
     from_nanos, to_millis, ArrowMessage, CheckpointBarrier, SignalMessage, Watermark,
+    MAX_EVENT_TIME, SOURCE_IDLE,
 };
 use async_trait::async_trait;
 use bincode::{Decode, Encode};
@@ -16,6 +19,7 @@ use datafusion::physical_expr::PhysicalExpr;
 use datafusion_proto::physical_plan::from_proto::parse_physical_expr;
 use datafusion_proto::physical_plan::DefaultPhysicalExtensionCodec;
 use datafusion_proto::protobuf::PhysicalExprNode;
+use prometheus::IntGauge;
 use prost::Message;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -34,6 +38,9 @@ pub struct WatermarkGenerator {
     idle_time: Option<Duration>,
     last_event: SystemTime,
     idle: bool,
+    max_event_time: SystemTime,
+    max_event_time_gauge: Option<IntGauge>,
+    idle_gauge: Option<IntGauge>,
     expression: Arc<dyn PhysicalExpr>,
 }
 
@@ -52,9 +59,19 @@ impl WatermarkGenerator {
             idle_time,
             last_event: SystemTime::now(),
             idle: false,
+            max_event_time: SystemTime::UNIX_EPOCH,
+            max_event_time_gauge: None,
+            idle_gauge: None,
             expression,
         }
     }
+
+    fn set_idle(&mut self, idle: bool) {
+        self.idle = idle;
+        if let Some(gauge) = &self.idle_gauge {
+            gauge.set(idle as i64);
+        }
+    }
 }
 
 pub struct WatermarkGeneratorConstructor;
@@ -88,7 +105,7 @@ impl OperatorConstructor for WatermarkGeneratorConstructor {
 #[async_trait]
 impl ArrowOperator for WatermarkGenerator {
     fn tables(&self) -> HashMap<String, TableConfig> {
-        global_table_config("s", "expression watermark state")
+        global_table_config(EXPRESSION_WATERMARK.name, EXPRESSION_WATERMARK.description)
     }
 
     fn name(&self) -> String {
@@ -102,7 +119,7 @@ impl ArrowOperator for WatermarkGenerator {
     async fn on_start(&mut self, ctx: &mut ArrowContext) {
         let gs = ctx
             .table_manager
-            .get_global_keyed_state("s")
+            .get_global_keyed_state(EXPRESSION_WATERMARK.name)
             .await
             .expect("should have watermark table.");
         self.last_event = SystemTime::now();
@@ -115,6 +132,20 @@ impl ArrowOperator for WatermarkGenerator {
             }));
 
         self.state_cache = state;
+
+        self.max_event_time_gauge = gauge_for_task(
+            &ctx.task_info,
+            MAX_EVENT_TIME,
+            "Maximum event time observed by this source subtask, in milliseconds since the epoch",
+            HashMap::new(),
+        );
+
+        self.idle_gauge = gauge_for_task(
+            &ctx.task_info,
+            SOURCE_IDLE,
+            "Whether this source subtask is idle (1) or active (0)",
+            HashMap::new(),
+        );
     }
 
     async fn on_close(&mut self, final_message: &Option<SignalMessage>, ctx: &mut ArrowContext) {
@@ -140,6 +171,13 @@ impl ArrowOperator for WatermarkGenerator {
         };
         let max_timestamp = from_nanos(max_timestamp as u128);
 
+        if max_timestamp > self.max_event_time {
+            self.max_event_time = max_timestamp;
+            if let Some(gauge) = &self.max_event_time_gauge {
+                gauge.set(to_millis(max_timestamp) as i64);
+            }
+        }
+
         // calculate watermark using expression
         let watermark = self
             .expression
@@ -173,14 +211,14 @@ impl ArrowOperator for WatermarkGenerator {
                 )))
                 .await;
             self.state_cache.last_watermark_emitted_at = max_timestamp;
-            self.idle = false;
+            self.set_idle(false);
         }
     }
 
     async fn handle_checkpoint(&mut self, _: CheckpointBarrier, ctx: &mut ArrowContext) {
         let gs = ctx
             .table_manager
-            .get_global_keyed_state("s")
+            .get_global_keyed_state(EXPRESSION_WATERMARK.name)
             .await
             .expect("state");
 
@@ -198,7 +236,7 @@ impl ArrowOperator for WatermarkGenerator {
                     Watermark::Idle,
                 )))
                 .await;
-                self.idle = true;
+                self.set_idle(true);
             }
         }
     }
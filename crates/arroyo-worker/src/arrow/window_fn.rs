@@ -6,6 +6,7 @@ use std::{collections::BTreeMap, sync::RwLock, time::SystemTime};
 use anyhow::{anyhow, Result};
 use arrow::compute::{max, min};
 use arrow_array::RecordBatch;
+use arroyo_datastream::state_tables::WINDOW_FUNCTION_INPUT;
 use arroyo_df::physical::{ArroyoPhysicalExtensionCodec, DecodingContext};
 use arroyo_operator::context::ArrowContext;
 use arroyo_operator::operator::{ArrowOperator, OperatorConstructor, OperatorNode, Registry};
@@ -127,7 +128,7 @@ impl ArrowOperator for WindowFunctionOperator {
         let watermark = ctx.last_present_watermark();
         let table = ctx
             .table_manager
-            .get_expiring_time_key_table("input", watermark)
+            .get_expiring_time_key_table(WINDOW_FUNCTION_INPUT.name, watermark)
             .await
             .unwrap();
         for (timestamp, batches) in table.all_batches_for_watermark(watermark) {
@@ -139,9 +140,17 @@ impl ArrowOperator for WindowFunctionOperator {
     }
     async fn process_batch(&mut self, batch: RecordBatch, ctx: &mut ArrowContext) {
         let current_watermark = ctx.last_present_watermark();
+        let (batch, late) = self
+            .input_schema
+            .split_by_time(batch, current_watermark)
+            .unwrap();
+        ctx.collect_late(late).await;
+        if batch.num_rows() == 0 {
+            return;
+        }
         let table = ctx
             .table_manager
-            .get_expiring_time_key_table("input", current_watermark)
+            .get_expiring_time_key_table(WINDOW_FUNCTION_INPUT.name, current_watermark)
             .await
             .unwrap();
         for (batch, timestamp) in self
@@ -189,7 +198,7 @@ impl ArrowOperator for WindowFunctionOperator {
     async fn handle_checkpoint(&mut self, _cb: CheckpointBarrier, ctx: &mut ArrowContext) {
         let watermark = ctx.last_present_watermark();
         ctx.table_manager
-            .get_expiring_time_key_table("input", watermark)
+            .get_expiring_time_key_table(WINDOW_FUNCTION_INPUT.name, watermark)
             .await
             .expect("should have input table")
             .flush(watermark)
@@ -200,10 +209,10 @@ impl ArrowOperator for WindowFunctionOperator {
     fn tables(&self) -> HashMap<String, TableConfig> {
         let mut tables = HashMap::new();
         tables.insert(
-            "input".to_string(),
+            WINDOW_FUNCTION_INPUT.name.to_string(),
             timestamp_table_config(
-                "input",
-                "window function input",
+                WINDOW_FUNCTION_INPUT.name,
+                WINDOW_FUNCTION_INPUT.description,
                 Duration::ZERO,
                 false,
                 self.input_schema.as_ref().clone(),
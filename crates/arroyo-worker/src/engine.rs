@@ -13,10 +13,13 @@ use futures::StreamExt;
 use tracing::{info, warn};
 
 use crate::arrow::async_udf::AsyncUdfConstructor;
+use crate::arrow::deduplicate::DeduplicateConstructor;
 use crate::arrow::instant_join::InstantJoinConstructor;
 use crate::arrow::join_with_expiration::JoinWithExpirationConstructor;
+use crate::arrow::match_recognize::MatchRecognizeConstructor;
 use crate::arrow::session_aggregating_window::SessionAggregatingWindowConstructor;
 use crate::arrow::sliding_aggregating_window::SlidingAggregatingWindowConstructor;
+use crate::arrow::temporal_join::TemporalJoinConstructor;
 use crate::arrow::tumbling_aggregating_window::TumblingAggregateWindowConstructor;
 use crate::arrow::updating_aggregator::UpdatingAggregatingConstructor;
 use crate::arrow::watermark_generator::WatermarkGeneratorConstructor;
@@ -58,6 +61,8 @@ pub struct SubtaskNode {
     pub in_schemas: Vec<ArroyoSchema>,
     pub out_schema: Option<ArroyoSchema>,
     pub projection: Option<Vec<usize>>,
+    /// The schema and projection of the late-data side output, if this node has one
+    pub late_data: Option<(ArroyoSchema, Option<Vec<usize>>)>,
     pub node: OperatorNode,
 }
 
@@ -215,22 +220,38 @@ impl Program {
         }
 
         for idx in logical.node_indices() {
-            let in_schemas: Vec<_> = logical
-                .edges_directed(idx, Direction::Incoming)
+            // in the same order as the task's input queues, which are grouped by edge type and
+            // then by upstream operator
+            let mut in_edges: Vec<_> = logical.edges_directed(idx, Direction::Incoming).collect();
+            in_edges.sort_by_key(|edge| (edge.weight().edge_type, edge.source().index()));
+            let in_schemas: Vec<_> = in_edges
+                .iter()
                 .map(|edge| edge.weight().schema.clone())
                 .collect();
 
             let out_schema = logical
                 .edges_directed(idx, Direction::Outgoing)
+                .filter(|edge| edge.weight().edge_type != LogicalEdgeType::LateData)
                 .map(|edge| edge.weight().schema.clone())
                 .next();
 
             let projection = logical
                 .edges_directed(idx, Direction::Outgoing)
+                .filter(|edge| edge.weight().edge_type != LogicalEdgeType::LateData)
                 .map(|edge| edge.weight().projection.clone())
                 .next()
                 .unwrap_or_default();
 
+            let late_data = logical
+                .edges_directed(idx, Direction::Outgoing)
+                .find(|edge| edge.weight().edge_type == LogicalEdgeType::LateData)
+                .map(|edge| {
+                    (
+                        edge.weight().schema.clone(),
+                        edge.weight().projection.clone(),
+                    )
+                });
+
             let node = logical.node_weight(idx).unwrap();
             let parallelism = *parallelism_map.get(&node.operator_id).unwrap_or_else(|| {
                 warn!("no assignments for operator {}", node.operator_id);
@@ -249,6 +270,7 @@ impl Program {
                         registry.clone(),
                     ),
                     projection: projection.clone(),
+                    late_data: late_data.clone(),
                 }));
             }
         }
@@ -293,7 +315,8 @@ impl Program {
                 }
                 LogicalEdgeType::Shuffle
                 | LogicalEdgeType::LeftJoin
-                | LogicalEdgeType::RightJoin => {
+                | LogicalEdgeType::RightJoin
+                | LogicalEdgeType::LateData => {
                     for f in &from_nodes {
                         for (idx, t) in to_nodes.iter().enumerate() {
                             let (tx, rx) = batch_bounded(queue_size);
@@ -673,6 +696,7 @@ impl Engine {
 
         let mut in_qs_map: BTreeMap<(LogicalEdgeType, usize), Vec<BatchReceiver>> = BTreeMap::new();
         let mut out_qs_map: BTreeMap<usize, BTreeMap<usize, BatchSender>> = BTreeMap::new();
+        let mut late_qs: BTreeMap<usize, BatchSender> = BTreeMap::new();
         let task_info = {
             let mut graph = self.program.graph.write().unwrap();
             for edge in graph.edge_indices() {
@@ -697,6 +721,10 @@ impl Engine {
                 };
 
                 let tx = edge.weight().tx.as_ref().unwrap().clone();
+                if edge.weight().edge == LogicalEdgeType::LateData {
+                    late_qs.insert(edge.weight().edge_idx, tx);
+                    continue;
+                }
                 out_qs_map
                     .entry(edge.weight().out_logical_idx)
                     .or_default()
@@ -710,9 +738,10 @@ impl Engine {
         let task_index = task_info.task_index;
 
         let tables = node.node.tables();
+        let input_partitions: Vec<_> = in_qs_map.values().map(|qs| qs.len()).collect();
         let in_qs: Vec<_> = in_qs_map.into_values().flatten().collect();
 
-        let ctx = ArrowContext::new(
+        let mut ctx = ArrowContext::new(
             task_info,
             checkpoint_metadata.clone(),
             control_rx,
@@ -729,6 +758,12 @@ impl Engine {
         )
         .await;
 
+        ctx.set_input_partitions(input_partitions);
+
+        if let Some((schema, projection)) = node.late_data {
+            ctx.set_late_data_output(schema, projection, late_qs.into_values().collect());
+        }
+
         let operator = Box::new(node.node);
         let join_task = tokio::spawn(async move {
             operator.start(ctx, in_qs, ready).await;
@@ -774,7 +809,10 @@ pub fn construct_operator(
         OperatorName::ExpressionWatermark => Box::new(WatermarkGeneratorConstructor),
         OperatorName::Join => Box::new(JoinWithExpirationConstructor),
         OperatorName::InstantJoin => Box::new(InstantJoinConstructor),
+        OperatorName::TemporalJoin => Box::new(TemporalJoinConstructor),
         OperatorName::WindowFunction => Box::new(WindowFunctionConstructor),
+        OperatorName::Deduplicate => Box::new(DeduplicateConstructor),
+        OperatorName::MatchRecognize => Box::new(MatchRecognizeConstructor),
         OperatorName::ConnectorSource | OperatorName::ConnectorSink => {
             let op: api::ConnectorOp = prost::Message::decode(&mut config.as_slice()).unwrap();
             return connectors()
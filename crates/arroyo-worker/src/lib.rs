@@ -15,8 +15,9 @@ use arroyo_rpc::grpc::{
     TaskFinishedReq, TaskStartedReq, WorkerErrorReq, WorkerResources,
 };
 use arroyo_types::{
-    default_controller_addr, from_millis, grpc_port, to_micros, CheckpointBarrier, NodeId,
-    WorkerId, ARROYO_PROGRAM_ENV, ARROYO_PROGRAM_FILE_ENV, JOB_ID_ENV, RUN_ID_ENV,
+    default_controller_addr, from_millis, grpc_port, to_micros, CheckpointAlignment,
+    CheckpointBarrier, NodeId, WorkerId, ARROYO_PROGRAM_ENV, ARROYO_PROGRAM_FILE_ENV, JOB_ID_ENV,
+    RUN_ID_ENV,
 };
 use local_ip_address::local_ip;
 use rand::random;
@@ -554,6 +555,13 @@ impl WorkerGrpc for WorkerServer {
             min_epoch: req.min_epoch,
             timestamp: from_millis(req.timestamp),
             then_stop: req.then_stop,
+            alignment: if req.unaligned {
+                CheckpointAlignment::Unaligned
+            } else if let Some(timeout) = req.alignment_timeout_micros {
+                CheckpointAlignment::AlignedWithTimeout(Duration::from_micros(timeout))
+            } else {
+                CheckpointAlignment::Aligned
+            },
         };
 
         for n in &senders {
@@ -498,7 +498,9 @@ mod test {
 
     use arroyo_operator::context::batch_bounded;
     use arroyo_server_common::shutdown::Shutdown;
-    use arroyo_types::{to_nanos, ArrowMessage, CheckpointBarrier, SignalMessage};
+    use arroyo_types::{
+        to_nanos, ArrowMessage, CheckpointAlignment, CheckpointBarrier, SignalMessage,
+    };
     use tokio::time::timeout;
 
     use crate::network_manager::{MessageType, Quad};
@@ -593,6 +595,7 @@ mod test {
             min_epoch: 3,
             timestamp: SystemTime::now(),
             then_stop: false,
+            alignment: CheckpointAlignment::Aligned,
         }));
 
         client_tx.send(message.clone()).await.unwrap();
@@ -0,0 +1,987 @@
+use crate::manifest::{
+    strip_nulls, ConnectionProfileSpec, ConnectionTableSpec, Manifest, PipelineSpec, UdfSpec,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use arroyo_openapi::types::{
+    ConnectionProfile, ConnectionProfilePatch, ConnectionProfilePost, ConnectionSchema,
+    ConnectionTable, ConnectionTablePatch, ConnectionTablePost, GlobalUdf, Pipeline, PipelinePatch,
+    PipelinePost, StopType, Udf, UdfPost, ValidateUdfPost,
+};
+use arroyo_openapi::{Client, Error, ResponseValue};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Debug, Display, Formatter};
+use std::time::{Duration, Instant};
+
+/// The value the API returns in place of sensitive config fields (see `arroyo_rpc::secrets`)
+const REDACTED: &str = "********";
+const ENCRYPTED_PREFIX: &str = "{{ encrypted:";
+
+const PAGE_SIZE: u32 = 1000;
+const STOP_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(clap::Args)]
+pub struct ApiArgs {
+    /// Address of the Arroyo API
+    #[arg(long, env = "ARROYO_ENDPOINT", default_value = "http://localhost:8000")]
+    endpoint: String,
+
+    /// API token used to authenticate requests
+    #[arg(long, env = "ARROYO_API_TOKEN")]
+    token: Option<String>,
+}
+
+impl ApiArgs {
+    pub fn client(&self) -> Result<Client> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(token) = &self.token {
+            headers.insert(
+                reqwest::header::AUTHORIZATION,
+                format!("Bearer {}", token)
+                    .parse()
+                    .context("Invalid API token")?,
+            );
+        }
+
+        let client = reqwest::ClientBuilder::new()
+            .timeout(Duration::from_secs(60))
+            .default_headers(headers)
+            .build()?;
+
+        Ok(Client::new_with_client(
+            &format!("{}/api", self.endpoint.trim_end_matches('/')),
+            client,
+        ))
+    }
+}
+
+/// Unwraps an API response, including the body of error responses in the returned error
+async fn check<T, E: Debug>(
+    context: impl Display,
+    result: Result<ResponseValue<T>, Error<E>>,
+) -> Result<T> {
+    match result {
+        Ok(value) => Ok(value.into_inner()),
+        Err(Error::UnexpectedResponse(response)) => {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            Err(anyhow!("Failed {}: {} {}", context, status, body))
+        }
+        Err(e) => Err(anyhow!("Failed {}: {}", context, e)),
+    }
+}
+
+/// The resources that currently exist in the cluster
+pub struct ClusterState {
+    profiles: Vec<ConnectionProfile>,
+    tables: Vec<ConnectionTable>,
+    udfs: Vec<GlobalUdf>,
+    pipelines: Vec<Pipeline>,
+}
+
+impl ClusterState {
+    pub async fn fetch(client: &Client) -> Result<Self> {
+        let profiles = check(
+            "listing connection profiles",
+            client.get_connection_profiles().send().await,
+        )
+        .await?
+        .data;
+
+        let mut tables: Vec<ConnectionTable> = vec![];
+        loop {
+            let mut req = client.get_connection_tables().limit(PAGE_SIZE);
+            if let Some(last) = tables.last() {
+                req = req.starting_after(last.id.clone());
+            }
+            let page = check("listing connection tables", req.send().await).await?;
+            tables.extend(page.data);
+            if !page.has_more {
+                break;
+            }
+        }
+
+        let udfs = check("listing UDFs", client.get_udfs().send().await)
+            .await?
+            .data;
+
+        let mut pipelines: Vec<Pipeline> = vec![];
+        loop {
+            let mut req = client.get_pipelines().limit(PAGE_SIZE);
+            if let Some(last) = pipelines.last() {
+                req = req.starting_after(last.id.clone());
+            }
+            let page = check("listing pipelines", req.send().await).await?;
+            pipelines.extend(page.data);
+            if !page.has_more {
+                break;
+            }
+        }
+        pipelines.retain(|p| !p.preview);
+
+        Ok(Self {
+            profiles,
+            tables,
+            udfs,
+            pipelines,
+        })
+    }
+
+    fn profile(&self, name: &str) -> Result<Option<&ConnectionProfile>> {
+        unique_by_name("connection profile", name, self.profiles.iter(), |p| {
+            &p.name
+        })
+    }
+
+    fn table(&self, name: &str) -> Result<Option<&ConnectionTable>> {
+        unique_by_name("connection table", name, self.tables.iter(), |t| &t.name)
+    }
+
+    fn pipeline(&self, name: &str) -> Result<Option<&Pipeline>> {
+        unique_by_name("pipeline", name, self.pipelines.iter(), |p| &p.name)
+    }
+
+    /// Builds a manifest describing the current state of the cluster
+    pub fn export(&self) -> Manifest {
+        let connection_profiles = self
+            .profiles
+            .iter()
+            .map(|p| ConnectionProfileSpec {
+                name: p.name.clone(),
+                connector: p.connector.clone(),
+                config: p.config.clone(),
+            })
+            .collect();
+
+        let connection_tables = self
+            .tables
+            .iter()
+            .map(|t| {
+                let mut schema = serde_json::to_value(&t.schema).unwrap();
+                strip_nulls(&mut schema);
+                if let Value::Object(schema) = &mut schema {
+                    // fields are derived from the schema definition when one is provided
+                    if schema.contains_key("definition") {
+                        schema.remove("fields");
+                        schema.remove("inferred");
+                    }
+                }
+
+                ConnectionTableSpec {
+                    name: t.name.clone(),
+                    connector: t.connector.clone(),
+                    connection_profile: t.connection_profile.as_ref().map(|p| p.name.clone()),
+                    config: t.config.clone(),
+                    schema: Some(schema),
+                }
+            })
+            .collect();
+
+        let udfs = self
+            .udfs
+            .iter()
+            .map(|u| UdfSpec {
+                prefix: u.prefix.clone(),
+                definition: u.definition.clone(),
+                description: u.description.clone().filter(|d| !d.is_empty()),
+            })
+            .collect();
+
+        let pipelines = self
+            .pipelines
+            .iter()
+            .map(|p| PipelineSpec {
+                name: p.name.clone(),
+                query: p.query.clone(),
+                parallelism: parallelism(p),
+                checkpoint_interval_micros: Some(p.checkpoint_interval_micros),
+                updating_ttl_micros: p.updating_ttl_micros,
+                udfs: p.udfs.iter().map(|u| u.definition.clone()).collect(),
+            })
+            .collect();
+
+        Manifest {
+            connection_profiles,
+            connection_tables,
+            udfs,
+            pipelines,
+        }
+    }
+}
+
+fn unique_by_name<'a, T>(
+    kind: &str,
+    name: &str,
+    items: impl Iterator<Item = &'a T>,
+    get_name: impl Fn(&T) -> &String,
+) -> Result<Option<&'a T>> {
+    let mut matches = items.filter(|i| get_name(i) == name);
+    let first = matches.next();
+    if matches.next().is_some() {
+        bail!(
+            "There are multiple {}s named '{}'; rename or delete the duplicates before applying",
+            kind,
+            name
+        );
+    }
+    Ok(first)
+}
+
+/// The parallelism of a pipeline, which is not reported directly but is the largest
+/// parallelism of any of its operators
+fn parallelism(pipeline: &Pipeline) -> u64 {
+    pipeline
+        .graph
+        .nodes
+        .iter()
+        .map(|n| n.parallelism as u64)
+        .max()
+        .unwrap_or(1)
+}
+
+/// Returns whether every value set in `expected` is present in `actual`. Values that the API
+/// redacts or encrypts can't be compared, so they only match if the manifest leaves them
+/// redacted; a plaintext secret in the manifest is always treated as a change so that rotated
+/// credentials are applied. Secret references like `{{ file:name }}` are compared as-is.
+fn json_matches(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (expected, Value::String(s)) if s == REDACTED || s.starts_with(ENCRYPTED_PREFIX) => {
+            expected.as_str() == Some(REDACTED) || expected == actual
+        }
+        (Value::Object(expected), Value::Object(actual)) => expected.iter().all(|(k, v)| {
+            v.is_null() || actual.get(k).is_some_and(|actual| json_matches(v, actual))
+        }),
+        (Value::Array(expected), Value::Array(actual)) => {
+            expected.len() == actual.len()
+                && expected.iter().zip(actual).all(|(e, a)| json_matches(e, a))
+        }
+        (Value::Number(expected), Value::Number(actual)) => expected.as_f64() == actual.as_f64(),
+        (expected, actual) => expected == actual,
+    }
+}
+
+fn contains_redacted(value: &Value) -> bool {
+    match value {
+        Value::String(s) => s == REDACTED,
+        Value::Object(map) => map.values().any(contains_redacted),
+        Value::Array(values) => values.iter().any(contains_redacted),
+        _ => false,
+    }
+}
+
+/// Returns whether any config in the manifest contains redacted values, which will need to be
+/// filled in before it can be applied
+pub fn has_redacted_values(manifest: &Manifest) -> bool {
+    manifest
+        .connection_profiles
+        .iter()
+        .any(|p| contains_redacted(&p.config))
+        || manifest
+            .connection_tables
+            .iter()
+            .any(|t| contains_redacted(&t.config))
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Resource {
+    ConnectionProfile,
+    ConnectionTable,
+    Udf,
+    Pipeline,
+}
+
+impl Display for Resource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Resource::ConnectionProfile => "connection profile",
+            Resource::ConnectionTable => "connection table",
+            Resource::Udf => "udf",
+            Resource::Pipeline => "pipeline",
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Create,
+    /// The listed fields have changed and can be updated in place
+    Update(Vec<&'static str>),
+    /// The listed fields have changed and can't be updated in place, so the resource must be
+    /// deleted and recreated
+    Replace(Vec<&'static str>),
+    Delete,
+}
+
+#[derive(Clone, Debug)]
+pub struct Change {
+    pub resource: Resource,
+    pub name: String,
+    /// The id of the existing resource, for updates, replacements and deletions
+    id: Option<String>,
+    pub action: Action,
+}
+
+impl Change {
+    fn removes(&self) -> bool {
+        matches!(self.action, Action::Replace(_) | Action::Delete)
+    }
+
+    fn creates(&self) -> bool {
+        matches!(self.action, Action::Create | Action::Replace(_))
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.action {
+            Action::Create => write!(f, "+   {} {}", self.resource, self.name),
+            Action::Update(fields) => write!(
+                f,
+                "~   {} {} ({})",
+                self.resource,
+                self.name,
+                fields.join(", ")
+            ),
+            Action::Replace(fields) => write!(
+                f,
+                "-/+ {} {} ({})",
+                self.resource,
+                self.name,
+                fields.join(", ")
+            ),
+            Action::Delete => write!(f, "-   {} {}", self.resource, self.name),
+        }
+    }
+}
+
+/// The set of changes needed to bring the cluster in line with a manifest
+pub struct Plan {
+    pub changes: Vec<Change>,
+    /// UDF specs from the manifest, keyed by prefix and function name
+    udfs: HashMap<(String, String), UdfSpec>,
+}
+
+impl Plan {
+    /// Computes the changes required to reconcile `state` with `manifest`; if `prune` is set,
+    /// resources that are not in the manifest are deleted
+    pub async fn new(
+        client: &Client,
+        manifest: &Manifest,
+        state: &ClusterState,
+        prune: bool,
+    ) -> Result<Self> {
+        let mut changes = vec![];
+        let mut replaced_profiles = BTreeSet::new();
+
+        for spec in &manifest.connection_profiles {
+            let action = match state.profile(&spec.name)? {
+                None => Action::Create,
+                Some(existing) => {
+                    let mut update = vec![];
+                    if !json_matches(&spec.config, &existing.config) {
+                        update.push("config");
+                    }
+
+                    if spec.connector != existing.connector {
+                        replaced_profiles.insert(spec.name.as_str());
+                        let mut replace = vec!["connector"];
+                        replace.extend(update);
+                        Action::Replace(replace)
+                    } else {
+                        Action::Update(update)
+                    }
+                }
+            };
+
+            changes.push(Change {
+                resource: Resource::ConnectionProfile,
+                name: spec.name.clone(),
+                id: state.profile(&spec.name)?.map(|p| p.id.clone()),
+                action,
+            });
+        }
+
+        for spec in &manifest.connection_tables {
+            if let Some(profile) = &spec.connection_profile {
+                if !manifest
+                    .connection_profiles
+                    .iter()
+                    .any(|p| &p.name == profile)
+                    && state.profile(profile)?.is_none()
+                {
+                    bail!(
+                        "Connection table '{}' references connection profile '{}', which does not exist",
+                        spec.name,
+                        profile
+                    );
+                }
+            }
+
+            let existing = state.table(&spec.name)?;
+            let action = match existing {
+                None => Action::Create,
+                Some(existing) => {
+                    let existing_profile = existing.connection_profile.as_ref().map(|p| &p.name);
+
+                    let mut replace = vec![];
+                    if spec.connector != existing.connector {
+                        replace.push("connector");
+                    }
+                    if spec.connection_profile.as_ref() != existing_profile
+                        || spec
+                            .connection_profile
+                            .as_ref()
+                            .is_some_and(|p| replaced_profiles.contains(p.as_str()))
+                    {
+                        replace.push("connection_profile");
+                    }
+
+                    let mut update = vec![];
+                    if !json_matches(&spec.config, &existing.config) {
+                        update.push("config");
+                    }
+                    if let Some(schema) = &spec.schema {
+                        if !json_matches(schema, &serde_json::to_value(&existing.schema)?) {
+                            update.push("schema");
+                        }
+                    }
+
+                    if replace.is_empty() {
+                        Action::Update(update)
+                    } else {
+                        replace.extend(update);
+                        Action::Replace(replace)
+                    }
+                }
+            };
+
+            changes.push(Change {
+                resource: Resource::ConnectionTable,
+                name: spec.name.clone(),
+                id: existing.map(|t| t.id.clone()),
+                action,
+            });
+        }
+
+        let mut udfs = HashMap::new();
+        for spec in &manifest.udfs {
+            let unchanged = state
+                .udfs
+                .iter()
+                .find(|u| u.prefix == spec.prefix && u.definition.trim() == spec.definition.trim());
+
+            // UDFs that are already in the cluster don't need to be compiled to find their names
+            let name = match unchanged {
+                Some(udf) => udf.name.clone(),
+                None => {
+                    let validation = check(
+                        "validating UDF",
+                        client
+                            .validate_udf()
+                            .body(ValidateUdfPost::builder().definition(spec.definition.clone()))
+                            .send()
+                            .await,
+                    )
+                    .await?;
+
+                    let Some(name) = validation.udf_name.filter(|_| validation.errors.is_empty())
+                    else {
+                        bail!("Invalid UDF in manifest:\n{}", validation.errors.join("\n"));
+                    };
+                    name
+                }
+            };
+
+            let key = (spec.prefix.clone(), name);
+            if udfs.insert(key.clone(), spec.clone()).is_some() {
+                bail!("UDF '{}{}' is defined more than once", key.0, key.1);
+            }
+
+            let existing = state
+                .udfs
+                .iter()
+                .find(|u| u.prefix == key.0 && u.name == key.1);
+
+            let action = match existing {
+                None => Action::Create,
+                Some(existing) => {
+                    let mut fields = vec![];
+                    if spec.definition.trim() != existing.definition.trim() {
+                        fields.push("definition");
+                    }
+                    if spec.description.clone().unwrap_or_default()
+                        != existing.description.clone().unwrap_or_default()
+                    {
+                        fields.push("description");
+                    }
+                    Action::Replace(fields)
+                }
+            };
+
+            changes.push(Change {
+                resource: Resource::Udf,
+                name: format!("{}{}", key.0, key.1),
+                id: existing.map(|u| u.id.clone()),
+                action,
+            });
+        }
+
+        for spec in &manifest.pipelines {
+            let existing = state.pipeline(&spec.name)?;
+            let action = match existing {
+                None => Action::Create,
+                Some(existing) => {
+                    let mut replace = vec![];
+                    if spec.query.trim() != existing.query.trim() {
+                        replace.push("query");
+                    }
+                    let spec_udfs: BTreeSet<_> = spec.udfs.iter().map(|u| u.trim()).collect();
+                    let existing_udfs: BTreeSet<_> =
+                        existing.udfs.iter().map(|u| u.definition.trim()).collect();
+                    if spec_udfs != existing_udfs {
+                        replace.push("udfs");
+                    }
+                    if spec.updating_ttl_micros != existing.updating_ttl_micros {
+                        replace.push("updating_ttl_micros");
+                    }
+
+                    let mut update = vec![];
+                    if spec.parallelism != parallelism(existing) {
+                        update.push("parallelism");
+                    }
+                    if spec
+                        .checkpoint_interval_micros
+                        .is_some_and(|i| i != existing.checkpoint_interval_micros)
+                    {
+                        update.push("checkpoint_interval_micros");
+                    }
+
+                    if replace.is_empty() {
+                        Action::Update(update)
+                    } else {
+                        replace.extend(update);
+                        Action::Replace(replace)
+                    }
+                }
+            };
+
+            changes.push(Change {
+                resource: Resource::Pipeline,
+                name: spec.name.clone(),
+                id: existing.map(|p| p.id.clone()),
+                action,
+            });
+        }
+
+        // drop updates and replacements that don't change anything
+        changes.retain(|c| match &c.action {
+            Action::Update(fields) | Action::Replace(fields) => !fields.is_empty(),
+            _ => true,
+        });
+
+        if prune {
+            let delete = |resource, name: &str, id: &str| Change {
+                resource,
+                name: name.to_string(),
+                id: Some(id.to_string()),
+                action: Action::Delete,
+            };
+
+            for p in &state.profiles {
+                if !manifest
+                    .connection_profiles
+                    .iter()
+                    .any(|s| s.name == p.name)
+                {
+                    changes.push(delete(Resource::ConnectionProfile, &p.name, &p.id));
+                }
+            }
+            for t in &state.tables {
+                if !manifest.connection_tables.iter().any(|s| s.name == t.name) {
+                    changes.push(delete(Resource::ConnectionTable, &t.name, &t.id));
+                }
+            }
+            for u in &state.udfs {
+                if !udfs.contains_key(&(u.prefix.clone(), u.name.clone())) {
+                    changes.push(delete(
+                        Resource::Udf,
+                        &format!("{}{}", u.prefix, u.name),
+                        &u.id,
+                    ));
+                }
+            }
+            for p in &state.pipelines {
+                if !manifest.pipelines.iter().any(|s| s.name == p.name) {
+                    changes.push(delete(Resource::Pipeline, &p.name, &p.id));
+                }
+            }
+        }
+
+        Ok(Self { changes, udfs })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn requires_replacement(&self) -> bool {
+        self.changes
+            .iter()
+            .any(|c| matches!(c.action, Action::Replace(_)))
+    }
+
+    /// Applies the plan. Resources are removed in reverse dependency order (pipelines first)
+    /// and then created and updated in dependency order (connection profiles first). If
+    /// `restart_pipelines` is set, running pipelines that use updated connection profiles and
+    /// tables are restarted from a checkpoint to pick up the changes.
+    pub async fn apply(
+        &self,
+        client: &Client,
+        manifest: &Manifest,
+        restart_pipelines: bool,
+    ) -> Result<()> {
+        let mut removals: Vec<_> = self.changes.iter().filter(|c| c.removes()).collect();
+        removals.sort_by_key(|c| std::cmp::Reverse(c.resource));
+
+        for change in removals {
+            let id = change.id.as_deref().expect("removed resources must exist");
+            let context = format!("deleting {} {}", change.resource, change.name);
+            match change.resource {
+                Resource::ConnectionProfile => {
+                    check(
+                        context,
+                        client.delete_connection_profile().id(id).send().await,
+                    )
+                    .await?;
+                }
+                Resource::ConnectionTable => {
+                    check(
+                        context,
+                        client.delete_connection_table().id(id).send().await,
+                    )
+                    .await?;
+                }
+                Resource::Udf => {
+                    check(context, client.delete_udf().id(id).send().await).await?;
+                }
+                Resource::Pipeline => {
+                    stop_pipeline(client, &change.name, id).await?;
+                    check(context, client.delete_pipeline().id(id).send().await).await?;
+                }
+            }
+            println!("Deleted {} {}", change.resource, change.name);
+        }
+
+        let mut profile_ids: HashMap<String, String> = check(
+            "listing connection profiles",
+            client.get_connection_profiles().send().await,
+        )
+        .await?
+        .data
+        .into_iter()
+        .map(|p| (p.name, p.id))
+        .collect();
+
+        let mut creations: Vec<_> = self
+            .changes
+            .iter()
+            .filter(|c| c.creates() || matches!(c.action, Action::Update(_)))
+            .collect();
+        creations.sort_by_key(|c| c.resource);
+
+        for change in creations {
+            if let Action::Update(_) = change.action {
+                self.update(client, manifest, change, restart_pipelines)
+                    .await?;
+                continue;
+            }
+
+            let context = format!("creating {} {}", change.resource, change.name);
+            match change.resource {
+                Resource::ConnectionProfile => {
+                    let spec = find(&manifest.connection_profiles, |p| p.name == change.name);
+                    let created = check(
+                        context,
+                        client
+                            .create_connection_profile()
+                            .body(
+                                ConnectionProfilePost::builder()
+                                    .name(spec.name.clone())
+                                    .connector(spec.connector.clone())
+                                    .config(spec.config.clone()),
+                            )
+                            .send()
+                            .await,
+                    )
+                    .await?;
+                    profile_ids.insert(created.name, created.id);
+                }
+                Resource::ConnectionTable => {
+                    let spec = find(&manifest.connection_tables, |t| t.name == change.name);
+                    let profile_id =
+                        spec.connection_profile
+                            .as_ref()
+                            .map(|p| {
+                                profile_ids.get(p).cloned().ok_or_else(|| {
+                                    anyhow!("Connection profile '{}' does not exist", p)
+                                })
+                            })
+                            .transpose()?;
+                    let schema: Option<ConnectionSchema> = spec
+                        .schema
+                        .clone()
+                        .map(serde_json::from_value)
+                        .transpose()
+                        .with_context(|| {
+                            format!("Invalid schema for connection table '{}'", spec.name)
+                        })?;
+
+                    check(
+                        context,
+                        client
+                            .create_connection_table()
+                            .body(
+                                ConnectionTablePost::builder()
+                                    .name(spec.name.clone())
+                                    .connector(spec.connector.clone())
+                                    .connection_profile_id(profile_id)
+                                    .config(spec.config.clone())
+                                    .schema(schema),
+                            )
+                            .send()
+                            .await,
+                    )
+                    .await?;
+                }
+                Resource::Udf => {
+                    let spec = self
+                        .udfs
+                        .iter()
+                        .find(|((prefix, name), _)| format!("{}{}", prefix, name) == change.name)
+                        .map(|(_, spec)| spec)
+                        .expect("planned UDF must be in the manifest");
+                    check(
+                        context,
+                        client
+                            .create_udf()
+                            .body(
+                                UdfPost::builder()
+                                    .prefix(spec.prefix.clone())
+                                    .definition(spec.definition.clone())
+                                    .description(spec.description.clone()),
+                            )
+                            .send()
+                            .await,
+                    )
+                    .await?;
+                }
+                Resource::Pipeline => {
+                    let spec = find(&manifest.pipelines, |p| p.name == change.name);
+                    check(
+                        context,
+                        client
+                            .create_pipeline()
+                            .body(
+                                PipelinePost::builder()
+                                    .name(spec.name.clone())
+                                    .query(spec.query.clone())
+                                    .parallelism(spec.parallelism)
+                                    .checkpoint_interval_micros(spec.checkpoint_interval_micros)
+                                    .updating_ttl_micros(spec.updating_ttl_micros)
+                                    .udfs(Some(
+                                        spec.udfs
+                                            .iter()
+                                            .map(|definition| Udf {
+                                                definition: definition.clone(),
+                                            })
+                                            .collect::<Vec<_>>(),
+                                    )),
+                            )
+                            .send()
+                            .await,
+                    )
+                    .await?;
+                }
+            }
+            println!("Created {} {}", change.resource, change.name);
+        }
+
+        Ok(())
+    }
+
+    async fn update(
+        &self,
+        client: &Client,
+        manifest: &Manifest,
+        change: &Change,
+        restart_pipelines: bool,
+    ) -> Result<()> {
+        let id = change.id.as_deref().expect("updated resources must exist");
+        let context = format!("updating {} {}", change.resource, change.name);
+
+        let pipelines = match change.resource {
+            Resource::ConnectionProfile => {
+                let spec = find(&manifest.connection_profiles, |p| p.name == change.name);
+                check(
+                    context,
+                    client
+                        .patch_connection_profile()
+                        .id(id)
+                        .body(
+                            ConnectionProfilePatch::builder()
+                                .config(spec.config.clone())
+                                .restart_pipelines(Some(restart_pipelines)),
+                        )
+                        .send()
+                        .await,
+                )
+                .await?
+                .pipelines
+            }
+            Resource::ConnectionTable => {
+                let spec = find(&manifest.connection_tables, |t| t.name == change.name);
+                let schema: Option<ConnectionSchema> = spec
+                    .schema
+                    .clone()
+                    .map(serde_json::from_value)
+                    .transpose()
+                    .with_context(|| {
+                        format!("Invalid schema for connection table '{}'", spec.name)
+                    })?;
+
+                check(
+                    context,
+                    client
+                        .patch_connection_table()
+                        .id(id)
+                        .body(
+                            ConnectionTablePatch::builder()
+                                .config(Some(spec.config.clone()))
+                                .schema(schema)
+                                .restart_pipelines(Some(restart_pipelines)),
+                        )
+                        .send()
+                        .await,
+                )
+                .await?
+                .pipelines
+            }
+            Resource::Udf => unreachable!("UDFs can't be updated in place"),
+            Resource::Pipeline => {
+                let spec = find(&manifest.pipelines, |p| p.name == change.name);
+                check(
+                    context,
+                    client
+                        .patch_pipeline()
+                        .id(id)
+                        .body(
+                            PipelinePatch::builder()
+                                .parallelism(Some(spec.parallelism))
+                                .checkpoint_interval_micros(spec.checkpoint_interval_micros),
+                        )
+                        .send()
+                        .await,
+                )
+                .await?;
+                vec![]
+            }
+        };
+
+        println!("Updated {} {}", change.resource, change.name);
+        for pipeline in pipelines.iter().filter(|p| p.updated) {
+            if pipeline.restarted {
+                println!("  restarted pipeline {} from a checkpoint", pipeline.name);
+            } else {
+                println!(
+                    "  pipeline {} will use the new config when it is next restarted",
+                    pipeline.name
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn find<T>(specs: &[T], f: impl Fn(&T) -> bool) -> &T {
+    specs
+        .iter()
+        .find(|s| f(s))
+        .expect("planned resource must be in the manifest")
+}
+
+/// Stops all of a pipeline's jobs, which is required before it can be deleted
+async fn stop_pipeline(client: &Client, name: &str, id: &str) -> Result<()> {
+    check(
+        format!("stopping pipeline {}", name),
+        client
+            .patch_pipeline()
+            .id(id)
+            .body(PipelinePatch::builder().stop(Some(StopType::Immediate)))
+            .send()
+            .await,
+    )
+    .await?;
+
+    let start = Instant::now();
+    loop {
+        let jobs = check(
+            format!("fetching jobs for pipeline {}", name),
+            client.get_pipeline_jobs().id(id).send().await,
+        )
+        .await?;
+
+        if jobs
+            .data
+            .iter()
+            .all(|j| matches!(j.state.as_str(), "Stopped" | "Finished" | "Failed"))
+        {
+            return Ok(());
+        }
+
+        if start.elapsed() > STOP_TIMEOUT {
+            bail!("Timed out waiting for pipeline {} to stop", name);
+        }
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_matches() {
+        let actual = json!({
+            "bootstrapServers": "localhost:9092",
+            "authentication": {"type": "sasl", "password": REDACTED},
+            "token": "{{ encrypted:abcd }}",
+            "extra": 5,
+        });
+
+        assert!(json_matches(
+            &json!({
+                "bootstrapServers": "localhost:9092",
+                "authentication": {"type": "sasl", "password": REDACTED},
+                "token": REDACTED,
+                "missing": null,
+            }),
+            &actual
+        ));
+
+        // plaintext secrets can't be compared with the stored values, so they are always updated
+        assert!(!json_matches(
+            &json!({"authentication": {"type": "sasl", "password": "hunter2"}}),
+            &actual
+        ));
+        assert!(!json_matches(&json!({"token": "secret"}), &actual));
+
+        assert!(!json_matches(
+            &json!({"bootstrapServers": "localhost:9093"}),
+            &actual
+        ));
+        assert!(!json_matches(&json!({"other": 1}), &actual));
+    }
+}
@@ -1,3 +1,8 @@
+mod apply;
+mod manifest;
+
+use crate::apply::{has_redacted_values, ApiArgs, ClusterState, Plan};
+use crate::manifest::{Manifest, ManifestFormat};
 use anyhow::{bail, Context, Result};
 use bollard::container::{CreateContainerOptions, LogOutput, LogsOptions, StartContainerOptions};
 use bollard::image::CreateImageOptions;
@@ -7,6 +12,7 @@ use clap::{Parser, Subcommand};
 use std::collections::HashMap;
 use std::io;
 use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::signal::unix::{signal, SignalKind};
@@ -36,6 +42,59 @@ enum Commands {
 
     /// Stops a running Arroyo cluster
     Stop {},
+
+    /// Creates, updates and (with --prune) deletes resources so that the cluster matches a
+    /// YAML or TOML manifest
+    Apply {
+        /// Path to the manifest
+        file: PathBuf,
+
+        #[command(flatten)]
+        api: ApiArgs,
+
+        /// Delete and recreate resources whose changes can't be applied in place; replacing a
+        /// pipeline discards its state
+        #[arg(long)]
+        replace: bool,
+
+        /// Delete resources that are not in the manifest
+        #[arg(long)]
+        prune: bool,
+
+        /// Restart running pipelines that use updated connection profiles or tables from a
+        /// checkpoint, so that they pick up the changes
+        #[arg(long)]
+        restart_pipelines: bool,
+    },
+
+    /// Shows the changes that `apply` would make for a manifest. This reads the cluster's
+    /// resources through the API, and UDFs that aren't already in the cluster are compiled by
+    /// the API to determine their names, which requires the Editor role.
+    Diff {
+        /// Path to the manifest
+        file: PathBuf,
+
+        #[command(flatten)]
+        api: ApiArgs,
+
+        /// Include deletions of resources that are not in the manifest
+        #[arg(long)]
+        prune: bool,
+    },
+
+    /// Writes a manifest describing the resources in the cluster
+    Export {
+        #[command(flatten)]
+        api: ApiArgs,
+
+        /// File to write the manifest to; if not set, it is written to stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Manifest format, by default determined from the output file extension
+        #[arg(short, long, value_enum)]
+        format: Option<ManifestFormat>,
+    },
 }
 
 #[tokio::main]
@@ -45,6 +104,19 @@ pub async fn main() {
     let result = match &cli.command {
         Commands::Start { tag, daemon } => start(tag.clone(), *daemon).await,
         Commands::Stop {} => stop().await,
+        Commands::Apply {
+            file,
+            api,
+            replace,
+            prune,
+            restart_pipelines,
+        } => apply(file, api, *replace, *prune, *restart_pipelines).await,
+        Commands::Diff { file, api, prune } => diff(file, api, *prune).await,
+        Commands::Export {
+            api,
+            output,
+            format,
+        } => export(api, output.as_deref(), *format).await,
     };
 
     if let Err(e) = result {
@@ -313,3 +385,84 @@ async fn stop() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+async fn plan(file: &Path, api: &ApiArgs, prune: bool) -> Result<(Manifest, Plan)> {
+    let manifest = Manifest::load(file)?;
+    let client = api.client()?;
+    let state = ClusterState::fetch(&client).await?;
+    let plan = Plan::new(&client, &manifest, &state, prune).await?;
+    Ok((manifest, plan))
+}
+
+async fn diff(file: &Path, api: &ApiArgs, prune: bool) -> Result<()> {
+    let (_, plan) = plan(file, api, prune).await?;
+
+    if plan.is_empty() {
+        println!("No changes");
+        return Ok(());
+    }
+
+    for change in &plan.changes {
+        println!("{}", change);
+    }
+
+    Ok(())
+}
+
+async fn apply(
+    file: &Path,
+    api: &ApiArgs,
+    replace: bool,
+    prune: bool,
+    restart_pipelines: bool,
+) -> Result<()> {
+    let (manifest, plan) = plan(file, api, prune).await?;
+
+    if has_redacted_values(&manifest) {
+        bail!("The manifest contains redacted values (\"********\"), which must be replaced with the real values or secret references before it can be applied");
+    }
+
+    if plan.is_empty() {
+        println!("No changes");
+        return Ok(());
+    }
+
+    if plan.requires_replacement() && !replace {
+        for change in &plan.changes {
+            println!("{}", change);
+        }
+        bail!("Some resources have changes that can't be applied in place; rerun with --replace to delete and recreate them");
+    }
+
+    plan.apply(&api.client()?, &manifest, restart_pipelines)
+        .await?;
+
+    println!("Applied {} changes", plan.changes.len());
+    Ok(())
+}
+
+async fn export(
+    api: &ApiArgs,
+    output: Option<&Path>,
+    format: Option<ManifestFormat>,
+) -> Result<()> {
+    let client = api.client()?;
+    let manifest = ClusterState::fetch(&client).await?.export();
+
+    let format = format
+        .or_else(|| output.map(ManifestFormat::for_path))
+        .unwrap_or(ManifestFormat::Yaml);
+    let contents = manifest.serialize(format)?;
+
+    match output {
+        Some(path) => std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write manifest to {}", path.display()))?,
+        None => print!("{}", contents),
+    }
+
+    if has_redacted_values(&manifest) {
+        eprintln!("Warning: sensitive config values have been redacted and must be filled in before the manifest can be applied");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,247 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// A declarative description of the connection profiles, connection tables, UDFs and pipelines
+/// that should exist in an Arroyo cluster. Resources are identified by name (or, for UDFs, by
+/// prefix and function name) when reconciling against the API.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub connection_profiles: Vec<ConnectionProfileSpec>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub connection_tables: Vec<ConnectionTableSpec>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub udfs: Vec<UdfSpec>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pipelines: Vec<PipelineSpec>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectionProfileSpec {
+    pub name: String,
+    pub connector: String,
+    pub config: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectionTableSpec {
+    pub name: String,
+    pub connector: String,
+    /// The name of the connection profile used by this table
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_profile: Option<String>,
+    pub config: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct UdfSpec {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub prefix: String,
+    pub definition: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineSpec {
+    pub name: String,
+    pub query: String,
+    #[serde(default = "default_parallelism")]
+    pub parallelism: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checkpoint_interval_micros: Option<u64>,
+    /// How long updating aggregates and non-windowed joins keep state; changing it replaces
+    /// the pipeline
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updating_ttl_micros: Option<u64>,
+    /// Definitions of UDFs that are local to this pipeline
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub udfs: Vec<String>,
+}
+
+fn default_parallelism() -> u64 {
+    1
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum ManifestFormat {
+    Yaml,
+    Toml,
+}
+
+impl ManifestFormat {
+    /// Determines the format from a file extension, defaulting to YAML (which also accepts JSON)
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => ManifestFormat::Toml,
+            _ => ManifestFormat::Yaml,
+        }
+    }
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read manifest {}", path.display()))?;
+
+        let manifest = Self::parse(&contents, ManifestFormat::for_path(path))
+            .with_context(|| format!("Invalid manifest {}", path.display()))?;
+
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    pub fn parse(contents: &str, format: ManifestFormat) -> Result<Self> {
+        Ok(match format {
+            ManifestFormat::Yaml => serde_yaml::from_str(contents)?,
+            ManifestFormat::Toml => toml::from_str(contents)?,
+        })
+    }
+
+    pub fn serialize(&self, format: ManifestFormat) -> Result<String> {
+        Ok(match format {
+            ManifestFormat::Yaml => serde_yaml::to_string(self)?,
+            ManifestFormat::Toml => {
+                // TOML has no representation for nulls, so they are removed from the free-form
+                // config and schema values
+                let mut value = serde_json::to_value(self)?;
+                strip_nulls(&mut value);
+                toml::to_string_pretty(&value)?
+            }
+        })
+    }
+
+    /// Checks that resource names are unique and non-empty; references between resources are
+    /// checked against the cluster when planning
+    pub fn validate(&self) -> Result<()> {
+        fn unique<'a>(kind: &str, names: impl Iterator<Item = &'a str>) -> Result<()> {
+            let mut seen = HashSet::new();
+            for name in names {
+                if name.trim().is_empty() {
+                    bail!("{} names must not be empty", kind);
+                }
+                if !seen.insert(name) {
+                    bail!("{} '{}' is defined more than once", kind, name);
+                }
+            }
+            Ok(())
+        }
+
+        unique(
+            "Connection profile",
+            self.connection_profiles.iter().map(|p| p.name.as_str()),
+        )?;
+        unique(
+            "Connection table",
+            self.connection_tables.iter().map(|t| t.name.as_str()),
+        )?;
+        unique("Pipeline", self.pipelines.iter().map(|p| p.name.as_str()))?;
+
+        for pipeline in &self.pipelines {
+            if pipeline.parallelism == 0 {
+                bail!(
+                    "Pipeline '{}' must have a parallelism of at least 1",
+                    pipeline.name
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn strip_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YAML: &str = r#"
+connection_profiles:
+  - name: local-kafka
+    connector: kafka
+    config:
+      bootstrapServers: localhost:9092
+      authentication: {}
+connection_tables:
+  - name: orders
+    connector: kafka
+    connection_profile: local-kafka
+    config:
+      topic: orders
+      type:
+        source_config:
+          offset: latest
+    schema:
+      format:
+        json:
+          confluentSchemaRegistry: false
+      definition:
+        json_schema: "{}"
+pipelines:
+  - name: order_counts
+    query: SELECT count(*) FROM orders
+    parallelism: 2
+    checkpoint_interval_micros: 10000000
+    updating_ttl_micros: 3600000000
+"#;
+
+    #[test]
+    fn test_yaml_toml_round_trip() {
+        let manifest = Manifest::parse(YAML, ManifestFormat::Yaml).unwrap();
+        manifest.validate().unwrap();
+        assert_eq!(manifest.pipelines[0].parallelism, 2);
+        assert_eq!(
+            manifest.pipelines[0].updating_ttl_micros,
+            Some(3_600_000_000)
+        );
+        assert_eq!(
+            manifest.connection_tables[0].connection_profile.as_deref(),
+            Some("local-kafka")
+        );
+
+        let toml = manifest.serialize(ManifestFormat::Toml).unwrap();
+        assert_eq!(
+            Manifest::parse(&toml, ManifestFormat::Toml).unwrap(),
+            manifest
+        );
+
+        let yaml = manifest.serialize(ManifestFormat::Yaml).unwrap();
+        assert_eq!(
+            Manifest::parse(&yaml, ManifestFormat::Yaml).unwrap(),
+            manifest
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        let mut manifest = Manifest::parse(YAML, ManifestFormat::Yaml).unwrap();
+        manifest.pipelines.push(manifest.pipelines[0].clone());
+        assert!(manifest.validate().is_err());
+
+        let manifest = Manifest::parse(
+            "pipelines:\n  - name: p\n    query: SELECT 1\n    paralelism: 2\n",
+            ManifestFormat::Yaml,
+        );
+        assert!(manifest.is_err());
+    }
+}
@@ -1,12 +1,13 @@
 use anyhow::bail;
+use std::collections::HashMap;
 use std::env;
 use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 use arroyo_openapi::types::{
-    builder, ConnectionProfilePost, ConnectionSchema, ConnectionTablePost, Format, JsonFormat,
-    MetricName, PipelinePatch, PipelinePost, SchemaDefinition, StopType, Udf, ValidateQueryPost,
-    ValidateUdfPost,
+    builder, ApiTokenPost, AuditAction, CheckpointPatch, ConnectionProfilePost, ConnectionSchema,
+    ConnectionTablePatch, ConnectionTablePost, Format, JsonFormat, MetricName, PipelinePatch,
+    PipelinePost, Role, SchemaDefinition, StopType, Udf, ValidateQueryPost, ValidateUdfPost,
 };
 use arroyo_openapi::Client;
 use rand::random;
@@ -46,24 +47,32 @@ async fn wait_for_state(
     Ok(())
 }
 
+fn api_client(token: Option<&str>) -> Client {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(token) = token {
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            format!("Bearer {}", token).parse().unwrap(),
+        );
+    }
+
+    let client = reqwest::ClientBuilder::new()
+        .timeout(Duration::from_secs(60))
+        .default_headers(headers)
+        .build()
+        .unwrap();
+    Client::new_with_client(
+        &format!(
+            "{}/api",
+            env::var("API_ENDPOINT").unwrap_or_else(|_| "http://localhost:8000".to_string())
+        ),
+        client,
+    )
+}
+
 fn get_client() -> Arc<Client> {
     static CLIENT: OnceLock<Arc<Client>> = OnceLock::new();
-    CLIENT
-        .get_or_init(|| {
-            let client = reqwest::ClientBuilder::new()
-                .timeout(Duration::from_secs(60))
-                .build()
-                .unwrap();
-            Arc::new(Client::new_with_client(
-                &format!(
-                    "{}/api",
-                    env::var("API_ENDPOINT")
-                        .unwrap_or_else(|_| "http://localhost:8000".to_string())
-                ),
-                client,
-            ))
-        })
-        .clone()
+    CLIENT.get_or_init(|| Arc::new(api_client(None))).clone()
 }
 
 async fn start_pipeline(run_id: u32, query: &str, udfs: &[&str]) -> anyhow::Result<String> {
@@ -235,6 +244,36 @@ async fn basic_pipeline() {
         .into_inner();
     assert_eq!(errors.data.len(), 0);
 
+    // pin the most recent completed checkpoint
+    let checkpoint = api_client
+        .get_job_checkpoints()
+        .pipeline_id(&pipeline_id)
+        .job_id(&job_id)
+        .send()
+        .await
+        .unwrap()
+        .into_inner()
+        .data
+        .iter()
+        .filter(|c| c.finish_time.is_some())
+        .max_by_key(|c| c.epoch)
+        .cloned()
+        .unwrap();
+
+    let pinned = api_client
+        .patch_checkpoint()
+        .pipeline_id(&pipeline_id)
+        .job_id(&job_id)
+        .checkpoint_id(&checkpoint.id)
+        .body(CheckpointPatch::builder().pinned(true))
+        .send()
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(pinned.id, checkpoint.id);
+    assert_eq!(pinned.epoch, checkpoint.epoch);
+    assert!(pinned.pinned);
+
     loop {
         let metrics = api_client
             .get_operator_metric_groups()
@@ -269,6 +308,32 @@ async fn basic_pipeline() {
     .await
     .unwrap();
 
+    // restore to the pinned checkpoint, discarding the ones taken after it
+    let restored = api_client
+        .restore_checkpoint()
+        .pipeline_id(&pipeline_id)
+        .job_id(&job_id)
+        .checkpoint_id(&checkpoint.id)
+        .send()
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(restored.epoch, checkpoint.epoch);
+
+    let latest = api_client
+        .get_job_checkpoints()
+        .pipeline_id(&pipeline_id)
+        .job_id(&job_id)
+        .send()
+        .await
+        .unwrap()
+        .into_inner()
+        .data
+        .iter()
+        .map(|c| c.epoch)
+        .max();
+    assert_eq!(latest, Some(checkpoint.epoch));
+
     // start job
     patch_and_wait(
         &pipeline_id,
@@ -301,6 +366,44 @@ async fn basic_pipeline() {
         assert_eq!(node.parallelism, 2);
     }
 
+    // rescale a single operator (and the operators chained to it)
+    let sink = valid
+        .graph
+        .as_ref()
+        .unwrap()
+        .nodes
+        .last()
+        .unwrap()
+        .node_id
+        .clone();
+    patch_and_wait(
+        &pipeline_id,
+        PipelinePatch::builder().parallelism_overrides(HashMap::from([(sink.clone(), 3)])),
+        "Running",
+    )
+    .await
+    .unwrap();
+
+    let nodes = api_client
+        .get_pipeline()
+        .id(&pipeline_id)
+        .send()
+        .await
+        .unwrap()
+        .into_inner()
+        .graph
+        .nodes;
+
+    assert_eq!(
+        nodes
+            .iter()
+            .find(|n| n.node_id == sink)
+            .unwrap()
+            .parallelism,
+        3
+    );
+    assert!(nodes.iter().any(|n| n.parallelism == 2));
+
     // restart job
     println!("Restarting pipeline");
     api_client
@@ -401,6 +504,198 @@ select my_double(cast(counter as bigint)) from impulse;
         .unwrap();
 }
 
+#[tokio::test]
+async fn api_tokens() {
+    let name = format!("token_{}", random::<u32>());
+
+    let created = get_client()
+        .create_api_token()
+        .body(ApiTokenPost::builder().name(name.clone()))
+        .send()
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert!(created.secret.starts_with(&created.token.prefix));
+
+    let tokens = get_client()
+        .get_api_tokens()
+        .send()
+        .await
+        .unwrap()
+        .into_inner()
+        .data;
+    let listed = tokens.iter().find(|t| t.id == created.token.id).unwrap();
+    assert_eq!(listed.name, name);
+
+    // the token can be used to authenticate
+    let token_client = api_client(Some(&created.secret));
+    token_client.get_pipelines().send().await.unwrap();
+
+    // an unknown token is rejected
+    let err = api_client(Some("arroyo_not_a_real_token"))
+        .get_pipelines()
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::UNAUTHORIZED));
+
+    // revoke the token, after which it is no longer accepted
+    get_client()
+        .delete_api_token()
+        .id(&created.token.id)
+        .send()
+        .await
+        .unwrap();
+
+    let err = token_client.get_pipelines().send().await.unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::UNAUTHORIZED));
+
+    assert!(!get_client()
+        .get_api_tokens()
+        .send()
+        .await
+        .unwrap()
+        .into_inner()
+        .data
+        .iter()
+        .any(|t| t.id == created.token.id));
+
+    // both actions are recorded in the audit log
+    let actions: Vec<_> = get_client()
+        .get_audit_log()
+        .limit(100)
+        .send()
+        .await
+        .unwrap()
+        .into_inner()
+        .data
+        .into_iter()
+        .filter(|e| e.target_id == created.token.id)
+        .map(|e| e.action)
+        .collect();
+    assert_eq!(
+        actions,
+        vec![AuditAction::RevokeApiToken, AuditAction::CreateApiToken]
+    );
+}
+
+async fn create_token(role: Role) -> (String, Client) {
+    let created = get_client()
+        .create_api_token()
+        .body(
+            ApiTokenPost::builder()
+                .name(format!("{:?}_{}", role, random::<u32>()))
+                .role(role),
+        )
+        .send()
+        .await
+        .unwrap()
+        .into_inner();
+
+    (created.token.id, api_client(Some(&created.secret)))
+}
+
+#[tokio::test]
+async fn roles() {
+    let (viewer_id, viewer) = create_token(Role::Viewer).await;
+    let (editor_id, editor) = create_token(Role::Editor).await;
+
+    let query = r#"
+create table impulse with (
+   connector = 'impulse',
+   event_rate = '10'
+);
+
+select * from impulse;
+"#;
+
+    let pipeline_id = start_pipeline(random(), query, &[]).await.unwrap();
+
+    // viewers can read pipelines, but not modify them
+    viewer.get_pipeline().id(&pipeline_id).send().await.unwrap();
+    viewer.get_pipelines().send().await.unwrap();
+
+    let err = viewer
+        .patch_pipeline()
+        .id(&pipeline_id)
+        .body(PipelinePatch::builder().stop(StopType::Immediate))
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::FORBIDDEN));
+
+    let err = viewer
+        .delete_pipeline()
+        .id(&pipeline_id)
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::FORBIDDEN));
+
+    // editors can't manage connection profiles or API tokens
+    let err = editor
+        .create_connection_profile()
+        .body(
+            ConnectionProfilePost::builder()
+                .name(format!("profile_{}", random::<u32>()))
+                .connector("kafka")
+                .config(json!({"bootstrapServers": "localhost:9092", "authentication": {}})),
+        )
+        .send()
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::FORBIDDEN));
+
+    let err = editor.get_api_tokens().send().await.unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::FORBIDDEN));
+
+    // but editors can stop and delete pipelines
+    editor
+        .patch_pipeline()
+        .id(&pipeline_id)
+        .body(PipelinePatch::builder().stop(StopType::Immediate))
+        .send()
+        .await
+        .unwrap();
+    wait_for_state(&get_client(), &pipeline_id, "Stopped")
+        .await
+        .unwrap();
+
+    // the update is recorded as the fields that changed
+    let update = get_client()
+        .get_audit_log()
+        .limit(100)
+        .send()
+        .await
+        .unwrap()
+        .into_inner()
+        .data
+        .into_iter()
+        .find(|e| e.target_id == pipeline_id && e.action == AuditAction::UpdatePipeline)
+        .unwrap();
+    assert_eq!(
+        update.request.unwrap()["stop"],
+        json!({"before": "none", "after": "immediate"})
+    );
+
+    editor
+        .delete_pipeline()
+        .id(&pipeline_id)
+        .send()
+        .await
+        .unwrap();
+
+    for id in [viewer_id, editor_id] {
+        get_client()
+            .delete_api_token()
+            .id(&id)
+            .send()
+            .await
+            .unwrap();
+    }
+}
+
 fn create_kafka_admin() -> AdminClient<impl ClientContext> {
     ClientConfig::new()
         .set("bootstrap.servers", "localhost:9092")
@@ -603,6 +898,32 @@ async fn connection_table() {
     .await
     .unwrap();
 
+    // update the table while the pipeline is running, rolling it onto the new config
+    let update = api_client
+        .patch_connection_table()
+        .id(&connection_table.id)
+        .body(
+            ConnectionTablePatch::builder()
+                .config(Some(json!({
+                    "type": {
+                        "offset": "earliest",
+                        "read_mode": "read_uncommitted"
+                    },
+                    "topic": kafka_topic
+                })))
+                .restart_pipelines(Some(true)),
+        )
+        .send()
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(update.table.config["type"]["offset"], "earliest");
+    assert_eq!(update.pipelines.len(), 1);
+    assert_eq!(update.pipelines[0].id, pipeline_id);
+    assert!(update.pipelines[0].updated);
+    assert!(update.pipelines[0].restarted);
+
     // stop job
     patch_and_wait(
         &pipeline_id,
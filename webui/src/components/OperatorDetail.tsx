@@ -8,7 +8,12 @@ import {
   HStack,
   Spacer,
 } from '@chakra-ui/react';
-import { getCurrentMaxMetric, transformMetricGroup } from '../lib/util';
+import {
+  durationFormat,
+  getCurrentMaxMetric,
+  getCurrentMinMetric,
+  transformMetricGroup,
+} from '../lib/util';
 import React from 'react';
 import { TimeSeriesGraph } from './TimeSeriesGraph';
 import Loading from './Loading';
@@ -59,6 +64,43 @@ const OperatorDetail: React.FC<OperatorDetailProps> = ({ pipelineId, jobId, oper
     backpressureBadge = <Badge colorScheme={'red'}>HIGH</Badge>;
   }
 
+  // times are reported in micros since the epoch; watermarks are the min across subtasks
+  function currentTime(groupName: string, reduce: 'min' | 'max') {
+    const group = metricGroups.find(m => m.name === groupName);
+    if (!group) {
+      return undefined;
+    }
+    return reduce == 'min' ? getCurrentMinMetric(group) : getCurrentMaxMetric(group);
+  }
+
+  function formatTime(micros: number | undefined) {
+    if (micros == undefined) {
+      return 'none';
+    }
+    const lag = Date.now() * 1000 - micros;
+    return `${new Date(micros / 1000).toISOString()} (${durationFormat(Math.max(lag, 0))} behind)`;
+  }
+
+  const inputWatermark = currentTime('input_watermark', 'min');
+  const outputWatermark = currentTime('output_watermark', 'min');
+  const maxEventTime = currentTime('max_event_time', 'max');
+
+  const idleGroup = metricGroups.find(m => m.name == 'source_idle');
+  let idleBadge;
+  if (idleGroup) {
+    const idle = idleGroup.subtasks.filter(
+      s => s.metrics.length > 0 && s.metrics[s.metrics.length - 1].value > 0
+    ).length;
+    idleBadge =
+      idle == 0 ? (
+        <Badge colorScheme={'green'}>ACTIVE</Badge>
+      ) : (
+        <Badge colorScheme={'gray'}>
+          {idle}/{idleGroup.subtasks.length} IDLE
+        </Badge>
+      );
+  }
+
   function createGraph(
     metricGroups: components['schemas']['MetricGroup'][],
     groupName: string,
@@ -112,6 +154,12 @@ const OperatorDetail: React.FC<OperatorDetailProps> = ({ pipelineId, jobId, oper
         <Code>{Math.round(msgRecv)} eps</Code> rx
         <Code marginLeft="20px">{Math.round(msgSent)} eps</Code> tx
       </Box>
+      <Box marginTop="10px" fontSize={14} fontFamily="monaco,ubuntu mono,fixed-width">
+        <Box>Input watermark: {formatTime(inputWatermark)}</Box>
+        <Box>Output watermark: {formatTime(outputWatermark)}</Box>
+        {maxEventTime != undefined && <Box>Max event time: {formatTime(maxEventTime)}</Box>}
+        {idleBadge && <Box>Source: {idleBadge}</Box>}
+      </Box>
       {eventsReceivedGraph}
       {eventsSentGraph}
     </Box>
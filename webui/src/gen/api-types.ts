@@ -332,7 +332,7 @@ export interface components {
       subtasks: (components["schemas"]["SubtaskMetrics"])[];
     };
     /** @enum {string} */
-    MetricNames: "bytes_recv" | "bytes_sent" | "messages_recv" | "messages_sent" | "backpressure";
+    MetricNames: "bytes_recv" | "bytes_sent" | "messages_recv" | "messages_sent" | "backpressure" | "tx_queue_size" | "tx_queue_rem" | "input_watermark" | "output_watermark" | "max_event_time" | "source_idle";
     NewlineDelimitedFraming: {
       /** Format: int64 */
       maxLineLength?: number | null;
@@ -100,16 +100,10 @@ export function getBackpressureColor(backpressure: number): string {
   return colors[colors.length - 1];
 }
 
-export function getCurrentMaxMetric(metricGroup: MetricGroup): number {
-  if (
-    metricGroup.subtasks.length === 0 ||
-    metricGroup.subtasks.every(s => s.metrics.length === 0)
-  ) {
-    return 0;
-  }
-
+function getLatestSubtaskValues(metricGroup: MetricGroup): number[] {
   return metricGroup.subtasks
     .map(s => s.metrics)
+    .filter(m => m.length > 0)
     .map(m =>
       m.reduce((r: Metric, c: Metric) => {
         if (c.time > r.time) {
@@ -119,8 +113,25 @@ export function getCurrentMaxMetric(metricGroup: MetricGroup): number {
         }
       }, m[0])
     )
-    .map(m => m.value)
-    .reduce((max, curr) => Math.max(max, curr));
+    .map(m => m.value);
+}
+
+export function getCurrentMaxMetric(metricGroup: MetricGroup): number {
+  const values = getLatestSubtaskValues(metricGroup);
+  if (values.length === 0) {
+    return 0;
+  }
+
+  return values.reduce((max, curr) => Math.max(max, curr));
+}
+
+export function getCurrentMinMetric(metricGroup: MetricGroup): number | undefined {
+  const values = getLatestSubtaskValues(metricGroup);
+  if (values.length === 0) {
+    return undefined;
+  }
+
+  return values.reduce((min, curr) => Math.min(min, curr));
 }
 
 export function transformMetricGroup(metric_group: MetricGroup) {